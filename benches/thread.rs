@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use xcli::thread::{split_text, weighted_len};
+
+fn essay() -> String {
+    let paragraph = "X counts most Latin text as one character each, but CJK and emoji \
+                     as two. 한국어 문장도 섞여 있습니다. Does the splitter keep up? "
+        .repeat(20);
+    [paragraph.trim(); 200].join("\n\n")
+}
+
+fn bench_weighted_len(c: &mut Criterion) {
+    let text = essay();
+    c.bench_function("weighted_len/essay", |b| {
+        b.iter(|| weighted_len(black_box(&text)))
+    });
+    c.bench_function("weighted_len/tweet", |b| {
+        b.iter(|| weighted_len(black_box("Hello from xcli! “Quotes” – and 안녕 😀")))
+    });
+}
+
+fn bench_split(c: &mut Criterion) {
+    let text = essay();
+    c.bench_function("split_text/essay", |b| {
+        b.iter(|| split_text(black_box(&text)))
+    });
+
+    let words = ["word"; 5000].join(" ");
+    c.bench_function("split_text/no_sentences", |b| {
+        b.iter(|| split_text(black_box(&words)))
+    });
+}
+
+criterion_group!(benches, bench_weighted_len, bench_split);
+criterion_main!(benches);
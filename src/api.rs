@@ -1,15 +1,358 @@
+//! Typed wrappers around the X API v2 endpoints xcli uses. Most callers want
+//! [`crate::XClient`] instead.
+
+use std::io::SeekFrom;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
-use crate::auth::build_oauth_header;
-use crate::config::Config;
+use crate::applog;
+use crate::auth::{build_oauth_header, build_oauth_header_with_params, percent_encode};
+use crate::client::XClient;
+use crate::config;
+use crate::media;
+use crate::progress;
+use crate::transport::{Method, OnLine, Part, Request, Response, StatusCode};
+use crate::uploads::{self, Upload};
 
+const API_BASE: &str = "https://api.x.com/2";
 const TWEETS_URL: &str = "https://api.x.com/2/tweets";
+const MEDIA_UPLOAD_URL: &str = "https://api.x.com/2/media/upload";
+const OAUTH2_TOKEN_URL: &str = "https://api.x.com/oauth2/token";
+const STREAM_URL: &str = "https://api.x.com/2/tweets/search/stream";
+const STREAM_RULES_URL: &str = "https://api.x.com/2/tweets/search/stream/rules";
+
+/// User fields requested whenever user lists are read back.
+const USER_FIELDS: &str = "description,public_metrics";
+
+/// User fields for drawing an account, as `xcli render` does.
+const CARD_USER_FIELDS: &str = "profile_image_url";
+/// User fields for `xcli user show`.
+const PROFILE_USER_FIELDS: &str =
+    "description,public_metrics,location,url,created_at,verified_type,protected";
+/// User fields with when each account last posted.
+const ACTIVITY_USER_FIELDS: &str = "description,public_metrics,most_recent_tweet_id";
+
+/// Tweet fields requested whenever tweets are read back.
+const TWEET_FIELDS: &str = "created_at,public_metrics,author_id,conversation_id,referenced_tweets";
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_metrics: Option<UserMetrics>,
+    /// A small (48 by 48) avatar, when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_image_url: Option<String>,
+    /// ID of the account's latest tweet, when asked for and it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub most_recent_tweet_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct UserMetrics {
+    pub followers_count: u64,
+    pub following_count: u64,
+    pub tweet_count: u64,
+    pub listed_count: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Tweet {
+    pub id: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_metrics: Option<PublicMetrics>,
+    /// The tweets this one replies to, quotes or retweets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub referenced_tweets: Vec<ReferencedTweet>,
+}
+
+impl Tweet {
+    /// The ID of the tweet this one replies to.
+    pub fn in_reply_to(&self) -> Option<&str> {
+        self.referenced_tweets
+            .iter()
+            .find(|r| r.kind == "replied_to")
+            .map(|r| r.id.as_str())
+    }
+}
+
+/// A tweet ID given either bare or as the tweet's URL, e.g.
+/// `https://x.com/jack/status/20` (twitter.com too, with or without a query
+/// string or a `/photo/1` after the ID).
+pub fn parse_tweet_id(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    let id = match s
+        .split_once("/status/")
+        .or_else(|| s.split_once("/statuses/"))
+    {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next().unwrap_or(""),
+        None => s,
+    };
+    if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(id.to_string())
+    } else {
+        Err(format!(
+            "`{s}` is not a tweet ID or URL (e.g. 1234567890 or https://x.com/user/status/1234567890)"
+        ))
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ReferencedTweet {
+    /// `replied_to`, `quoted` or `retweeted`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct PublicMetrics {
+    pub impression_count: u64,
+    pub like_count: u64,
+    pub retweet_count: u64,
+    pub reply_count: u64,
+    pub quote_count: u64,
+    pub bookmark_count: u64,
+}
+
+/// Rate-limit state of an endpoint, from the `x-rate-limit-*` headers.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix time at which the window resets.
+    pub reset: u64,
+}
+
+impl RateLimit {
+    /// The window `resp` reports in its `{prefix}-limit`, `-remaining` and
+    /// `-reset` headers (`x-rate-limit`, or `x-user-limit-24hour` for the
+    /// daily cap on posting).
+    pub fn from_headers(resp: &Response, prefix: &str) -> Option<Self> {
+        let number = |name: &str| {
+            resp.header(&format!("{prefix}-{name}"))
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+        Some(RateLimit {
+            limit: number("limit")?,
+            remaining: number("remaining")?,
+            reset: number("reset")?,
+        })
+    }
+}
+
+/// What X reported about a request besides its data. The transaction ID is
+/// what X support asks for when a request misbehaves.
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+pub struct ResponseMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    pub latency_ms: u64,
+}
+
+impl ResponseMeta {
+    pub fn from_response(resp: &Response, latency: Duration) -> Self {
+        ResponseMeta {
+            transaction_id: resp.header("x-transaction-id").map(str::to_string),
+            rate_limit: RateLimit::from_headers(resp, "x-rate-limit"),
+            latency_ms: latency.as_millis() as u64,
+        }
+    }
+}
+
+/// Data returned by an endpoint together with the response metadata.
+#[derive(Serialize, Clone, Debug)]
+pub struct ApiResponse<T> {
+    pub data: T,
+    pub meta: ResponseMeta,
+}
+
+#[derive(Deserialize)]
+struct DataResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    data: Option<Vec<T>>,
+    #[serde(default)]
+    includes: Includes,
+    meta: Option<PageMeta>,
+}
+
+/// Objects referenced by a page, such as the authors of its tweets.
+#[derive(Deserialize, Default)]
+struct Includes {
+    #[serde(default)]
+    users: Vec<User>,
+}
+
+/// Tweets with their authors, as read from a timeline.
+#[derive(Default)]
+pub struct Timeline {
+    pub tweets: Vec<Tweet>,
+    pub users: Vec<User>,
+}
+
+impl Timeline {
+    /// The author of `tweet`, if X included it.
+    pub fn author(&self, tweet: &Tweet) -> Option<&User> {
+        let id = tweet.author_id.as_deref()?;
+        self.users.iter().find(|u| u.id == id)
+    }
+}
+
+#[derive(Deserialize)]
+struct PageMeta {
+    next_token: Option<String>,
+}
 
 #[derive(Serialize)]
 struct CreateTweetBody {
+    // A tweet with media may have no text.
+    #[serde(skip_serializing_if = "String::is_empty")]
     text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     reply: Option<ReplyTo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media: Option<MediaIds>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poll: Option<Poll>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_settings: Option<&'static str>,
+}
+
+/// What else goes on a new tweet besides its text and media.
+#[derive(Clone, Debug, Default)]
+pub struct TweetOptions {
+    /// A poll, on the first tweet of a thread.
+    pub poll: Option<Poll>,
+    /// Who may reply, to every tweet of a thread.
+    pub reply_settings: ReplySettings,
+}
+
+/// Who may reply to a tweet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReplySettings {
+    #[default]
+    Everyone,
+    /// Only the accounts the tweet mentions.
+    Mentioned,
+    /// Only the accounts you follow (and those the tweet mentions).
+    Following,
+}
+
+impl ReplySettings {
+    /// The `reply_settings` value X expects, none for everyone.
+    fn api_value(self) -> Option<&'static str> {
+        match self {
+            ReplySettings::Everyone => None,
+            ReplySettings::Mentioned => Some("mentionedUsers"),
+            ReplySettings::Following => Some("following"),
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            ReplySettings::Everyone => "everyone",
+            ReplySettings::Mentioned => "only the accounts mentioned",
+            ReplySettings::Following => "only the accounts you follow or mention",
+        }
+    }
+}
+
+/// Parse a `--reply-to` value.
+pub fn parse_reply_settings(s: &str) -> Result<ReplySettings, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "everyone" => Ok(ReplySettings::Everyone),
+        "mentioned" => Ok(ReplySettings::Mentioned),
+        "following" => Ok(ReplySettings::Following),
+        _ => Err(format!(
+            "unknown reply setting `{s}` (expected everyone, mentioned or following)"
+        )),
+    }
+}
+
+/// A poll to attach to a tweet, checked against X's limits by `Poll::new`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Poll {
+    options: Vec<String>,
+    duration_minutes: u32,
+}
+
+/// Most choices X allows in a poll (and the fewest is two).
+pub const MAX_POLL_OPTIONS: usize = 4;
+/// Longest a poll choice may be, in characters.
+pub const MAX_POLL_OPTION_CHARS: usize = 25;
+/// Shortest and longest a poll may run, in minutes (5 minutes to 7 days).
+pub const POLL_MINUTES: std::ops::RangeInclusive<u32> = 5..=10_080;
+
+impl Poll {
+    pub fn new(options: Vec<String>, duration_minutes: u32) -> Result<Poll, String> {
+        if !(2..=MAX_POLL_OPTIONS).contains(&options.len()) {
+            return Err(format!(
+                "A poll needs 2 to {MAX_POLL_OPTIONS} options, got {}",
+                options.len()
+            ));
+        }
+        for option in &options {
+            let chars = option.trim().chars().count();
+            if chars == 0 {
+                return Err("Poll options cannot be empty".to_string());
+            }
+            if chars > MAX_POLL_OPTION_CHARS {
+                return Err(format!(
+                    "Poll option \"{option}\" is {chars} characters long (at most {MAX_POLL_OPTION_CHARS})"
+                ));
+            }
+        }
+        if !POLL_MINUTES.contains(&duration_minutes) {
+            return Err(format!(
+                "A poll runs for {} to {} minutes, not {duration_minutes}",
+                POLL_MINUTES.start(),
+                POLL_MINUTES.end()
+            ));
+        }
+        let options = options.iter().map(|o| o.trim().to_string()).collect();
+        Ok(Poll {
+            options,
+            duration_minutes,
+        })
+    }
+
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    pub fn duration_minutes(&self) -> u32 {
+        self.duration_minutes
+    }
+}
+
+#[derive(Serialize)]
+struct MediaIds {
+    media_ids: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -38,120 +381,2827 @@ struct DeleteData {
 }
 
 pub async fn create_tweet(
-    config: &Config,
+    client: &XClient,
     text: &str,
     reply_to: Option<&str>,
-) -> Result<String, String> {
-    let auth_header = build_oauth_header(config, "POST", TWEETS_URL);
+) -> Result<ApiResponse<String>, String> {
+    create_tweet_with_media(client, text, reply_to, &[]).await
+}
+
+/// Post a tweet with media already uploaded by `upload_media`.
+pub async fn create_tweet_with_media(
+    client: &XClient,
+    text: &str,
+    reply_to: Option<&str>,
+    media_ids: &[String],
+) -> Result<ApiResponse<String>, String> {
+    post_tweet(
+        client,
+        text,
+        reply_to,
+        media_ids,
+        None,
+        ReplySettings::Everyone,
+    )
+    .await
+}
+
+/// `create_tweet_with_media` for a new tweet, with a poll or reply settings.
+pub async fn create_tweet_with(
+    client: &XClient,
+    text: &str,
+    media_ids: &[String],
+    options: &TweetOptions,
+) -> Result<ApiResponse<String>, String> {
+    post_tweet(
+        client,
+        text,
+        None,
+        media_ids,
+        options.poll.as_ref(),
+        options.reply_settings,
+    )
+    .await
+}
 
-    let client = reqwest::Client::new();
+async fn post_tweet(
+    client: &XClient,
+    text: &str,
+    reply_to: Option<&str>,
+    media_ids: &[String],
+    poll: Option<&Poll>,
+    reply_settings: ReplySettings,
+) -> Result<ApiResponse<String>, String> {
     let body = CreateTweetBody {
         text: text.to_string(),
         reply: reply_to.map(|id| ReplyTo {
             in_reply_to_tweet_id: id.to_string(),
         }),
+        media: (!media_ids.is_empty()).then(|| MediaIds {
+            media_ids: media_ids.to_vec(),
+        }),
+        poll: poll.cloned(),
+        reply_settings: reply_settings.api_value(),
     };
 
-    let resp = client
-        .post(TWEETS_URL)
-        .header("Authorization", &auth_header)
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?;
+    let req = Request::new(Method::POST, TWEETS_URL)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", TWEETS_URL),
+        )
+        .json(&body);
+    let resp: ApiResponse<CreateTweetResponse> = fetch(client, req).await?;
+
+    Ok(ApiResponse {
+        data: resp.data.data.id,
+        meta: resp.meta,
+    })
+}
+
+pub async fn delete_tweet(client: &XClient, id: &str) -> Result<ApiResponse<bool>, String> {
+    let url = format!("{TWEETS_URL}/{id}");
+    let auth_header = build_oauth_header(client.config(), "DELETE", &url);
+    let req = Request::new(Method::DELETE, &url).header("Authorization", auth_header);
+    let resp: ApiResponse<DeleteTweetResponse> = fetch(client, req).await?;
+
+    Ok(ApiResponse {
+        data: resp.data.data.deleted,
+        meta: resp.meta,
+    })
+}
+
+/// Send `req` and parse the JSON answer, keeping the response metadata.
+async fn fetch<T: DeserializeOwned>(
+    client: &XClient,
+    req: Request,
+) -> Result<ApiResponse<T>, String> {
+    let started = Instant::now();
+    let resp = client.send(req).await?;
+    let meta = ResponseMeta::from_response(&resp, started.elapsed());
+    Ok(ApiResponse {
+        data: parse_json(resp)?,
+        meta,
+    })
+}
+
+/// Signed GET request returning parsed JSON.
+async fn get_json<T: DeserializeOwned>(
+    client: &XClient,
+    url: &str,
+    query: &[(&str, &str)],
+) -> Result<T, String> {
+    let resp = send_get(client, url, query).await?;
+    parse_json(resp)
+}
+
+/// Like `get_json`, but when rate limited waits for the limit to reset and
+/// tries again. For long-running bulk reads.
+async fn get_json_paced<T: DeserializeOwned>(
+    client: &XClient,
+    url: &str,
+    query: &[(&str, &str)],
+) -> Result<T, String> {
+    loop {
+        let resp = send_get(client, url, query).await?;
+        if let Some(resp) = wait_if_rate_limited(resp).await {
+            return parse_json(resp);
+        }
+    }
+}
+
+/// Signed JSON POST that waits out rate limits, for bulk writes.
+async fn post_json_paced<B: Serialize, T: DeserializeOwned>(
+    client: &XClient,
+    url: &str,
+    body: &B,
+) -> Result<T, String> {
+    loop {
+        let req = Request::new(Method::POST, url)
+            .header(
+                "Authorization",
+                build_oauth_header(client.config(), "POST", url),
+            )
+            .json(body);
+        if let Some(resp) = wait_if_rate_limited(client.send(req).await?).await {
+            return parse_json(resp);
+        }
+    }
+}
+
+/// Returns the response unless it is a 429, in which case it sleeps until the
+/// rate limit resets and returns `None` so the caller retries.
+async fn wait_if_rate_limited(resp: Response) -> Option<Response> {
+    if resp.status != StatusCode::TOO_MANY_REQUESTS {
+        return Some(resp);
+    }
+    let wait = rate_limit_wait(&resp);
+    eprintln!("Rate limited by X, waiting {wait}s...");
+    applog::warn(&format!("Rate limited by X, retrying in {wait}s"));
+    tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+    None
+}
+
+/// Seconds until `x-rate-limit-reset`, clamped to 1s..15min (the window length).
+fn rate_limit_wait(resp: &Response) -> u64 {
+    let reset = resp
+        .header("x-rate-limit-reset")
+        .and_then(|v| v.parse::<u64>().ok());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    match reset {
+        Some(reset) => reset.saturating_sub(now).clamp(1, 15 * 60) + 1,
+        None => 60,
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(resp: Response) -> Result<T, String> {
+    check_status(&resp)?;
+    resp.json()
+}
 
-    let status = resp.status();
+/// The API error of a failed response, for answers without a body to parse.
+fn check_status(resp: &Response) -> Result<(), String> {
+    let status = resp.status;
     if !status.is_success() {
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API error ({status}): {body}"));
+        let transaction = resp
+            .header("x-transaction-id")
+            .map(|id| format!(" (transaction ID {id})"))
+            .unwrap_or_default();
+        return Err(format!(
+            "API error ({status}): {}{transaction}",
+            resp.text()
+        ));
     }
+    Ok(())
+}
 
-    let data: CreateTweetResponse = resp
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {e}"))?;
+async fn send_get(client: &XClient, url: &str, query: &[(&str, &str)]) -> Result<Response, String> {
+    client.send(signed_get(client, url, query)).await
+}
 
-    Ok(data.data.id)
+fn signed_get(client: &XClient, url: &str, query: &[(&str, &str)]) -> Request {
+    let auth_header = build_oauth_header_with_params(client.config(), "GET", url, query);
+    let full_url = if query.is_empty() {
+        url.to_string()
+    } else {
+        let qs = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{url}?{qs}")
+    };
+    Request::new(Method::GET, full_url).header("Authorization", auth_header)
 }
 
-pub async fn delete_tweet(config: &Config, id: &str) -> Result<bool, String> {
-    let url = format!("{TWEETS_URL}/{id}");
-    let auth_header = build_oauth_header(config, "DELETE", &url);
+/// Follow `pagination_token` until the results run out or `max_items` is reached,
+/// waiting out rate limits along the way.
+async fn get_all_pages<T: DeserializeOwned>(
+    client: &XClient,
+    url: &str,
+    query: &[(&str, &str)],
+    max_items: usize,
+) -> Result<Vec<T>, String> {
+    get_all_pages_by(client, url, query, "pagination_token", max_items).await
+}
 
-    let client = reqwest::Client::new();
+/// Like `get_all_pages`, for endpoints that name the cursor differently
+/// (search takes `next_token`).
+async fn get_all_pages_by<T: DeserializeOwned>(
+    client: &XClient,
+    url: &str,
+    query: &[(&str, &str)],
+    token_param: &str,
+    max_items: usize,
+) -> Result<Vec<T>, String> {
+    let mut items = Vec::new();
+    for_each_page(
+        client,
+        url,
+        query,
+        token_param,
+        None,
+        max_items,
+        |page, _, _| items.extend(page),
+    )
+    .await?;
+    Ok(items)
+}
 
-    let resp = client
-        .delete(&url)
-        .header("Authorization", &auth_header)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?;
+/// Like `get_all_pages_by`, handing each page to `each` as soon as it
+/// arrives instead of collecting them, so callers can stream large reads.
+/// Starts at `first_token` if given; `each` also gets the page's includes
+/// and the token of the next page, `None` after the last.
+async fn for_each_page<T: DeserializeOwned>(
+    client: &XClient,
+    url: &str,
+    query: &[(&str, &str)],
+    token_param: &str,
+    first_token: Option<&str>,
+    max_items: usize,
+    mut each: impl FnMut(Vec<T>, Includes, Option<&str>),
+) -> Result<(), String> {
+    let mut seen = 0;
+    let mut token: Option<String> = first_token.map(str::to_string);
 
-    let status = resp.status();
-    if !status.is_success() {
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API error ({status}): {body}"));
+    loop {
+        let mut q: Vec<(&str, &str)> = query.to_vec();
+        if let Some(t) = &token {
+            q.push((token_param, t));
+        }
+        let page: Page<T> = get_json_paced(client, url, &q).await?;
+        let mut items = page.data.unwrap_or_default();
+        items.truncate(max_items - seen);
+        seen += items.len();
+        token = page.meta.and_then(|m| m.next_token);
+        each(items, page.includes, token.as_deref());
+        if token.is_none() || seen >= max_items {
+            break;
+        }
     }
+    Ok(())
+}
 
-    let data: DeleteTweetResponse = resp
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {e}"))?;
+/// The authenticated user.
+pub async fn get_me(client: &XClient) -> Result<User, String> {
+    get_me_with(client, USER_FIELDS).await
+}
 
-    Ok(data.data.deleted)
+/// The authenticated account with its avatar.
+pub async fn get_me_card(client: &XClient) -> Result<User, String> {
+    get_me_with(client, CARD_USER_FIELDS).await
 }
 
-pub struct ThreadError {
-    pub posted_ids: Vec<String>,
-    pub failed_index: usize,
-    pub error: String,
+async fn get_me_with(client: &XClient, fields: &str) -> Result<User, String> {
+    let resp: DataResponse<User> = get_json(
+        client,
+        &format!("{API_BASE}/users/me"),
+        &[("user.fields", fields)],
+    )
+    .await?;
+    Ok(resp.data)
 }
 
-pub async fn create_reply_thread(
-    config: &Config,
-    reply_to_id: &str,
-    chunks: &[String],
-) -> Result<Vec<String>, ThreadError> {
-    let mut posted_ids: Vec<String> = Vec::new();
+/// The authenticated account, and what its token may do.
+pub struct Identity {
+    pub user: User,
+    /// X's `x-access-level`: `read`, `read-write` or
+    /// `read-write-directmessages`, when it says.
+    pub access_level: Option<String>,
+}
 
-    for (i, chunk) in chunks.iter().enumerate() {
-        let parent = if i == 0 {
-            reply_to_id
-        } else {
-            posted_ids.last().unwrap()
-        };
-        match create_tweet(config, chunk, Some(parent)).await {
-            Ok(id) => posted_ids.push(id),
-            Err(e) => {
-                return Err(ThreadError {
-                    posted_ids,
-                    failed_index: i,
-                    error: e,
-                });
+/// Check the stored credentials against X: who they log in as and the
+/// access the app was granted.
+pub async fn whoami(client: &XClient) -> Result<Identity, String> {
+    let resp = send_get(
+        client,
+        &format!("{API_BASE}/users/me"),
+        &[("user.fields", USER_FIELDS)],
+    )
+    .await?;
+    let access_level = resp.header("x-access-level").map(str::to_string);
+    let resp: DataResponse<User> = parse_json(resp)?;
+    Ok(Identity {
+        user: resp.data,
+        access_level,
+    })
+}
+
+/// The permissions an `x-access-level` grants, e.g. `["read", "write"]`.
+pub fn access_scopes(level: &str) -> Vec<&'static str> {
+    let mut scopes = Vec::new();
+    for part in level.split('-') {
+        match part {
+            "read" => scopes.push("read"),
+            "write" => scopes.push("write"),
+            "directmessages" => scopes.push("direct messages"),
+            _ => {}
+        }
+    }
+    scopes
+}
+
+/// Tweet `id` with its metrics, and its author with their avatar.
+pub async fn get_tweet_card(client: &XClient, id: &str) -> Result<(Tweet, Option<User>), String> {
+    let resp: TweetWithIncludes = get_json(
+        client,
+        &format!("{TWEETS_URL}/{id}"),
+        &[
+            ("tweet.fields", TWEET_FIELDS),
+            ("expansions", "author_id"),
+            ("user.fields", CARD_USER_FIELDS),
+        ],
+    )
+    .await?;
+    let tweet = resp.data;
+    let author = resp
+        .includes
+        .users
+        .into_iter()
+        .find(|u| tweet.author_id.as_deref() == Some(u.id.as_str()));
+    Ok((tweet, author))
+}
+
+pub async fn get_tweet(client: &XClient, id: &str) -> Result<ApiResponse<Tweet>, String> {
+    let req = signed_get(
+        client,
+        &format!("{TWEETS_URL}/{id}"),
+        &[("tweet.fields", TWEET_FIELDS)],
+    );
+    let resp: ApiResponse<DataResponse<Tweet>> = fetch(client, req).await?;
+    Ok(ApiResponse {
+        data: resp.data.data,
+        meta: resp.meta,
+    })
+}
+
+/// Tweets posted by `user_id` since `start_time` (RFC 3339), newest first.
+/// Retweets are excluded.
+pub async fn get_user_tweets(
+    client: &XClient,
+    user_id: &str,
+    start_time: &str,
+    max_items: usize,
+) -> Result<Vec<Tweet>, String> {
+    get_all_pages(
+        client,
+        &format!("{API_BASE}/users/{user_id}/tweets"),
+        &[
+            ("max_results", "100"),
+            ("start_time", start_time),
+            ("exclude", "retweets"),
+            ("tweet.fields", TWEET_FIELDS),
+        ],
+        max_items,
+    )
+    .await
+}
+
+/// Look up tweets by ID, 100 per request. Deleted tweets are simply missing
+/// from the result.
+pub async fn get_tweets(client: &XClient, ids: &[String]) -> Result<Vec<Tweet>, String> {
+    let mut tweets = Vec::new();
+    for batch in ids.chunks(100) {
+        let ids = batch.join(",");
+        let page: Page<Tweet> = get_json_paced(
+            client,
+            TWEETS_URL,
+            &[("ids", &ids), ("tweet.fields", TWEET_FIELDS)],
+        )
+        .await?;
+        tweets.extend(page.data.unwrap_or_default());
+    }
+    Ok(tweets)
+}
+
+/// Up to `max_items` of the latest tweets of list `list_id`, newest first,
+/// with their authors.
+pub async fn get_list_tweets(
+    client: &XClient,
+    list_id: &str,
+    max_items: usize,
+) -> Result<Timeline, String> {
+    let page_size = max_items.clamp(1, 100).to_string();
+    read_timeline(
+        client,
+        &format!("{API_BASE}/lists/{list_id}/tweets"),
+        &[("max_results", &page_size)],
+        "pagination_token",
+        max_items,
+    )
+    .await
+}
+
+/// Which tweets of an account to read.
+#[derive(Clone, Copy)]
+pub enum TimelineKind {
+    /// The account's own tweets.
+    Tweets,
+    /// Tweets mentioning the account.
+    Mentions,
+}
+
+impl TimelineKind {
+    /// The last part of the endpoint's path.
+    pub fn endpoint(self) -> &'static str {
+        match self {
+            TimelineKind::Tweets => "tweets",
+            TimelineKind::Mentions => "mentions",
+        }
+    }
+}
+
+/// Bounds on a timeline read, so scripts can fetch exactly what is new since
+/// their last run. Times are RFC 3339 timestamps.
+#[derive(Default)]
+pub struct Window<'a> {
+    pub since_id: Option<&'a str>,
+    pub until_id: Option<&'a str>,
+    pub start_time: Option<&'a str>,
+    pub end_time: Option<&'a str>,
+}
+
+/// Up to `max_items` of the latest tweets of (or mentioning) `user_id`
+/// within `window`, newest first, with their authors.
+pub async fn get_timeline(
+    client: &XClient,
+    user_id: &str,
+    kind: TimelineKind,
+    window: &Window<'_>,
+    max_items: usize,
+) -> Result<Timeline, String> {
+    let endpoint = kind.endpoint();
+    let page_size = max_items.clamp(5, 100).to_string();
+    let mut query = vec![("max_results", page_size.as_str())];
+    let bounds = [
+        ("since_id", window.since_id),
+        ("until_id", window.until_id),
+        ("start_time", window.start_time),
+        ("end_time", window.end_time),
+    ];
+    for (name, value) in bounds {
+        if let Some(value) = value {
+            query.push((name, value));
+        }
+    }
+    read_timeline(
+        client,
+        &format!("{API_BASE}/users/{user_id}/{endpoint}"),
+        &query,
+        "pagination_token",
+        max_items,
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+struct TweetWithIncludes {
+    data: Tweet,
+    #[serde(default)]
+    includes: Includes,
+}
+
+/// Tweet `tweet_id` followed by up to `max_items` tweets of its conversation,
+/// with their authors. Replies come from search, which covers the last 7
+/// days unless `all` searches the full archive (Pro and Enterprise access).
+pub async fn get_conversation(
+    client: &XClient,
+    tweet_id: &str,
+    all: bool,
+    max_items: usize,
+) -> Result<Timeline, String> {
+    let tweet: TweetWithIncludes = get_json(
+        client,
+        &format!("{TWEETS_URL}/{tweet_id}"),
+        &[
+            ("tweet.fields", TWEET_FIELDS),
+            ("expansions", "author_id"),
+            ("user.fields", USER_FIELDS),
+        ],
+    )
+    .await?;
+    let conversation = tweet.data.conversation_id.as_deref().unwrap_or(tweet_id);
+    let query = format!("conversation_id:{conversation}");
+    let (endpoint, most) = if all { ("all", 500) } else { ("recent", 100) };
+    let page_size = max_items.clamp(10, most).to_string();
+    let replies = read_timeline(
+        client,
+        &format!("{API_BASE}/tweets/search/{endpoint}"),
+        &[("query", &query), ("max_results", &page_size)],
+        "next_token",
+        max_items,
+    )
+    .await?;
+
+    let mut timeline = Timeline {
+        tweets: vec![tweet.data],
+        users: tweet.includes.users,
+    };
+    timeline.tweets.extend(replies.tweets);
+    for user in replies.users {
+        if !timeline.users.iter().any(|u| u.id == user.id) {
+            timeline.users.push(user);
+        }
+    }
+    Ok(timeline)
+}
+
+/// Page through the timeline at `url` until `max_items` tweets, collecting
+/// the authors X includes along the way. The cursor is sent as
+/// `token_param`.
+async fn read_timeline(
+    client: &XClient,
+    url: &str,
+    query: &[(&str, &str)],
+    token_param: &str,
+    max_items: usize,
+) -> Result<Timeline, String> {
+    let mut query = query.to_vec();
+    query.extend([
+        ("tweet.fields", TWEET_FIELDS),
+        ("expansions", "author_id"),
+        ("user.fields", USER_FIELDS),
+    ]);
+    let mut timeline = Timeline::default();
+    for_each_page(
+        client,
+        url,
+        &query,
+        token_param,
+        None,
+        max_items,
+        |tweets, includes, _| {
+            timeline.tweets.extend(tweets);
+            for user in includes.users {
+                if !timeline.users.iter().any(|u| u.id == user.id) {
+                    timeline.users.push(user);
+                }
             }
+        },
+    )
+    .await?;
+    Ok(timeline)
+}
+
+/// Tweets from the last 7 days matching `query`, newest first.
+pub async fn search_recent(
+    client: &XClient,
+    query: &str,
+    max_items: usize,
+) -> Result<Vec<Tweet>, String> {
+    let search = Search {
+        query,
+        ..Search::default()
+    };
+    let mut tweets = Vec::new();
+    search_pages(client, &search, max_items, |page, _| tweets.extend(page)).await?;
+    Ok(tweets)
+}
+
+/// A tweet search: of the last 7 days, or with `all` of the full archive
+/// (Pro and Enterprise access only).
+#[derive(Default)]
+pub struct Search<'a> {
+    pub query: &'a str,
+    pub all: bool,
+    /// Oldest and newest tweets to match, as RFC 3339 timestamps.
+    pub start_time: Option<&'a str>,
+    pub end_time: Option<&'a str>,
+    /// Continue an earlier search from this `next_token`.
+    pub next_token: Option<&'a str>,
+    /// Only match tweets newer than this one.
+    pub since_id: Option<&'a str>,
+}
+
+/// Run `search`, handing each page of results to `each` as it is fetched,
+/// with the token that continues the search (`None` after the last page).
+pub async fn search_pages(
+    client: &XClient,
+    search: &Search<'_>,
+    max_items: usize,
+    mut each: impl FnMut(Vec<Tweet>, Option<&str>),
+) -> Result<(), String> {
+    let (endpoint, most) = if search.all {
+        ("all", 500)
+    } else {
+        ("recent", 100)
+    };
+    let page_size = max_items.clamp(10, most).to_string();
+    let mut query = vec![
+        ("query", search.query),
+        ("max_results", page_size.as_str()),
+        ("tweet.fields", TWEET_FIELDS),
+    ];
+    if let Some(start) = search.start_time {
+        query.push(("start_time", start));
+    }
+    if let Some(end) = search.end_time {
+        query.push(("end_time", end));
+    }
+    if let Some(id) = search.since_id {
+        query.push(("since_id", id));
+    }
+    for_each_page(
+        client,
+        &format!("{API_BASE}/tweets/search/{endpoint}"),
+        &query,
+        "next_token",
+        search.next_token,
+        max_items,
+        |tweets, _, token| each(tweets, token),
+    )
+    .await
+}
+
+/// MIME type for the image formats X accepts in a single upload request.
+fn image_type(path: &Path) -> Result<&'static str, String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "jpg" | "jpeg" => Ok("image/jpeg"),
+        "png" => Ok("image/png"),
+        "webp" => Ok("image/webp"),
+        "gif" => Ok("image/gif"),
+        _ => Err(format!(
+            "Unsupported media file {}: use JPEG, PNG, WebP, GIF, MP4 or MOV",
+            path.display()
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+struct MediaUploadData {
+    id: String,
+}
+
+/// How `upload_media_with` prepares an image.
+#[derive(Clone, Debug)]
+pub struct MediaOptions {
+    /// Scale down and re-encode a JPEG or PNG over X's size or dimension
+    /// limits, instead of having X reject it after the upload.
+    pub resize: bool,
+    /// Remove EXIF (GPS position, camera, date) and other metadata first.
+    pub strip_metadata: bool,
+}
+
+impl Default for MediaOptions {
+    fn default() -> Self {
+        MediaOptions {
+            resize: true,
+            strip_metadata: true,
         }
     }
+}
 
-    Ok(posted_ids)
+/// MIME type of a video file X accepts, by its extension.
+pub fn video_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "mp4" | "m4v" => Some("video/mp4"),
+        "mov" => Some("video/quicktime"),
+        _ => None,
+    }
 }
 
-pub async fn create_thread(config: &Config, chunks: &[String]) -> Result<Vec<String>, ThreadError> {
-    let mut posted_ids: Vec<String> = Vec::new();
+/// MIME type of a media file X accepts, by its extension.
+pub fn media_type(path: &Path) -> Result<&'static str, String> {
+    video_type(path).map_or_else(|| image_type(path), Ok)
+}
 
-    for (i, chunk) in chunks.iter().enumerate() {
-        let reply_to = posted_ids.last().map(|s| s.as_str());
-        match create_tweet(config, chunk, reply_to).await {
-            Ok(id) => posted_ids.push(id),
-            Err(e) => {
-                return Err(ThreadError {
-                    posted_ids,
-                    failed_index: i,
-                    error: e,
-                });
+/// Upload an image or video and return its media ID, for
+/// `create_tweet_with_media`.
+pub async fn upload_media(client: &XClient, path: &Path) -> Result<String, String> {
+    upload_media_with(client, path, &MediaOptions::default()).await
+}
+
+/// `upload_media`, with a say in how the image is prepared.
+pub async fn upload_media_with(
+    client: &XClient,
+    path: &Path,
+    options: &MediaOptions,
+) -> Result<String, String> {
+    if let Some(mime) = video_type(path) {
+        return upload_video(client, path, mime).await;
+    }
+    let mime = image_type(path)?;
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "media".to_string());
+    upload_media_data(client, bytes, mime, &file_name, options).await
+}
+
+/// Upload an image already in memory, of MIME type `mime` (`image/jpeg`,
+/// `image/png`, `image/webp` or `image/gif`). `file_name` is sent along and
+/// names the image in warnings.
+pub async fn upload_media_data(
+    client: &XClient,
+    mut bytes: Vec<u8>,
+    mut mime: &'static str,
+    file_name: &str,
+    options: &MediaOptions,
+) -> Result<String, String> {
+    if mime == "image/gif" {
+        media::check_gif(&bytes)?;
+        // X takes GIFs over the image limit only in chunks.
+        if bytes.len() > media::MAX_BYTES {
+            return upload_chunked(client, &bytes, mime, "tweet_gif", file_name).await;
+        }
+    }
+    let category = if mime == "image/gif" {
+        "tweet_gif"
+    } else {
+        "tweet_image"
+    };
+    let mut file_name = file_name.to_string();
+
+    if options.strip_metadata {
+        match media::strip_metadata(&bytes, mime) {
+            Ok(Some(stripped)) => bytes = stripped,
+            Ok(None) => {}
+            Err(e) => eprintln!(
+                "Warning: could not strip metadata from {file_name}, uploading it as is: {e}"
+            ),
+        }
+    }
+    if options.resize {
+        let original = bytes.clone();
+        let fitted = tokio::task::spawn_blocking(move || media::fit(&original, mime))
+            .await
+            .map_err(|e| format!("Failed to resize {file_name}: {e}"))?;
+        match fitted {
+            Ok(Some(resized)) => {
+                let (width, height) = media::dimensions(&bytes, mime).unwrap_or_default();
+                let how = if resized.width * resized.height < width * height {
+                    format!("scaled down to {}x{}", resized.width, resized.height)
+                } else {
+                    "re-encoded".to_string()
+                };
+                eprintln!(
+                    "Warning: {file_name} is over X's limits ({width}x{height}, {}), uploading it {how} ({})",
+                    media::format_size(bytes.len()),
+                    media::format_size(resized.data.len()),
+                );
+                if resized.mime != mime {
+                    let stem = Path::new(&file_name)
+                        .file_stem()
+                        .map_or("media".into(), |s| s.to_string_lossy().into_owned());
+                    file_name = format!("{stem}.jpg");
+                }
+                mime = resized.mime;
+                bytes = resized.data;
             }
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: could not resize {file_name}, uploading it as is: {e}"),
         }
     }
 
-    Ok(posted_ids)
+    let parts = vec![
+        Part {
+            name: "media_category".to_string(),
+            data: category.as_bytes().to_vec(),
+            file_name: None,
+            mime: None,
+        },
+        Part {
+            name: "media".to_string(),
+            data: bytes,
+            file_name: Some(file_name),
+            mime: Some(mime.to_string()),
+        },
+    ];
+
+    // Multipart bodies are not part of the OAuth signature.
+    let req = Request::new(Method::POST, MEDIA_UPLOAD_URL)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", MEDIA_UPLOAD_URL),
+        )
+        .multipart(parts);
+    let uploaded: DataResponse<MediaUploadData> = parse_json(client.send(req).await?)?;
+    Ok(uploaded.data.id)
+}
+
+/// Largest video X accepts.
+const MAX_VIDEO_BYTES: u64 = 512 * 1024 * 1024;
+/// Bytes sent per append request of a chunked upload.
+const MEDIA_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Serialize)]
+struct InitializeUploadBody<'a> {
+    media_type: &'a str,
+    total_bytes: u64,
+    media_category: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChunkedUpload {
+    id: String,
+    expires_after_secs: Option<i64>,
+    processing_info: Option<ProcessingInfo>,
+}
+
+/// How far X got transcoding an uploaded video.
+#[derive(Deserialize)]
+struct ProcessingInfo {
+    state: String,
+    check_after_secs: Option<u64>,
+    progress_percent: Option<u64>,
+    error: Option<ProcessingError>,
+}
+
+#[derive(Deserialize)]
+struct ProcessingError {
+    #[serde(default)]
+    message: String,
+}
+
+/// Upload a video in chunks and wait until X has processed it, so a tweet
+/// can use it right away. Progress is recorded (see `uploads`): an upload
+/// of the same file that was interrupted carries on where it stopped, and
+/// one that finished is not repeated.
+async fn upload_video(client: &XClient, path: &Path, mime: &str) -> Result<String, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read {}: {e}", path.display());
+    let path = tokio::fs::canonicalize(path).await.map_err(read_error)?;
+    let (total_bytes, modified) = file_version(&path).await.map_err(read_error)?;
+    if total_bytes > MAX_VIDEO_BYTES {
+        return Err(format!(
+            "{} is too large for X ({}, at most 512 MB)",
+            path.display(),
+            media::format_size(total_bytes as usize)
+        ));
+    }
+
+    let state = config::uploads_path();
+    let now = chrono::Utc::now().timestamp();
+    let earlier = uploads::load(&state, now)
+        .into_iter()
+        .find(|u| u.is_of(&path, total_bytes, modified));
+    let mut upload = match earlier {
+        Some(upload) if upload.finished => return Ok(upload.media_id),
+        Some(upload) => {
+            eprintln!(
+                "Resuming the upload of {} ({} of {} parts already sent)...",
+                path.display(),
+                upload.segments.len(),
+                upload.chunk_count()
+            );
+            upload
+        }
+        None => {
+            let init = initialize_upload(client, mime, total_bytes, "tweet_video").await?;
+            let upload = Upload {
+                media_id: init.id,
+                path,
+                media_type: mime.to_string(),
+                total_bytes,
+                modified,
+                chunk_bytes: MEDIA_CHUNK_BYTES,
+                segments: Vec::new(),
+                finished: false,
+                expires_at: now + init.expires_after_secs.unwrap_or(24 * 60 * 60),
+            };
+            remember(&upload);
+            upload
+        }
+    };
+    continue_upload(client, &mut upload).await
+}
+
+/// Finish the interrupted video upload of `media_id` (see `upload_video`)
+/// and return the media ID.
+pub async fn resume_upload(client: &XClient, media_id: &str) -> Result<String, String> {
+    let state = config::uploads_path();
+    let now = chrono::Utc::now().timestamp();
+    let mut upload = uploads::load(&state, now)
+        .into_iter()
+        .find(|u| u.media_id == media_id)
+        .ok_or_else(|| format!("No upload with media ID {media_id} to resume"))?;
+    if upload.finished {
+        return Ok(upload.media_id);
+    }
+    let unchanged = file_version(&upload.path)
+        .await
+        .is_ok_and(|(size, modified)| upload.is_of(&upload.path, size, modified));
+    if !unchanged {
+        let _ = uploads::forget(&state, std::slice::from_ref(&upload.media_id), now);
+        return Err(format!(
+            "{} is gone or changed since its upload started; it has to be uploaded again",
+            upload.path.display()
+        ));
+    }
+    continue_upload(client, &mut upload).await
+}
+
+/// Size and modification time (Unix seconds) of the file at `path`.
+async fn file_version(path: &Path) -> std::io::Result<(u64, i64)> {
+    let metadata = tokio::fs::metadata(path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    Ok((metadata.len(), modified))
+}
+
+/// Record the progress of `upload`. Failing to only costs the ability to
+/// resume, so it is a warning.
+fn remember(upload: &Upload) {
+    let now = chrono::Utc::now().timestamp();
+    if let Err(e) = uploads::record(&config::uploads_path(), upload, now) {
+        eprintln!("Warning: {e}");
+    }
+}
+
+/// Send the chunks of `upload` X does not have yet, then finalize it.
+async fn continue_upload(client: &XClient, upload: &mut Upload) -> Result<String, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read {}: {e}", upload.path.display());
+    let mut file = tokio::fs::File::open(&upload.path)
+        .await
+        .map_err(read_error)?;
+    let name = upload.path.file_name().map_or_else(
+        || upload.path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    let mut bar = progress::Bar::new(&name, upload.total_bytes);
+    let chunk_len = |index: usize| {
+        let offset = (index * upload.chunk_bytes) as u64;
+        (upload.total_bytes - offset).min(upload.chunk_bytes as u64)
+    };
+    let mut sent: u64 = upload.segments.iter().map(|&i| chunk_len(i)).sum();
+    bar.set(sent);
+    for index in upload.missing_segments() {
+        let len = chunk_len(index);
+        let mut chunk = vec![0; len as usize];
+        file.seek(SeekFrom::Start((index * upload.chunk_bytes) as u64))
+            .await
+            .map_err(read_error)?;
+        file.read_exact(&mut chunk).await.map_err(read_error)?;
+        append_chunk(client, &upload.media_id, index, chunk).await?;
+        upload.segments.push(index);
+        remember(upload);
+        sent += len;
+        bar.set(sent);
+    }
+
+    let info = finalize_upload(client, &upload.media_id).await?;
+    if let Err(e) = wait_for_processing(client, &upload.media_id, info, &bar).await {
+        // X will not take more chunks for it; the next attempt starts over.
+        let now = chrono::Utc::now().timestamp();
+        let _ = uploads::forget(
+            &config::uploads_path(),
+            std::slice::from_ref(&upload.media_id),
+            now,
+        );
+        return Err(e);
+    }
+    upload.finished = true;
+    remember(upload);
+    Ok(upload.media_id.clone())
+}
+
+/// The initialize, append and finalize steps of X's chunked upload, for
+/// media too large for a single request (or not allowed in one). `name`
+/// labels the progress bar.
+async fn upload_chunked(
+    client: &XClient,
+    data: &[u8],
+    mime: &str,
+    category: &str,
+    name: &str,
+) -> Result<String, String> {
+    let id = initialize_upload(client, mime, data.len() as u64, category)
+        .await?
+        .id;
+    let mut bar = progress::Bar::new(name, data.len() as u64);
+    let mut sent = 0;
+    for (index, chunk) in data.chunks(MEDIA_CHUNK_BYTES).enumerate() {
+        append_chunk(client, &id, index, chunk.to_vec()).await?;
+        sent += chunk.len() as u64;
+        bar.set(sent);
+    }
+    let info = finalize_upload(client, &id).await?;
+    wait_for_processing(client, &id, info, &bar).await?;
+    Ok(id)
+}
+
+async fn initialize_upload(
+    client: &XClient,
+    mime: &str,
+    total_bytes: u64,
+    category: &str,
+) -> Result<ChunkedUpload, String> {
+    let url = format!("{MEDIA_UPLOAD_URL}/initialize");
+    let body = InitializeUploadBody {
+        media_type: mime,
+        total_bytes,
+        media_category: category,
+    };
+    let req = Request::new(Method::POST, &url)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", &url),
+        )
+        .json(&body);
+    let init: DataResponse<ChunkedUpload> = parse_json(client.send(req).await?)?;
+    Ok(init.data)
+}
+
+async fn append_chunk(
+    client: &XClient,
+    id: &str,
+    index: usize,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    let url = format!("{MEDIA_UPLOAD_URL}/{id}/append");
+    let parts = vec![
+        Part {
+            name: "segment_index".to_string(),
+            data: index.to_string().into_bytes(),
+            file_name: None,
+            mime: None,
+        },
+        Part {
+            name: "media".to_string(),
+            data,
+            file_name: Some("media".to_string()),
+            mime: Some("application/octet-stream".to_string()),
+        },
+    ];
+    let req = Request::new(Method::POST, &url)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", &url),
+        )
+        .multipart(parts);
+    check_status(&client.send(req).await?)
+}
+
+/// Finalize an upload, returning how far X got processing it.
+async fn finalize_upload(client: &XClient, id: &str) -> Result<Option<ProcessingInfo>, String> {
+    let url = format!("{MEDIA_UPLOAD_URL}/{id}/finalize");
+    let req = Request::new(Method::POST, &url).header(
+        "Authorization",
+        build_oauth_header(client.config(), "POST", &url),
+    );
+    let finalized: DataResponse<ChunkedUpload> = parse_json(client.send(req).await?)?;
+    Ok(finalized.data.processing_info)
+}
+
+/// Poll the upload status until X has finished processing the media.
+async fn wait_for_processing(
+    client: &XClient,
+    id: &str,
+    mut info: Option<ProcessingInfo>,
+    bar: &progress::Bar,
+) -> Result<(), String> {
+    while let Some(current) = info {
+        match current.state.as_str() {
+            "succeeded" => return Ok(()),
+            "failed" => {
+                let message = current.error.map(|e| e.message).unwrap_or_default();
+                return Err(format!("X could not process the media: {message}"));
+            }
+            _ => bar.processing(current.progress_percent),
+        }
+        let wait = current.check_after_secs.unwrap_or(5).clamp(1, 60);
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+        let status: DataResponse<ChunkedUpload> = get_json(
+            client,
+            MEDIA_UPLOAD_URL,
+            &[("command", "STATUS"), ("media_id", id)],
+        )
+        .await?;
+        info = status.data.processing_info;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SubtitlesBody<'a> {
+    id: &'a str,
+    media_category: &'a str,
+    subtitles: Subtitles<'a>,
+}
+
+#[derive(Serialize)]
+struct Subtitles<'a> {
+    id: &'a str,
+    language_code: String,
+    display_name: &'a str,
+}
+
+/// Upload an SRT file and associate it with the uploaded video `video_id`
+/// as captions in `language` (an ISO 639-1 code such as `en`).
+pub async fn upload_subtitles(
+    client: &XClient,
+    video_id: &str,
+    path: &Path,
+    language: &str,
+) -> Result<(), String> {
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let name = path
+        .file_name()
+        .map_or_else(|| "subtitles".into(), |n| n.to_string_lossy());
+    let subtitles_id = upload_chunked(client, &data, "text/srt", "subtitles", &name).await?;
+
+    let url = format!("{API_BASE}/media/subtitles");
+    let body = SubtitlesBody {
+        id: video_id,
+        media_category: "TweetVideo",
+        subtitles: Subtitles {
+            id: &subtitles_id,
+            language_code: language.to_ascii_uppercase(),
+            display_name: media::language_name(language),
+        },
+    };
+    let req = Request::new(Method::POST, &url)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", &url),
+        )
+        .json(&body);
+    check_status(&client.send(req).await?)
+}
+
+/// Longest alt text X takes, in characters.
+pub const MAX_ALT_TEXT: usize = 1000;
+
+#[derive(Serialize)]
+struct MetadataBody<'a> {
+    id: &'a str,
+    metadata: Metadata<'a>,
+}
+
+#[derive(Serialize)]
+struct Metadata<'a> {
+    alt_text: AltText<'a>,
+}
+
+#[derive(Serialize)]
+struct AltText<'a> {
+    text: &'a str,
+}
+
+/// Describe the uploaded image `media_id` for people using screen readers.
+pub async fn set_alt_text(client: &XClient, media_id: &str, text: &str) -> Result<(), String> {
+    let url = format!("{API_BASE}/media/metadata");
+    let body = MetadataBody {
+        id: media_id,
+        metadata: Metadata {
+            alt_text: AltText { text },
+        },
+    };
+    let req = Request::new(Method::POST, &url)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", &url),
+        )
+        .json(&body);
+    check_status(&client.send(req).await?)
+}
+
+/// Accounts following `user_id`, at most `max_items` of them.
+pub async fn get_followers(
+    client: &XClient,
+    user_id: &str,
+    max_items: usize,
+) -> Result<Vec<User>, String> {
+    get_all_pages(
+        client,
+        &format!("{API_BASE}/users/{user_id}/followers"),
+        &[("max_results", "1000"), ("user.fields", USER_FIELDS)],
+        max_items,
+    )
+    .await
+}
+
+/// Accounts `user_id` follows, at most `max_items` of them.
+pub async fn get_following(
+    client: &XClient,
+    user_id: &str,
+    max_items: usize,
+) -> Result<Vec<User>, String> {
+    get_following_with(client, user_id, max_items, USER_FIELDS).await
+}
+
+/// Like `get_following`, with each account's `most_recent_tweet_id`.
+pub async fn get_following_activity(
+    client: &XClient,
+    user_id: &str,
+    max_items: usize,
+) -> Result<Vec<User>, String> {
+    get_following_with(client, user_id, max_items, ACTIVITY_USER_FIELDS).await
+}
+
+async fn get_following_with(
+    client: &XClient,
+    user_id: &str,
+    max_items: usize,
+    fields: &str,
+) -> Result<Vec<User>, String> {
+    get_all_pages(
+        client,
+        &format!("{API_BASE}/users/{user_id}/following"),
+        &[("max_results", "1000"), ("user.fields", fields)],
+        max_items,
+    )
+    .await
+}
+
+/// When `user_id` last posted (RFC 3339), from their timeline; `None` if it
+/// is empty. Waits out rate limits, for checking many accounts.
+pub async fn get_last_posted(client: &XClient, user_id: &str) -> Result<Option<String>, String> {
+    let page: Page<Tweet> = get_json_paced(
+        client,
+        &format!("{API_BASE}/users/{user_id}/tweets"),
+        &[("max_results", "5"), ("tweet.fields", "created_at")],
+    )
+    .await?;
+    Ok(page
+        .data
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|t| t.created_at))
+}
+
+/// Every account blocked by `user_id`.
+pub async fn get_blocking(client: &XClient, user_id: &str) -> Result<Vec<User>, String> {
+    get_all_pages(
+        client,
+        &format!("{API_BASE}/users/{user_id}/blocking"),
+        &[("max_results", "1000"), ("user.fields", USER_FIELDS)],
+        usize::MAX,
+    )
+    .await
+}
+
+/// Every account muted by `user_id`.
+pub async fn get_muting(client: &XClient, user_id: &str) -> Result<Vec<User>, String> {
+    get_all_pages(
+        client,
+        &format!("{API_BASE}/users/{user_id}/muting"),
+        &[("max_results", "1000"), ("user.fields", USER_FIELDS)],
+        usize::MAX,
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+struct UsersLookupResponse {
+    #[serde(default)]
+    data: Vec<User>,
+}
+
+/// Look up accounts by handle, 100 per request. Handles that don't exist (or
+/// are suspended) are simply missing from the result.
+pub async fn get_users_by_usernames(
+    client: &XClient,
+    usernames: &[String],
+) -> Result<Vec<User>, String> {
+    let mut users = Vec::new();
+    for batch in usernames.chunks(100) {
+        let names = batch.join(",");
+        let resp: UsersLookupResponse = get_json_paced(
+            client,
+            &format!("{API_BASE}/users/by"),
+            &[("usernames", &names), ("user.fields", USER_FIELDS)],
+        )
+        .await?;
+        users.extend(resp.data);
+    }
+    Ok(users)
+}
+
+/// An account with its full profile, as `xcli user show` prints it.
+#[derive(Deserialize, Serialize)]
+pub struct UserProfile {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// When the account was made (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// `blue`, `business`, `government` or `none`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_type: Option<String>,
+    #[serde(default)]
+    pub protected: bool,
+}
+
+#[derive(Deserialize)]
+struct UserProfileResponse {
+    data: Option<UserProfile>,
+}
+
+/// The profile of `username`, or `None` if there is no such account (or it
+/// is suspended).
+pub async fn get_user_profile(
+    client: &XClient,
+    username: &str,
+) -> Result<Option<UserProfile>, String> {
+    let resp: UserProfileResponse = get_json(
+        client,
+        &format!("{API_BASE}/users/by/username/{username}"),
+        &[("user.fields", PROFILE_USER_FIELDS)],
+    )
+    .await?;
+    Ok(resp.data)
+}
+
+#[derive(Serialize)]
+struct TargetUser<'a> {
+    target_user_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct BlockingData {
+    blocking: bool,
+}
+
+#[derive(Deserialize)]
+struct MutingData {
+    muting: bool,
+}
+
+/// Block `target_id` on behalf of `user_id` (the authenticated user).
+pub async fn block_user(client: &XClient, user_id: &str, target_id: &str) -> Result<bool, String> {
+    let resp: DataResponse<BlockingData> = post_json_paced(
+        client,
+        &format!("{API_BASE}/users/{user_id}/blocking"),
+        &TargetUser {
+            target_user_id: target_id,
+        },
+    )
+    .await?;
+    Ok(resp.data.blocking)
+}
+
+/// Mute `target_id` on behalf of `user_id` (the authenticated user).
+pub async fn mute_user(client: &XClient, user_id: &str, target_id: &str) -> Result<bool, String> {
+    let resp: DataResponse<MutingData> = post_json_paced(
+        client,
+        &format!("{API_BASE}/users/{user_id}/muting"),
+        &TargetUser {
+            target_user_id: target_id,
+        },
+    )
+    .await?;
+    Ok(resp.data.muting)
+}
+
+#[derive(Deserialize)]
+struct FollowData {
+    following: bool,
+    /// The account is protected and has yet to accept.
+    #[serde(default)]
+    pending_follow: bool,
+}
+
+/// Follow `target_id` on behalf of `user_id` (the authenticated user),
+/// waiting out rate limits. Returns whether it is now followed or, for a
+/// protected account, the request is pending.
+pub async fn follow_user(client: &XClient, user_id: &str, target_id: &str) -> Result<bool, String> {
+    let resp: DataResponse<FollowData> = post_json_paced(
+        client,
+        &format!("{API_BASE}/users/{user_id}/following"),
+        &TargetUser {
+            target_user_id: target_id,
+        },
+    )
+    .await?;
+    Ok(resp.data.following || resp.data.pending_follow)
+}
+
+#[derive(Serialize)]
+struct TargetList<'a> {
+    list_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct FollowingData {
+    following: bool,
+}
+
+#[derive(Deserialize)]
+struct PinnedData {
+    pinned: bool,
+}
+
+/// Signed JSON POST of `body` to `url`.
+fn post_request<B: Serialize>(client: &XClient, url: &str, body: &B) -> Request {
+    Request::new(Method::POST, url)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "POST", url),
+        )
+        .json(body)
+}
+
+/// Follow List `list_id` on behalf of `user_id` (the authenticated user).
+/// Returns whether the List is now followed.
+pub async fn follow_list(client: &XClient, user_id: &str, list_id: &str) -> Result<bool, String> {
+    let url = format!("{API_BASE}/users/{user_id}/followed_lists");
+    let req = post_request(client, &url, &TargetList { list_id });
+    let resp: ApiResponse<DataResponse<FollowingData>> = fetch(client, req).await?;
+    Ok(resp.data.data.following)
+}
+
+/// Stop following List `list_id`. Returns whether it is still followed.
+pub async fn unfollow_list(client: &XClient, user_id: &str, list_id: &str) -> Result<bool, String> {
+    let url = format!("{API_BASE}/users/{user_id}/followed_lists/{list_id}");
+    let auth_header = build_oauth_header(client.config(), "DELETE", &url);
+    let req = Request::new(Method::DELETE, &url).header("Authorization", auth_header);
+    let resp: ApiResponse<DataResponse<FollowingData>> = fetch(client, req).await?;
+    Ok(resp.data.data.following)
+}
+
+/// Stop following `target_id` on behalf of `user_id` (the authenticated
+/// user), waiting out rate limits. Returns whether it is still followed.
+pub async fn unfollow_user(
+    client: &XClient,
+    user_id: &str,
+    target_id: &str,
+) -> Result<bool, String> {
+    let url = format!("{API_BASE}/users/{user_id}/following/{target_id}");
+    loop {
+        let auth_header = build_oauth_header(client.config(), "DELETE", &url);
+        let req = Request::new(Method::DELETE, &url).header("Authorization", auth_header);
+        if let Some(resp) = wait_if_rate_limited(client.send(req).await?).await {
+            let resp: DataResponse<FollowingData> = parse_json(resp)?;
+            return Ok(resp.data.following);
+        }
+    }
+}
+
+/// Pin List `list_id` for `user_id`. Returns whether it is now pinned.
+pub async fn pin_list(client: &XClient, user_id: &str, list_id: &str) -> Result<bool, String> {
+    let url = format!("{API_BASE}/users/{user_id}/pinned_lists");
+    let req = post_request(client, &url, &TargetList { list_id });
+    let resp: ApiResponse<DataResponse<PinnedData>> = fetch(client, req).await?;
+    Ok(resp.data.data.pinned)
+}
+
+/// Space fields for `xcli spaces`.
+const SPACE_FIELDS: &str = "title,state,host_ids,created_at,scheduled_start,started_at,ended_at,\
+participant_count,subscriber_count,lang,is_ticketed";
+
+/// A Space ID given either bare or as the Space's URL, e.g.
+/// `https://x.com/i/spaces/1YqKDqWqdPLsV`.
+pub fn parse_space_id(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    let id = match s.split_once("/spaces/") {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next().unwrap_or(""),
+        None => s,
+    };
+    if !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        Ok(id.to_string())
+    } else {
+        Err(format!(
+            "`{s}` is not a Space ID or URL (e.g. 1YqKDqWqdPLsV or https://x.com/i/spaces/1YqKDqWqdPLsV)"
+        ))
+    }
+}
+
+/// An audio Space.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Space {
+    pub id: String,
+    /// `live`, `scheduled` or `ended`.
+    pub state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub host_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<String>,
+    /// People listening, for a live Space.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant_count: Option<u64>,
+    /// People set to be reminded, for a scheduled Space.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscriber_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub is_ticketed: bool,
+}
+
+/// Spaces with their hosts.
+#[derive(Default)]
+pub struct Spaces {
+    pub spaces: Vec<Space>,
+    pub users: Vec<User>,
+}
+
+impl Spaces {
+    /// The hosts of `space` X included.
+    pub fn hosts(&self, space: &Space) -> Vec<&User> {
+        space
+            .host_ids
+            .iter()
+            .filter_map(|id| self.users.iter().find(|u| &u.id == id))
+            .collect()
+    }
+}
+
+/// Which Spaces a search returns.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum SpaceState {
+    All,
+    Live,
+    Scheduled,
+}
+
+#[derive(Deserialize)]
+struct SpacesResponse {
+    #[serde(default)]
+    data: Vec<Space>,
+    #[serde(default)]
+    includes: Includes,
+}
+
+#[derive(Deserialize)]
+struct SpaceResponse {
+    data: Option<Space>,
+    #[serde(default)]
+    includes: Includes,
+}
+
+/// Up to `max_results` (at most 100) Spaces in `state` whose title matches
+/// `query`.
+pub async fn search_spaces(
+    client: &XClient,
+    query: &str,
+    state: SpaceState,
+    max_results: usize,
+) -> Result<Spaces, String> {
+    let state = match state {
+        SpaceState::All => "all",
+        SpaceState::Live => "live",
+        SpaceState::Scheduled => "scheduled",
+    };
+    let max_results = max_results.clamp(1, 100).to_string();
+    let resp: SpacesResponse = get_json(
+        client,
+        &format!("{API_BASE}/spaces/search"),
+        &[
+            ("query", query),
+            ("state", state),
+            ("max_results", &max_results),
+            ("space.fields", SPACE_FIELDS),
+            ("expansions", "host_ids"),
+            ("user.fields", "username,name"),
+        ],
+    )
+    .await?;
+    Ok(Spaces {
+        spaces: resp.data,
+        users: resp.includes.users,
+    })
+}
+
+/// Space `id` with its hosts, or `None` if there is no such Space.
+pub async fn get_space(client: &XClient, id: &str) -> Result<Option<Spaces>, String> {
+    let resp: SpaceResponse = get_json(
+        client,
+        &format!("{API_BASE}/spaces/{id}"),
+        &[
+            ("space.fields", SPACE_FIELDS),
+            ("expansions", "host_ids"),
+            ("user.fields", "username,name"),
+        ],
+    )
+    .await?;
+    Ok(resp.data.map(|space| Spaces {
+        spaces: vec![space],
+        users: resp.includes.users,
+    }))
+}
+
+/// The settings of a List. `None` fields are left as they are when
+/// updating one.
+#[derive(Serialize, Default)]
+pub struct ListSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct CreatedList {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct UpdatedData {
+    updated: bool,
+}
+
+#[derive(Deserialize)]
+struct MemberData {
+    is_member: bool,
+}
+
+#[derive(Serialize)]
+struct MemberUser<'a> {
+    user_id: &'a str,
+}
+
+/// Create a List owned by the authenticated user. Returns its ID.
+pub async fn create_list(client: &XClient, settings: &ListSettings) -> Result<String, String> {
+    let req = post_request(client, &format!("{API_BASE}/lists"), settings);
+    let resp: ApiResponse<DataResponse<CreatedList>> = fetch(client, req).await?;
+    Ok(resp.data.data.id)
+}
+
+/// Change the `Some` settings of List `list_id`. Returns whether X did.
+pub async fn update_list(
+    client: &XClient,
+    list_id: &str,
+    settings: &ListSettings,
+) -> Result<bool, String> {
+    let url = format!("{API_BASE}/lists/{list_id}");
+    let req = Request::new(Method::PUT, &url)
+        .header(
+            "Authorization",
+            build_oauth_header(client.config(), "PUT", &url),
+        )
+        .json(settings);
+    let resp: ApiResponse<DataResponse<UpdatedData>> = fetch(client, req).await?;
+    Ok(resp.data.data.updated)
+}
+
+/// Delete List `list_id`. Returns whether it is gone.
+pub async fn delete_list(client: &XClient, list_id: &str) -> Result<bool, String> {
+    let url = format!("{API_BASE}/lists/{list_id}");
+    let auth_header = build_oauth_header(client.config(), "DELETE", &url);
+    let req = Request::new(Method::DELETE, &url).header("Authorization", auth_header);
+    let resp: ApiResponse<DeleteTweetResponse> = fetch(client, req).await?;
+    Ok(resp.data.data.deleted)
+}
+
+/// Add `user_id` to List `list_id`, waiting out rate limits. Returns
+/// whether they are now a member.
+pub async fn add_list_member(
+    client: &XClient,
+    list_id: &str,
+    user_id: &str,
+) -> Result<bool, String> {
+    let resp: DataResponse<MemberData> = post_json_paced(
+        client,
+        &format!("{API_BASE}/lists/{list_id}/members"),
+        &MemberUser { user_id },
+    )
+    .await?;
+    Ok(resp.data.is_member)
+}
+
+/// Remove `user_id` from List `list_id`, waiting out rate limits. Returns
+/// whether they are still a member.
+pub async fn remove_list_member(
+    client: &XClient,
+    list_id: &str,
+    user_id: &str,
+) -> Result<bool, String> {
+    let url = format!("{API_BASE}/lists/{list_id}/members/{user_id}");
+    loop {
+        let auth_header = build_oauth_header(client.config(), "DELETE", &url);
+        let req = Request::new(Method::DELETE, &url).header("Authorization", auth_header);
+        if let Some(resp) = wait_if_rate_limited(client.send(req).await?).await {
+            let resp: DataResponse<MemberData> = parse_json(resp)?;
+            return Ok(resp.data.is_member);
+        }
+    }
+}
+
+/// A filtered-stream rule. `id` is assigned by X and omitted when exporting.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct StreamRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// Every tweet `user_id` liked that X still lists (the most recent few
+/// thousand).
+pub async fn get_liked_tweets(client: &XClient, user_id: &str) -> Result<Vec<Tweet>, String> {
+    get_all_pages(
+        client,
+        &format!("{API_BASE}/users/{user_id}/liked_tweets"),
+        &[("max_results", "100")],
+        usize::MAX,
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+struct BearerTokenResponse {
+    access_token: String,
+}
+
+#[derive(Serialize)]
+struct AddRulesBody<'a> {
+    add: &'a [StreamRule],
+}
+
+#[derive(Serialize)]
+struct DeleteRulesBody<'a> {
+    delete: RuleIds<'a>,
+}
+
+#[derive(Serialize)]
+struct RuleIds<'a> {
+    ids: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct DeleteRulesResponse {
+    meta: RulesMeta,
+    #[serde(default)]
+    errors: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct RulesMeta {
+    summary: RulesSummary,
+}
+
+#[derive(Deserialize)]
+struct RulesSummary {
+    #[serde(default)]
+    deleted: usize,
+}
+
+#[derive(Deserialize)]
+struct RulesResponse {
+    #[serde(default)]
+    data: Vec<StreamRule>,
+    #[serde(default)]
+    errors: Vec<serde_json::Value>,
+}
+
+/// Exchange the API key and secret for an app-only bearer token, which the
+/// filtered-stream endpoints require.
+pub async fn get_bearer_token(client: &XClient) -> Result<String, String> {
+    let config = client.config();
+    let credentials = STANDARD.encode(format!("{}:{}", config.api_key, config.api_secret));
+    let req = Request::new(Method::POST, OAUTH2_TOKEN_URL)
+        .header("Authorization", format!("Basic {credentials}"))
+        .form("grant_type=client_credentials");
+    let token: BearerTokenResponse = parse_json(client.send(req).await?)?;
+    Ok(token.access_token)
+}
+
+/// A count X sends as a number or, in the usage endpoint, as a string.
+fn count<'de, D: serde::Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Count {
+        Number(u64),
+        Text(String),
+    }
+    match Count::deserialize(d)? {
+        Count::Number(n) => Ok(n),
+        Count::Text(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// How much of the project's monthly cap of posts read has been used.
+#[derive(Deserialize, Serialize)]
+pub struct Usage {
+    #[serde(rename = "project_cap", deserialize_with = "count")]
+    pub cap: u64,
+    #[serde(rename = "project_usage", deserialize_with = "count")]
+    pub used: u64,
+    /// Day of the month the count starts again.
+    #[serde(rename = "cap_reset_day")]
+    pub reset_day: u32,
+    #[serde(rename = "daily_project_usage", default)]
+    pub daily: DailyUsage,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct DailyUsage {
+    #[serde(default)]
+    pub usage: Vec<DayUsage>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DayUsage {
+    /// The day (RFC 3339, midnight UTC).
+    pub date: String,
+    #[serde(deserialize_with = "count")]
+    pub usage: u64,
+}
+
+impl Usage {
+    /// The first day after `today` that the count starts again (the reset
+    /// day, or the month's last day when it is shorter).
+    pub fn next_reset(&self, today: chrono::NaiveDate) -> chrono::NaiveDate {
+        use chrono::{Datelike, Months, NaiveDate};
+        let month = |first: NaiveDate| {
+            let last = (first + Months::new(1)).pred_opt().unwrap_or(first).day();
+            first
+                .with_day(self.reset_day.clamp(1, last))
+                .unwrap_or(first)
+        };
+        let first = today.with_day(1).unwrap_or(today);
+        let this_month = month(first);
+        if this_month > today {
+            this_month
+        } else {
+            month(first + Months::new(1))
+        }
+    }
+}
+
+/// The project's use of its monthly cap, with the posts read on each of
+/// the last `days` days (1 to 90). Needs an app-only `bearer` token.
+pub async fn get_usage(client: &XClient, bearer: &str, days: u32) -> Result<Usage, String> {
+    let days = days.clamp(1, 90).to_string();
+    let url = format!("{API_BASE}/usage/tweets?days={days}&usage.fields=daily_project_usage");
+    let req = Request::new(Method::GET, url).header("Authorization", format!("Bearer {bearer}"));
+    let resp: DataResponse<Usage> = parse_json(client.send(req).await?)?;
+    Ok(resp.data)
+}
+
+/// Current filtered-stream rules of the app.
+pub async fn get_stream_rules(client: &XClient, bearer: &str) -> Result<Vec<StreamRule>, String> {
+    let req = Request::new(Method::GET, STREAM_RULES_URL)
+        .header("Authorization", format!("Bearer {bearer}"));
+    let rules: RulesResponse = parse_json(client.send(req).await?)?;
+    Ok(rules.data)
+}
+
+/// Add filtered-stream rules, returning them with their new IDs. With
+/// `dry_run`, X only validates the rules.
+pub async fn add_stream_rules(
+    client: &XClient,
+    bearer: &str,
+    rules: &[StreamRule],
+    dry_run: bool,
+) -> Result<Vec<StreamRule>, String> {
+    let url = if dry_run {
+        format!("{STREAM_RULES_URL}?dry_run=true")
+    } else {
+        STREAM_RULES_URL.to_string()
+    };
+    let req = Request::new(Method::POST, url)
+        .header("Authorization", format!("Bearer {bearer}"))
+        .json(&AddRulesBody { add: rules });
+    let added: RulesResponse = parse_json(client.send(req).await?)?;
+    if !added.errors.is_empty() {
+        let errors: Vec<String> = added.errors.iter().map(|e| e.to_string()).collect();
+        return Err(format!("X rejected some rules: {}", errors.join("; ")));
+    }
+    Ok(added.data)
+}
+
+/// Delete the filtered-stream rules with these IDs, returning how many went.
+pub async fn delete_stream_rules(
+    client: &XClient,
+    bearer: &str,
+    ids: &[String],
+) -> Result<usize, String> {
+    let req = Request::new(Method::POST, STREAM_RULES_URL)
+        .header("Authorization", format!("Bearer {bearer}"))
+        .json(&DeleteRulesBody {
+            delete: RuleIds { ids },
+        });
+    let deleted: DeleteRulesResponse = parse_json(client.send(req).await?)?;
+    if !deleted.errors.is_empty() {
+        let errors: Vec<String> = deleted.errors.iter().map(|e| e.to_string()).collect();
+        return Err(format!(
+            "X did not delete some rules: {}",
+            errors.join("; ")
+        ));
+    }
+    Ok(deleted.meta.summary.deleted)
+}
+
+/// Read the app's filtered stream until X closes it, passing each tweet to
+/// `each` as the JSON line X sent: the tweet under `data`, its author under
+/// `includes` and the rules it matched under `matching_rules`. Keep-alives
+/// are skipped. An error X sends before closing the stream is returned.
+pub async fn read_stream(
+    client: &XClient,
+    bearer: &str,
+    each: &mut OnLine<'_>,
+) -> Result<(), String> {
+    let query = [
+        ("tweet.fields", TWEET_FIELDS),
+        ("expansions", "author_id"),
+        ("user.fields", USER_FIELDS),
+    ]
+    .iter()
+    .map(|(k, v)| format!("{k}={}", percent_encode(v)))
+    .collect::<Vec<_>>()
+    .join("&");
+    let req = Request::new(Method::GET, format!("{STREAM_URL}?{query}"))
+        .header("Authorization", format!("Bearer {bearer}"));
+    let mut error = None;
+    let resp = client
+        .transport()
+        .send_lines(req, &mut |line| {
+            if line.trim().is_empty() {
+                return;
+            }
+            match serde_json::from_str::<serde_json::Value>(line) {
+                Ok(message) if message.get("data").is_some() => each(line),
+                Ok(message) => error = Some(format!("X closed the stream: {}", message["errors"])),
+                Err(_) => error = Some(format!("Unexpected line on the stream: {line}")),
+            }
+        })
+        .await?;
+    check_status(&resp)?;
+    error.map_or(Ok(()), Err)
+}
+
+/// A thread that failed part-way. `posted_ids` are the tweets that made it.
+#[derive(Debug)]
+pub struct ThreadError {
+    pub posted_ids: Vec<String>,
+    pub failed_index: usize,
+    pub error: String,
+}
+
+pub async fn create_reply_thread(
+    client: &XClient,
+    reply_to_id: &str,
+    chunks: &[String],
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    post_chain(client, Some(reply_to_id), chunks).await
+}
+
+pub async fn create_thread(
+    client: &XClient,
+    chunks: &[String],
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    post_chain(client, None, chunks).await
+}
+
+/// `create_thread` with media already uploaded by `upload_media` on its
+/// first tweet.
+pub async fn create_thread_with_media(
+    client: &XClient,
+    chunks: &[String],
+    media_ids: &[String],
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    create_thread_with(client, chunks, media_ids, &TweetOptions::default()).await
+}
+
+/// `create_thread_with_media`, with a poll or reply settings.
+pub async fn create_thread_with(
+    client: &XClient,
+    chunks: &[String],
+    media_ids: &[String],
+    options: &TweetOptions,
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    post_chain_with_media(client, None, chunks, &[media_ids.to_vec()], options).await
+}
+
+/// `create_thread` with the media of `media[i]` on tweet `i`, such as a
+/// thread of photos.
+pub async fn create_thread_with_tweet_media(
+    client: &XClient,
+    chunks: &[String],
+    media: &[Vec<String>],
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    post_chain_with_media(client, None, chunks, media, &TweetOptions::default()).await
+}
+
+/// Post `chunks` each replying to the previous one, the first replying to
+/// `parent` (or starting a new thread). Also resumes a failed thread from the
+/// last tweet that made it.
+pub async fn post_chain(
+    client: &XClient,
+    parent: Option<&str>,
+    chunks: &[String],
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    post_chain_with_media(client, parent, chunks, &[], &TweetOptions::default()).await
+}
+
+/// `post_chain` with the media of `media[i]` on tweet `i`, and `options`.
+async fn post_chain_with_media(
+    client: &XClient,
+    parent: Option<&str>,
+    chunks: &[String],
+    media: &[Vec<String>],
+    options: &TweetOptions,
+) -> Result<Vec<ApiResponse<String>>, ThreadError> {
+    let mut posted: Vec<ApiResponse<String>> = Vec::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let reply_to = posted.last().map(|r| r.data.as_str()).or(parent);
+        let media_ids = media.get(i).map_or(&[][..], Vec::as_slice);
+        let poll = options.poll.as_ref().filter(|_| i == 0);
+        match post_tweet(
+            client,
+            chunk,
+            reply_to,
+            media_ids,
+            poll,
+            options.reply_settings,
+        )
+        .await
+        {
+            Ok(resp) => posted.push(resp),
+            Err(e) => {
+                return Err(ThreadError {
+                    posted_ids: ids(&posted),
+                    failed_index: i,
+                    error: e,
+                });
+            }
+        }
+    }
+
+    Ok(posted)
+}
+
+/// Tweet IDs of the responses of `create_thread` or `create_reply_thread`.
+pub fn ids(posted: &[ApiResponse<String>]) -> Vec<String> {
+    posted.iter().map(|r| r.data.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::transport::{Body, MockTransport};
+    use std::sync::Arc;
+
+    fn mock_client() -> (XClient, Arc<MockTransport>) {
+        let mock = Arc::new(MockTransport::new());
+        let config = Config {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string(),
+            access_token: "token".to_string(),
+            access_token_secret: "token_secret".to_string(),
+            screen_name: None,
+        };
+        (XClient::with_transport(config, mock.clone()), mock)
+    }
+
+    fn created(id: &str) -> String {
+        format!(r#"{{"data":{{"id":"{id}","text":"..."}}}}"#)
+    }
+
+    #[tokio::test]
+    async fn create_tweet_sends_signed_json() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("42"));
+
+        let resp = create_tweet(&client, "hello", Some("7")).await.unwrap();
+        assert_eq!(resp.data, "42");
+
+        let req = &mock.requests()[0];
+        assert_eq!(req.method, Method::POST);
+        assert_eq!(req.url, TWEETS_URL);
+        assert!(req
+            .header_value("authorization")
+            .unwrap()
+            .starts_with("OAuth "));
+        assert_eq!(
+            req.body_text(),
+            r#"{"text":"hello","reply":{"in_reply_to_tweet_id":"7"}}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn thread_chains_replies() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("1"))
+            .respond(201, &created("2"))
+            .respond(201, &created("3"));
+
+        let chunks = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let posted = create_thread(&client, &chunks).await.unwrap();
+        assert_eq!(ids(&posted), vec!["1", "2", "3"]);
+
+        let bodies: Vec<String> = mock.requests().iter().map(|r| r.body_text()).collect();
+        assert!(!bodies[0].contains("reply"));
+        assert!(bodies[1].contains(r#""in_reply_to_tweet_id":"1""#));
+        assert!(bodies[2].contains(r#""in_reply_to_tweet_id":"2""#));
+    }
+
+    #[tokio::test]
+    async fn thread_media_goes_on_the_first_tweet() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("1")).respond(201, &created("2"));
+
+        let chunks = vec!["a".to_string(), "b".to_string()];
+        create_thread_with_media(&client, &chunks, &["99".to_string()])
+            .await
+            .unwrap();
+
+        let bodies: Vec<String> = mock.requests().iter().map(|r| r.body_text()).collect();
+        assert!(bodies[0].contains(r#""media":{"media_ids":["99"]}"#));
+        assert!(!bodies[1].contains("media"));
+    }
+
+    #[tokio::test]
+    async fn thread_poll_goes_on_the_first_tweet_and_reply_settings_on_all() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("1")).respond(201, &created("2"));
+
+        let poll = Poll::new(vec!["Tabs".to_string(), " Spaces ".to_string()], 1440).unwrap();
+        let options = TweetOptions {
+            poll: Some(poll),
+            reply_settings: ReplySettings::Mentioned,
+        };
+        let chunks = vec!["a".to_string(), "b".to_string()];
+        create_thread_with(&client, &chunks, &[], &options)
+            .await
+            .unwrap();
+
+        let bodies: Vec<String> = mock.requests().iter().map(|r| r.body_text()).collect();
+        assert_eq!(
+            bodies[0],
+            r#"{"text":"a","poll":{"options":["Tabs","Spaces"],"duration_minutes":1440},"reply_settings":"mentionedUsers"}"#
+        );
+        assert!(!bodies[1].contains("poll"));
+        assert!(bodies[1].ends_with(r#""reply_settings":"mentionedUsers"}"#));
+    }
+
+    #[test]
+    fn tweet_ids_are_read_from_urls() {
+        for s in [
+            "20",
+            " 20\n",
+            "https://x.com/jack/status/20",
+            "https://twitter.com/jack/status/20?s=46&t=abc",
+            "x.com/jack/status/20/photo/1",
+            "https://mobile.twitter.com/i/web/status/20",
+        ] {
+            assert_eq!(parse_tweet_id(s).as_deref(), Ok("20"), "{s}");
+        }
+        assert!(parse_tweet_id("https://x.com/jack").is_err());
+        assert!(parse_tweet_id("@jack").is_err());
+        assert!(parse_tweet_id("").is_err());
+    }
+
+    #[test]
+    fn space_ids_are_read_from_urls() {
+        for s in [
+            "1YqKDqWqdPLsV",
+            "https://x.com/i/spaces/1YqKDqWqdPLsV",
+            "https://twitter.com/i/spaces/1YqKDqWqdPLsV/peek?s=20",
+        ] {
+            assert_eq!(parse_space_id(s).as_deref(), Ok("1YqKDqWqdPLsV"), "{s}");
+        }
+        assert!(parse_space_id("https://x.com/i/spaces/").is_err());
+        assert!(parse_space_id("not a space").is_err());
+    }
+
+    #[tokio::test]
+    async fn searches_spaces_with_their_hosts() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":[{"id":"1abc","state":"live","title":"Rust chat","host_ids":["9"],
+                "participant_count":42}],
+                "includes":{"users":[{"id":"9","username":"rustlang","name":"Rust"}]}}"#,
+        )
+        .respond(200, r#"{"meta":{"result_count":0}}"#)
+        .respond(
+            200,
+            r#"{"errors":[{"detail":"Could not find space with id: [2xyz]."}]}"#,
+        );
+
+        let found = search_spaces(&client, "rust", SpaceState::Live, 500)
+            .await
+            .unwrap();
+        assert_eq!(found.spaces[0].participant_count, Some(42));
+        let hosts = found.hosts(&found.spaces[0]);
+        assert_eq!(hosts[0].username, "rustlang");
+        let url = &mock.requests()[0].url;
+        assert!(url.contains("/spaces/search?query=rust&state=live&max_results=100"));
+        assert!(url.contains("expansions=host_ids"));
+
+        assert!(search_spaces(&client, "none", SpaceState::All, 10)
+            .await
+            .unwrap()
+            .spaces
+            .is_empty());
+        assert!(get_space(&client, "2xyz").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn reads_usage_counts_sent_as_strings() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":{"cap_reset_day":31,"project_id":"1","project_cap":"10000",
+                "project_usage":"2500","daily_project_usage":{"project_id":"1",
+                "usage":[{"date":"2024-02-27T00:00:00.000Z","usage":"40"}]}}}"#,
+        );
+
+        let usage = get_usage(&client, "bearer", 7).await.unwrap();
+        assert_eq!((usage.cap, usage.used), (10_000, 2_500));
+        assert_eq!(usage.daily.usage[0].usage, 40);
+        let req = &mock.requests()[0];
+        assert!(req.url.contains("/usage/tweets?days=7"));
+        assert_eq!(req.header_value("authorization"), Some("Bearer bearer"));
+
+        let day = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(usage.next_reset(day("2024-02-27")), day("2024-02-29"));
+        assert_eq!(usage.next_reset(day("2024-02-29")), day("2024-03-31"));
+        let usage = Usage {
+            reset_day: 15,
+            ..usage
+        };
+        assert_eq!(usage.next_reset(day("2024-12-15")), day("2025-01-15"));
+        assert_eq!(usage.next_reset(day("2024-12-14")), day("2024-12-15"));
+    }
+
+    #[tokio::test]
+    async fn deletes_stream_rules_by_id() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"meta":{"sent":"2024-01-01T00:00:00.000Z","summary":{"deleted":2,"not_deleted":0}}}"#,
+        );
+
+        let ids = ["11".to_string(), "12".to_string()];
+        assert_eq!(delete_stream_rules(&client, "t", &ids).await, Ok(2));
+
+        let req = &mock.requests()[0];
+        assert_eq!(req.url, STREAM_RULES_URL);
+        assert_eq!(req.header_value("authorization"), Some("Bearer t"));
+        assert_eq!(req.body_text(), r#"{"delete":{"ids":["11","12"]}}"#);
+    }
+
+    #[test]
+    fn polls_are_checked_against_the_limits() {
+        let options = |list: &[&str]| list.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+        assert!(Poll::new(options(&["Yes", "No"]), 5).is_ok());
+        assert!(Poll::new(options(&["Yes"]), 60).is_err());
+        assert!(Poll::new(options(&["a", "b", "c", "d", "e"]), 60).is_err());
+        assert!(Poll::new(options(&["Yes", " "]), 60).is_err());
+        assert!(Poll::new(options(&["Yes", &"n".repeat(26)]), 60).is_err());
+        assert!(Poll::new(options(&["Yes", "No"]), 4).is_err());
+        assert!(Poll::new(options(&["Yes", "No"]), 10_081).is_err());
+    }
+
+    #[tokio::test]
+    async fn list_timeline_pages_and_keeps_authors() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":[{"id":"3","text":"c","author_id":"9"},{"id":"2","text":"b","author_id":"9"}],
+                "includes":{"users":[{"id":"9","username":"rustlang","name":"Rust"}]},
+                "meta":{"next_token":"p2"}}"#,
+        )
+        .respond(
+            200,
+            r#"{"data":[{"id":"1","text":"a","author_id":"8"}],
+                "includes":{"users":[{"id":"8","username":"jack","name":"jack"}]},
+                "meta":{}}"#,
+        );
+
+        let timeline = get_list_tweets(&client, "77", 5).await.unwrap();
+        let ids: Vec<&str> = timeline.tweets.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ["3", "2", "1"]);
+        let author = timeline.author(&timeline.tweets[2]).unwrap();
+        assert_eq!(author.username, "jack");
+
+        let requests = mock.requests();
+        assert!(requests[0].url.contains("/lists/77/tweets?"));
+        assert!(requests[1].url.contains("pagination_token=p2"));
+    }
+
+    #[tokio::test]
+    async fn timeline_reads_only_the_window() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":[{"id":"12","text":"@me hi","author_id":"9"}],"meta":{}}"#,
+        );
+
+        let window = Window {
+            since_id: Some("10"),
+            end_time: Some("2024-06-01T00:00:00Z"),
+            ..Window::default()
+        };
+        let timeline = get_timeline(&client, "5", TimelineKind::Mentions, &window, 3)
+            .await
+            .unwrap();
+        assert_eq!(timeline.tweets.len(), 1);
+
+        let url = &mock.requests()[0].url;
+        assert!(url.contains("/users/5/mentions?"));
+        assert!(url.contains("max_results=5"));
+        assert!(url.contains("since_id=10"));
+        assert!(url.contains("end_time=2024-06-01T00%3A00%3A00Z"));
+        assert!(!url.contains("until_id"));
+    }
+
+    #[tokio::test]
+    async fn conversation_starts_with_the_tweet() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":{"id":"5","text":"reply","conversation_id":"1","author_id":"9"},
+                "includes":{"users":[{"id":"9","username":"jack","name":"jack"}]}}"#,
+        )
+        .respond(
+            200,
+            r#"{"data":[{"id":"6","text":"b","referenced_tweets":[{"type":"replied_to","id":"5"}]}],
+                "meta":{}}"#,
+        );
+
+        let conversation = get_conversation(&client, "5", false, 100).await.unwrap();
+        assert_eq!(conversation.tweets[0].id, "5");
+        assert_eq!(conversation.tweets[1].in_reply_to(), Some("5"));
+        assert_eq!(conversation.users.len(), 1);
+
+        let search = &mock.requests()[1].url;
+        assert!(search.contains("/tweets/search/recent?"));
+        assert!(search.contains("query=conversation_id%3A1"));
+    }
+
+    #[tokio::test]
+    async fn follows_unfollows_and_pins_lists() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":{"following":true}}"#)
+            .respond(200, r#"{"data":{"following":false}}"#)
+            .respond(200, r#"{"data":{"pinned":true}}"#);
+
+        assert!(follow_list(&client, "5", "77").await.unwrap());
+        assert!(!unfollow_list(&client, "5", "77").await.unwrap());
+        assert!(pin_list(&client, "5", "77").await.unwrap());
+
+        let requests = mock.requests();
+        assert_eq!(
+            requests[0].url,
+            format!("{API_BASE}/users/5/followed_lists")
+        );
+        assert_eq!(requests[0].body_text(), r#"{"list_id":"77"}"#);
+        assert_eq!(requests[1].method, Method::DELETE);
+        assert_eq!(
+            requests[1].url,
+            format!("{API_BASE}/users/5/followed_lists/77")
+        );
+        assert_eq!(requests[2].url, format!("{API_BASE}/users/5/pinned_lists"));
+    }
+
+    #[tokio::test]
+    async fn whoami_reads_the_access_level() {
+        let (client, mock) = mock_client();
+        mock.push(Ok(Response::new(
+            200,
+            r#"{"data":{"id":"5","username":"me","name":"Me"}}"#,
+        )
+        .with_header("x-access-level", "read-write")))
+            .respond(401, r#"{"title":"Unauthorized"}"#);
+
+        let identity = whoami(&client).await.unwrap();
+        assert_eq!(identity.user.username, "me");
+        assert_eq!(identity.access_level.as_deref(), Some("read-write"));
+        let rejected = whoami(&client).await.err().unwrap();
+        assert!(rejected.contains("401"));
+
+        assert_eq!(access_scopes("read"), ["read"]);
+        assert_eq!(
+            access_scopes("read-write-directmessages"),
+            ["read", "write", "direct messages"]
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_a_profile_by_handle() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":{"id":"9","username":"rustlang","name":"Rust","location":"Internet",
+                "created_at":"2012-11-13T09:00:00.000Z","verified_type":"business",
+                "public_metrics":{"followers_count":5,"following_count":1}}}"#,
+        )
+        .respond(
+            200,
+            r#"{"errors":[{"detail":"Could not find user with username: [nobody]."}]}"#,
+        );
+
+        let profile = get_user_profile(&client, "rustlang")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(profile.user.id, "9");
+        assert_eq!(profile.location.as_deref(), Some("Internet"));
+        assert_eq!(profile.verified_type.as_deref(), Some("business"));
+        assert!(!profile.protected);
+        assert!(get_user_profile(&client, "nobody").await.unwrap().is_none());
+        assert!(mock.requests()[0]
+            .url
+            .starts_with(&format!("{API_BASE}/users/by/username/rustlang?")));
+    }
+
+    #[tokio::test]
+    async fn follows_users() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":{"following":false,"pending_follow":true}}"#);
+
+        assert!(follow_user(&client, "5", "9").await.unwrap());
+        let requests = mock.requests();
+        assert_eq!(requests[0].url, format!("{API_BASE}/users/5/following"));
+        assert_eq!(requests[0].body_text(), r#"{"target_user_id":"9"}"#);
+    }
+
+    #[tokio::test]
+    async fn manages_lists_and_their_members() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":{"id":"77","name":"Rust"}}"#)
+            .respond(200, r#"{"data":{"updated":true}}"#)
+            .respond(200, r#"{"data":{"is_member":true}}"#)
+            .respond(200, r#"{"data":{"is_member":false}}"#)
+            .respond(200, r#"{"data":{"deleted":true}}"#);
+
+        let settings = ListSettings {
+            name: Some("Rust".to_string()),
+            private: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(create_list(&client, &settings).await.unwrap(), "77");
+        let rename = ListSettings {
+            description: Some("Crates".to_string()),
+            ..Default::default()
+        };
+        assert!(update_list(&client, "77", &rename).await.unwrap());
+        assert!(add_list_member(&client, "77", "9").await.unwrap());
+        assert!(!remove_list_member(&client, "77", "9").await.unwrap());
+        assert!(delete_list(&client, "77").await.unwrap());
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].url, format!("{API_BASE}/lists"));
+        assert_eq!(requests[0].body_text(), r#"{"name":"Rust","private":true}"#);
+        assert_eq!(requests[1].method, Method::PUT);
+        assert_eq!(requests[1].body_text(), r#"{"description":"Crates"}"#);
+        assert_eq!(requests[2].url, format!("{API_BASE}/lists/77/members"));
+        assert_eq!(requests[2].body_text(), r#"{"user_id":"9"}"#);
+        assert_eq!(requests[3].method, Method::DELETE);
+        assert_eq!(requests[3].url, format!("{API_BASE}/lists/77/members/9"));
+        assert_eq!(requests[4].method, Method::DELETE);
+        assert_eq!(requests[4].url, format!("{API_BASE}/lists/77"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unfollows_after_rate_limit() {
+        let (client, mock) = mock_client();
+        mock.push(Ok(
+            Response::new(429, "").with_header("x-rate-limit-reset", "0")
+        ))
+        .respond(200, r#"{"data":{"following":false}}"#);
+
+        assert!(!unfollow_user(&client, "5", "9").await.unwrap());
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].method, Method::DELETE);
+        assert_eq!(requests[1].url, format!("{API_BASE}/users/5/following/9"));
+    }
+
+    #[tokio::test]
+    async fn each_tweet_can_have_its_own_media() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("1")).respond(201, &created("2"));
+
+        let chunks = vec!["Day one".to_string(), String::new()];
+        let media = vec![
+            vec!["7".to_string(), "8".to_string()],
+            vec!["9".to_string()],
+        ];
+        create_thread_with_tweet_media(&client, &chunks, &media)
+            .await
+            .unwrap();
+
+        let bodies: Vec<String> = mock.requests().iter().map(|r| r.body_text()).collect();
+        assert!(bodies[0].contains(r#""media":{"media_ids":["7","8"]}"#));
+        assert!(bodies[1].contains(r#""media":{"media_ids":["9"]}"#));
+        assert!(!bodies[1].contains("text"));
+    }
+
+    #[tokio::test]
+    async fn chain_continues_from_parent() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("5")).respond(201, &created("6"));
+
+        let chunks = vec!["b".to_string(), "c".to_string()];
+        let posted = post_chain(&client, Some("4"), &chunks).await.unwrap();
+        assert_eq!(ids(&posted), vec!["5", "6"]);
+
+        let bodies: Vec<String> = mock.requests().iter().map(|r| r.body_text()).collect();
+        assert!(bodies[0].contains(r#""in_reply_to_tweet_id":"4""#));
+        assert!(bodies[1].contains(r#""in_reply_to_tweet_id":"5""#));
+    }
+
+    #[tokio::test]
+    async fn thread_failure_reports_posted_ids() {
+        let (client, mock) = mock_client();
+        mock.respond(201, &created("1"))
+            .respond(403, r#"{"detail":"duplicate content"}"#);
+
+        let chunks = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let err = create_thread(&client, &chunks).await.unwrap_err();
+        assert_eq!(err.posted_ids, vec!["1"]);
+        assert_eq!(err.failed_index, 1);
+        assert_eq!(
+            err.error,
+            r#"API error (403 Forbidden): {"detail":"duplicate content"}"#
+        );
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn responses_carry_metadata() {
+        let (client, mock) = mock_client();
+        mock.push(Ok(Response::new(200, r#"{"data":{"deleted":true}}"#)
+            .with_header("x-transaction-id", "abc123")
+            .with_header("x-rate-limit-limit", "50")
+            .with_header("x-rate-limit-remaining", "49")
+            .with_header("x-rate-limit-reset", "1700000000")))
+            .push(Ok(
+                Response::new(403, "forbidden").with_header("x-transaction-id", "def456")
+            ));
+
+        let resp = delete_tweet(&client, "1").await.unwrap();
+        assert!(resp.data);
+        assert_eq!(resp.meta.transaction_id.as_deref(), Some("abc123"));
+        assert_eq!(
+            resp.meta.rate_limit,
+            Some(RateLimit {
+                limit: 50,
+                remaining: 49,
+                reset: 1700000000
+            })
+        );
+
+        let err = delete_tweet(&client, "1").await.unwrap_err();
+        assert!(err.ends_with("(transaction ID def456)"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn transport_errors_pass_through() {
+        let (client, mock) = mock_client();
+        mock.push(Err("Request failed: connection refused".to_string()));
+        assert_eq!(
+            delete_tweet(&client, "1").await.unwrap_err(),
+            "Request failed: connection refused"
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_success_body_is_an_error() {
+        let (client, mock) = mock_client();
+        mock.respond(200, "not json");
+        let err = delete_tweet(&client, "1").await.unwrap_err();
+        assert!(err.starts_with("Failed to parse response"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paced_reads_retry_after_rate_limit() {
+        let (client, mock) = mock_client();
+        mock.push(Ok(
+            Response::new(429, "").with_header("x-rate-limit-reset", "0")
+        ))
+        .respond(
+            200,
+            r#"{"data":[{"id":"9","username":"bob","name":"Bob"}]}"#,
+        );
+
+        let users = get_users_by_usernames(&client, &["bob".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(users[0].id, "9");
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn chunked_upload_waits_for_processing() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":{"id":"77"}}"#)
+            .respond(200, r#"{"data":{}}"#)
+            .respond(200, r#"{"data":{}}"#)
+            .respond(
+                200,
+                r#"{"data":{"id":"77","processing_info":{"state":"pending","check_after_secs":2}}}"#,
+            )
+            .respond(
+                200,
+                r#"{"data":{"id":"77","processing_info":{"state":"succeeded"}}}"#,
+            );
+
+        let data = vec![0u8; MEDIA_CHUNK_BYTES + 10];
+        let id = upload_chunked(&client, &data, "video/mp4", "tweet_video", "clip.mp4")
+            .await
+            .unwrap();
+        assert_eq!(id, "77");
+
+        let requests = mock.requests();
+        assert!(requests[0].body_text().contains(r#""total_bytes":4194314"#));
+        assert!(requests[1].url.ends_with("/2/media/upload/77/append"));
+        let Body::Multipart(parts) = &requests[2].body else {
+            panic!("append is not multipart");
+        };
+        assert_eq!(parts[0].data, b"1");
+        assert_eq!(parts[1].data.len(), 10);
+        assert!(requests[3].url.ends_with("/77/finalize"));
+        assert!(requests[4].url.contains("command=STATUS&media_id=77"));
+    }
+
+    #[tokio::test]
+    async fn heavy_gifs_are_uploaded_in_chunks() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":{"id":"8"}}"#)
+            .respond(200, r#"{"data":{}}"#)
+            .respond(200, r#"{"data":{}}"#)
+            .respond(200, r#"{"data":{"id":"8"}}"#);
+
+        let mut gif = b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x3b".to_vec();
+        gif.resize(media::MAX_BYTES + 1, 0);
+        let options = MediaOptions::default();
+        let id = upload_media_data(&client, gif, "image/gif", "big.gif", &options)
+            .await
+            .unwrap();
+        assert_eq!(id, "8");
+        let requests = mock.requests();
+        assert!(requests[0].url.ends_with("/2/media/upload/initialize"));
+        assert!(requests[0]
+            .body_text()
+            .contains(r#""media_category":"tweet_gif""#));
+
+        let too_tall = b"GIF89a\x01\x00\x00\x10\x00\x00\x00\x3b".to_vec();
+        let err = upload_media_data(&client, too_tall, "image/gif", "tall.gif", &options)
+            .await
+            .unwrap_err();
+        assert!(err.contains("1280x1080"), "{err}");
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn failed_processing_is_an_error() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":{"id":"5"}}"#)
+            .respond(200, r#"{"data":{}}"#)
+            .respond(
+                200,
+                r#"{"data":{"id":"5","processing_info":{"state":"failed","error":{"message":"Unsupported codec"}}}}"#,
+            );
+        let err = upload_chunked(&client, b"x", "video/mp4", "tweet_video", "clip.mp4")
+            .await
+            .unwrap_err();
+        assert!(err.ends_with("Unsupported codec"), "{err}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pages_follow_next_token() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":[{"id":"1","text":"a"}],"meta":{"next_token":"abc"}}"#,
+        )
+        .respond(200, r#"{"data":[{"id":"2","text":"b"}],"meta":{}}"#);
+
+        let tweets = search_recent(&client, "rust", 50).await.unwrap();
+        let ids: Vec<_> = tweets.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+
+        let second = &mock.requests()[1].url;
+        assert!(second.contains("next_token=abc"));
+        assert!(second.contains("query=rust"));
+    }
+
+    #[tokio::test]
+    async fn search_pages_arrive_one_by_one() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            r#"{"data":[{"id":"1","text":"a"},{"id":"2","text":"b"}],"meta":{"next_token":"abc"}}"#,
+        )
+        .respond(
+            200,
+            r#"{"data":[{"id":"3","text":"c"},{"id":"4","text":"d"}],"meta":{"next_token":"def"}}"#,
+        );
+
+        let search = Search {
+            query: "rust",
+            ..Search::default()
+        };
+        let mut pages = Vec::new();
+        search_pages(&client, &search, 3, |page, next| {
+            pages.push((page.len(), next.map(str::to_string)))
+        })
+        .await
+        .unwrap();
+        // The limit cuts the second page short and stops paging.
+        assert_eq!(
+            pages,
+            vec![(2, Some("abc".to_string())), (1, Some("def".to_string()))]
+        );
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn full_archive_search_continues_from_a_token() {
+        let (client, mock) = mock_client();
+        mock.respond(200, r#"{"data":[{"id":"1","text":"a"}],"meta":{}}"#);
+
+        let search = Search {
+            query: "rust",
+            all: true,
+            start_time: Some("2010-01-01T00:00:00Z"),
+            end_time: None,
+            next_token: Some("saved"),
+            since_id: Some("1790000000000000000"),
+        };
+        search_pages(&client, &search, 1000, |_, next| assert!(next.is_none()))
+            .await
+            .unwrap();
+
+        let url = &mock.requests()[0].url;
+        assert!(url.contains("/tweets/search/all?"));
+        assert!(url.contains("max_results=500"));
+        assert!(url.contains("start_time=2010-01-01T00%3A00%3A00Z"));
+        assert!(url.contains("next_token=saved"));
+        assert!(url.contains("since_id=1790000000000000000"));
+    }
+
+    #[tokio::test]
+    async fn reads_the_stream_line_by_line() {
+        let (client, mock) = mock_client();
+        mock.respond(
+            200,
+            "{\"data\":{\"id\":\"1\",\"text\":\"a\"}}\r\n\r\n{\"data\":{\"id\":\"2\",\"text\":\"b\"}}\r\n",
+        );
+        let mut lines = Vec::new();
+        read_stream(&client, "bearer", &mut |l| lines.push(l.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("\"id\":\"2\""));
+        let req = &mock.requests()[0];
+        assert_eq!(req.header_value("authorization"), Some("Bearer bearer"));
+        assert!(req.url.contains("expansions=author_id"));
+
+        mock.respond(200, r#"{"errors":[{"title":"operational-disconnect"}]}"#);
+        let err = read_stream(&client, "bearer", &mut |_| {})
+            .await
+            .unwrap_err();
+        assert!(err.contains("operational-disconnect"), "{err}");
+        mock.respond(429, "Too Many Requests");
+        assert!(read_stream(&client, "bearer", &mut |_| {}).await.is_err());
+    }
+
+    #[test]
+    fn rate_limit_wait_uses_reset_header() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let resp = Response::new(429, "").with_header("x-rate-limit-reset", (now + 30).to_string());
+        let wait = rate_limit_wait(&resp);
+        assert!((30..=32).contains(&wait));
+        assert_eq!(rate_limit_wait(&Response::new(429, "")), 60);
+    }
 }
@@ -0,0 +1,271 @@
+//! Application log (`log = true` in `config.toml`): one JSON object per line
+//! in `~/.local/state/xcli/xcli.log`, recording command invocations, retries
+//! and errors so problems of `xcli serve` or the queue runner can be looked
+//! into after the fact.
+//!
+//! Unlike a `--trace-file`, the log holds no requests, only what went wrong
+//! with them. It is rotated by size, keeping a few older files next to it
+//! (`xcli.log.1` is the most recent). Logging never fails a command.
+
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::cassette::redact;
+use crate::transport::{OnLine, Request, Response, StatusCode, Transport};
+
+/// Rotate once the log reaches this size.
+const MAX_SIZE: u64 = 1024 * 1024;
+/// Rotated files kept besides the current one.
+const KEEP: usize = 3;
+/// Error bodies are cut to this many characters.
+const MAX_DETAIL: usize = 300;
+
+static LOG: OnceLock<Log> = OnceLock::new();
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    time: String,
+    level: Level,
+    pid: u32,
+    message: &'a str,
+}
+
+pub struct Log {
+    path: PathBuf,
+    max_size: u64,
+    /// Serializes writes (and rotation) within this process.
+    lock: Mutex<()>,
+}
+
+impl Log {
+    pub fn new(path: PathBuf, max_size: u64) -> Self {
+        Log {
+            path,
+            max_size,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn write(&self, level: Level, message: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        }
+        if fs::metadata(&self.path).is_ok_and(|m| m.len() >= self.max_size) {
+            rotate(&self.path, KEEP);
+        }
+        let entry = Entry {
+            time: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            level,
+            pid: std::process::id(),
+            message,
+        };
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize log entry: {e}"))?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write {}: {e}", self.path.display()))
+    }
+}
+
+/// `xcli.log` → `xcli.log.<n>`.
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Shift `path.1` to `path.2` and so on, dropping the oldest, then move the
+/// current file to `path.1`.
+fn rotate(path: &Path, keep: usize) {
+    let _ = fs::remove_file(rotated(path, keep));
+    for n in (1..keep).rev() {
+        let _ = fs::rename(rotated(path, n), rotated(path, n + 1));
+    }
+    let _ = fs::rename(path, rotated(path, 1));
+}
+
+/// Start logging to `path` for the rest of this process.
+pub fn init(path: PathBuf) {
+    let _ = LOG.set(Log::new(path, MAX_SIZE));
+}
+
+pub fn enabled() -> bool {
+    LOG.get().is_some()
+}
+
+fn log(level: Level, message: &str) {
+    if let Some(log) = LOG.get() {
+        let _ = log.write(level, message);
+    }
+}
+
+pub fn info(message: &str) {
+    log(Level::Info, message);
+}
+
+pub fn warn(message: &str) {
+    log(Level::Warn, message);
+}
+
+pub fn error(message: &str) {
+    log(Level::Error, message);
+}
+
+/// Describe a failed exchange, or `None` if it succeeded.
+fn describe_failure(req: &Request, result: &Result<Response, String>) -> Option<(Level, String)> {
+    // Query strings can be long and are rarely what went wrong.
+    let endpoint = req.url.split('?').next().unwrap_or_default();
+    let what = format!("{} {endpoint}", req.method);
+    match result {
+        Err(e) => Some((Level::Error, format!("{what} failed: {e}"))),
+        Ok(resp) if resp.status.is_success() => None,
+        Ok(resp) => {
+            let level = if resp.status == StatusCode::TOO_MANY_REQUESTS {
+                Level::Warn
+            } else {
+                Level::Error
+            };
+            let transaction = resp
+                .header("x-transaction-id")
+                .map(|id| format!(" (transaction ID {id})"))
+                .unwrap_or_default();
+            let detail: String = redact(&resp.text()).chars().take(MAX_DETAIL).collect();
+            Some((
+                level,
+                format!("{what} returned {}{transaction}: {detail}", resp.status),
+            ))
+        }
+    }
+}
+
+/// Wraps a transport and logs every exchange that fails.
+pub struct LoggingTransport {
+    inner: Arc<dyn Transport>,
+}
+
+impl LoggingTransport {
+    pub fn new(inner: Arc<dyn Transport>) -> Self {
+        LoggingTransport { inner }
+    }
+}
+
+#[async_trait]
+impl Transport for LoggingTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let sent = req.clone();
+        let result = self.inner.send(req).await;
+        if let Some((level, message)) = describe_failure(&sent, &result) {
+            log(level, &message);
+        }
+        result
+    }
+
+    async fn send_lines(&self, req: Request, line: &mut OnLine<'_>) -> Result<Response, String> {
+        let sent = req.clone();
+        let result = self.inner.send_lines(req, line).await;
+        if let Some((level, message)) = describe_failure(&sent, &result) {
+            log(level, &message);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Method;
+    use std::env::temp_dir;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let dir = temp_dir().join(format!("xcli_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("state").join("xcli.log")
+    }
+
+    fn lines(path: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let path = temp_log("applog");
+        let log = Log::new(path.clone(), MAX_SIZE);
+        log.write(Level::Info, "xcli tweet hi").unwrap();
+        log.write(Level::Error, "boom").unwrap();
+
+        let entries = lines(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["level"], "info");
+        assert_eq!(entries[0]["message"], "xcli tweet hi");
+        assert_eq!(entries[1]["level"], "error");
+        assert_eq!(entries[1]["pid"], std::process::id());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn rotates_by_size() {
+        let path = temp_log("applog_rotate");
+        let log = Log::new(path.clone(), 10);
+        for n in 0..6 {
+            log.write(Level::Info, &format!("entry {n}")).unwrap();
+        }
+
+        assert_eq!(lines(&path)[0]["message"], "entry 5");
+        assert_eq!(lines(&rotated(&path, 1))[0]["message"], "entry 4");
+        assert_eq!(lines(&rotated(&path, KEEP))[0]["message"], "entry 2");
+        assert!(!rotated(&path, KEEP + 1).exists());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn describes_failed_exchanges() {
+        let req = Request::new(
+            Method::GET,
+            "https://api.x.com/2/tweets/search/recent?query=x",
+        );
+        assert_eq!(describe_failure(&req, &Ok(Response::new(200, "{}"))), None);
+
+        let resp = Response::new(503, r#"{"title":"Service Unavailable"}"#)
+            .with_header("x-transaction-id", "abc");
+        let (level, message) = describe_failure(&req, &Ok(resp)).unwrap();
+        assert_eq!(level, Level::Error);
+        assert_eq!(
+            message,
+            "GET https://api.x.com/2/tweets/search/recent returned 503 Service Unavailable \
+             (transaction ID abc): {\"title\":\"Service Unavailable\"}"
+        );
+
+        let (level, _) = describe_failure(&req, &Ok(Response::new(429, ""))).unwrap();
+        assert_eq!(level, Level::Warn);
+
+        let (level, message) = describe_failure(&req, &Err("timed out".to_string())).unwrap();
+        assert_eq!(level, Level::Error);
+        assert!(message.ends_with("failed: timed out"), "{message}");
+    }
+}
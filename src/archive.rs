@@ -0,0 +1,219 @@
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// A tweet from the official X account archive.
+pub struct ArchiveTweet {
+    pub id: String,
+    pub text: String,
+    pub created_at: i64,
+    pub in_reply_to: Option<String>,
+}
+
+pub struct Archive {
+    /// Username from `account.js`, when the full archive was given.
+    pub account: Option<String>,
+    pub tweets: Vec<ArchiveTweet>,
+}
+
+#[derive(Deserialize)]
+struct TweetWrapper {
+    tweet: RawTweet,
+}
+
+#[derive(Deserialize)]
+struct RawTweet {
+    id_str: String,
+    full_text: String,
+    created_at: String,
+    in_reply_to_status_id_str: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AccountWrapper {
+    account: RawAccount,
+}
+
+#[derive(Deserialize)]
+struct RawAccount {
+    username: String,
+}
+
+/// Read an archive `.zip` (as downloaded from X) or a bare `tweets.js` file.
+pub fn read_archive(path: &Path) -> Result<Archive, String> {
+    let is_zip = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    if !is_zip {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        return Ok(Archive {
+            account: None,
+            tweets: parse_tweets_js(&content)?,
+        });
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+    let mut account = None;
+    let mut tweets = Vec::new();
+    let mut found = false;
+    for i in 0..zip.len() {
+        let mut entry = zip
+            .by_index(i)
+            .map_err(|e| format!("Failed to read archive entry: {e}"))?;
+        let name = entry.name().to_string();
+        let is_tweets = is_tweets_file(&name);
+        if !is_tweets && !name.ends_with("data/account.js") {
+            continue;
+        }
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Failed to read {name} from archive: {e}"))?;
+        if is_tweets {
+            found = true;
+            tweets.extend(parse_tweets_js(&content)?);
+        } else {
+            account = parse_account_js(&content);
+        }
+    }
+
+    if !found {
+        return Err(format!(
+            "No data/tweets.js found in {}. Is this an X account archive?",
+            path.display()
+        ));
+    }
+    Ok(Archive { account, tweets })
+}
+
+/// `data/tweets.js`, `data/tweets-part1.js`, or the older singular `data/tweet.js`.
+fn is_tweets_file(name: &str) -> bool {
+    let Some(file) = name
+        .strip_prefix("data/")
+        .or_else(|| name.rsplit_once("/data/").map(|(_, file)| file))
+    else {
+        return false;
+    };
+    let stem = file.strip_suffix(".js").unwrap_or("");
+    stem == "tweets"
+        || stem == "tweet"
+        || stem.starts_with("tweets-part")
+        || stem.starts_with("tweet-part")
+}
+
+/// Archive files are JavaScript: `window.YTD.tweets.part0 = [ ... ]`.
+fn strip_js_assignment(content: &str) -> &str {
+    match content.find('=') {
+        Some(pos) if content[..pos].trim_start().starts_with("window.") => &content[pos + 1..],
+        _ => content,
+    }
+}
+
+pub fn parse_tweets_js(content: &str) -> Result<Vec<ArchiveTweet>, String> {
+    let wrappers: Vec<TweetWrapper> = serde_json::from_str(strip_js_assignment(content))
+        .map_err(|e| format!("Failed to parse tweets.js: {e}"))?;
+
+    wrappers
+        .into_iter()
+        .map(|w| {
+            let created_at =
+                chrono::DateTime::parse_from_str(&w.tweet.created_at, "%a %b %d %H:%M:%S %z %Y")
+                    .map_err(|e| {
+                        format!(
+                            "Invalid created_at '{}' for tweet {}: {e}",
+                            w.tweet.created_at, w.tweet.id_str
+                        )
+                    })?
+                    .timestamp();
+            Ok(ArchiveTweet {
+                id: w.tweet.id_str,
+                text: unescape_html(&w.tweet.full_text),
+                created_at,
+                in_reply_to: w.tweet.in_reply_to_status_id_str,
+            })
+        })
+        .collect()
+}
+
+fn parse_account_js(content: &str) -> Option<String> {
+    let accounts: Vec<AccountWrapper> = serde_json::from_str(strip_js_assignment(content)).ok()?;
+    accounts.into_iter().next().map(|a| a.account.username)
+}
+
+/// The archive stores tweet text with `&`, `<` and `>` HTML-escaped.
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWEETS_JS: &str = r#"window.YTD.tweets.part0 = [
+  {
+    "tweet" : {
+      "id_str" : "1050118621198921728",
+      "full_text" : "Fish &amp; chips &lt;3",
+      "created_at" : "Wed Oct 10 20:19:24 +0000 2018",
+      "in_reply_to_status_id_str" : "1050118600000000000",
+      "favorite_count" : "3"
+    }
+  },
+  {
+    "tweet" : {
+      "id_str" : "2",
+      "full_text" : "hello",
+      "created_at" : "Thu Oct 11 00:00:00 +0000 2018"
+    }
+  }
+]"#;
+
+    #[test]
+    fn parses_tweets_js() {
+        let tweets = parse_tweets_js(TWEETS_JS).unwrap();
+        assert_eq!(tweets.len(), 2);
+        assert_eq!(tweets[0].id, "1050118621198921728");
+        assert_eq!(tweets[0].text, "Fish & chips <3");
+        assert_eq!(tweets[0].created_at, 1539202764);
+        assert_eq!(
+            tweets[0].in_reply_to.as_deref(),
+            Some("1050118600000000000")
+        );
+        assert!(tweets[1].in_reply_to.is_none());
+    }
+
+    #[test]
+    fn parses_plain_json_array() {
+        let json = TWEETS_JS.split_once('=').unwrap().1;
+        assert_eq!(parse_tweets_js(json).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_content() {
+        assert!(parse_tweets_js("window.YTD.tweets.part0 = {").is_err());
+    }
+
+    #[test]
+    fn parses_account_js() {
+        let js = r#"window.YTD.account.part0 = [ { "account" : { "username" : "jack", "accountId" : "12" } } ]"#;
+        assert_eq!(parse_account_js(js).as_deref(), Some("jack"));
+    }
+
+    #[test]
+    fn recognizes_tweet_files() {
+        assert!(is_tweets_file("data/tweets.js"));
+        assert!(is_tweets_file("data/tweet.js"));
+        assert!(is_tweets_file("data/tweets-part1.js"));
+        assert!(is_tweets_file("twitter-2024/data/tweets.js"));
+        assert!(!is_tweets_file("data/tweets-media/abc.jpg"));
+        assert!(!is_tweets_file("data/tweetdeck.js"));
+        assert!(!is_tweets_file("data/account.js"));
+    }
+}
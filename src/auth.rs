@@ -1,3 +1,5 @@
+//! OAuth 1.0a request signing (HMAC-SHA1).
+
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use hmac::{Hmac, Mac};
@@ -19,26 +21,60 @@ pub fn percent_encode(input: &str) -> String {
     utf8_percent_encode(input, ENCODE_SET).to_string()
 }
 
-fn generate_nonce() -> String {
-    let mut rng = rand::thread_rng();
-    (0..32)
-        .map(|_| {
-            let idx = rng.gen_range(0..36);
-            if idx < 10 {
-                (b'0' + idx) as char
-            } else {
-                (b'a' + idx - 10) as char
-            }
-        })
-        .collect()
+/// Source of `oauth_timestamp` values.
+pub trait Clock: Send + Sync {
+    /// Seconds since the unix epoch.
+    fn timestamp(&self) -> u64;
+}
+
+/// Source of `oauth_nonce` values, which must not repeat.
+pub trait NonceSource: Send + Sync {
+    fn nonce(&self) -> String;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn timestamp(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+}
+
+/// 32 random lowercase alphanumerics.
+pub struct RandomNonce;
+
+impl NonceSource for RandomNonce {
+    fn nonce(&self) -> String {
+        let mut rng = rand::thread_rng();
+        (0..32)
+            .map(|_| {
+                let idx = rng.gen_range(0..36);
+                if idx < 10 {
+                    (b'0' + idx) as char
+                } else {
+                    (b'a' + idx - 10) as char
+                }
+            })
+            .collect()
+    }
 }
 
-fn generate_timestamp() -> String {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        .to_string()
+/// Signs requests with the nonce and timestamp of its sources. Only tests
+/// need anything but `Signer::SYSTEM`, to check signatures against fixed
+/// values.
+pub struct Signer<'a> {
+    pub clock: &'a dyn Clock,
+    pub nonces: &'a dyn NonceSource,
+}
+
+impl Signer<'static> {
+    pub const SYSTEM: Signer<'static> = Signer {
+        clock: &SystemClock,
+        nonces: &RandomNonce,
+    };
 }
 
 /// Flexible OAuth 1.0a header builder that supports the 3-legged flow.
@@ -53,83 +89,117 @@ pub fn build_flexible_oauth_header(
     url: &str,
     extra_params: &[(&str, &str)],
 ) -> String {
-    let nonce = generate_nonce();
-    let timestamp = generate_timestamp();
-
-    let mut params: Vec<(&str, &str)> = vec![
-        ("oauth_consumer_key", consumer_key),
-        ("oauth_nonce", &nonce),
-        ("oauth_signature_method", "HMAC-SHA1"),
-        ("oauth_timestamp", &timestamp),
-        ("oauth_version", "1.0"),
-    ];
-
-    if let Some(t) = token {
-        params.push(("oauth_token", t));
-    }
+    Signer::SYSTEM.sign(
+        consumer_key,
+        consumer_secret,
+        token,
+        token_secret,
+        method,
+        url,
+        extra_params,
+        &[],
+    )
+}
 
-    for &(k, v) in extra_params {
-        params.push((k, v));
-    }
+impl Signer<'_> {
+    /// Build the Authorization header. `extra_params` are oauth_* parameters
+    /// that go into both the signature and the header; `request_params` (query
+    /// string or form body) are only signed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign(
+        &self,
+        consumer_key: &str,
+        consumer_secret: &str,
+        token: Option<&str>,
+        token_secret: &str,
+        method: &str,
+        url: &str,
+        extra_params: &[(&str, &str)],
+        request_params: &[(&str, &str)],
+    ) -> String {
+        let nonce = self.nonces.nonce();
+        let timestamp = self.clock.timestamp().to_string();
 
-    // Sort parameters lexicographically
-    params.sort_by_key(|&(k, _)| k);
-
-    // Build parameter string
-    let param_string: String = params
-        .iter()
-        .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
-        .collect::<Vec<_>>()
-        .join("&");
-
-    // Build signature base string
-    let base_string = format!(
-        "{}&{}&{}",
-        method.to_uppercase(),
-        percent_encode(url),
-        percent_encode(&param_string)
-    );
-
-    // Build signing key
-    let signing_key = format!(
-        "{}&{}",
-        percent_encode(consumer_secret),
-        percent_encode(token_secret)
-    );
-
-    // HMAC-SHA1
-    let mut mac = Hmac::<Sha1>::new_from_slice(signing_key.as_bytes())
-        .expect("HMAC can take key of any size");
-    mac.update(base_string.as_bytes());
-    let signature = STANDARD.encode(mac.finalize().into_bytes());
-
-    // Build Authorization header
-    let mut header_params: Vec<(&str, String)> = vec![
-        ("oauth_consumer_key", percent_encode(consumer_key)),
-        ("oauth_nonce", percent_encode(&nonce)),
-        ("oauth_signature", percent_encode(&signature)),
-        ("oauth_signature_method", "HMAC-SHA1".to_string()),
-        ("oauth_timestamp", percent_encode(&timestamp)),
-        ("oauth_version", "1.0".to_string()),
-    ];
-
-    if let Some(t) = token {
-        header_params.push(("oauth_token", percent_encode(t)));
-    }
+        let mut params: Vec<(&str, &str)> = vec![
+            ("oauth_consumer_key", consumer_key),
+            ("oauth_nonce", &nonce),
+            ("oauth_signature_method", "HMAC-SHA1"),
+            ("oauth_timestamp", &timestamp),
+            ("oauth_version", "1.0"),
+        ];
 
-    for &(k, v) in extra_params {
-        header_params.push((k, percent_encode(v)));
-    }
+        if let Some(t) = token {
+            params.push(("oauth_token", t));
+        }
+
+        for &(k, v) in extra_params {
+            params.push((k, v));
+        }
+
+        // Encode, then sort lexicographically by key and value
+        let mut encoded: Vec<(String, String)> = params
+            .iter()
+            .chain(request_params)
+            .map(|(k, v)| (percent_encode(k), percent_encode(v)))
+            .collect();
+        encoded.sort();
+
+        // Build parameter string
+        let param_string: String = encoded
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        // Build signature base string
+        let base_string = format!(
+            "{}&{}&{}",
+            method.to_uppercase(),
+            percent_encode(url),
+            percent_encode(&param_string)
+        );
+
+        // Build signing key
+        let signing_key = format!(
+            "{}&{}",
+            percent_encode(consumer_secret),
+            percent_encode(token_secret)
+        );
+
+        // HMAC-SHA1
+        let mut mac = Hmac::<Sha1>::new_from_slice(signing_key.as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.update(base_string.as_bytes());
+        let signature = STANDARD.encode(mac.finalize().into_bytes());
+
+        // Build Authorization header
+        let mut header_params: Vec<(&str, String)> = vec![
+            ("oauth_consumer_key", percent_encode(consumer_key)),
+            ("oauth_nonce", percent_encode(&nonce)),
+            ("oauth_signature", percent_encode(&signature)),
+            ("oauth_signature_method", "HMAC-SHA1".to_string()),
+            ("oauth_timestamp", percent_encode(&timestamp)),
+            ("oauth_version", "1.0".to_string()),
+        ];
 
-    header_params.sort_by_key(|(k, _)| *k);
+        if let Some(t) = token {
+            header_params.push(("oauth_token", percent_encode(t)));
+        }
 
-    let header_str = header_params
-        .iter()
-        .map(|(k, v)| format!("{k}=\"{v}\""))
-        .collect::<Vec<_>>()
-        .join(", ");
+        for &(k, v) in extra_params {
+            header_params.push((k, percent_encode(v)));
+        }
 
-    format!("OAuth {header_str}")
+        header_params.sort_by_key(|(k, _)| *k);
+
+        let header_str = header_params
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{v}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!("OAuth {header_str}")
+    }
 }
 
 /// Convenience wrapper for authenticated API calls (existing behavior).
@@ -145,10 +215,103 @@ pub fn build_oauth_header(config: &Config, method: &str, url: &str) -> String {
     )
 }
 
+/// Header for an authenticated API call whose query parameters must be signed.
+pub fn build_oauth_header_with_params(
+    config: &Config,
+    method: &str,
+    url: &str,
+    params: &[(&str, &str)],
+) -> String {
+    Signer::SYSTEM.sign(
+        &config.api_key,
+        &config.api_secret,
+        Some(&config.access_token),
+        &config.access_token_secret,
+        method,
+        url,
+        &[],
+        params,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    struct FixedClock(u64);
+
+    impl Clock for FixedClock {
+        fn timestamp(&self) -> u64 {
+            self.0
+        }
+    }
+
+    struct FixedNonce(&'static str);
+
+    impl NonceSource for FixedNonce {
+        fn nonce(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    /// The example from X's "Creating a signature" documentation.
+    #[test]
+    fn matches_x_reference_signature() {
+        let signer = Signer {
+            clock: &FixedClock(1318622958),
+            nonces: &FixedNonce("kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg"),
+        };
+        let header = signer.sign(
+            "xvz1evFS4wEEPTGEFPHBog",
+            "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+            Some("370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb"),
+            "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+            "POST",
+            "https://api.twitter.com/1.1/statuses/update.json",
+            &[],
+            &[
+                ("include_entities", "true"),
+                (
+                    "status",
+                    "Hello Ladies + Gentlemen, a signed OAuth request!",
+                ),
+            ],
+        );
+        assert_eq!(
+            header,
+            "OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", \
+             oauth_nonce=\"kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg\", \
+             oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\", \
+             oauth_signature_method=\"HMAC-SHA1\", \
+             oauth_timestamp=\"1318622958\", \
+             oauth_token=\"370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb\", \
+             oauth_version=\"1.0\""
+        );
+    }
+
+    /// The HMAC-SHA1 example of OAuth Core 1.0, appendix A.5.
+    #[test]
+    fn matches_oauth_core_example_signature() {
+        let signer = Signer {
+            clock: &FixedClock(1191242096),
+            nonces: &FixedNonce("kllo9940pd9333jh"),
+        };
+        let header = signer.sign(
+            "dpf43f3p2l4k3l03",
+            "kd94hf93k423kf44",
+            Some("nnch734d00sl2jdk"),
+            "pfkkdhi9sl3r4s00",
+            "GET",
+            "http://photos.example.net/photos",
+            &[],
+            &[("file", "vacation.jpg"), ("size", "original")],
+        );
+        assert!(
+            header.contains("oauth_signature=\"tR3%2BTy81lMeYAr%2FFid0kMTYa%2FWM%3D\""),
+            "{header}"
+        );
+    }
+
     #[test]
     fn percent_encode_unreserved_unchanged() {
         assert_eq!(percent_encode("abc123"), "abc123");
@@ -225,6 +388,26 @@ mod tests {
         assert!(header.contains("oauth_verifier=\"verifier123\""));
     }
 
+    #[test]
+    fn request_params_are_signed_but_not_in_header() {
+        let config = Config {
+            api_key: "ck".to_string(),
+            api_secret: "cs".to_string(),
+            access_token: "at".to_string(),
+            access_token_secret: "ats".to_string(),
+            screen_name: None,
+        };
+        let header = build_oauth_header_with_params(
+            &config,
+            "GET",
+            "https://api.x.com/2/users/me",
+            &[("user.fields", "public_metrics")],
+        );
+        assert!(header.starts_with("OAuth "));
+        assert!(header.contains("oauth_signature="));
+        assert!(!header.contains("user.fields"));
+    }
+
     #[test]
     fn build_oauth_header_wraps_flexible() {
         let config = Config {
@@ -232,6 +415,7 @@ mod tests {
             api_secret: "cs".to_string(),
             access_token: "at".to_string(),
             access_token_secret: "ats".to_string(),
+            screen_name: None,
         };
         let header = build_oauth_header(&config, "GET", "https://api.x.com/2/tweets");
         assert!(header.starts_with("OAuth "));
@@ -0,0 +1,162 @@
+//! `xcli bot run`: answer mentions that match a pattern with a reply
+//! template, a simple support or FAQ bot.
+//!
+//! Where the bot got to is kept in `bot.json`, so a restart neither answers
+//! a mention twice nor floods anyone: the mentions there are when the bot
+//! first starts count as seen, each account gets at most one reply per
+//! cooldown, and replies are capped per hour.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Placeholders a reply template may use.
+pub const PLACEHOLDERS: &[&str] = &["{handle}", "{name}", "{text}", "{match}"];
+
+/// What the mention being answered offers to the template.
+pub struct Mention<'a> {
+    pub handle: &'a str,
+    pub name: &'a str,
+    pub text: &'a str,
+    /// The part of the text the pattern matched.
+    pub matched: &'a str,
+}
+
+/// Check that `template` uses only known placeholders.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let placeholder = Regex::new(r"\{[a-z_]+\}").unwrap();
+    for found in placeholder.find_iter(template) {
+        if !PLACEHOLDERS.contains(&found.as_str()) {
+            return Err(format!(
+                "Unknown placeholder {} in template (expected {})",
+                found.as_str(),
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Fill `template` in for `mention`.
+pub fn render(template: &str, mention: &Mention) -> String {
+    template
+        .replace("{handle}", mention.handle)
+        .replace("{name}", mention.name)
+        .replace("{text}", mention.text)
+        .replace("{match}", mention.matched)
+        .trim()
+        .to_string()
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct State {
+    /// The newest mention seen.
+    pub since_id: Option<String>,
+    /// When each account (by user ID) was last answered, in Unix seconds.
+    #[serde(default)]
+    pub replied: HashMap<String, i64>,
+    /// When the replies of the last hour were sent.
+    #[serde(default)]
+    pub sent: Vec<i64>,
+}
+
+impl State {
+    /// Remember mention `id` as seen if it is newer than the last.
+    pub fn saw(&mut self, id: &str) {
+        // IDs grow over time; compare them as numbers.
+        let newer = self
+            .since_id
+            .as_deref()
+            .is_none_or(|last| (id.len(), id) > (last.len(), last));
+        if newer {
+            self.since_id = Some(id.to_string());
+        }
+    }
+
+    /// Whether `user_id` was last answered at least `cooldown` seconds ago.
+    pub fn cooled_down(&self, user_id: &str, now: i64, cooldown: i64) -> bool {
+        self.replied
+            .get(user_id)
+            .is_none_or(|&at| now - at >= cooldown)
+    }
+
+    /// Whether fewer than `per_hour` replies went out in the last hour.
+    pub fn under_limit(&self, now: i64, per_hour: usize) -> bool {
+        self.sent.iter().filter(|&&at| now - at < 3600).count() < per_hour
+    }
+
+    pub fn record_reply(&mut self, user_id: &str, now: i64) {
+        self.replied.insert(user_id.to_string(), now);
+        self.sent.retain(|&at| now - at < 3600);
+        self.sent.push(now);
+    }
+}
+
+/// The state saved at `path`, or a fresh one.
+pub fn load(path: &Path) -> State {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(path: &Path, state: &State) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string_pretty(state).expect("bot state serializes to JSON");
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn renders_templates() {
+        let mention = Mention {
+            handle: "jack",
+            name: "Jack",
+            text: "@acme how do I reset my password?",
+            matched: "reset my password",
+        };
+        assert_eq!(
+            render(
+                "@{handle} Hi {name}! To {match}, see https://acme.test/reset\n",
+                &mention
+            ),
+            "@jack Hi Jack! To reset my password, see https://acme.test/reset"
+        );
+        assert!(validate_template("@{handle} {match}").is_ok());
+        assert!(validate_template("{user}").unwrap_err().contains("{user}"));
+    }
+
+    #[test]
+    fn limits_replies() {
+        let mut state = State::default();
+        assert!(state.cooled_down("1", 0, 3600));
+        state.record_reply("1", 100);
+        state.record_reply("2", 200);
+        assert!(!state.cooled_down("1", 3000, 3600));
+        assert!(state.cooled_down("1", 3700, 3600));
+        assert!(!state.under_limit(300, 2));
+        assert!(state.under_limit(3750, 2));
+    }
+
+    #[test]
+    fn keeps_the_newest_mention() {
+        let path = temp_dir().join(format!("xcli_test_{}_bot.json", std::process::id()));
+        let mut state = load(&path);
+        state.saw("99");
+        state.saw("100");
+        state.saw("98");
+        assert_eq!(state.since_id.as_deref(), Some("100"));
+        save(&path, &state).unwrap();
+        assert_eq!(load(&path), state);
+        fs::remove_file(&path).unwrap();
+    }
+}
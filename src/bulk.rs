@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::api::{self, User};
+use crate::client::XClient;
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Block,
+    Mute,
+    Follow,
+    Unfollow,
+}
+
+impl Action {
+    pub fn past_tense(self) -> &'static str {
+        match self {
+            Action::Block => "blocked",
+            Action::Mute => "muted",
+            Action::Follow => "followed",
+            Action::Unfollow => "unfollowed",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub applied: usize,
+    /// Handles already processed by an earlier, interrupted run.
+    pub resumed: usize,
+    pub not_found: Vec<String>,
+    pub failed: Vec<(String, String)>,
+    /// Accounts whose handles had to be looked up.
+    pub looked_up: Vec<User>,
+}
+
+/// Normalize a handle: strip whitespace, a leading `@`, or a profile URL.
+pub fn normalize_handle(raw: &str) -> String {
+    let s = raw.trim();
+    let s = s
+        .strip_prefix("https://")
+        .or_else(|| s.strip_prefix("http://"))
+        .unwrap_or(s);
+    let s = s.strip_prefix("www.").unwrap_or(s);
+    let s = s
+        .strip_prefix("x.com/")
+        .or_else(|| s.strip_prefix("twitter.com/"))
+        .unwrap_or(s);
+    let s = s.trim_start_matches('@');
+    s.split(['/', '?']).next().unwrap_or("").to_string()
+}
+
+/// Parse a handles file: one handle per line, `#` comments and blank lines
+/// ignored, duplicates (case-insensitive) dropped while keeping order.
+pub fn parse_handles(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .map(normalize_handle)
+        .filter(|h| !h.is_empty())
+        .filter(|h| seen.insert(h.to_lowercase()))
+        .collect()
+}
+
+/// A handles file readable by `parse_handles`, with `header` as a comment.
+pub fn format_handles(header: &str, users: &[User]) -> String {
+    let mut out = format!("# {header}\n");
+    for user in users {
+        out.push_str(&format!("@{}\n", user.username));
+    }
+    out
+}
+
+/// Drop handles of accounts already in `existing`. Returns the remaining
+/// handles and how many were dropped.
+pub fn exclude_existing(handles: Vec<String>, existing: &[User]) -> (Vec<String>, usize) {
+    let known: HashSet<String> = existing.iter().map(|u| u.username.to_lowercase()).collect();
+    let total = handles.len();
+    let pending: Vec<String> = handles
+        .into_iter()
+        .filter(|h| !known.contains(&h.to_lowercase()))
+        .collect();
+    let dropped = total - pending.len();
+    (pending, dropped)
+}
+
+/// Progress for `handles.txt` is kept next to it in `handles.txt.progress`.
+pub fn progress_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".progress");
+    PathBuf::from(name)
+}
+
+/// Handles already done, lowercased.
+pub fn load_progress(path: &Path) -> HashSet<String> {
+    fs::read_to_string(path)
+        .map(|s| {
+            s.lines()
+                .map(|l| l.trim().to_lowercase())
+                .filter(|l| !l.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn mark_done(path: &Path, handle: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    writeln!(file, "{handle}").map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Block, mute, follow or unfollow every handle, waiting `pace` between requests. Accounts in
+/// `known` (e.g. from the contact book) skip the handle lookup. When
+/// `progress` is given, finished handles are recorded there so an interrupted
+/// run can pick up where it stopped; the file is removed once all are done.
+pub async fn apply(
+    client: &XClient,
+    action: Action,
+    me_id: &str,
+    handles: &[String],
+    known: Vec<User>,
+    progress: Option<&Path>,
+    pace: Duration,
+) -> Result<Report, String> {
+    let mut report = Report::default();
+    let done = progress.map(load_progress).unwrap_or_default();
+    let pending: Vec<String> = handles
+        .iter()
+        .filter(|h| !done.contains(&h.to_lowercase()))
+        .cloned()
+        .collect();
+    report.resumed = handles.len() - pending.len();
+
+    let unknown: Vec<String> = pending
+        .iter()
+        .filter(|h| !known.iter().any(|u| u.username.eq_ignore_ascii_case(h)))
+        .cloned()
+        .collect();
+    report.looked_up = api::get_users_by_usernames(client, &unknown).await?;
+    let mut users = known;
+    users.extend(report.looked_up.iter().cloned());
+
+    for (i, handle) in pending.iter().enumerate() {
+        let Some(user) = users
+            .iter()
+            .find(|u| u.username.eq_ignore_ascii_case(handle))
+        else {
+            report.not_found.push(handle.clone());
+            continue;
+        };
+
+        if i > 0 {
+            tokio::time::sleep(pace).await;
+        }
+        let result = match action {
+            Action::Block => api::block_user(client, me_id, &user.id).await,
+            Action::Mute => api::mute_user(client, me_id, &user.id).await,
+            Action::Follow => api::follow_user(client, me_id, &user.id).await,
+            Action::Unfollow => api::unfollow_user(client, me_id, &user.id)
+                .await
+                .map(|following| !following),
+        };
+        match result {
+            Ok(true) => {
+                report.applied += 1;
+                println!(
+                    "[{}/{}] @{} {}.",
+                    i + 1,
+                    pending.len(),
+                    user.username,
+                    action.past_tense()
+                );
+                if let Some(path) = progress {
+                    mark_done(path, handle)?;
+                }
+            }
+            Ok(false) => report
+                .failed
+                .push((handle.clone(), "request was not applied".to_string())),
+            Err(e) => report.failed.push((handle.clone(), e)),
+        }
+    }
+
+    if let Some(path) = progress {
+        if report.failed.is_empty() {
+            let _ = fs::remove_file(path);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn normalize_strips_prefixes() {
+        assert_eq!(normalize_handle("  @jack "), "jack");
+        assert_eq!(normalize_handle("https://x.com/jack"), "jack");
+        assert_eq!(
+            normalize_handle("https://twitter.com/jack/status/20"),
+            "jack"
+        );
+        assert_eq!(normalize_handle("www.x.com/jack?s=20"), "jack");
+        assert_eq!(normalize_handle("jack"), "jack");
+    }
+
+    #[test]
+    fn parse_handles_skips_comments_and_duplicates() {
+        let content = "# spam wave\n@alice\nbob  # reported\n\nAlice\n@carol\n";
+        assert_eq!(parse_handles(content), vec!["alice", "bob", "carol"]);
+    }
+
+    fn user(username: &str) -> User {
+        User {
+            id: "1".to_string(),
+            username: username.to_string(),
+            name: username.to_string(),
+            description: None,
+            public_metrics: None,
+            profile_image_url: None,
+            most_recent_tweet_id: None,
+        }
+    }
+
+    #[test]
+    fn exported_handles_parse_back() {
+        let out = format_handles("Blocked by @me", &[user("alice"), user("Bob")]);
+        assert_eq!(out, "# Blocked by @me\n@alice\n@Bob\n");
+        assert_eq!(parse_handles(&out), vec!["alice", "Bob"]);
+    }
+
+    #[test]
+    fn exclude_existing_is_case_insensitive() {
+        let handles = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        let (pending, dropped) = exclude_existing(handles, &[user("Alice"), user("dave")]);
+        assert_eq!(pending, vec!["bob", "carol"]);
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn progress_path_appends_suffix() {
+        assert_eq!(
+            progress_path(Path::new("/tmp/handles.txt")),
+            PathBuf::from("/tmp/handles.txt.progress")
+        );
+    }
+
+    #[test]
+    fn progress_round_trip() {
+        let path = temp_dir().join(format!("xcli_test_{}_progress", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(load_progress(&path).is_empty());
+
+        mark_done(&path, "Alice").unwrap();
+        mark_done(&path, "bob").unwrap();
+        let done = load_progress(&path);
+        assert!(done.contains("alice"));
+        assert!(done.contains("bob"));
+        assert_eq!(done.len(), 2);
+
+        let _ = fs::remove_file(&path);
+    }
+}
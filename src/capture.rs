@@ -0,0 +1,137 @@
+//! Posting command output (`xcli tweet --from-command`, or output piped to
+//! `--file -`): terminal escapes are stripped, and long output is threaded at
+//! line breaks, so lines stay whole.
+
+use std::process::Stdio;
+
+use regex::Regex;
+
+use crate::hooks::shell;
+use crate::thread::{weighted_len, MAX_WEIGHTED_LEN};
+
+/// Run `command` in the shell and return what it printed on stdout. The
+/// output is wanted whatever the exit status (failing tests are news too),
+/// so a non-zero status is only reported alongside it.
+pub fn run(command: &str) -> Result<(String, Option<String>), String> {
+    let output = shell(command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Failed to run `{command}`: {e}"))?;
+    let failure = (!output.status.success()).then(|| {
+        output
+            .status
+            .code()
+            .map_or("killed".to_string(), |c| format!("exit code {c}"))
+    });
+    Ok((
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        failure,
+    ))
+}
+
+/// Strip ANSI escapes (colors, cursor movement, window titles), keep only
+/// what the last carriage return of a line left visible (progress bars), and
+/// drop trailing whitespace and blank lines at either end.
+pub fn clean(text: &str) -> String {
+    let escapes =
+        Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)|\x1b[@-_]").unwrap();
+    let text = escapes.replace_all(text, "");
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| {
+            line.rsplit('\r')
+                .find(|s| !s.is_empty())
+                .unwrap_or_default()
+        })
+        .map(str::trim_end)
+        .collect();
+    let first = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let last = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(first, |i| i + 1);
+    lines[first..last].join("\n")
+}
+
+/// Put thread separators between lines so that every part fits a tweet. A
+/// line too long for one is cut at the limit.
+pub fn thread_lines(text: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut weight = 0;
+    for line in text.lines() {
+        for piece in cut(line, MAX_WEIGHTED_LEN) {
+            let w = weighted_len(&piece);
+            if !current.is_empty() && weight + 1 + w > MAX_WEIGHTED_LEN {
+                parts.push(std::mem::take(&mut current));
+                weight = 0;
+            }
+            if !current.is_empty() {
+                current.push('\n');
+                weight += 1;
+            }
+            current.push_str(&piece);
+            weight += w;
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts.join("\n---\n")
+}
+
+/// `line` in pieces of at most `limit`.
+fn cut(line: &str, limit: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut weight = 0;
+    for c in line.chars() {
+        let w = weighted_len(c.encode_utf8(&mut [0; 4]));
+        if weight + w > limit {
+            pieces.push(String::new());
+            weight = 0;
+        }
+        pieces.last_mut().unwrap().push(c);
+        weight += w;
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thread::split_text;
+
+    #[test]
+    fn strips_escapes_and_progress() {
+        let raw = "\x1b]0;title\x07\n\x1b[32mok\x1b[0m  \n10%\r50%\r100%\r\n\n";
+        assert_eq!(clean(raw), "ok\n100%");
+        assert_eq!(clean("  indented\n\tkept"), "  indented\n\tkept");
+    }
+
+    #[test]
+    fn threads_at_line_breaks() {
+        let line = "x".repeat(100);
+        let text = [line.as_str(); 5].join("\n");
+        let chunks = split_text(&thread_lines(&text));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], format!("{line}\n{line}"));
+        assert_eq!(chunks[2], line);
+
+        let long = "y".repeat(300);
+        let chunks = split_text(&thread_lines(&long));
+        assert_eq!(chunks, vec!["y".repeat(280), "y".repeat(20)]);
+    }
+
+    #[test]
+    fn captures_stdout() {
+        let (out, failure) = run("echo hello; echo oops >&2").unwrap();
+        assert_eq!(out.trim(), "hello");
+        assert_eq!(failure, None);
+        let (_, failure) = run("exit 3").unwrap();
+        assert_eq!(failure.as_deref(), Some("exit code 3"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Record-and-replay of API traffic (`--record` / `--replay`).
+//!
+//! A cassette is a directory of numbered JSON files, one request/response
+//! pair each. Credentials never reach disk: auth headers are dropped and
+//! token values in bodies are redacted. Replay serves the pairs in order and
+//! fails on the first request that does not match the recording.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::transport::{Request, Response, StatusCode, Transport};
+
+/// Response headers worth keeping; the rest is noise or identifying.
+const KEPT_HEADERS: &[&str] = &[
+    "content-type",
+    "x-rate-limit-limit",
+    "x-rate-limit-remaining",
+    "x-rate-limit-reset",
+];
+
+const REDACTED: &str = "REDACTED";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+/// Replace token and password values in JSON (`"access_token": "..."`) and form-encoded
+/// (`oauth_token=...`) bodies.
+pub(crate) fn redact(text: &str) -> String {
+    let json =
+        Regex::new(r#""(access_token|oauth_token|oauth_token_secret|password|accessJwt|refreshJwt)"(\s*):(\s*)"[^"]*""#).unwrap();
+    let form =
+        Regex::new(r"\b(access_token|oauth_token|oauth_token_secret|oauth_verifier)=[^&\s]*")
+            .unwrap();
+    let text = json.replace_all(text, format!(r#""$1"$2:$3"{REDACTED}""#));
+    form.replace_all(&text, format!("$1={REDACTED}"))
+        .into_owned()
+}
+
+fn record(req: &Request, resp: &Response) -> Interaction {
+    Interaction {
+        request: RecordedRequest {
+            method: req.method.to_string(),
+            url: redact(&req.url),
+            body: redact(&req.body_text()),
+        },
+        response: RecordedResponse {
+            status: resp.status.as_u16(),
+            headers: resp
+                .headers
+                .iter()
+                .filter(|(k, _)| KEPT_HEADERS.iter().any(|h| k.eq_ignore_ascii_case(h)))
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.clone()))
+                .collect(),
+            body: redact(&resp.text()),
+        },
+    }
+}
+
+fn interaction_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{:04}.json", index + 1))
+}
+
+/// Wraps a transport and saves every exchange into a cassette directory.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    dir: PathBuf,
+    count: AtomicUsize,
+}
+
+impl RecordingTransport {
+    /// Start a new cassette in `dir`, which must not hold one already.
+    pub fn create(inner: Arc<dyn Transport>, dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        if interaction_path(dir, 0).exists() {
+            return Err(format!(
+                "{} already contains a recording. Delete it or pick another directory.",
+                dir.display()
+            ));
+        }
+        Ok(RecordingTransport {
+            inner,
+            dir: dir.to_path_buf(),
+            count: AtomicUsize::new(0),
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let resp = self.inner.send(req.clone()).await?;
+        let index = self.count.fetch_add(1, Ordering::SeqCst);
+        let json = serde_json::to_string_pretty(&record(&req, &resp))
+            .map_err(|e| format!("Failed to serialize recording: {e}"))?;
+        let path = interaction_path(&self.dir, index);
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(resp)
+    }
+}
+
+/// Serves a recorded cassette back without touching the network.
+pub struct ReplayTransport {
+    interactions: Mutex<std::vec::IntoIter<Interaction>>,
+    dir: PathBuf,
+}
+
+impl ReplayTransport {
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let mut interactions = Vec::new();
+        loop {
+            let path = interaction_path(dir, interactions.len());
+            let Ok(data) = fs::read_to_string(&path) else {
+                break;
+            };
+            let interaction: Interaction = serde_json::from_str(&data)
+                .map_err(|e| format!("Invalid recording {}: {e}", path.display()))?;
+            interactions.push(interaction);
+        }
+        if interactions.is_empty() {
+            return Err(format!("No recording found in {}", dir.display()));
+        }
+        Ok(ReplayTransport {
+            interactions: Mutex::new(interactions.into_iter()),
+            dir: dir.to_path_buf(),
+        })
+    }
+}
+
+/// Requests match on method and URL without the query string, so
+/// parameters derived from the clock (e.g. `start_time`) do not break replay.
+fn same_endpoint(recorded: &RecordedRequest, req: &Request) -> bool {
+    let path = |url: &str| url.split('?').next().unwrap_or_default().to_string();
+    recorded.method == req.method.as_str() && path(&recorded.url) == path(&req.url)
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let Some(next) = self.interactions.lock().unwrap().next() else {
+            return Err(format!(
+                "Recording in {} has no more responses (next request: {} {})",
+                self.dir.display(),
+                req.method,
+                req.url
+            ));
+        };
+        if !same_endpoint(&next.request, &req) {
+            return Err(format!(
+                "Request does not match the recording in {}: expected {} {}, got {} {}",
+                self.dir.display(),
+                next.request.method,
+                next.request.url,
+                req.method,
+                req.url
+            ));
+        }
+        let status = StatusCode::from_u16(next.response.status)
+            .map_err(|e| format!("Invalid status in recording: {e}"))?;
+        Ok(Response {
+            status,
+            headers: next.response.headers.into_iter().collect(),
+            body: next.response.body.into_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{Method, MockTransport};
+    use std::env::temp_dir;
+
+    fn temp_cassette(name: &str) -> PathBuf {
+        let dir = temp_dir().join(format!("xcli_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn redacts_tokens_in_json_and_forms() {
+        assert_eq!(
+            redact(r#"{"token_type":"bearer","access_token": "AAAA"}"#),
+            r#"{"token_type":"bearer","access_token": "REDACTED"}"#
+        );
+        assert_eq!(
+            redact("oauth_token=abc&oauth_token_secret=def&screen_name=me"),
+            "oauth_token=REDACTED&oauth_token_secret=REDACTED&screen_name=me"
+        );
+        assert_eq!(
+            redact(r#"{"identifier":"me","password":"app-pw"}"#),
+            r#"{"identifier":"me","password":"REDACTED"}"#
+        );
+        assert_eq!(redact(r#"{"text":"hello"}"#), r#"{"text":"hello"}"#);
+    }
+
+    #[test]
+    fn matches_on_endpoint_not_query() {
+        let recorded = RecordedRequest {
+            method: "GET".to_string(),
+            url: "https://api.x.com/2/tweets?since=1".to_string(),
+            body: String::new(),
+        };
+        let same = Request::new(Method::GET, "https://api.x.com/2/tweets?since=2");
+        let other = Request::new(Method::POST, "https://api.x.com/2/tweets");
+        assert!(same_endpoint(&recorded, &same));
+        assert!(!same_endpoint(&recorded, &other));
+    }
+
+    #[tokio::test]
+    async fn record_then_replay() {
+        let dir = temp_cassette("cassette");
+        let mock = Arc::new(MockTransport::new());
+        mock.push(Ok(Response::new(201, r#"{"data":{"id":"1"}}"#)
+            .with_header("content-type", "application/json")
+            .with_header("set-cookie", "secret")));
+
+        let recorder = RecordingTransport::create(mock, &dir).unwrap();
+        let req = Request::new(Method::POST, "https://api.x.com/2/tweets")
+            .header("Authorization", "OAuth oauth_token=\"t\"")
+            .json(&serde_json::json!({"text": "hi"}));
+        recorder.send(req.clone()).await.unwrap();
+
+        let saved = fs::read_to_string(dir.join("0001.json")).unwrap();
+        assert!(!saved.contains("oauth_token"));
+        assert!(!saved.contains("set-cookie"));
+        assert!(RecordingTransport::create(Arc::new(MockTransport::new()), &dir).is_err());
+
+        let replay = ReplayTransport::load(&dir).unwrap();
+        let resp = replay.send(req.clone()).await.unwrap();
+        assert_eq!(resp.status.as_u16(), 201);
+        assert_eq!(resp.text(), r#"{"data":{"id":"1"}}"#);
+        assert_eq!(resp.header("content-type"), Some("application/json"));
+
+        let err = replay.send(req).await.unwrap_err();
+        assert!(err.contains("no more responses"), "{err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn replay_rejects_unexpected_requests() {
+        let dir = temp_cassette("cassette_mismatch");
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(200, "{}");
+        RecordingTransport::create(mock, &dir)
+            .unwrap()
+            .send(Request::new(Method::GET, "https://api.x.com/2/users/me"))
+            .await
+            .unwrap();
+
+        let replay = ReplayTransport::load(&dir).unwrap();
+        let err = replay
+            .send(Request::new(Method::DELETE, "https://api.x.com/2/tweets/1"))
+            .await
+            .unwrap_err();
+        assert!(
+            err.contains("expected GET https://api.x.com/2/users/me"),
+            "{err}"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+        assert!(ReplayTransport::load(&dir).is_err());
+    }
+}
@@ -0,0 +1,6761 @@
+//! The `xcli` command-line interface.
+
+use crate::cassette::{RecordingTransport, ReplayTransport};
+use crate::client::XClient;
+use crate::config::{self, ApiKeys, Config, Credentials, HashtagPosition, Settings};
+use crate::mirror::{self, Network};
+#[cfg(feature = "render")]
+use crate::render;
+use crate::sandbox::{self, SandboxTransport};
+use crate::store::Store;
+#[cfg(feature = "stream")]
+use crate::stream;
+use crate::transport::{HttpTransport, Transport};
+use crate::{
+    api, applog, bot, bulk, capture, duration, export, hooks, idcache, lint, lock, markdown, media,
+    mutuals, notify, oauth, photos, plugin, prune, queue, ratelimits, replies, roster, seen, serve,
+    service, spell, stats, store, thread, trace, typography, update, uploads, urls,
+};
+#[cfg(feature = "history")]
+use crate::{archive, feed, retention};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "xcli",
+    version,
+    about = "X (Twitter) API CLI",
+    disable_version_flag = true,
+    long_about = "X (Twitter) API CLI\n\nPost tweets, threads, and manage authentication from the command line.\nLong text is automatically split into threads. Supports OAuth and direct token auth.\n\nUnknown commands run plugins: `xcli foo ARGS` runs `xcli-foo ARGS` from PATH."
+)]
+struct Cli {
+    /// Print version
+    #[arg(short = 'v', long = "version", action = clap::ArgAction::Version)]
+    version: (),
+
+    /// Talk to a built-in fake X API instead of the real one. Nothing is
+    /// posted; history and the queue are kept apart from real runs.
+    #[arg(long, global = true)]
+    sandbox: bool,
+
+    /// Profile of ~/.config/xcli/config.toml to use [env: XCLI_PROFILE]
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Save every API request/response (tokens redacted) into DIR
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer API requests from a recording made with --record, offline
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "sandbox")]
+    replay: Option<PathBuf>,
+
+    /// Print response metadata (transaction ID, rate limit, latency) on stderr
+    #[arg(long, global = true)]
+    verbose: bool,
+
+    /// Log every HTTP request/response of this run (secrets redacted) to PATH
+    #[arg(long, global = true, value_name = "PATH")]
+    trace_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Post a new tweet (long text is automatically threaded)
+    #[command(
+        long_about = "Post a new tweet (long text is automatically threaded)\n\nIf the text exceeds 280 weighted characters, it is automatically split into\na thread. You can also use '---' on its own line to manually control where\nthe split occurs.\n\nCJK characters (Korean, Chinese, Japanese) and emoji count as 2 characters.\n\nWith --delete-after, the tweet (or every tweet of the thread) is queued for\ndeletion and removed by `xcli queue run` once the duration has passed.\n\nWith --markdown, **bold** and *italic* are rendered with Unicode look-alike\nletters (which count as 2 characters each).\n\nWith --from-command, the output of a shell command is posted (as is text\npiped to --file -): terminal colors are stripped, and long output is threaded\nat line breaks. --code sets the text in monospace letters.\n\nWith --also, the same tweets are posted to Mastodon and/or Bluesky as well,\nwith the accounts configured in the profile (see `[profiles.<name>.mastodon]`\nand `[profiles.<name>.bluesky]` in config.toml).\n\nWith --translate, the text is run through the profile's translate_command\nbefore splitting; --with-original posts the original, then the translation.\n\nWith --media, up to 4 images (JPEG, PNG, WebP or GIF), from files, http(s)\nURLs or stdin (-), or one video (MP4 or MOV) are attached to the (first)\ntweet. --subtitles adds SRT captions to the video. A JPEG or PNG over X's\nlimits (5 MB, 8192 pixels on a side) is scaled down and re-encoded first,\nunless --no-resize is given. EXIF and other metadata, such as the GPS position\na photo was taken at, is removed unless --keep-metadata is given or the\nprofile sets strip_metadata = false (then --strip-metadata removes it). The\norientation is kept. --alt describes the images for screen readers; for those\nwithout, the profile's alt_text_command (if any) suggests alt text to confirm.\n\nWith --poll-option (2 to 4 times), the (first) tweet gets a poll, open for\n--poll-duration minutes (a day by default). A tweet cannot have both a poll\nand media.\n\n--reply-to mentioned or following limits who may reply to the tweet (every\ntweet of a thread), e.g. to keep an announcement free of reply spam.\n\nExamples:\n  xcli tweet \"Hello from xcli!\"\n  xcli tweet \"First tweet\\n---\\nSecond tweet\"\n  xcli tweet \"Long text...\" --dry-run\n  xcli tweet --file essay.md --dry-run\n  xcli tweet --from-command \"cargo bench 2>&1 | tail -5\" --code\n  xcli tweet \"Flash sale, today only\" --delete-after 24h\n  xcli tweet \"Shipping **today**, *finally*\" --markdown\n  xcli tweet \"Now on three networks\" --also mastodon,bluesky\n  xcli tweet \"Release notes are up\" --translate ko --with-original\n  xcli tweet \"Sunset\" --media photo.jpg --alt \"Orange sky over the bay\"\n  xcli tweet \"Today's signups\" --media https://example.com/chart.png\n  generate_plot | xcli tweet \"Results\" --media -\n  xcli tweet \"Launch recap\" --media recap.mp4 --subtitles recap.srt --subtitles-lang en\n  xcli tweet \"Tabs or spaces?\" --poll-option Tabs --poll-option Spaces --poll-duration 60\n  xcli tweet --file announcement.md --reply-to following\n  xcli tweet \"Hello\" --json --verbose"
+    )]
+    Tweet {
+        /// Text content of the tweet
+        #[arg(required_unless_present_any = ["file", "from_command"], conflicts_with_all = ["file", "from_command"])]
+        text: Option<String>,
+        /// Read the text from a file ("-" for stdin), e.g. a long essay to thread
+        #[arg(long, value_name = "PATH", conflicts_with = "from_command")]
+        file: Option<PathBuf>,
+        /// Post what a shell command prints, e.g. benchmark results
+        #[arg(long, value_name = "COMMAND")]
+        from_command: Option<String>,
+        /// Set the text in monospace letters, threaded line by line
+        #[arg(long)]
+        code: bool,
+        /// Preview thread split without posting (shows character counts)
+        #[arg(long)]
+        dry_run: bool,
+        /// Post even if the same text was posted recently
+        #[arg(long)]
+        allow_duplicate: bool,
+        /// Delete the tweet after this long, e.g. 30m, 24h, 7d (requires `xcli queue run`)
+        #[arg(long, value_name = "DURATION", value_parser = duration::parse_duration)]
+        delete_after: Option<Duration>,
+        /// Render **bold** and *italic* with Unicode look-alike letters
+        #[arg(long)]
+        markdown: bool,
+        /// Leave out the profile's default hashtags
+        #[arg(long)]
+        no_tags: bool,
+        /// Post to these networks too: mastodon, bluesky (comma-separated)
+        #[arg(long, value_name = "NETWORKS", value_delimiter = ',', value_parser = mirror::parse_network)]
+        also: Vec<Network>,
+        /// Translate the text with the profile's translate_command, e.g. to ko
+        #[arg(long, value_name = "LANG")]
+        translate: Option<String>,
+        /// With --translate, post the original first and the translation after it
+        #[arg(long, requires = "translate")]
+        with_original: bool,
+        /// Attach an image from a file, an http(s) URL or "-" for stdin (repeatable, up to 4)
+        #[arg(long, value_name = "PATH|URL")]
+        media: Vec<String>,
+        /// Type of the image read with --media -: png, jpeg, webp or gif (detected if omitted)
+        #[arg(long, value_name = "TYPE", value_parser = media::parse_media_type)]
+        media_type: Option<&'static str>,
+        /// Alt text for the --media images, in the same order (repeatable)
+        #[arg(long, value_name = "TEXT", requires = "media")]
+        alt: Vec<String>,
+        /// Captions for the attached video, an SRT file
+        #[arg(long, value_name = "PATH", requires = "subtitles_lang")]
+        subtitles: Option<PathBuf>,
+        /// Language of the --subtitles, e.g. en
+        #[arg(long, value_name = "CODE", value_parser = media::parse_language, requires = "subtitles")]
+        subtitles_lang: Option<String>,
+        /// Upload images as they are, even over X's limits
+        #[arg(long, requires = "media")]
+        no_resize: bool,
+        /// Keep EXIF metadata (e.g. the GPS position) in the images
+        #[arg(long, requires = "media")]
+        keep_metadata: bool,
+        /// Remove EXIF metadata even if the profile sets strip_metadata = false
+        #[arg(
+            long,
+            visible_alias = "strip-exif",
+            requires = "media",
+            conflicts_with = "keep_metadata"
+        )]
+        strip_metadata: bool,
+        /// Add a poll with this choice (repeat 2 to 4 times)
+        #[arg(long, value_name = "TEXT", conflicts_with_all = ["media", "also"])]
+        poll_option: Vec<String>,
+        /// How long the poll stays open, in minutes (5 to 10080; default 1440, a day)
+        #[arg(long, value_name = "MINUTES", requires = "poll_option")]
+        poll_duration: Option<u32>,
+        /// Who may reply: everyone, mentioned or following
+        #[arg(long, value_name = "WHO", default_value = "everyone", value_parser = api::parse_reply_settings)]
+        reply_to: api::ReplySettings,
+        /// Print the posted IDs and response metadata as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Post special kinds of threads
+    #[command(
+        long_about = "Post special kinds of threads\n\n`photos` posts a thread of images: up to 4 per tweet (a GIF gets a tweet of\nits own), or one per tweet with --one-per-tweet. Directories and patterns\nsuch as 'trip/*.jpg' are expanded in natural order (2.jpg before 10.jpg);\nquote patterns so xcli sees them. --caption-file and --alt-file hold one line\nper photo, in that order (a blank line for none); the captions of the photos\nin one tweet become its text. Photos without alt text get a suggestion from\nthe profile's alt_text_command, if any. Everything is uploaded before the\nfirst tweet is posted.\n\nExamples:\n  xcli thread photos trip/ --dry-run\n  xcli thread photos 'trip/*.jpg' --caption-file captions.txt --alt-file alts.txt\n  xcli thread photos day1.jpg day2.jpg --one-per-tweet"
+    )]
+    Thread {
+        #[command(subcommand)]
+        action: ThreadAction,
+    },
+    /// Reply to a tweet by ID (long text is automatically threaded)
+    #[command(
+        long_about = "Reply to a tweet by ID (long text is automatically threaded)\n\nPosts a reply to the specified tweet. If the text exceeds 280 weighted\ncharacters, subsequent tweets are threaded as replies to each other.\n\nExamples:\n  xcli reply 1234567890 \"This is a reply!\"\n  xcli reply https://x.com/rustlang/status/1234567890 \"Congrats!\"\n  xcli reply 1234567890 \"Long reply...\" --dry-run"
+    )]
+    Reply {
+        /// Tweet ID or URL to reply to
+        #[arg(value_parser = api::parse_tweet_id)]
+        id: String,
+        /// Text content of the reply
+        text: String,
+        /// Preview thread split without posting
+        #[arg(long)]
+        dry_run: bool,
+        /// Post even if the same text was posted recently
+        #[arg(long)]
+        allow_duplicate: bool,
+        /// Render **bold** and *italic* with Unicode look-alike letters
+        #[arg(long)]
+        markdown: bool,
+        /// Leave out the profile's default hashtags
+        #[arg(long)]
+        no_tags: bool,
+        /// Translate the text with the profile's translate_command, e.g. to ko
+        #[arg(long, value_name = "LANG")]
+        translate: Option<String>,
+        /// With --translate, post the original first and the translation after it
+        #[arg(long, requires = "translate")]
+        with_original: bool,
+        /// Print the posted IDs and response metadata as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete a tweet by ID, or old tweets in bulk
+    #[command(
+        long_about = "Delete a tweet by ID, or old tweets in bulk\n\nPermanently deletes the specified tweet from your account.\n\nWith --older-than and/or --match, candidates are selected from the local\nhistory (including an imported archive, see `xcli archive import`). A summary\nis shown before anything is deleted, and deletions are paced to stay within\nX's rate limits. Interrupted runs can simply be restarted.\n\nExamples:\n  xcli delete 1234567890\n  xcli delete --older-than 2y --dry-run\n  xcli delete --older-than 1y --match \"(?i)hot take\""
+    )]
+    Delete {
+        /// Tweet ID or URL to delete
+        #[arg(required_unless_present_any = ["older_than", "matching"], conflicts_with_all = ["older_than", "matching"], value_parser = api::parse_tweet_id)]
+        id: Option<String>,
+        /// Delete tweets older than this, e.g. 90d or 2y
+        #[arg(long, value_name = "DURATION", value_parser = duration::parse_duration)]
+        older_than: Option<Duration>,
+        /// Only delete tweets whose text matches this regular expression
+        #[arg(long = "match", id = "matching", value_name = "REGEX", value_parser = parse_regex)]
+        matching: Option<regex::Regex>,
+        /// List the tweets that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+        /// Wait this long between deletions (X allows 50 deletions per 15 minutes)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+        /// Print the result and response metadata as JSON (single ID only)
+        #[arg(long, requires = "id")]
+        json: bool,
+    },
+    /// Show a tweet and its metrics
+    #[command(
+        long_about = "Show a tweet and its metrics\n\nWith --watch-metrics, keeps polling the tweet and prints how its metrics\nchange, which is handy right after posting an announcement.\n\nExamples:\n  xcli show 1234567890\n  xcli show https://x.com/rustlang/status/1234567890\n  xcli show 1234567890 --watch-metrics --interval 60"
+    )]
+    Show {
+        /// Tweet ID or URL
+        #[arg(value_parser = api::parse_tweet_id)]
+        id: String,
+        /// Keep polling and print metric changes until interrupted
+        #[arg(long)]
+        watch_metrics: bool,
+        /// Seconds between polls with --watch-metrics
+        #[arg(long, default_value_t = 60, requires = "watch_metrics")]
+        interval: u64,
+        /// Print the tweet and response metadata as JSON
+        #[arg(long, conflicts_with = "watch_metrics")]
+        json: bool,
+    },
+    /// Search recent tweets (the last 7 days), or all of them with --all
+    #[command(
+        long_about = "Search recent tweets (the last 7 days), or all of them with --all\n\nThe query uses X's search syntax (from:, -is:retweet, has:links, ...). With\n--jsonl, each tweet is printed as one JSON object per line as soon as its page\nis fetched, so large searches can feed jq or other tools as they go.\n\n--all searches the full archive, back to 2006. X offers it only with Pro and\nEnterprise access, so it has to be enabled with `full_archive_search = true`\nin ~/.config/xcli/config.toml. --cursor FILE saves where the search got to\nafter each page; running it again with the same FILE continues from there,\ne.g. after an interruption or to read the next --limit tweets. --all-pages\nfollows the pages until X has no more, however many tweets that is.\n\n--new-only prints only the tweets newer than the newest one the last\n--new-only search for the same query printed, e.g. for a cron job. --watch\nkeeps polling every --interval seconds and prints only the new tweets.\n\nExamples:\n  xcli search \"rust lang -is:retweet\"\n  xcli search \"from:rustlang\" --limit 50\n  xcli search \"#rustlang\" --limit 5000 --jsonl | jq -r .text\n  xcli search \"from:rustlang\" --all-pages --jsonl > rustlang.jsonl\n  xcli search \"xcli -is:retweet\" --new-only --jsonl >> xcli.jsonl\n  xcli search \"xcli -is:retweet\" --watch --interval 120\n  xcli search \"from:rustlang\" --all --start-time 2015-05-01 --end-time 2015-06-01\n  xcli search \"rust\" --all --limit 10000 --jsonl --cursor rust.cursor >> rust.jsonl"
+    )]
+    Search {
+        /// Search query
+        query: String,
+        #[command(flatten)]
+        limit: Limit,
+        /// Print one JSON object per tweet and line, streamed page by page
+        #[arg(long)]
+        jsonl: bool,
+        /// Search the full archive (Pro and Enterprise access)
+        #[arg(long)]
+        all: bool,
+        /// Oldest tweets to match: a date (YYYY-MM-DD), timestamp or duration ago (e.g. 30d)
+        #[arg(long, value_parser = duration::parse_since)]
+        start_time: Option<i64>,
+        /// Newest tweets to match, in the same forms as --start-time
+        #[arg(long, value_parser = duration::parse_since)]
+        end_time: Option<i64>,
+        /// Save the search position in FILE and continue from it
+        #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+        cursor: Option<PathBuf>,
+        /// Only tweets newer than the newest one the last --new-only run of this query printed, all of them whatever --limit says
+        #[arg(long, conflicts_with = "cursor")]
+        new_only: bool,
+        #[command(flatten)]
+        watch: WatchArgs,
+    },
+    /// Print your latest tweets, or an account's
+    #[command(
+        long_about = "Print your latest tweets, or an account's\n\n--since-id and --until-id (tweet IDs) and --start-time and --end-time limit\nthe tweets to a window, so a script can fetch exactly what is new since its\nlast run: keep the newest ID it saw and pass it as --since-id next time.\nOr let xcli keep it: --new-only prints only the tweets after the newest one\nthe last --new-only run printed (kept in seen.json in the config directory).\n\n--all-pages reads every page X has (up to the 3200 most recent tweets)\ninstead of stopping at --limit.\n\nExamples:\n  xcli timeline\n  xcli timeline @rustlang --limit 50 --format markdown\n  xcli timeline @rustlang --since-id 1790000000000000000 --format jsonl\n  xcli timeline --start-time 2024-05-01 --end-time 2024-06-01 --format csv"
+    )]
+    Timeline {
+        /// Handle of the account (default: you)
+        handle: Option<String>,
+        #[command(flatten)]
+        args: TimelineArgs,
+    },
+    /// Print the latest tweets mentioning you
+    #[command(
+        long_about = "Print the latest tweets mentioning you\n\nTakes the same window flags as `xcli timeline`, so a script can poll for new\nmentions by passing the newest ID it saw as --since-id, or with --new-only,\nwhich remembers the newest mention printed for the next run.\n\nWith --watch, keeps polling every --interval seconds and prints only the new\nmentions, waiting out X's rate limits, as a lightweight terminal monitor.\n--notify also shows each new mention (author and text) as a desktop\nnotification.\n\nExamples:\n  xcli mentions\n  xcli mentions --new-only --format jsonl\n  xcli mentions --watch --interval 60 --notify\n  xcli mentions --since-id 1790000000000000000 --format jsonl\n  xcli mentions --start-time 1d --format csv > mentions.csv"
+    )]
+    Mentions {
+        #[command(flatten)]
+        args: TimelineArgs,
+    },
+    /// Show the replies to a tweet as a tree
+    #[command(
+        long_about = "Show the replies to a tweet as a tree\n\nReads the tweet's conversation and prints every reply under the tweet it\nanswers, oldest first, so the responses to an announcement can be triaged\nfrom the terminal. Replies are found with search, which covers the last 7\ndays; with `full_archive_search = true` in ~/.config/xcli/config.toml (Pro\nand Enterprise access) older conversations are read too.\n\nExamples:\n  xcli replies 1234567890\n  xcli replies 1234567890 --limit 500 | less"
+    )]
+    Replies {
+        /// Tweet ID or URL
+        #[arg(value_parser = api::parse_tweet_id)]
+        id: String,
+        /// Most replies to read
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Keep a local contact book of frequently used accounts
+    #[command(
+        long_about = "Keep a local contact book of frequently used accounts\n\nContacts map a handle to its account ID and display name, stored in\n~/.config/xcli/xcli.db. Saved handles resolve without an API call, e.g. in\n`xcli block` and `xcli mute`.\n\nExamples:\n  xcli contacts add jack @rustlang\n  xcli contacts list\n  xcli contacts remove jack"
+    )]
+    Contacts {
+        #[command(subcommand)]
+        action: ContactsAction,
+    },
+    /// Run a daemon that accepts JSON-RPC commands over a unix socket
+    #[command(
+        long_about = "Run a daemon that accepts JSON-RPC commands over a unix socket\n\nCredentials are loaded once and connections to X stay open, so bots avoid\nthe startup cost of one xcli process per action. Send one JSON-RPC 2.0\nrequest per line; each gets one response line. Stop with Ctrl-C.\n\nMethods:\n  post    {\"text\": \"...\", \"reply_to\": \"ID\"}  -> {\"ids\": [...]}\n  delete  {\"id\": \"ID\"}                      -> {\"deleted\": true}\n  search  {\"query\": \"...\", \"max_results\": 10} -> {\"tweets\": [...]}\n\nExamples:\n  xcli serve --socket /tmp/xcli.sock\n  echo '{\"jsonrpc\":\"2.0\",\"method\":\"post\",\"params\":{\"text\":\"hi\"},\"id\":1}' | nc -U /tmp/xcli.sock"
+    )]
+    Serve {
+        /// Path of the unix socket to listen on
+        #[arg(long)]
+        socket: PathBuf,
+    },
+    /// Work with your followers
+    #[command(
+        long_about = "Work with your followers\n\n`export` writes every follower of your account, or of the one given, with\ntheir handle, name, ID, follower count and bio. Large lists take a while:\nX hands out 1000 followers per request and 15 requests per 15 minutes, and\nxcli waits out the limit.\n\n`watch` polls your follower count, logs every reading to\n~/.config/xcli/followers.csv and raises an alert when the count moves by an\n--alert-on amount since the last alert (negative for losses).\n\nExamples:\n  xcli followers export --format csv > followers.csv\n  xcli followers export --format jsonl -o followers.jsonl\n  xcli followers export @rustlang --format json -o rustlang.json\n  xcli followers watch --interval 10m --alert-on -50 --alert-on 500 --notify"
+    )]
+    Followers {
+        #[command(subcommand)]
+        action: FollowersAction,
+    },
+    /// Work with the accounts you follow
+    #[command(
+        long_about = "Work with the accounts you follow\n\n`export` writes every account you follow, or the one given follows, with\ntheir handle, name, ID, follower count and bio. With --diff it compares the\nlist against one exported earlier (in any format) and prints the accounts\nfollowed since (+) and no longer followed (-); the fresh list is written\nonly when --output is given.\n\n`prune` unfollows the accounts that don't follow you back (--non-mutual) or\nhave not posted in --inactive-days days, or both. When each account last\nposted is read from its profile, or from its timeline when X doesn't say.\nThe accounts and the reasons are listed before anything is unfollowed, and\nunfollows are paced to stay within X's rate limits.\n\nExamples:\n  xcli following export --format csv > following.csv\n  xcli following export @rustlang --format json -o rustlang.json\n  xcli following export --diff following.csv -o following.csv\n  xcli following prune --non-mutual --inactive-days 365 --dry-run\n  xcli following prune --inactive-days 730\n  xcli following prune --non-mutual --yes --pace 30s"
+    )]
+    Following {
+        #[command(subcommand)]
+        action: FollowingAction,
+    },
+    /// Work with Lists
+    #[command(
+        long_about = "Work with Lists\n\n`timeline` prints the latest tweets of a List, newest first: as text, as a\nMarkdown digest, or as CSV or JSON lines for other tools. `follow`, `unfollow`\nand `pin` change which Lists you follow and which are pinned. `create`,\n`update`, `delete`, `add-member` and `remove-member` manage Lists you own;\n`create` prints the new List's ID. The List ID is the number in its URL\n(x.com/i/lists/ID).\n\n`tweets` is another name for `timeline`.\n\nExamples:\n  xcli list timeline 1234567890\n  xcli list tweets 1234567890 --all-pages --format jsonl > list.jsonl\n  xcli list timeline 1234567890 --limit 100 --format markdown > digest.md\n  xcli list timeline 1234567890 --format jsonl | jq -r .text\n  xcli list follow 1234567890\n  xcli list pin 1234567890\n  xcli list create \"Rust\" --description \"Rust people\" --private\n  xcli list update 1234567890 --name \"Rustaceans\" --public\n  xcli list add-member 1234567890 rustlang @tokio_rs\n  xcli list remove-member 1234567890 tokio_rs\n  xcli list delete 1234567890"
+    )]
+    List {
+        #[command(subcommand)]
+        action: ListAction,
+    },
+    /// Show the connections you share with an account
+    #[command(
+        long_about = "Show the connections you share with an account\n\nLists the people you follow who follow the account, and the people who follow\nboth of you, most followed first. Follower and following lists are read up to\n--max accounts each (X returns 1000 per request, and larger reads take longer\nand use more of the rate limit).\n\nExamples:\n  xcli mutuals @rustlang\n  xcli mutuals rustlang --max 20000\n  xcli mutuals @rustlang --json | jq -r '.you_follow[].username'"
+    )]
+    Mutuals {
+        /// Handle of the account (with or without @)
+        handle: String,
+        /// Most accounts to read from each follower or following list
+        #[arg(long, default_value_t = 5000)]
+        max: usize,
+        /// Print the shared accounts as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Look up accounts
+    #[command(
+        long_about = "Look up accounts\n\n`show` prints an account's profile: name, bio, location, link, when it\njoined, its verification type and its follower, following, tweet and List\ncounts.\n\nExamples:\n  xcli user show @rustlang\n  xcli user show rustlang --json | jq .public_metrics.followers_count"
+    )]
+    User {
+        #[command(subcommand)]
+        action: UserAction,
+    },
+    /// Find audio Spaces
+    #[command(
+        long_about = "Find audio Spaces\n\n`search` lists the Spaces whose title matches a query: live ones, scheduled\nones, or both (--state). `show` prints one Space. Each comes with its state,\nhosts, when it is scheduled, started or ended, and how many are listening\n(live) or set to be reminded (scheduled).\n\nExamples:\n  xcli spaces search rust\n  xcli spaces search \"rust async\" --state live\n  xcli spaces show 1YqKDqWqdPLsV\n  xcli spaces show https://x.com/i/spaces/1YqKDqWqdPLsV --json"
+    )]
+    Spaces {
+        #[command(subcommand)]
+        action: SpacesAction,
+    },
+    /// Block accounts, one by one or in bulk from a file
+    #[command(
+        long_about = "Block accounts, one by one or in bulk from a file\n\nHandles are resolved in batches and blocked with pacing to stay within X's\nrate limits. With --from-file, progress is saved next to the file\n(FILE.progress) so an interrupted run resumes where it stopped.\n\nThe file holds one handle per line; @, profile URLs and # comments are fine.\nBlock lists can be shared between accounts with `export` and `import`.\n\nExamples:\n  xcli block spammer1 @spammer2\n  xcli block --from-file handles.txt\n  xcli block export -o blocklist.txt\n  xcli block import blocklist.txt\n  xcli block list --format csv > blocked.csv",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
+    Block {
+        #[command(subcommand)]
+        action: Option<BlockAction>,
+        /// Handles to block
+        #[arg(required_unless_present = "from_file")]
+        handles: Vec<String>,
+        /// Read handles from a file, one per line
+        #[arg(long, value_name = "FILE")]
+        from_file: Option<PathBuf>,
+        /// Wait this long between requests (X allows 50 per 15 minutes)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+    },
+    /// Mute accounts, one by one or in bulk from a file
+    #[command(
+        long_about = "Mute accounts, one by one or in bulk from a file\n\nWorks like `xcli block`, including resumable progress with --from-file.\n\nExamples:\n  xcli mute noisy1 @noisy2\n  xcli mute --from-file handles.txt\n  xcli mute list --format jsonl",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
+    Mute {
+        #[command(subcommand)]
+        action: Option<MuteAction>,
+        /// Handles to mute
+        #[arg(required_unless_present = "from_file")]
+        handles: Vec<String>,
+        /// Read handles from a file, one per line
+        #[arg(long, value_name = "FILE")]
+        from_file: Option<PathBuf>,
+        /// Wait this long between requests (X allows 50 per 15 minutes)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+    },
+    /// Follow accounts, one by one or in bulk from a file
+    #[command(
+        long_about = "Follow accounts, one by one or in bulk from a file\n\nWorks like `xcli block`, including resumable progress with --from-file.\nThe account IDs handles resolve to are remembered for 30 days in\n~/.config/xcli/user-ids.json, so following or unfollowing the same accounts\nagain skips the lookup. Protected accounts get a follow request.\n\nExamples:\n  xcli follow rustlang @tokio_rs\n  xcli follow --from-file handles.txt --pace 30s"
+    )]
+    Follow {
+        /// Handles to follow
+        #[arg(required_unless_present = "from_file")]
+        handles: Vec<String>,
+        /// Read handles from a file, one per line
+        #[arg(long, value_name = "FILE")]
+        from_file: Option<PathBuf>,
+        /// Wait this long between requests (X allows 400 follows a day)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+    },
+    /// Unfollow accounts, one by one or in bulk from a file
+    #[command(
+        long_about = "Unfollow accounts, one by one or in bulk from a file\n\nWorks like `xcli follow`. To unfollow by activity or follow-back rather than\nby name, see `xcli following prune`.\n\nExamples:\n  xcli unfollow rustlang\n  xcli unfollow --from-file handles.txt"
+    )]
+    Unfollow {
+        /// Handles to unfollow
+        #[arg(required_unless_present = "from_file")]
+        handles: Vec<String>,
+        /// Read handles from a file, one per line
+        #[arg(long, value_name = "FILE")]
+        from_file: Option<PathBuf>,
+        /// Wait this long between requests (X allows 50 per 15 minutes)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+    },
+    /// Browse tweets and threads posted with xcli
+    #[command(
+        long_about = "Browse tweets and threads posted with xcli\n\nEvery successful post is recorded in ~/.config/xcli/xcli.db with its IDs,\ntext, account and timestamp. Threads are shown as one entry unless --thread\nis given.\n\nExamples:\n  xcli history\n  xcli history --limit 5 --thread\n  xcli history search \"release notes\"\n  xcli history export --format csv --since 2024-01-01 > posts.csv",
+        args_conflicts_with_subcommands = true
+    )]
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+        /// Number of posts to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Show every tweet of each thread
+        #[arg(long)]
+        thread: bool,
+    },
+    /// Delete the most recent tweet or thread posted with xcli
+    #[command(
+        long_about = "Delete the most recent tweet or thread posted with xcli\n\nLooks up the last post in the local history, shows it, and asks for\nconfirmation before deleting it. Threads are deleted as a whole, last tweet\nfirst.\n\nExamples:\n  xcli undo\n  xcli undo --yes"
+    )]
+    Undo {
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Work with the official X account archive
+    #[cfg(feature = "history")]
+    #[command(
+        long_about = "Work with the official X account archive\n\nRequest your archive from X (Settings > Your account > Download an archive\nof your data), then import it into the local store. Imported tweets can be\nsearched with `xcli history search` without using any API quota.\n\nExamples:\n  xcli archive import twitter-2024-05-01.zip\n  xcli archive import data/tweets.js --account myhandle"
+    )]
+    Archive {
+        #[command(subcommand)]
+        action: ArchiveAction,
+    },
+    /// Cross-post new entries of RSS and Atom feeds
+    #[cfg(feature = "history")]
+    #[command(
+        long_about = "Cross-post new entries of RSS and Atom feeds\n\nSubscribed feeds are checked by `xcli feed sync` and on every round of\n`xcli queue run`. Each new entry is posted once, through the feed's\ntemplate ({title}, {link} and {summary}). Entries already in a feed when it\nis added are not posted.\n\nExamples:\n  xcli feed add https://blog.example/feed.xml\n  xcli feed add https://blog.example/atom.xml --template \"New post: {title} {link}\"\n  xcli feed sync --dry-run\n  xcli feed list\n  xcli feed remove https://blog.example/feed.xml"
+    )]
+    Feed {
+        #[command(subcommand)]
+        action: FeedAction,
+    },
+    /// Answer mentions that match a pattern with a reply template
+    #[command(
+        long_about = "Answer mentions that match a pattern with a reply template\n\n`xcli bot run` polls your mentions and replies to those whose text matches\n--match, through the template file ({handle}, {name}, {text} and {match}).\nMentions from before the bot first started are not answered. Each account\ngets at most one reply per --cooldown, and at most --max-per-hour replies go\nout in any hour. Progress is kept in bot.json in the config directory.\n\nExamples:\n  xcli bot run --match \"(?i)reset (my )?password\" --reply-template reset.tmpl\n  xcli bot run --match \"(?i)pricing\" --reply-template pricing.tmpl --dry-run --once\n  xcli bot run --match \"(?i)hours\" --reply-template hours.tmpl --cooldown 1d"
+    )]
+    Bot {
+        #[command(subcommand)]
+        action: BotAction,
+    },
+    /// Preview what the retention policy deletes
+    #[cfg(feature = "history")]
+    #[command(
+        long_about = "Preview what the retention policy deletes\n\nThe policy is the [retention] table of the profile in config.toml:\n\n  [profiles.default.retention]\n  delete_after = \"90d\"\n  keep_liked = true\n  keep_tags = [\"keep\"]\n  keep_patterns = [\"(?i)release\"]\n\nOnce an hour, `xcli queue run` and `xcli queue flush` delete the tweets of\nthe local history older than delete_after, unless you liked them yourself or\nthey carry one of keep_tags or match one of keep_patterns. At most 50 tweets\nare deleted a run, oldest first.\n\nExamples:\n  xcli retention preview"
+    )]
+    Retention {
+        #[command(subcommand)]
+        action: RetentionAction,
+    },
+    /// Check a text against the profile's lint rules without posting
+    #[command(
+        long_about = "Check a text against the profile's lint rules without posting\n\nThe rules live in the profile's [lint] table in config.toml: banned words and\npatterns, required tags (such as #ad), a hashtag limit and tweets that are\nnothing but a link. `xcli tweet` and `xcli reply` check them too and refuse to\npost on a problem, unless warn_only is set. The text is split, and gets the\nprofile's hashtags and signature, as when posting.\n\nExit status is 1 if a rule is broken.\n\nExamples:\n  xcli lint draft.txt\n  pbpaste | xcli lint -"
+    )]
+    Lint {
+        /// File with the text to check ("-" for stdin)
+        file: PathBuf,
+    },
+    /// Summarize engagement on your recent tweets
+    #[command(
+        long_about = "Summarize engagement on your recent tweets\n\nFetches public metrics for your tweets (retweets excluded) and prints totals,\nper-tweet averages and the most engaging posts. `activity` charts how often\nyou post, from local history. `best-time` fetches the metrics of the posts in\nlocal history and shows which weekdays and hours got the most engagement.\n\nExamples:\n  xcli stats\n  xcli stats --since 7d --top 3\n  xcli stats --since 2024-01-01 --json\n  xcli stats activity --heatmap\n  xcli stats best-time --since 180d",
+        args_conflicts_with_subcommands = true
+    )]
+    Stats {
+        #[command(subcommand)]
+        action: Option<StatsAction>,
+        /// Start date (YYYY-MM-DD) or duration ago (e.g. 7d)
+        #[arg(long, default_value = "30d", value_parser = duration::parse_since)]
+        since: i64,
+        /// Number of top posts to show
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+        /// Print the summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export the metrics of a set of tweets
+    #[command(
+        long_about = "Export the metrics of a set of tweets\n\n`export` looks up the tweets listed in a file, 100 per request, and writes\ntheir impressions, likes, reposts, replies, quotes and bookmarks as CSV, for\nweekly reports or a spreadsheet. Unlike `xcli stats`, the tweets need not be\nyours or recent.\n\nExamples:\n  xcli metrics export --ids-file tweets.txt --csv out.csv\n  xcli metrics export --ids-file - < tweets.txt"
+    )]
+    Metrics {
+        #[command(subcommand)]
+        action: MetricsAction,
+    },
+    /// Show how much of the monthly cap of posts read the project has used
+    #[command(
+        long_about = "Show how much of the monthly cap of posts read the project has used\n\nX caps how many posts a project may read each month (searches, timelines,\nthe stream and lookups all count). This prints the cap, how much of it is\nused, when the count starts again and the posts read on each of the last\n--days days. It uses an app-only token derived from your API key and secret.\n\nExamples:\n  xcli usage\n  xcli usage --days 30\n  xcli usage --json | jq .project_usage"
+    )]
+    Usage {
+        /// Days of daily counts to show (up to 90)
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u32).range(1..=90))]
+        days: u32,
+        /// Print the usage as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the rate limits X last reported for each endpoint
+    #[command(
+        long_about = "Show the rate limits X last reported for each endpoint\n\nEvery response from X says how many requests are left in the current window\nof its endpoint and when the window resets; xcli keeps the latest of these in\n~/.config/xcli/rate-limits.json. This prints them without calling X, so\nendpoints show up once xcli has used them. Posting also has a daily cap,\nshown as a (24h) entry. --probe first calls users/me (75 requests per 15\nminutes), which also checks the credentials.\n\nExamples:\n  xcli limits\n  xcli limits --probe\n  xcli limits --json | jq '.[\"POST /2/tweets (24h)\"].remaining'"
+    )]
+    Limits {
+        /// Call X once first, to have a fresh reading
+        #[arg(long)]
+        probe: bool,
+        /// Print the limits as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Work with the filtered stream
+    #[cfg(feature = "stream")]
+    #[command(
+        long_about = "Work with the filtered stream\n\nStream rules belong to the app (API key), not the account, and are managed\nwith an app-only token derived from your API key and secret. Back them up to\na JSON file to version-control them or move them to another app. Rules are\nchecked locally (length, closed quotes and parentheses) before X sees them.\n\n`xcli stream run` prints every tweet matching the rules to stdout as one\nline of JSON, for jq or other tools; keep-alives are skipped and the stream is\nreconnected with a growing delay when it drops. `xcli stream forward` POSTs every tweet matching the rules to a webhook, as\nthe JSON X streams (the tweet under data, its author under includes and the\nrules it matched under matching_rules).\n\nExamples:\n  xcli stream rules add \"from:rustlang -is:retweet\" --tag rust\n  xcli stream rules list\n  xcli stream rules delete 1790000000000000001\n  xcli stream run | jq -r .data.text\n  xcli stream rules export rules.json\n  xcli stream rules import rules.json --dry-run\n  xcli stream rules import rules.json\n  xcli stream forward --url https://hooks.internal.example/x"
+    )]
+    Stream {
+        #[command(subcommand)]
+        action: StreamAction,
+    },
+    /// Draw a tweet as a card image (PNG)
+    #[cfg(feature = "render")]
+    #[command(
+        long_about = "Draw a tweet as a card image (PNG)\n\nThe card shows the author's avatar, name and handle, the text, when it was\nposted and its replies, reposts, likes and views, for blogs and slides. With\n--text, draws a tweet you have not posted (as your account, without time or\nmetrics). Text is set in --font, or in a common system font (Arial, DejaVu\nSans, Noto Sans, Segoe UI).\n\nExamples:\n  xcli render 1789012345678901234 --out tweet.png\n  xcli render 1789012345678901234 --out tweet.png --dark\n  xcli render --text \"Shipping today!\" --out draft.png --font Inter.ttf"
+    )]
+    Render {
+        /// Tweet ID or URL to draw
+        #[arg(required_unless_present = "text", conflicts_with = "text", value_parser = api::parse_tweet_id)]
+        id: Option<String>,
+        /// Draw this text instead of a posted tweet
+        #[arg(long)]
+        text: Option<String>,
+        /// PNG file to write
+        #[arg(long, short = 'o')]
+        out: PathBuf,
+        /// TrueType or OpenType font to set the text in
+        #[arg(long, value_name = "FILE")]
+        font: Option<PathBuf>,
+        /// Light text on black
+        #[arg(long)]
+        dark: bool,
+    },
+    /// Process queued actions such as scheduled deletions
+    #[command(
+        long_about = "Process queued actions such as scheduled deletions\n\nActions are stored in ~/.config/xcli/xcli.db and executed once they are due.\nUse `flush` from cron, or keep `run` going in the background. To keep it\nrunning across reboots, `install-service` writes a user-level systemd unit\n(or launchd agent on macOS).\n\nExamples:\n  xcli queue list\n  xcli queue flush\n  xcli queue run --interval 60\n  xcli queue install-service"
+    )]
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+    /// Look after video uploads that were interrupted
+    #[command(
+        long_about = "Look after video uploads that were interrupted\n\nVideos are uploaded in 4 MB parts, and the parts X already has are noted in\n~/.config/xcli/uploads.json. If an upload is cut off, running the same command\nagain carries on where it stopped; `resume` does so without posting. A\nfinished upload is used by the next command attaching the same file, as long\nas X keeps it (about a day).\n\nExamples:\n  xcli media list\n  xcli media resume\n  xcli media resume 1880028106020515840"
+    )]
+    Media {
+        #[command(subcommand)]
+        action: MediaAction,
+    },
+    /// Set up xcli step by step: app, keys, login and a test
+    #[command(
+        long_about = "Set up xcli step by step: app, keys, login and a test\n\nWalks through creating an app in the X Developer Portal (with the exact\nsettings and callback URL to enter), saving its API key and secret, logging\nin with OAuth and checking the login with users/me, and offers to post (and\ndelete) a test tweet. Steps already done can be kept. Run it again to switch\nto another app or account. The keys and login are shared by every profile of\nconfig.toml, so --profile does not give a profile an account of its own.\n\nExamples:\n  xcli init"
+    )]
+    Init,
+    /// Manage authentication
+    #[command(
+        long_about = "Manage authentication\n\nSet up API keys, login via OAuth, check status, or logout.\nSupports both team (OAuth) and personal (direct token) workflows.\n\nExamples:\n  xcli auth setup --api-key KEY --api-secret SECRET\n  xcli auth login\n  xcli auth status\n  xcli auth logout"
+    )]
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    /// Check the stored credentials with X and show who they log in as
+    #[command(
+        long_about = "Check the stored credentials with X and show who they log in as\n\nUnlike `xcli auth status`, which only reads the files, this calls X's\nusers/me with the credentials in use (the saved ones, or those in the\nenvironment) and prints the handle, user ID and the access the app was\ngranted: read, write and direct messages. Exits non-zero when X rejects\nthem, so scripts can check tokens before relying on them. --profile does not\nchange the account: profiles only hold settings.\n\nExamples:\n  xcli whoami\n  xcli whoami && xcli queue flush"
+    )]
+    Whoami,
+    /// Tools for investigating problems
+    #[command(
+        long_about = "Tools for investigating problems\n\n`replay` runs the command line captured with --trace-file again, answering\nits API requests from the trace instead of X, and prints what the user saw.\nIt needs no credentials and uses a throwaway config directory, so your\nhistory, queue and hooks are left alone.\n\nExamples:\n  xcli --trace-file trace.jsonl tweet \"Hello\"\n  xcli debug replay trace.jsonl"
+    )]
+    Debug {
+        #[command(subcommand)]
+        action: DebugAction,
+    },
+    /// Any other command runs the `xcli-<name>` plugin from PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Full-text search over posted tweets (offline, no API quota used)
+    Search {
+        /// Words to search for; every word must match
+        terms: String,
+        /// Maximum number of results
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Export posted tweets as CSV, JSON or JSON Lines
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Only tweets posted since this date (YYYY-MM-DD) or duration ago (e.g. 30d)
+        #[arg(long, value_parser = duration::parse_since)]
+        since: Option<i64>,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Subcommand)]
+enum ListAction {
+    /// Print the latest tweets of a List
+    #[command(visible_alias = "tweets")]
+    Timeline {
+        /// List ID
+        list_id: String,
+        #[command(flatten)]
+        limit: Limit,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = TimelineFormat::Text)]
+        format: TimelineFormat,
+    },
+    /// Follow a List
+    Follow {
+        /// List ID
+        list_id: String,
+    },
+    /// Stop following a List
+    Unfollow {
+        /// List ID
+        list_id: String,
+    },
+    /// Pin a List to the top of your Lists tab
+    Pin {
+        /// List ID
+        list_id: String,
+    },
+    /// Create a List and print its ID
+    Create {
+        /// Name of the List (up to 25 characters)
+        name: String,
+        /// Description of the List (up to 100 characters)
+        #[arg(long)]
+        description: Option<String>,
+        /// Only you can see the List
+        #[arg(long)]
+        private: bool,
+    },
+    /// Change the name, description or visibility of a List you own
+    Update {
+        /// List ID
+        list_id: String,
+        /// New name
+        #[arg(long, required_unless_present_any = ["description", "private", "public"])]
+        name: Option<String>,
+        /// New description
+        #[arg(long)]
+        description: Option<String>,
+        /// Make the List private
+        #[arg(long, conflicts_with = "public")]
+        private: bool,
+        /// Make the List public
+        #[arg(long)]
+        public: bool,
+    },
+    /// Delete a List you own
+    Delete {
+        /// List ID
+        list_id: String,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Add accounts to a List you own
+    AddMember {
+        /// List ID
+        list_id: String,
+        /// Handles to add
+        #[arg(required = true)]
+        handles: Vec<String>,
+    },
+    /// Remove accounts from a List you own
+    RemoveMember {
+        /// List ID
+        list_id: String,
+        /// Handles to remove
+        #[arg(required = true)]
+        handles: Vec<String>,
+    },
+}
+
+/// How many tweets to read: `--limit`, or with `--all-pages` every page X
+/// has.
+#[derive(Args)]
+struct Limit {
+    /// Most tweets to print
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    /// Follow the pages until X has no more (instead of stopping at --limit)
+    #[arg(long, conflicts_with = "limit")]
+    all_pages: bool,
+}
+
+impl Limit {
+    fn max_items(&self) -> usize {
+        if self.all_pages {
+            usize::MAX
+        } else {
+            self.limit
+        }
+    }
+}
+
+/// `--watch`: keep reading and print only the tweets that are new.
+#[derive(Args)]
+struct WatchArgs {
+    /// Keep polling and print new tweets as they come, until interrupted
+    #[arg(long, conflicts_with = "end_time")]
+    watch: bool,
+    /// Seconds between polls with --watch
+    #[arg(long, default_value_t = 60, requires = "watch")]
+    interval: u64,
+    /// With --watch, also show the new tweets as desktop notifications
+    #[arg(long, requires = "watch")]
+    notify: bool,
+}
+
+impl WatchArgs {
+    /// Time between polls, if watching.
+    fn interval(&self) -> Option<Duration> {
+        self.watch.then(|| Duration::from_secs(self.interval))
+    }
+}
+
+#[derive(Args)]
+struct TimelineArgs {
+    #[command(flatten)]
+    limit: Limit,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = TimelineFormat::Text)]
+    format: TimelineFormat,
+    /// Only tweets newer than this tweet (ID or URL)
+    #[arg(long, value_parser = api::parse_tweet_id)]
+    since_id: Option<String>,
+    /// Only tweets older than this tweet (ID or URL)
+    #[arg(long, value_parser = api::parse_tweet_id, conflicts_with = "watch")]
+    until_id: Option<String>,
+    /// Only tweets newer than the newest one the last --new-only run printed, all of them whatever --limit says
+    #[arg(long, conflicts_with = "since_id")]
+    new_only: bool,
+    #[command(flatten)]
+    watch: WatchArgs,
+    /// Only tweets from this date (YYYY-MM-DD), timestamp or duration ago (e.g. 1d) on
+    #[arg(long, value_parser = duration::parse_since)]
+    start_time: Option<i64>,
+    /// Only tweets before this time, in the same forms as --start-time
+    #[arg(long, value_parser = duration::parse_since)]
+    end_time: Option<i64>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TimelineFormat {
+    Text,
+    Markdown,
+    Csv,
+    Jsonl,
+}
+
+#[derive(Subcommand)]
+enum StatsAction {
+    /// Chart posts per day from local history (offline, no API quota used)
+    Activity {
+        /// Number of days to chart, ending today
+        #[arg(long, default_value_t = 90)]
+        days: usize,
+        /// Show a weekday-by-week heatmap instead of a sparkline
+        #[arg(long)]
+        heatmap: bool,
+    },
+    /// Suggest posting times from the engagement of past posts
+    BestTime {
+        /// Start date (YYYY-MM-DD) or duration ago (e.g. 90d)
+        #[arg(long, default_value = "90d", value_parser = duration::parse_since)]
+        since: i64,
+        /// Number of posting windows to suggest
+        #[arg(long, default_value_t = 3)]
+        top: usize,
+        /// Only suggest windows with at least this many posts
+        #[arg(long, default_value_t = 2)]
+        min_posts: u32,
+        /// Print the windows as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetricsAction {
+    /// Write the public metrics of the tweets in a file as CSV
+    #[command(
+        long_about = "Write the public metrics of the tweets in a file as CSV\n\nThe file has one tweet ID or URL per line; blank lines and # comments are\nskipped. Tweets are looked up 100 at a time, and written in the order of the\nfile with their impressions, likes, reposts, replies, quotes and bookmarks.\nIDs of tweets X no longer has are listed on stderr.\n\nExamples:\n  xcli metrics export --ids-file tweets.txt --csv out.csv\n  xcli metrics export --ids-file - < tweets.txt"
+    )]
+    Export {
+        /// File with one tweet ID or URL per line ("-" for stdin)
+        #[arg(long, value_name = "FILE")]
+        ids_file: PathBuf,
+        /// Write the CSV to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContactsAction {
+    /// Look up handles and save them (refreshes existing contacts)
+    Add {
+        /// Handles to save
+        #[arg(required = true)]
+        handles: Vec<String>,
+    },
+    /// List saved contacts
+    List,
+    /// Remove contacts
+    Remove {
+        /// Handles to remove
+        #[arg(required = true)]
+        handles: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BlockAction {
+    /// Write every account you block to a handles file
+    Export {
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Block every account in a handles file you don't already block
+    Import {
+        /// Handles file, e.g. one written by `xcli block export`
+        file: PathBuf,
+        /// Wait this long between requests (X allows 50 per 15 minutes)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+    },
+    /// List every account you block (handle, name, ID, follower count, bio)
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MuteAction {
+    /// List every account you mute (handle, name, ID, follower count, bio)
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FollowersAction {
+    /// Export all followers (handle, name, ID, follower count, bio)
+    Export {
+        /// Handle of the account (default: you)
+        handle: Option<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Poll your follower count and alert when it moves by a set amount
+    Watch {
+        /// Time between polls
+        #[arg(long, value_name = "DURATION", default_value = "10m", value_parser = duration::parse_duration)]
+        interval: Duration,
+        /// Alert when the count changes by this much, e.g. -50 for a loss of 50 (repeatable)
+        #[arg(long, value_name = "DELTA", allow_negative_numbers = true)]
+        alert_on: Vec<i64>,
+        /// Also show alerts as desktop notifications
+        #[arg(long)]
+        notify: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum UserAction {
+    /// Show an account's profile
+    Show {
+        /// Handle of the account (with or without @)
+        handle: String,
+        /// Print the profile as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SpacesAction {
+    /// Search Spaces by title
+    Search {
+        /// Words to look for in titles
+        query: String,
+        /// Which Spaces to look for
+        #[arg(long, value_enum, default_value_t = api::SpaceState::All)]
+        state: api::SpaceState,
+        /// Most Spaces to print (up to 100)
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Print the Spaces as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show a Space
+    Show {
+        /// Space ID or URL
+        #[arg(value_parser = api::parse_space_id)]
+        id: String,
+        /// Print the Space as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FollowingAction {
+    /// Export all accounts followed (handle, name, ID, follower count, bio)
+    Export {
+        /// Handle of the account (default: you)
+        handle: Option<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Print what changed since this earlier export instead of the list
+        #[arg(long, value_name = "FILE")]
+        diff: Option<PathBuf>,
+    },
+    /// Unfollow accounts that don't follow back or have gone quiet
+    Prune {
+        /// Unfollow accounts that don't follow you
+        #[arg(long, required_unless_present = "inactive_days")]
+        non_mutual: bool,
+        /// Unfollow accounts that have not posted in this many days
+        #[arg(long, value_name = "DAYS")]
+        inactive_days: Option<u64>,
+        /// Most accounts to read from your following and follower lists
+        #[arg(long, default_value_t = 5000)]
+        max: usize,
+        /// List the accounts that would be unfollowed without unfollowing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+        /// Wait this long between unfollows (X allows 50 per 15 minutes)
+        #[arg(long, value_name = "DURATION", default_value = "18s", value_parser = duration::parse_duration)]
+        pace: Duration,
+    },
+}
+
+#[derive(Subcommand)]
+enum DebugAction {
+    /// Re-render the output of a run captured with --trace-file
+    Replay {
+        /// Trace file written by --trace-file
+        trace: PathBuf,
+    },
+}
+
+#[cfg(feature = "history")]
+#[derive(Subcommand)]
+enum ArchiveAction {
+    /// Import tweets from an archive .zip or its data/tweets.js
+    Import {
+        /// Path to the archive .zip or tweets.js
+        path: PathBuf,
+        /// Account name to record (read from the archive when importing a .zip)
+        #[arg(long)]
+        account: Option<String>,
+    },
+}
+
+#[cfg(feature = "history")]
+#[derive(Subcommand)]
+enum FeedAction {
+    /// Subscribe to a feed (its current entries are not posted)
+    Add {
+        /// URL of the RSS or Atom feed
+        url: String,
+        /// How entries are posted, with {title}, {link} and {summary}
+        #[arg(long, default_value = feed::DEFAULT_TEMPLATE)]
+        template: String,
+    },
+    /// List subscribed feeds
+    List,
+    /// Unsubscribe from a feed
+    Remove {
+        /// URL of the feed
+        url: String,
+    },
+    /// Post new entries of every feed
+    Sync {
+        /// Show what would be posted without posting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BotAction {
+    /// Watch your mentions and reply to those that match
+    Run {
+        /// Regular expression a mention must match to be answered
+        #[arg(long = "match", value_name = "REGEX", value_parser = parse_regex)]
+        pattern: regex::Regex,
+        /// File with the reply, using {handle}, {name}, {text} and {match}
+        #[arg(long, value_name = "FILE")]
+        reply_template: PathBuf,
+        /// Time between checks of your mentions
+        #[arg(long, value_name = "DURATION", default_value = "1m", value_parser = duration::parse_duration)]
+        interval: Duration,
+        /// Least time between two replies to the same account
+        #[arg(long, value_name = "DURATION", default_value = "1h", value_parser = duration::parse_duration)]
+        cooldown: Duration,
+        /// Most replies sent in any hour
+        #[arg(long, default_value_t = 20)]
+        max_per_hour: usize,
+        /// Show the replies that would be sent without sending them
+        #[arg(long)]
+        dry_run: bool,
+        /// Check once and exit
+        #[arg(long)]
+        once: bool,
+    },
+}
+
+#[cfg(feature = "history")]
+#[derive(Subcommand)]
+enum RetentionAction {
+    /// Show what the next run of the retention policy would delete
+    Preview,
+}
+
+#[derive(Subcommand)]
+enum QueueAction {
+    /// List queued actions
+    List,
+    /// Process all due actions once and exit
+    Flush,
+    /// Keep processing due actions until interrupted
+    Run {
+        /// Seconds to wait between checks
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+    },
+    /// Write a systemd user unit (launchd agent on macOS) running `queue run`
+    #[command(
+        long_about = "Write a systemd user unit (launchd agent on macOS) running `queue run`\n\nThe service runs this xcli binary from the current directory, so the same\naccount (.env, credentials) is used as when running xcli here by hand. With\n--profile, it runs with that profile's settings (retention, hooks). The queue\nis shared by every profile, so there is one service: installing it again,\nfor another profile, replaces it.\n\nExamples:\n  xcli queue install-service\n  xcli --profile work queue install-service\n  xcli queue install-service --interval 300\n  xcli queue install-service --print"
+    )]
+    InstallService {
+        /// Seconds to wait between checks
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+        /// Print the unit instead of writing it
+        #[arg(long)]
+        print: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ThreadAction {
+    /// Post a thread of photos
+    Photos {
+        /// Images, directories of images or patterns like 'trip/*.jpg'
+        #[arg(required = true, value_name = "PATH")]
+        sources: Vec<String>,
+        /// Captions, one line per photo
+        #[arg(long, value_name = "PATH")]
+        caption_file: Option<PathBuf>,
+        /// Alt text, one line per photo
+        #[arg(long, value_name = "PATH")]
+        alt_file: Option<PathBuf>,
+        /// Post one photo per tweet instead of up to 4
+        #[arg(long)]
+        one_per_tweet: bool,
+        /// Show the thread without uploading or posting
+        #[arg(long)]
+        dry_run: bool,
+        /// Upload images as they are, even over X's limits
+        #[arg(long)]
+        no_resize: bool,
+        /// Keep EXIF metadata (e.g. the GPS position) in the images
+        #[arg(long)]
+        keep_metadata: bool,
+        /// Remove EXIF metadata even if the profile sets strip_metadata = false
+        #[arg(long, visible_alias = "strip-exif", conflicts_with = "keep_metadata")]
+        strip_metadata: bool,
+        /// Print the posted IDs and response metadata as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MediaAction {
+    /// List unfinished and unused video uploads
+    List,
+    /// Finish interrupted uploads without posting
+    Resume {
+        /// Media ID of the upload to finish (default: all of them)
+        media_id: Option<String>,
+    },
+}
+
+#[cfg(feature = "stream")]
+#[derive(Subcommand)]
+enum StreamAction {
+    /// Manage, back up and restore filtered-stream rules
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+    /// Print each tweet matching the stream rules as a line of JSON
+    Run,
+    /// POST each tweet matching the stream rules to a webhook
+    Forward {
+        /// Webhook URL that receives each tweet as JSON
+        #[arg(long)]
+        url: String,
+    },
+}
+
+#[cfg(feature = "stream")]
+#[derive(Subcommand)]
+enum RulesAction {
+    /// List the app's stream rules with their IDs and tags
+    List,
+    /// Add a stream rule
+    Add {
+        /// The rule, in X's search syntax, e.g. "from:rustlang -is:retweet"
+        value: String,
+        /// Tag for the tweets the rule matches
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only validate the rule with X, don't add it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Delete stream rules by ID (see `xcli stream rules list`)
+    Delete {
+        /// IDs of the rules to delete
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Write the app's stream rules to a JSON file
+    Export {
+        /// File to write
+        file: PathBuf,
+    },
+    /// Add the rules from a JSON file, skipping ones that already exist
+    Import {
+        /// File written by `xcli stream rules export`
+        file: PathBuf,
+        /// Only validate the rules with X, don't add them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthAction {
+    /// Login via OAuth (opens browser)
+    #[command(
+        long_about = "Login via OAuth (opens browser)\n\nStarts a 3-legged OAuth flow: opens the browser for authorization,\nthen saves the access token to ~/.config/xcli/credentials.json.\nRequires API keys (run `xcli auth setup` first or set .env)."
+    )]
+    Login,
+    /// Logout (delete stored credentials)
+    #[command(
+        long_about = "Logout (delete stored credentials)\n\nRemoves ~/.config/xcli/credentials.json.\nAPI keys in keys.json are kept."
+    )]
+    Logout,
+    /// Show current auth status
+    #[command(
+        long_about = "Show current auth status\n\nDisplays the logged-in screen name and credentials path,\nor indicates that no user is logged in."
+    )]
+    Status,
+    /// Set up API keys
+    #[command(
+        long_about = "Set up API keys\n\nSaves API keys to ~/.config/xcli/keys.json.\nPass keys as arguments or omit them for interactive prompts.\n\nExamples:\n  xcli auth setup --api-key KEY --api-secret SECRET\n  xcli auth setup --api-key KEY --api-secret SECRET --access-token TOKEN --access-token-secret TOKEN_SECRET\n  xcli auth setup   (interactive)"
+    )]
+    Setup {
+        /// API Key (Consumer Key)
+        #[arg(long)]
+        api_key: Option<String>,
+        /// API Secret (Consumer Secret)
+        #[arg(long)]
+        api_secret: Option<String>,
+        /// Access Token (optional)
+        #[arg(long)]
+        access_token: Option<String>,
+        /// Access Token Secret (optional)
+        #[arg(long)]
+        access_token_secret: Option<String>,
+    },
+}
+
+pub async fn run() {
+    let cli = Cli::parse();
+    if cli.sandbox {
+        config::enable_sandbox();
+        eprintln!("[sandbox] Using a fake X API; nothing will be posted.");
+    }
+    if let Some(name) = &cli.profile {
+        config::select_profile(name);
+    }
+    VERBOSE.store(cli.verbose, Ordering::Relaxed);
+    if Settings::load().is_ok_and(|s| s.log) {
+        applog::init(config::log_path());
+        let args: Vec<String> = env::args().skip(1).collect();
+        applog::info(&format!(
+            "xcli {}",
+            command_line(&trace::sanitize_args(&args))
+        ));
+    }
+    if let Some(path) = &cli.trace_file {
+        let args: Vec<String> = env::args().collect();
+        match trace::Trace::create(path, &args) {
+            Ok(t) => {
+                let _ = TRACE.set(Arc::new(t));
+            }
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(dir) = cli.record {
+        let _ = CASSETTE.set(Cassette::Record(dir));
+    } else if let Some(dir) = cli.replay {
+        let _ = CASSETTE.set(Cassette::Replay(dir));
+    }
+
+    dispatch(cli.command).await;
+    notify_new_release().await;
+}
+
+/// Arguments joined for display, quoting those with whitespace.
+fn command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|a| {
+            if a.contains(char::is_whitespace) {
+                format!("{a:?}")
+            } else {
+                a.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// With `check_updates` on, mention a newer release in one line. Only for
+/// people at a terminal; scripts and failures hear nothing.
+async fn notify_new_release() {
+    if !io::stderr().is_terminal() || config::is_sandbox() || CASSETTE.get().is_some() {
+        return;
+    }
+    if !Settings::load().is_ok_and(|s| s.check_updates) {
+        return;
+    }
+    let current = env!("CARGO_PKG_VERSION");
+    let transport = traced(Arc::new(HttpTransport::default()));
+    let cache = config::update_cache_path();
+    if let Some(latest) = update::newer_release(&*transport, &cache, current, store::now()).await {
+        eprintln!(
+            "xcli {latest} is available (you have {current}): {}",
+            update::RELEASES_PAGE
+        );
+    }
+}
+
+async fn dispatch(command: Commands) {
+    match command {
+        Commands::Init => handle_init().await,
+        Commands::Auth { action } => handle_auth(action).await,
+        Commands::Whoami => handle_whoami().await,
+        Commands::Usage { days, json } => handle_usage(days, json).await,
+        Commands::Limits { probe, json } => handle_limits(probe, json).await,
+        Commands::Queue { action } => handle_queue(action).await,
+        #[cfg(feature = "history")]
+        Commands::Retention {
+            action: RetentionAction::Preview,
+        } => handle_retention_preview().await,
+        Commands::Media { action } => handle_media(action).await,
+        Commands::Thread {
+            action:
+                ThreadAction::Photos {
+                    sources,
+                    caption_file,
+                    alt_file,
+                    one_per_tweet,
+                    dry_run,
+                    no_resize,
+                    keep_metadata,
+                    strip_metadata,
+                    json,
+                },
+        } => {
+            let options = api::MediaOptions {
+                resize: !no_resize,
+                strip_metadata: strips_metadata(keep_metadata, strip_metadata),
+            };
+            let files = PhotoFiles {
+                captions: caption_file,
+                alts: alt_file,
+            };
+            handle_thread_photos(&sources, &files, one_per_tweet, dry_run, &options, json).await
+        }
+        #[cfg(feature = "stream")]
+        Commands::Stream {
+            action: StreamAction::Rules { action },
+        } => handle_stream_rules(action).await,
+        #[cfg(feature = "stream")]
+        Commands::Stream {
+            action: StreamAction::Run,
+        } => handle_stream_run().await,
+        #[cfg(feature = "stream")]
+        Commands::Stream {
+            action: StreamAction::Forward { url },
+        } => handle_stream_forward(&url).await,
+        #[cfg(feature = "render")]
+        Commands::Render {
+            id,
+            text,
+            out,
+            font,
+            dark,
+        } => handle_render(id, text, &out, font.as_deref(), dark).await,
+        Commands::History {
+            action: Some(HistoryAction::Search { terms, limit }),
+            ..
+        } => handle_history_search(&terms, limit),
+        Commands::History {
+            action:
+                Some(HistoryAction::Export {
+                    format,
+                    since,
+                    output,
+                }),
+            ..
+        } => handle_history_export(format, since, output),
+        Commands::History {
+            action: None,
+            limit,
+            thread,
+        } => handle_history(limit, thread),
+        Commands::Undo { yes } => handle_undo(yes).await,
+        Commands::Lint { file } => handle_lint(&file),
+        Commands::Stats {
+            action: Some(StatsAction::Activity { days, heatmap }),
+            ..
+        } => handle_stats_activity(days, heatmap),
+        Commands::Stats {
+            action:
+                Some(StatsAction::BestTime {
+                    since,
+                    top,
+                    min_posts,
+                    json,
+                }),
+            ..
+        } => handle_stats_best_time(since, top, min_posts, json).await,
+        Commands::Stats {
+            action: None,
+            since,
+            top,
+            json,
+        } => handle_stats(since, top, json).await,
+        Commands::Metrics {
+            action: MetricsAction::Export { ids_file, csv },
+        } => handle_metrics_export(&ids_file, csv).await,
+        Commands::Block {
+            action: Some(BlockAction::Export { output }),
+            ..
+        } => handle_block_export(output).await,
+        Commands::Block {
+            action: Some(BlockAction::Import { file, pace }),
+            ..
+        } => handle_bulk(bulk::Action::Block, Vec::new(), Some(file), pace, true).await,
+        Commands::Block {
+            action: Some(BlockAction::List { format, output }),
+            ..
+        } => handle_relation_list(bulk::Action::Block, format, output).await,
+        Commands::Block {
+            action: None,
+            handles,
+            from_file,
+            pace,
+        } => handle_bulk(bulk::Action::Block, handles, from_file, pace, false).await,
+        Commands::Mute {
+            action: Some(MuteAction::List { format, output }),
+            ..
+        } => handle_relation_list(bulk::Action::Mute, format, output).await,
+        Commands::Mute {
+            action: None,
+            handles,
+            from_file,
+            pace,
+        } => handle_bulk(bulk::Action::Mute, handles, from_file, pace, false).await,
+        Commands::Follow {
+            handles,
+            from_file,
+            pace,
+        } => handle_bulk(bulk::Action::Follow, handles, from_file, pace, false).await,
+        Commands::Unfollow {
+            handles,
+            from_file,
+            pace,
+        } => handle_bulk(bulk::Action::Unfollow, handles, from_file, pace, false).await,
+        Commands::Contacts { action } => handle_contacts(action).await,
+        Commands::Serve { socket } => handle_serve(&socket).await,
+        Commands::Debug {
+            action: DebugAction::Replay { trace },
+        } => handle_debug_replay(&trace).await,
+        Commands::External(args) => handle_plugin(args),
+        Commands::Followers {
+            action:
+                FollowersAction::Export {
+                    handle,
+                    format,
+                    output,
+                },
+        } => handle_followers_export(handle.as_deref(), format, output).await,
+        Commands::Followers {
+            action:
+                FollowersAction::Watch {
+                    interval,
+                    alert_on,
+                    notify,
+                },
+        } => handle_followers_watch(interval, &alert_on, notify).await,
+        Commands::List {
+            action:
+                ListAction::Timeline {
+                    list_id,
+                    limit,
+                    format,
+                },
+        } => handle_list_timeline(&list_id, limit.max_items(), format).await,
+        Commands::List {
+            action: ListAction::Follow { list_id },
+        } => handle_list_change(&list_id, ListChange::Follow).await,
+        Commands::List {
+            action: ListAction::Unfollow { list_id },
+        } => handle_list_change(&list_id, ListChange::Unfollow).await,
+        Commands::List {
+            action: ListAction::Pin { list_id },
+        } => handle_list_change(&list_id, ListChange::Pin).await,
+        Commands::List {
+            action:
+                ListAction::Create {
+                    name,
+                    description,
+                    private,
+                },
+        } => {
+            let settings = api::ListSettings {
+                name: Some(name),
+                description,
+                private: private.then_some(true),
+            };
+            handle_list_create(&settings).await
+        }
+        Commands::List {
+            action:
+                ListAction::Update {
+                    list_id,
+                    name,
+                    description,
+                    private,
+                    public,
+                },
+        } => {
+            let settings = api::ListSettings {
+                name,
+                description,
+                private: (private || public).then_some(private),
+            };
+            handle_list_update(&list_id, &settings).await
+        }
+        Commands::List {
+            action: ListAction::Delete { list_id, yes },
+        } => handle_list_delete(&list_id, yes).await,
+        Commands::List {
+            action: ListAction::AddMember { list_id, handles },
+        } => handle_list_members(&list_id, &handles, true).await,
+        Commands::List {
+            action: ListAction::RemoveMember { list_id, handles },
+        } => handle_list_members(&list_id, &handles, false).await,
+        Commands::Search {
+            query,
+            limit,
+            jsonl,
+            all,
+            start_time,
+            end_time,
+            cursor,
+            new_only,
+            watch,
+        } => {
+            let run = SearchRun {
+                jsonl,
+                cursor,
+                new_only,
+                watch: watch.interval(),
+                notify: watch.notify,
+            };
+            handle_search(&query, limit.max_items(), all, start_time, end_time, run).await
+        }
+        Commands::Timeline { handle, args } => {
+            handle_timeline(handle.as_deref(), api::TimelineKind::Tweets, args).await
+        }
+        Commands::Mentions { args } => {
+            handle_timeline(None, api::TimelineKind::Mentions, args).await
+        }
+        Commands::Replies { id, limit } => handle_replies(&id, limit).await,
+        Commands::Following {
+            action:
+                FollowingAction::Export {
+                    handle,
+                    format,
+                    output,
+                    diff,
+                },
+        } => handle_following_export(handle.as_deref(), format, output, diff).await,
+        Commands::Following {
+            action:
+                FollowingAction::Prune {
+                    non_mutual,
+                    inactive_days,
+                    max,
+                    dry_run,
+                    yes,
+                    pace,
+                },
+        } => {
+            handle_following_prune(
+                non_mutual,
+                inactive_days,
+                max,
+                PruneRun { dry_run, yes, pace },
+            )
+            .await
+        }
+        Commands::Mutuals { handle, max, json } => handle_mutuals(&handle, max, json).await,
+        Commands::User {
+            action: UserAction::Show { handle, json },
+        } => handle_user_show(&handle, json).await,
+        Commands::Spaces {
+            action:
+                SpacesAction::Search {
+                    query,
+                    state,
+                    limit,
+                    json,
+                },
+        } => handle_spaces_search(&query, state, limit, json).await,
+        Commands::Spaces {
+            action: SpacesAction::Show { id, json },
+        } => handle_spaces_show(&id, json).await,
+        Commands::Show {
+            id,
+            watch_metrics,
+            interval,
+            json,
+        } => handle_show(&id, watch_metrics, interval, json).await,
+        #[cfg(feature = "history")]
+        Commands::Archive {
+            action: ArchiveAction::Import { path, account },
+        } => handle_archive_import(&path, account),
+        #[cfg(feature = "history")]
+        Commands::Feed { action } => handle_feed(action).await,
+        Commands::Bot {
+            action:
+                BotAction::Run {
+                    pattern,
+                    reply_template,
+                    interval,
+                    cooldown,
+                    max_per_hour,
+                    dry_run,
+                    once,
+                },
+        } => {
+            let limits = BotLimits {
+                interval,
+                cooldown,
+                max_per_hour,
+            };
+            handle_bot_run(&pattern, &reply_template, limits, dry_run, once).await
+        }
+        Commands::Tweet {
+            text,
+            file,
+            from_command,
+            code,
+            dry_run,
+            allow_duplicate,
+            delete_after,
+            markdown,
+            no_tags,
+            also,
+            translate,
+            with_original,
+            media,
+            no_resize,
+            keep_metadata,
+            strip_metadata,
+            media_type,
+            alt,
+            subtitles,
+            subtitles_lang,
+            poll_option,
+            poll_duration,
+            reply_to,
+            json,
+        } => {
+            let poll = (!poll_option.is_empty()).then(|| {
+                api::Poll::new(poll_option, poll_duration.unwrap_or(1440)).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                })
+            });
+            let stdin_media = media.iter().filter(|m| *m == "-").count();
+            if stdin_media > 1 || (stdin_media == 1 && file.as_deref() == Some(Path::new("-"))) {
+                eprintln!("Error: only one of --file and --media can read stdin");
+                std::process::exit(1);
+            }
+            if media_type.is_some() && stdin_media == 0 {
+                eprintln!("Error: --media-type is for an image read with --media -");
+                std::process::exit(1);
+            }
+            let kinds: Vec<media::Kind> = media
+                .iter()
+                .map(|m| expected_kind(m, media_type))
+                .collect::<Result<_, _>>()
+                .and_then(|kinds: Vec<_>| media::check_combination(&kinds).map(|()| kinds))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                });
+            let has_video = kinds.contains(&media::Kind::Video);
+            if alt.len() > media.len() {
+                eprintln!("Error: there are more --alt texts than --media attachments");
+                std::process::exit(1);
+            }
+            if has_video && !alt.is_empty() {
+                eprintln!("Error: --alt is for images; give a video captions with --subtitles");
+                std::process::exit(1);
+            }
+            if let Some(text) = alt.iter().find(|t| t.chars().count() > api::MAX_ALT_TEXT) {
+                eprintln!(
+                    "Error: alt text is limited to {} characters ({} given)",
+                    api::MAX_ALT_TEXT,
+                    text.chars().count()
+                );
+                std::process::exit(1);
+            }
+            if let Some(path) = &subtitles {
+                if !has_video {
+                    eprintln!("Error: --subtitles needs a video attached with --media");
+                    std::process::exit(1);
+                }
+                if !path
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("srt"))
+                {
+                    eprintln!("Error: subtitles must be an SRT file");
+                    std::process::exit(1);
+                }
+            }
+            let output = from_command.is_some() || file.as_deref() == Some(Path::new("-"));
+            let text = match from_command {
+                Some(command) => run_command_or_exit(&command),
+                None => read_text_or_exit(text, file.as_deref()),
+            };
+            let text = if output { capture::clean(&text) } else { text };
+            let text = match &translate {
+                Some(lang) => translate_or_exit(text, lang, with_original),
+                None => text,
+            };
+            let text = if code {
+                markdown::monospace(&text)
+            } else {
+                text
+            };
+            let text = if code || output {
+                capture::thread_lines(&text)
+            } else {
+                text
+            };
+            let chunks = prepare_chunks(text, markdown, !no_tags);
+            if let Err(e) = mirror::check_accounts(&load_profile_or_exit(), &also) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+            lint_or_exit(&chunks, dry_run);
+            // Without a dry run, checked once the alt text command has had its say.
+            if dry_run && !has_video && load_profile_or_exit().alt_text_command.is_none() {
+                let images: Vec<(String, &str)> = media
+                    .iter()
+                    .enumerate()
+                    .map(|(i, m)| {
+                        let name = if m == "-" { "stdin" } else { m };
+                        (name.to_string(), alt.get(i).map_or("", String::as_str))
+                    })
+                    .collect();
+                alt_text_lint_or_exit(&images, true);
+            }
+
+            if dry_run {
+                print_preview(
+                    "Tweet preview",
+                    &format!("Thread preview ({} tweets)", chunks.len()),
+                    &chunks,
+                );
+                if let Some(after) = delete_after {
+                    println!(
+                        "Would be deleted after {}.",
+                        duration::format_duration(after)
+                    );
+                }
+                for &network in &also {
+                    let posts = mirror::fit(network, &chunks).len();
+                    println!(
+                        "Would also be posted to {} ({posts} post{}).",
+                        network.name(),
+                        if posts == 1 { "" } else { "s" }
+                    );
+                }
+                for (i, source) in media.iter().enumerate() {
+                    let source = if source == "-" {
+                        "the image from stdin"
+                    } else {
+                        source
+                    };
+                    match alt.get(i) {
+                        Some(text) => println!("Would attach {source} (alt text: {text})."),
+                        None => println!("Would attach {source}."),
+                    }
+                }
+                if let (Some(path), Some(lang)) = (&subtitles, &subtitles_lang) {
+                    println!(
+                        "Would add {} subtitles from {}.",
+                        media::language_name(lang),
+                        path.display()
+                    );
+                }
+                if reply_to != api::ReplySettings::Everyone {
+                    println!("Replies would be limited to {}.", reply_to.describe());
+                }
+                if let Some(poll) = &poll {
+                    println!(
+                        "Would add a poll open for {}: {}",
+                        duration::format_duration(Duration::from_secs(
+                            u64::from(poll.duration_minutes()) * 60
+                        )),
+                        poll.options().join(" / ")
+                    );
+                }
+                return;
+            }
+
+            if let Err((idx, len)) = thread::validate_chunks(&chunks) {
+                eprintln!(
+                    "Error: chunk {} exceeds 280 characters ({}/280). Cannot post.",
+                    idx + 1,
+                    len
+                );
+                eprintln!("Use --dry-run to preview the split, or use --- separators to control splitting.");
+                std::process::exit(1);
+            }
+
+            let chunks = run_pre_post_hook(chunks);
+            check_duplicates(&chunks, allow_duplicate);
+
+            let client = load_client_or_exit();
+            let options = api::MediaOptions {
+                resize: !no_resize,
+                strip_metadata: strips_metadata(keep_metadata, strip_metadata),
+            };
+            let media_ids = upload_media_or_exit(&client, &media, &alt, media_type, &options).await;
+            if let (Some(path), Some(lang)) = (&subtitles, &subtitles_lang) {
+                if let Err(e) = api::upload_subtitles(&client, &media_ids[0], path, lang).await {
+                    eprintln!("Failed to add subtitles from {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+            }
+
+            let options = api::TweetOptions {
+                poll,
+                reply_settings: reply_to,
+            };
+            if chunks.len() == 1 {
+                match api::create_tweet_with(&client, &chunks[0], &media_ids, &options).await {
+                    Ok(resp) => {
+                        let id = resp.data.clone();
+                        print_posted("Tweet", None, &[resp], json);
+                        forget_uploads(&media_ids);
+                        record_history(&client, None, std::slice::from_ref(&id), &chunks);
+                        run_post_post_hook(&client, std::slice::from_ref(&id));
+                        notify_webhook(&client, None, 1, std::slice::from_ref(&id), None).await;
+                        if let Some(after) = delete_after {
+                            schedule_deletion(&[id], after);
+                        }
+                        mirror_chunks(&also, &chunks, json).await;
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to post tweet: {e}");
+                        notify_webhook(&client, None, 1, &[], Some(&e)).await;
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                let mut chunks = chunks;
+                let result =
+                    match api::create_thread_with(&client, &chunks, &media_ids, &options).await {
+                        // Resuming would post the remaining tweets without the images,
+                        // poll or reply settings.
+                        Err(e)
+                            if media_ids.is_empty()
+                                && options.poll.is_none()
+                                && options.reply_settings == api::ReplySettings::Everyone
+                                && can_recover(json) =>
+                        {
+                            recover_thread(&client, None, &mut chunks, e).await
+                        }
+                        result => result,
+                    };
+                match result {
+                    Ok(posted) => {
+                        print_posted("Thread", None, &posted, json);
+                        forget_uploads(&media_ids);
+                        let ids = api::ids(&posted);
+                        record_history(&client, None, &ids, &chunks);
+                        run_post_post_hook(&client, &ids);
+                        notify_webhook(&client, None, chunks.len(), &ids, None).await;
+                        if let Some(after) = delete_after {
+                            schedule_deletion(&ids, after);
+                        }
+                        mirror_chunks(&also, &chunks, json).await;
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "Thread failed at tweet [{}/{}]: {}",
+                            e.failed_index + 1,
+                            chunks.len(),
+                            e.error
+                        );
+                        if !e.posted_ids.is_empty() {
+                            eprintln!("Already posted:");
+                            for (i, id) in e.posted_ids.iter().enumerate() {
+                                eprintln!("  [{}/{}] ID: {id}", i + 1, chunks.len());
+                            }
+                            record_history(&client, None, &e.posted_ids, &chunks);
+                            // What did get posted is just as ephemeral.
+                            if let Some(after) = delete_after {
+                                schedule_deletion(&e.posted_ids, after);
+                            }
+                        }
+                        notify_webhook(&client, None, chunks.len(), &e.posted_ids, Some(&e.error))
+                            .await;
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Commands::Reply {
+            id,
+            text,
+            dry_run,
+            allow_duplicate,
+            markdown,
+            no_tags,
+            translate,
+            with_original,
+            json,
+        } => {
+            let text = match &translate {
+                Some(lang) => translate_or_exit(text, lang, with_original),
+                None => text,
+            };
+            let chunks = prepare_chunks(text, markdown, !no_tags);
+            lint_or_exit(&chunks, dry_run);
+
+            if dry_run {
+                print_preview(
+                    &format!("Reply preview to {id}"),
+                    &format!(
+                        "Reply thread preview ({} tweets, replying to {id})",
+                        chunks.len()
+                    ),
+                    &chunks,
+                );
+                return;
+            }
+
+            if let Err((idx, len)) = thread::validate_chunks(&chunks) {
+                eprintln!(
+                    "Error: chunk {} exceeds 280 characters ({}/280). Cannot post.",
+                    idx + 1,
+                    len
+                );
+                eprintln!("Use --dry-run to preview the split, or use --- separators to control splitting.");
+                std::process::exit(1);
+            }
+
+            let chunks = run_pre_post_hook(chunks);
+            check_duplicates(&chunks, allow_duplicate);
+
+            let client = load_client_or_exit();
+
+            if chunks.len() == 1 {
+                match api::create_tweet(&client, &chunks[0], Some(&id)).await {
+                    Ok(resp) => {
+                        let ids = [resp.data.clone()];
+                        print_posted("Reply", Some(&id), &[resp], json);
+                        record_history(&client, Some(&id), &ids, &chunks);
+                        run_post_post_hook(&client, &ids);
+                        notify_webhook(&client, Some(&id), 1, &ids, None).await;
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to post reply: {e}");
+                        notify_webhook(&client, Some(&id), 1, &[], Some(&e)).await;
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                let mut chunks = chunks;
+                let result = match api::create_reply_thread(&client, &id, &chunks).await {
+                    Err(e) if can_recover(json) => {
+                        recover_thread(&client, Some(&id), &mut chunks, e).await
+                    }
+                    result => result,
+                };
+                match result {
+                    Ok(posted) => {
+                        print_posted("Reply thread", Some(&id), &posted, json);
+                        let ids = api::ids(&posted);
+                        record_history(&client, Some(&id), &ids, &chunks);
+                        run_post_post_hook(&client, &ids);
+                        notify_webhook(&client, Some(&id), chunks.len(), &ids, None).await;
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "Reply thread failed at tweet [{}/{}]: {}",
+                            e.failed_index + 1,
+                            chunks.len(),
+                            e.error
+                        );
+                        if !e.posted_ids.is_empty() {
+                            eprintln!("Already posted:");
+                            for (i, tid) in e.posted_ids.iter().enumerate() {
+                                eprintln!("  [{}/{}] ID: {tid}", i + 1, chunks.len());
+                            }
+                            record_history(&client, Some(&id), &e.posted_ids, &chunks);
+                        }
+                        let (posted, error) = (&e.posted_ids, Some(e.error.as_str()));
+                        notify_webhook(&client, Some(&id), chunks.len(), posted, error).await;
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Commands::Delete {
+            id: None,
+            older_than,
+            matching,
+            dry_run,
+            yes,
+            pace,
+            ..
+        } => handle_bulk_delete(older_than, matching, dry_run, yes, pace).await,
+        Commands::Delete {
+            id: Some(id), json, ..
+        } => {
+            let client = load_client_or_exit();
+            match api::delete_tweet(&client, &id).await {
+                Ok(resp) if json => {
+                    let value = serde_json::json!({
+                        "id": id,
+                        "deleted": resp.data,
+                        "meta": resp.meta,
+                    });
+                    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+                    if resp.data {
+                        forget_deleted(&id);
+                    } else {
+                        std::process::exit(1);
+                    }
+                }
+                Ok(resp) if resp.data => {
+                    print_meta("", &resp.meta);
+                    println!("Tweet {id} deleted.");
+                    forget_deleted(&id);
+                }
+                Ok(resp) => {
+                    print_meta("", &resp.meta);
+                    eprintln!("Tweet {id} was not deleted.");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to delete tweet: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a failed thread can be rescued interactively: someone is at the
+/// terminal and no machine is parsing the output.
+fn can_recover(json: bool) -> bool {
+    !json && io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Ask what to do about the tweet a thread failed at: retry it, skip it,
+/// edit it, or give up, optionally deleting what was already posted. Skips
+/// and edits are applied to `chunks`, so it lines up with the posted IDs.
+async fn recover_thread(
+    client: &XClient,
+    in_reply_to: Option<&str>,
+    chunks: &mut Vec<String>,
+    mut err: api::ThreadError,
+) -> Result<Vec<api::ApiResponse<String>>, api::ThreadError> {
+    let without_meta = |ids: &[String]| {
+        ids.iter()
+            .map(|id| api::ApiResponse {
+                data: id.clone(),
+                meta: api::ResponseMeta::default(),
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut posted = without_meta(&err.posted_ids);
+    loop {
+        let i = err.failed_index;
+        eprintln!(
+            "Tweet [{}/{}] failed: {}
+  {}",
+            i + 1,
+            chunks.len(),
+            err.error,
+            preview(&chunks[i], 70)
+        );
+        eprint!("[r]etry, [s]kip it, [e]dit it, [a]bort and delete posted tweets, or [q]uit? ");
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() {
+            answer.clear();
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "r" | "retry" => {}
+            "s" | "skip" => {
+                chunks.remove(i);
+                if i == chunks.len() {
+                    if posted.is_empty() {
+                        return Err(api::ThreadError {
+                            error: "every tweet was skipped".to_string(),
+                            ..err
+                        });
+                    }
+                    return Ok(posted);
+                }
+            }
+            "e" | "edit" => {
+                let text = prompt("New text");
+                if let Err((_, len)) = thread::validate_chunks(std::slice::from_ref(&text)) {
+                    eprintln!("That is {len}/280 characters; try again.");
+                    continue;
+                }
+                chunks[i] = text;
+            }
+            "a" | "abort" => {
+                let kept = rollback(client, &api::ids(&posted)).await;
+                return Err(api::ThreadError {
+                    posted_ids: kept,
+                    ..err
+                });
+            }
+            // Anything else, including end of input, keeps what was posted.
+            _ => {
+                return Err(api::ThreadError {
+                    posted_ids: api::ids(&posted),
+                    ..err
+                })
+            }
+        }
+
+        let parent = posted.last().map(|r| r.data.as_str()).or(in_reply_to);
+        match api::post_chain(client, parent, &chunks[i..]).await {
+            Ok(rest) => {
+                posted.extend(rest);
+                return Ok(posted);
+            }
+            Err(e) => {
+                posted.extend(without_meta(&e.posted_ids));
+                err = api::ThreadError {
+                    posted_ids: api::ids(&posted),
+                    failed_index: i + e.failed_index,
+                    error: e.error,
+                };
+            }
+        }
+    }
+}
+
+/// Delete `ids`, newest first. Returns the ones that are still up.
+async fn rollback(client: &XClient, ids: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    for id in ids.iter().rev() {
+        match api::delete_tweet(client, id).await.map(|r| r.data) {
+            Ok(true) => eprintln!("Tweet {id} deleted."),
+            Ok(false) => {
+                eprintln!("Tweet {id} was not deleted.");
+                kept.insert(0, id.clone());
+            }
+            Err(e) => {
+                eprintln!("Failed to delete tweet {id}: {e}");
+                kept.insert(0, id.clone());
+            }
+        }
+    }
+    kept
+}
+
+fn forget_deleted(id: &str) {
+    if let Err(e) = Store::open().and_then(|store| store.forget_tweet(id)) {
+        eprintln!("Warning: could not update history: {e}");
+    }
+}
+
+/// Report posted tweets: IDs as text (with metadata under --verbose), or
+/// IDs and metadata as JSON.
+fn print_posted(
+    what: &str,
+    in_reply_to: Option<&str>,
+    posted: &[api::ApiResponse<String>],
+    json: bool,
+) {
+    if json {
+        let meta: Vec<&api::ResponseMeta> = posted.iter().map(|r| &r.meta).collect();
+        let value = serde_json::json!({
+            "ids": api::ids(posted),
+            "in_reply_to": in_reply_to,
+            "meta": meta,
+        });
+        println!("{}", serde_json::to_string_pretty(&value).unwrap());
+        return;
+    }
+    if let [resp] = posted {
+        println!("{what} posted! ID: {}", resp.data);
+        print_meta("  ", &resp.meta);
+        return;
+    }
+    println!("{what} posted! ({} tweets)", posted.len());
+    for (i, resp) in posted.iter().enumerate() {
+        println!("  [{}/{}] ID: {}", i + 1, posted.len(), resp.data);
+        print_meta("    ", &resp.meta);
+    }
+}
+
+/// Set by `--verbose`.
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Under --verbose, print response metadata on stderr.
+fn print_meta(indent: &str, meta: &api::ResponseMeta) {
+    if VERBOSE.load(Ordering::Relaxed) {
+        eprintln!("{indent}{}", format_meta(meta));
+    }
+}
+
+fn format_meta(meta: &api::ResponseMeta) -> String {
+    let mut parts = Vec::new();
+    if let Some(id) = &meta.transaction_id {
+        parts.push(format!("transaction {id}"));
+    }
+    parts.push(format!("{}ms", meta.latency_ms));
+    if let Some(limit) = &meta.rate_limit {
+        let reset = chrono::DateTime::from_timestamp(limit.reset as i64, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| limit.reset.to_string());
+        parts.push(format!(
+            "rate limit {}/{} left, resets {reset}",
+            limit.remaining, limit.limit
+        ));
+    }
+    parts.join(", ")
+}
+
+fn open_store_or_exit() -> Store {
+    match Store::open() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn handle_debug_replay(path: &Path) {
+    let trace = match trace::TraceFile::load(path) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    let args = trace.replay_args();
+    let cli =
+        match Cli::try_parse_from(std::iter::once("xcli").chain(args.iter().map(|a| a.as_str()))) {
+            Ok(cli) => cli,
+            Err(e) => {
+                eprintln!("Error: the traced command line does not parse:\n{e}");
+                std::process::exit(1);
+            }
+        };
+    let command = args
+        .iter()
+        .find(|a| !a.starts_with('-'))
+        .map_or("", |a| a.as_str());
+    if matches!(
+        cli.command,
+        Commands::Init
+            | Commands::Auth { .. }
+            | Commands::Debug { .. }
+            | Commands::Serve { .. }
+            | Commands::External(_)
+    ) {
+        eprintln!("Error: `xcli {command}` runs cannot be replayed.");
+        std::process::exit(1);
+    }
+
+    eprintln!(
+        "Replaying `xcli {}` (traced {} with xcli {}, {} requests)",
+        command_line(&args),
+        trace.started_at,
+        trace.version,
+        trace.exchanges.len()
+    );
+    let state = env::temp_dir().join(format!("xcli-replay-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&state);
+    config::isolate(state.clone());
+    if cli.sandbox {
+        config::enable_sandbox();
+    }
+    if cli.verbose {
+        VERBOSE.store(true, Ordering::Relaxed);
+    }
+    let _ = CASSETTE.set(Cassette::Trace(trace.exchanges));
+
+    Box::pin(dispatch(cli.command)).await;
+    let _ = fs::remove_dir_all(&state);
+}
+
+/// Record posted tweets in the local history. Failures only warn: the post itself succeeded.
+fn handle_plugin(args: Vec<OsString>) {
+    let (name, rest) = args.split_first().expect("clap passes the command name");
+    let name = name.to_string_lossy();
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    let Some(program) = plugin::find(&name, &path_var) else {
+        eprintln!(
+            "Error: unknown command `{name}` (no `{}{name}` on PATH).",
+            plugin::PREFIX
+        );
+        let installed = plugin::list(&path_var);
+        if !installed.is_empty() {
+            eprintln!("Installed plugins: {}", installed.join(", "));
+        }
+        eprintln!("Run `xcli --help` for the built-in commands.");
+        std::process::exit(1);
+    };
+
+    let context = plugin::Context {
+        exe: env::current_exe().ok(),
+        config_dir: config::config_dir(),
+        account: if config::is_sandbox() {
+            Some(sandbox::SANDBOX_USERNAME.to_string())
+        } else {
+            Credentials::load().map(|c| c.screen_name)
+        },
+        profile: config::profile_name(),
+        json_output: !io::stdout().is_terminal(),
+        sandbox: config::is_sandbox(),
+    };
+    match plugin::run(&program, rest, &context) {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn handle_serve(socket: &std::path::Path) {
+    let client = load_client_or_exit();
+    let server = serve::Server::new(client, Some(config::store_path()));
+    eprintln!("Listening on {} (Ctrl-C to stop)", socket.display());
+    if let Err(e) = serve::run(server, socket).await {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    eprintln!("Server stopped.");
+}
+
+#[cfg(not(unix))]
+async fn handle_serve(_socket: &std::path::Path) {
+    eprintln!("Error: `xcli serve` needs unix sockets, which this platform does not support.");
+    std::process::exit(1);
+}
+
+fn record_history(client: &XClient, in_reply_to: Option<&str>, ids: &[String], chunks: &[String]) {
+    if !store::ENABLED {
+        return;
+    }
+    let account = client.config().screen_name.as_deref().unwrap_or("");
+    let result = Store::open()
+        .and_then(|store| store.record_post(account, in_reply_to, ids, chunks, store::now()));
+    if let Err(e) = result {
+        eprintln!("Warning: could not record history: {e}");
+    }
+}
+
+fn handle_history(limit: usize, expand_threads: bool) {
+    let store = open_store_or_exit();
+    let posts = match store.recent_posts(limit) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    if posts.is_empty() {
+        println!("No posts recorded yet.");
+        return;
+    }
+
+    for post in posts {
+        let entries = match store.thread_entries(&post.root_id) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        };
+        let account = if post.account.is_empty() {
+            String::new()
+        } else {
+            format!("@{}  ", post.account)
+        };
+        let when = store::format_timestamp(post.posted_at);
+        let reply = match &post.in_reply_to {
+            Some(parent) => format!("(reply to {parent}) "),
+            None => String::new(),
+        };
+
+        if entries.len() <= 1 {
+            println!(
+                "{when}  {account}{}  {reply}{}",
+                post.tweet_id,
+                preview(&post.text, 60)
+            );
+        } else if expand_threads {
+            println!("{when}  {account}{reply}thread ({} tweets)", entries.len());
+            for entry in &entries {
+                println!(
+                    "    [{}/{}] {}  {}",
+                    entry.position + 1,
+                    entries.len(),
+                    entry.tweet_id,
+                    preview(&entry.text, 60)
+                );
+            }
+        } else {
+            println!(
+                "{when}  {account}{}  {reply}(thread, {} tweets) {}",
+                post.tweet_id,
+                entries.len(),
+                preview(&post.text, 50)
+            );
+        }
+    }
+}
+
+fn handle_history_search(terms: &str, limit: usize) {
+    let store = open_store_or_exit();
+    let hits = match store.search_history(terms, limit) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    if hits.is_empty() {
+        println!("No matches.");
+        return;
+    }
+    for hit in hits {
+        let part = if hit.position > 0 {
+            format!("(thread {}, part {}) ", hit.root_id, hit.position + 1)
+        } else {
+            String::new()
+        };
+        println!(
+            "{}  {}  {part}{}",
+            store::format_timestamp(hit.posted_at),
+            hit.tweet_id,
+            preview(&hit.text, 60)
+        );
+    }
+}
+
+fn handle_history_export(format: ExportFormat, since: Option<i64>, output: Option<PathBuf>) {
+    let store = open_store_or_exit();
+    let entries = match store.history_since(since) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut out = String::new();
+    match format {
+        ExportFormat::Csv => {
+            out.push_str("posted_at,tweet_id,root_id,position,account,in_reply_to,source,text\n");
+            for e in &entries {
+                out.push_str(&export::csv_row(&[
+                    store::format_rfc3339(e.posted_at),
+                    e.tweet_id.clone(),
+                    e.root_id.clone(),
+                    e.position.to_string(),
+                    e.account.clone(),
+                    e.in_reply_to.clone().unwrap_or_default(),
+                    e.source.clone(),
+                    e.text.clone(),
+                ]));
+                out.push('\n');
+            }
+        }
+        ExportFormat::Json | ExportFormat::Jsonl => {
+            let records: Vec<serde_json::Value> = entries
+                .iter()
+                .map(|e| {
+                    serde_json::json!({
+                        "posted_at": store::format_rfc3339(e.posted_at),
+                        "tweet_id": e.tweet_id,
+                        "root_id": e.root_id,
+                        "position": e.position,
+                        "account": e.account,
+                        "in_reply_to": e.in_reply_to,
+                        "source": e.source,
+                        "text": e.text,
+                    })
+                })
+                .collect();
+            if let ExportFormat::Json = format {
+                out.push_str(&serde_json::to_string_pretty(&records).unwrap());
+                out.push('\n');
+            } else {
+                for record in &records {
+                    out.push_str(&record.to_string());
+                    out.push('\n');
+                }
+            }
+        }
+    }
+
+    write_export(output, &out, &format!("{} tweets", entries.len()));
+}
+
+/// Write export output to a file (with a note on stderr) or stdout.
+fn write_export(output: Option<PathBuf>, content: &str, what: &str) {
+    match output {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, content) {
+                eprintln!("Failed to write {}: {e}", path.display());
+                std::process::exit(1);
+            }
+            eprintln!("Exported {what} to {}", path.display());
+        }
+        None => print!("{content}"),
+    }
+}
+
+async fn handle_followers_export(
+    handle: Option<&str>,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) {
+    let client = load_client_or_exit();
+    let user = match handle {
+        Some(handle) => lookup_user_or_exit(&client, handle).await,
+        None => match api::get_me(&client).await {
+            Ok(u) => u,
+            Err(e) => {
+                eprintln!("Failed to look up your account: {e}");
+                std::process::exit(1);
+            }
+        },
+    };
+    eprintln!("Fetching followers of @{}...", user.username);
+    let followers = match api::get_followers(&client, &user.id, usize::MAX).await {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Failed to fetch followers: {e}");
+            std::process::exit(1);
+        }
+    };
+    write_export(
+        output,
+        &format_users(&followers, format),
+        &format!("{} followers", followers.len()),
+    );
+}
+
+async fn handle_following_export(
+    handle: Option<&str>,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    diff: Option<PathBuf>,
+) {
+    // Read the earlier list first: `-o` may be about to overwrite it.
+    let earlier = diff.map(|path| {
+        let read = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| roster::read_export(&data));
+        match read {
+            Ok(users) => (path, users),
+            Err(e) => {
+                eprintln!("Failed to read {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    });
+    let client = load_client_or_exit();
+    let user = match handle {
+        Some(handle) => lookup_user_or_exit(&client, handle).await,
+        None => match api::get_me(&client).await {
+            Ok(u) => u,
+            Err(e) => {
+                eprintln!("Failed to look up your account: {e}");
+                std::process::exit(1);
+            }
+        },
+    };
+    eprintln!("Fetching accounts @{} follows...", user.username);
+    let following = match api::get_following(&client, &user.id, usize::MAX).await {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Failed to fetch followed accounts: {e}");
+            std::process::exit(1);
+        }
+    };
+    let what = format!("{} followed accounts", following.len());
+    let Some((path, earlier)) = earlier else {
+        write_export(output, &format_users(&following, format), &what);
+        return;
+    };
+    let changes = roster::compare(&earlier, &following);
+    for u in &changes.added {
+        println!("+ @{}  {}", u.username, u.name);
+    }
+    for u in &changes.removed {
+        println!("- @{}  {}", u.username, u.name);
+    }
+    eprintln!(
+        "{} followed, {} no longer followed since {}.",
+        changes.added.len(),
+        changes.removed.len(),
+        path.display()
+    );
+    if output.is_some() {
+        write_export(output, &format_users(&following, format), &what);
+    }
+}
+
+async fn handle_followers_watch(interval: Duration, alert_on: &[i64], notify: bool) {
+    let client = load_client_or_exit();
+    let log = config::follower_log_path();
+    let mut baseline: Option<u64> = None;
+    let mut last: Option<u64> = None;
+    println!(
+        "Watching followers every {}, logging to {}. Press Ctrl-C to stop.",
+        duration::format_duration(interval),
+        log.display()
+    );
+    loop {
+        let now = chrono::Local::now().format("%H:%M:%S");
+        match api::get_me(&client).await {
+            Ok(me) => {
+                let count = me.public_metrics.map_or(0, |m| m.followers_count);
+                if let Err(e) = stats::log_followers(&log, store::now(), &me.username, count) {
+                    eprintln!("{now}  {e}");
+                }
+                match last {
+                    Some(last) => println!(
+                        "{now}  {count} followers ({:+})",
+                        count as i64 - last as i64
+                    ),
+                    None => println!("{now}  {count} followers"),
+                }
+                let base = *baseline.get_or_insert(count);
+                if let Some(threshold) = stats::crossed(base, count, alert_on) {
+                    let message = format!(
+                        "@{} followers went from {base} to {count} ({:+}, alert at {threshold:+})",
+                        me.username,
+                        count as i64 - base as i64
+                    );
+                    println!("{now}  ALERT: {message}");
+                    if notify {
+                        if let Err(e) = notify::desktop("xcli followers", &message) {
+                            eprintln!("{now}  {e}");
+                        }
+                    }
+                    baseline = Some(count);
+                }
+                last = Some(count);
+            }
+            Err(e) => eprintln!("{now}  Failed to fetch your follower count: {e}"),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// How often `xcli bot run` checks and how much it may answer.
+struct BotLimits {
+    interval: Duration,
+    cooldown: Duration,
+    max_per_hour: usize,
+}
+
+async fn handle_bot_run(
+    pattern: &regex::Regex,
+    template_path: &Path,
+    limits: BotLimits,
+    dry_run: bool,
+    once: bool,
+) {
+    let template = match std::fs::read_to_string(template_path) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", template_path.display());
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = bot::validate_template(&template) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    let state_path = config::bot_state_path();
+    let mut state = bot::load(&state_path);
+    // On the first run, only mentions from now on are answered.
+    let started = state
+        .since_id
+        .is_none()
+        .then(|| store::format_rfc3339(store::now()));
+    println!(
+        "Answering mentions of @{} every {}{}. Press Ctrl-C to stop.",
+        me.username,
+        duration::format_duration(limits.interval),
+        if dry_run { " (dry run)" } else { "" }
+    );
+
+    loop {
+        let now = chrono::Local::now().format("%H:%M:%S");
+        let window = api::Window {
+            since_id: state.since_id.as_deref(),
+            start_time: if state.since_id.is_none() {
+                started.as_deref()
+            } else {
+                None
+            },
+            ..api::Window::default()
+        };
+        let timeline =
+            api::get_timeline(&client, &me.id, api::TimelineKind::Mentions, &window, 100).await;
+        let timeline = timeline.unwrap_or_else(|e| {
+            eprintln!("{now}  Failed to fetch your mentions: {e}");
+            api::Timeline::default()
+        });
+        // Oldest first, so replies go out in the order mentions came in.
+        for tweet in timeline.tweets.iter().rev() {
+            state.saw(&tweet.id);
+            let Some(author) = timeline.author(tweet) else {
+                continue;
+            };
+            if author.id == me.id {
+                continue;
+            }
+            let Some(matched) = pattern.find(&tweet.text) else {
+                continue;
+            };
+            let at = store::now();
+            if !state.cooled_down(&author.id, at, limits.cooldown.as_secs() as i64) {
+                println!(
+                    "{now}  Skipping {} from @{}: answered recently",
+                    tweet.id, author.username
+                );
+                continue;
+            }
+            if !state.under_limit(at, limits.max_per_hour) {
+                println!(
+                    "{now}  Skipping {} from @{}: hourly limit reached",
+                    tweet.id, author.username
+                );
+                continue;
+            }
+            let reply = bot::render(
+                &template,
+                &bot::Mention {
+                    handle: &author.username,
+                    name: &author.name,
+                    text: &tweet.text,
+                    matched: matched.as_str(),
+                },
+            );
+            if dry_run {
+                println!(
+                    "{now}  Would reply to {} from @{}:\n{reply}",
+                    tweet.id, author.username
+                );
+                state.record_reply(&author.id, at);
+                continue;
+            }
+            match api::create_tweet(&client, &reply, Some(&tweet.id)).await {
+                Ok(resp) => {
+                    println!(
+                        "{now}  Replied to {} from @{} ({})",
+                        tweet.id, author.username, resp.data
+                    );
+                    state.record_reply(&author.id, at);
+                }
+                Err(e) => eprintln!("{now}  Failed to reply to {}: {e}", tweet.id),
+            }
+        }
+        if !dry_run {
+            if let Err(e) = bot::save(&state_path, &state) {
+                eprintln!("{now}  {e}");
+            }
+        }
+        if once {
+            break;
+        }
+        tokio::time::sleep(limits.interval).await;
+    }
+}
+
+/// The accounts you block or mute, as CSV or JSON lines.
+async fn handle_relation_list(action: bulk::Action, format: ExportFormat, output: Option<PathBuf>) {
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    let past = action.past_tense();
+    eprintln!("Fetching accounts {past} by @{}...", me.username);
+    let (users, list) = match action {
+        bulk::Action::Block => (api::get_blocking(&client, &me.id).await, "block"),
+        bulk::Action::Mute => (api::get_muting(&client, &me.id).await, "mute"),
+        bulk::Action::Follow | bulk::Action::Unfollow => {
+            unreachable!("only block and mute lists are listed here")
+        }
+    };
+    let users = match users {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to fetch your {list} list: {e}");
+            std::process::exit(1);
+        }
+    };
+    write_export(
+        output,
+        &format_users(&users, format),
+        &format!("{} {past} accounts", users.len()),
+    );
+}
+
+/// `users` as CSV (handle, name, ID, follower count, bio) or JSON lines.
+fn format_users(users: &[api::User], format: ExportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ExportFormat::Csv => {
+            out.push_str("handle,name,id,followers_count,bio\n");
+            for user in users {
+                let followers_count = user
+                    .public_metrics
+                    .map(|m| m.followers_count.to_string())
+                    .unwrap_or_default();
+                out.push_str(&export::csv_row(&[
+                    user.username.as_str(),
+                    user.name.as_str(),
+                    user.id.as_str(),
+                    followers_count.as_str(),
+                    user.description.as_deref().unwrap_or(""),
+                ]));
+                out.push('\n');
+            }
+        }
+        ExportFormat::Json => {
+            out.push_str(&serde_json::to_string_pretty(users).unwrap());
+            out.push('\n');
+        }
+        ExportFormat::Jsonl => {
+            for user in users {
+                out.push_str(&serde_json::to_string(user).unwrap());
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// How `search` prints its results and keeps track of where it got to.
+struct SearchRun {
+    jsonl: bool,
+    cursor: Option<PathBuf>,
+    new_only: bool,
+    /// Time between polls with `--watch`.
+    watch: Option<Duration>,
+    /// Show the new tweets of `--watch` as desktop notifications.
+    notify: bool,
+}
+
+async fn handle_search(
+    query: &str,
+    limit: usize,
+    all: bool,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    run: SearchRun,
+) {
+    let SearchRun {
+        jsonl,
+        cursor,
+        new_only,
+        watch,
+        notify,
+    } = run;
+    if all && !Settings::load().is_ok_and(|s| s.full_archive_search) {
+        eprintln!(
+            "Full-archive search needs Pro or Enterprise access to the X API. If yours has \
+             it, set `full_archive_search = true` in {}",
+            config::settings_path().display()
+        );
+        std::process::exit(1);
+    }
+    let start_time = start_time.map(store::format_rfc3339);
+    let end_time = end_time.map(store::format_rfc3339);
+    let saved = cursor.as_deref().and_then(|path| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+    });
+    let seen_path = config::seen_path();
+    let mut seen = seen::load(&seen_path);
+    let key = seen::search_key(query);
+    let since_id = seen.get(&key).cloned().filter(|_| new_only);
+    let search = api::Search {
+        query,
+        all,
+        start_time: start_time.as_deref(),
+        end_time: end_time.as_deref(),
+        next_token: saved.as_deref(),
+        since_id: since_id.as_deref(),
+    };
+
+    // Every tweet since the last run, however many, or some would never be
+    // printed; rate limits are waited out.
+    let limit = if since_id.is_some() {
+        usize::MAX
+    } else {
+        limit
+    };
+    let client = load_client_or_exit();
+    let mut newest: Option<String> = None;
+    let result = api::search_pages(&client, &search, limit, |tweets, next| {
+        if newest.is_none() {
+            newest = tweets.first().map(|t| t.id.clone());
+        }
+        print_search_results(&tweets, jsonl);
+        if let Some(path) = &cursor {
+            let saved = match next {
+                Some(token) => fs::write(path, token),
+                // Done: the next run starts a new search.
+                None => fs::remove_file(path).or_else(|e| match e.kind() {
+                    io::ErrorKind::NotFound => Ok(()),
+                    _ => Err(e),
+                }),
+            };
+            if let Err(e) = saved {
+                eprintln!("Failed to save the cursor to {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    })
+    .await;
+    if let Err(e) = result {
+        eprintln!("Search failed: {e}");
+        std::process::exit(1);
+    }
+    // Only once every new tweet is printed, so a failed run is read again.
+    if let (true, Some(newest)) = (new_only, &newest) {
+        seen::record(&mut seen, &key, newest);
+        save_seen(&seen_path, &seen);
+    }
+
+    let Some(interval) = watch else {
+        return;
+    };
+    eprintln!(
+        "Watching for new tweets every {}. Press Ctrl-C to stop.",
+        duration::format_duration(interval)
+    );
+    let mut newest = newest.or(since_id);
+    loop {
+        tokio::time::sleep(interval).await;
+        let search = api::Search {
+            query,
+            all,
+            start_time: start_time.as_deref().filter(|_| newest.is_none()),
+            since_id: newest.as_deref(),
+            ..api::Search::default()
+        };
+        let mut latest: Option<String> = None;
+        // Everything since the last poll, however many; rate limits are waited out.
+        let result = api::search_pages(&client, &search, usize::MAX, |tweets, _| {
+            if latest.is_none() {
+                latest = tweets.first().map(|t| t.id.clone());
+            }
+            print_search_results(&tweets, jsonl);
+            if notify {
+                notify_new_tweets(&tweets, "tweets", |_| format!("New tweet matching {query}"));
+            }
+        })
+        .await;
+        if let Err(e) = result {
+            eprintln!(
+                "{}  Search failed: {e}",
+                chrono::Local::now().format("%H:%M:%S")
+            );
+        } else if let Some(id) = latest {
+            if new_only {
+                seen::record(&mut seen, &key, &id);
+                save_seen(&seen_path, &seen);
+            }
+            newest = Some(id);
+        }
+    }
+}
+
+/// More new tweets than this get one desktop notification between them.
+const MAX_NOTIFICATIONS: usize = 3;
+
+/// Show `tweets` as desktop notifications for `--watch --notify`, each with
+/// the `title` it gets (naming the author, say), or one saying how many new
+/// `what` there are if there are many.
+fn notify_new_tweets(tweets: &[api::Tweet], what: &str, title: impl Fn(&api::Tweet) -> String) {
+    let shown = if tweets.len() > MAX_NOTIFICATIONS {
+        notify::desktop("xcli", &format!("{} new {what}", tweets.len()))
+    } else {
+        tweets
+            .iter()
+            .try_for_each(|tweet| notify::desktop(&title(tweet), &tweet.text))
+    };
+    if let Err(e) = shown {
+        eprintln!("{}  {e}", chrono::Local::now().format("%H:%M:%S"));
+    }
+}
+
+/// Print a page of search results as they arrive: as text, or one JSON
+/// object per line.
+fn print_search_results(tweets: &[api::Tweet], jsonl: bool) {
+    let mut out = String::new();
+    for tweet in tweets {
+        if jsonl {
+            out.push_str(&serde_json::to_string(tweet).unwrap());
+            out.push('\n');
+            continue;
+        }
+        let posted = tweet
+            .created_at
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| store::format_timestamp(t.timestamp()))
+            .unwrap_or_default();
+        out.push_str(&format!("{posted}  {}\n", tweet.id));
+        for line in tweet.text.lines() {
+            out.push_str(&format!("  {line}\n"));
+        }
+        out.push('\n');
+    }
+    let mut stdout = io::stdout();
+    if let Err(e) = stdout
+        .write_all(out.as_bytes())
+        .and_then(|_| stdout.flush())
+    {
+        // The reader (`head`, say) has what it wants.
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            std::process::exit(0);
+        }
+        eprintln!("Failed to write the results: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// The account with `handle` (`@` and profile URLs are fine).
+async fn lookup_user_or_exit(client: &XClient, handle: &str) -> api::User {
+    let handle = bulk::normalize_handle(handle);
+    match api::get_users_by_usernames(client, std::slice::from_ref(&handle)).await {
+        Ok(users) => match users.into_iter().next() {
+            Some(user) => user,
+            None => {
+                eprintln!("No account @{handle}");
+                std::process::exit(1);
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to look up @{handle}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// How `following prune` goes about unfollowing.
+struct PruneRun {
+    dry_run: bool,
+    yes: bool,
+    pace: Duration,
+}
+
+async fn handle_following_prune(
+    non_mutual: bool,
+    inactive_days: Option<u64>,
+    max: usize,
+    run: PruneRun,
+) {
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    let read = |what: &str, users: Result<Vec<api::User>, String>| match users {
+        Ok(users) => {
+            if users.len() >= max {
+                eprintln!("Note: only the first {max} of {what} were checked (see --max)");
+            }
+            users
+        }
+        Err(e) => {
+            eprintln!("Failed to fetch {what}: {e}");
+            std::process::exit(1);
+        }
+    };
+    let following = read(
+        "the accounts you follow",
+        api::get_following_activity(&client, &me.id, max).await,
+    );
+    let followers = if non_mutual {
+        Some(read(
+            "your followers",
+            api::get_followers(&client, &me.id, max).await,
+        ))
+    } else {
+        None
+    };
+
+    let active_since = inactive_days.map(|days| store::now() - days as i64 * 86_400);
+    let mut last_posted = HashMap::new();
+    if active_since.is_some() {
+        let unknown = following
+            .iter()
+            .filter(|u| u.most_recent_tweet_id.is_none())
+            .count();
+        if unknown > 0 {
+            eprintln!("Reading the timelines of {unknown} accounts...");
+        }
+        for user in &following {
+            let last = match &user.most_recent_tweet_id {
+                Some(id) => prune::posted_at(id),
+                None => match api::get_last_posted(&client, &user.id).await {
+                    Ok(time) => time
+                        .as_deref()
+                        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                        .map(|t| t.timestamp()),
+                    // Kept: an unreadable timeline is no sign of inactivity.
+                    Err(e) => {
+                        eprintln!("Warning: could not read @{}'s timeline: {e}", user.username);
+                        continue;
+                    }
+                },
+            };
+            last_posted.insert(user.id.clone(), last);
+        }
+    }
+
+    let total = following.len();
+    let candidates = prune::select(following, followers.as_deref(), active_since, &last_posted);
+    if candidates.is_empty() {
+        println!("Nothing to unfollow among the {total} accounts you follow.");
+        return;
+    }
+    println!(
+        "{} of the {total} accounts you follow would be unfollowed:",
+        candidates.len()
+    );
+    for c in &candidates {
+        let reasons: Vec<String> = c.reasons.iter().map(prune::Reason::describe).collect();
+        println!(
+            "  @{} ({}): {}",
+            c.user.username,
+            c.user.name,
+            reasons.join(", ")
+        );
+    }
+    if run.dry_run {
+        return;
+    }
+
+    let total_secs = run.pace.as_secs() * (candidates.len() as u64 - 1);
+    println!(
+        "Unfollowing at one account every {} will take about {}.",
+        duration::format_duration(run.pace),
+        duration::format_duration(Duration::from_secs(total_secs))
+    );
+    if !run.yes && !confirm(&format!("Unfollow {} accounts?", candidates.len())) {
+        println!("Aborted.");
+        return;
+    }
+
+    let mut unfollowed = 0;
+    let mut failed = 0;
+    for (i, c) in candidates.iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(run.pace).await;
+        }
+        let progress = format!("[{}/{}]", i + 1, candidates.len());
+        match api::unfollow_user(&client, &me.id, &c.user.id).await {
+            Ok(false) => {
+                unfollowed += 1;
+                println!("{progress} Unfollowed @{}.", c.user.username);
+            }
+            Ok(true) => {
+                failed += 1;
+                eprintln!("{progress} @{} is still followed.", c.user.username);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("{progress} Failed to unfollow @{}: {e}", c.user.username);
+            }
+        }
+    }
+
+    println!("Unfollowed {unfollowed} accounts.");
+    if failed > 0 {
+        eprintln!(
+            "{failed} accounts could not be unfollowed. Run the same command again to retry."
+        );
+        std::process::exit(1);
+    }
+}
+
+async fn handle_user_show(handle: &str, json: bool) {
+    let client = load_client_or_exit();
+    let handle = bulk::normalize_handle(handle);
+    let profile = match api::get_user_profile(&client, &handle).await {
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            eprintln!("No account @{handle}");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to look up @{handle}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&profile).unwrap());
+        return;
+    }
+
+    let user = &profile.user;
+    println!("{} (@{})", user.name, user.username);
+    println!("ID:        {}", user.id);
+    let fields = [
+        ("Bio", user.description.clone()),
+        ("Location", profile.location.clone()),
+        ("Link", profile.url.clone()),
+        (
+            "Joined",
+            profile.created_at.as_deref().map(|at| {
+                chrono::DateTime::parse_from_rfc3339(at)
+                    .map(|t| t.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|_| at.to_string())
+            }),
+        ),
+        (
+            "Verified",
+            profile.verified_type.as_deref().map(|t| match t {
+                "none" => "no".to_string(),
+                t => t.to_string(),
+            }),
+        ),
+        ("Protected", profile.protected.then(|| "yes".to_string())),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            println!("{:<10} {value}", format!("{label}:"));
+        }
+    }
+    if let Some(m) = user.public_metrics {
+        println!(
+            "Followers: {}  Following: {}  Tweets: {}  Listed: {}",
+            m.followers_count, m.following_count, m.tweet_count, m.listed_count
+        );
+    }
+}
+
+async fn handle_spaces_search(query: &str, state: api::SpaceState, limit: usize, json: bool) {
+    let client = load_client_or_exit();
+    let found = match api::search_spaces(&client, query, state, limit).await {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("Failed to search Spaces: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        print_spaces_json(&found);
+    } else if found.spaces.is_empty() {
+        println!("No Spaces match.");
+    } else {
+        for space in &found.spaces {
+            print!("{}", format_space(space, &found.hosts(space)));
+            println!();
+        }
+    }
+}
+
+async fn handle_spaces_show(id: &str, json: bool) {
+    let client = load_client_or_exit();
+    let found = match api::get_space(&client, id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            eprintln!("No Space {id}");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to fetch Space {id}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        print_spaces_json(&found);
+    } else {
+        let space = &found.spaces[0];
+        print!("{}", format_space(space, &found.hosts(space)));
+    }
+}
+
+/// Each Space as JSON, with its hosts under `hosts`.
+fn print_spaces_json(found: &api::Spaces) {
+    let spaces: Vec<serde_json::Value> = found
+        .spaces
+        .iter()
+        .map(|space| {
+            let mut value = serde_json::to_value(space).unwrap();
+            value["hosts"] = serde_json::to_value(found.hosts(space)).unwrap();
+            value
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&spaces).unwrap());
+}
+
+/// A Space as a few lines of text: state, title and ID, hosts, then when it
+/// is happening and to how many.
+fn format_space(space: &api::Space, hosts: &[&api::User]) -> String {
+    let at = |time: &Option<String>| {
+        time.as_deref().map(|t| {
+            chrono::DateTime::parse_from_rfc3339(t)
+                .map(|t| store::format_timestamp(t.timestamp()))
+                .unwrap_or_else(|_| t.to_string())
+        })
+    };
+    let mut out = format!(
+        "[{}] {}  https://x.com/i/spaces/{}\n",
+        space.state,
+        space.title.as_deref().unwrap_or("(untitled)"),
+        space.id
+    );
+    if !hosts.is_empty() {
+        let hosts: Vec<String> = hosts
+            .iter()
+            .map(|u| format!("@{} ({})", u.username, u.name))
+            .collect();
+        out.push_str(&format!("  Hosted by {}\n", hosts.join(", ")));
+    }
+    let when = match space.state.as_str() {
+        "scheduled" => at(&space.scheduled_start).map(|t| format!("Starts {t}")),
+        "ended" => at(&space.ended_at).map(|t| format!("Ended {t}")),
+        _ => at(&space.started_at).map(|t| format!("Started {t}")),
+    };
+    let count = match space.state.as_str() {
+        "scheduled" => space.subscriber_count.map(|n| format!("{n} interested")),
+        _ => space.participant_count.map(|n| format!("{n} listening")),
+    };
+    let details: Vec<String> = [
+        when,
+        count,
+        space.is_ticketed.then(|| "ticketed".to_string()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !details.is_empty() {
+        out.push_str(&format!("  {}\n", details.join(" · ")));
+    }
+    out
+}
+
+async fn handle_mutuals(handle: &str, max: usize, json: bool) {
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    let target = lookup_user_or_exit(&client, handle).await;
+
+    let read = |what: &str, users: Result<Vec<api::User>, String>| match users {
+        Ok(users) => {
+            if users.len() >= max {
+                eprintln!("Note: only the first {max} of {what} were compared (see --max)");
+            }
+            users
+        }
+        Err(e) => {
+            eprintln!("Failed to fetch {what}: {e}");
+            std::process::exit(1);
+        }
+    };
+    eprintln!("Comparing your connections with @{}...", target.username);
+    let my_following = read(
+        "the accounts you follow",
+        api::get_following(&client, &me.id, max).await,
+    );
+    let my_followers = read(
+        "your followers",
+        api::get_followers(&client, &me.id, max).await,
+    );
+    let their_followers = read(
+        &format!("@{}'s followers", target.username),
+        api::get_followers(&client, &target.id, max).await,
+    );
+    let you_follow = mutuals::shared(&my_following, &their_followers);
+    let common_followers = mutuals::shared(&my_followers, &their_followers);
+
+    if json {
+        let out = serde_json::json!({
+            "you_follow": you_follow,
+            "common_followers": common_followers,
+        });
+        println!("{}", serde_json::to_string_pretty(&out).unwrap());
+        return;
+    }
+    let section = |title: String, users: &[api::User]| {
+        println!("{title} ({})", users.len());
+        for user in users {
+            println!("  @{} ({})", user.username, user.name);
+        }
+    };
+    section(
+        format!("People you follow who follow @{}", target.username),
+        &you_follow,
+    );
+    println!();
+    section(
+        format!("People who follow both you and @{}", target.username),
+        &common_followers,
+    );
+}
+
+#[derive(Clone, Copy)]
+enum ListChange {
+    Follow,
+    Unfollow,
+    Pin,
+}
+
+async fn handle_list_change(list_id: &str, change: ListChange) {
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    // The state X reports afterwards: following, or pinned.
+    let (result, wanted, action, done) = match change {
+        ListChange::Follow => (
+            api::follow_list(&client, &me.id, list_id).await,
+            true,
+            "follow",
+            "Followed",
+        ),
+        ListChange::Unfollow => (
+            api::unfollow_list(&client, &me.id, list_id).await,
+            false,
+            "unfollow",
+            "Unfollowed",
+        ),
+        ListChange::Pin => (
+            api::pin_list(&client, &me.id, list_id).await,
+            true,
+            "pin",
+            "Pinned",
+        ),
+    };
+    match result {
+        Ok(state) if state == wanted => println!("{done} List {list_id}"),
+        Ok(_) => {
+            eprintln!("X did not {action} List {list_id}");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to {action} List {list_id}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn handle_list_create(settings: &api::ListSettings) {
+    let client = load_client_or_exit();
+    let name = settings.name.as_deref().unwrap_or_default();
+    match api::create_list(&client, settings).await {
+        Ok(id) => {
+            eprintln!("Created List \"{name}\": https://x.com/i/lists/{id}");
+            println!("{id}");
+        }
+        Err(e) => {
+            eprintln!("Failed to create List \"{name}\": {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn handle_list_update(list_id: &str, settings: &api::ListSettings) {
+    let client = load_client_or_exit();
+    match api::update_list(&client, list_id, settings).await {
+        Ok(true) => println!("Updated List {list_id}"),
+        Ok(false) => {
+            eprintln!("X did not update List {list_id}");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to update List {list_id}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn handle_list_delete(list_id: &str, yes: bool) {
+    if !yes && !confirm(&format!("Delete List {list_id}?")) {
+        println!("Aborted.");
+        return;
+    }
+    let client = load_client_or_exit();
+    match api::delete_list(&client, list_id).await {
+        Ok(true) => println!("Deleted List {list_id}"),
+        Ok(false) => {
+            eprintln!("X did not delete List {list_id}");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to delete List {list_id}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Add (or with `add` false, remove) the accounts of `handles` to List
+/// `list_id`.
+async fn handle_list_members(list_id: &str, handles: &[String], add: bool) {
+    let client = load_client_or_exit();
+    let handles: Vec<String> = handles.iter().map(|h| bulk::normalize_handle(h)).collect();
+    let users = match api::get_users_by_usernames(&client, &handles).await {
+        Ok(users) => users,
+        Err(e) => {
+            eprintln!("Failed to look up the accounts: {e}");
+            std::process::exit(1);
+        }
+    };
+    let (action, done, preposition) = if add {
+        ("add", "Added", "to")
+    } else {
+        ("remove", "Removed", "from")
+    };
+    let mut failed = false;
+    for handle in &handles {
+        let Some(user) = users
+            .iter()
+            .find(|u| u.username.eq_ignore_ascii_case(handle))
+        else {
+            eprintln!("No account @{handle}");
+            failed = true;
+            continue;
+        };
+        let result = if add {
+            api::add_list_member(&client, list_id, &user.id).await
+        } else {
+            api::remove_list_member(&client, list_id, &user.id)
+                .await
+                .map(|member| !member)
+        };
+        match result {
+            Ok(true) => println!("{done} @{} {preposition} List {list_id}", user.username),
+            Ok(false) => {
+                eprintln!(
+                    "X did not {action} @{} {preposition} List {list_id}",
+                    user.username
+                );
+                failed = true;
+            }
+            Err(e) => {
+                eprintln!("Failed to {action} @{}: {e}", user.username);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+async fn handle_list_timeline(list_id: &str, limit: usize, format: TimelineFormat) {
+    let client = load_client_or_exit();
+    let timeline = match api::get_list_tweets(&client, list_id, limit).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to fetch the List's tweets: {e}");
+            std::process::exit(1);
+        }
+    };
+    print_timeline(&timeline, format, true);
+}
+
+async fn handle_timeline(handle: Option<&str>, kind: api::TimelineKind, args: TimelineArgs) {
+    let client = load_client_or_exit();
+    let user = match handle {
+        Some(handle) => lookup_user_or_exit(&client, handle).await,
+        None => match api::get_me(&client).await {
+            Ok(u) => u,
+            Err(e) => {
+                eprintln!("Failed to look up your account: {e}");
+                std::process::exit(1);
+            }
+        },
+    };
+    let start_time = args.start_time.map(store::format_rfc3339);
+    let end_time = args.end_time.map(store::format_rfc3339);
+    let seen_path = config::seen_path();
+    let mut seen = seen::load(&seen_path);
+    let key = seen::timeline_key(kind.endpoint(), &user.id);
+    let since_id = match args.new_only {
+        true => seen.get(&key).cloned(),
+        false => args.since_id,
+    };
+    let window = api::Window {
+        since_id: since_id.as_deref(),
+        until_id: args.until_id.as_deref(),
+        start_time: start_time.as_deref(),
+        end_time: end_time.as_deref(),
+    };
+    // With --new-only, every tweet since the last run, however many, or some
+    // would never be printed.
+    let limit = match (args.new_only, &since_id) {
+        (true, Some(_)) => usize::MAX,
+        _ => args.limit.max_items(),
+    };
+    let timeline = match api::get_timeline(&client, &user.id, kind, &window, limit).await {
+        Ok(timeline) => timeline,
+        Err(e) => {
+            eprintln!("Failed to fetch tweets: {e}");
+            std::process::exit(1);
+        }
+    };
+    print_timeline(&timeline, args.format, true);
+    let mut newest = timeline.tweets.first().map(|t| t.id.clone()).or(since_id);
+    if let (true, Some(newest)) = (args.new_only, &newest) {
+        seen::record(&mut seen, &key, newest);
+        save_seen(&seen_path, &seen);
+    }
+
+    let Some(interval) = args.watch.interval() else {
+        return;
+    };
+    eprintln!(
+        "Watching for new tweets every {}. Press Ctrl-C to stop.",
+        duration::format_duration(interval)
+    );
+    loop {
+        tokio::time::sleep(interval).await;
+        let window = api::Window {
+            since_id: newest.as_deref(),
+            start_time: start_time.as_deref().filter(|_| newest.is_none()),
+            ..api::Window::default()
+        };
+        // Everything since the last poll, however many; rate limits are waited out.
+        match api::get_timeline(&client, &user.id, kind, &window, usize::MAX).await {
+            Ok(timeline) => {
+                print_timeline(&timeline, args.format, false);
+                if args.watch.notify {
+                    let (kind, what) = match kind {
+                        api::TimelineKind::Tweets => ("tweet", "tweets"),
+                        api::TimelineKind::Mentions => ("mention", "mentions"),
+                    };
+                    notify_new_tweets(&timeline.tweets, what, |tweet| {
+                        let handle = timeline.author(tweet).map_or("i", |u| u.username.as_str());
+                        format!("New {kind} from @{handle}")
+                    });
+                }
+                if let Some(tweet) = timeline.tweets.first() {
+                    newest = Some(tweet.id.clone());
+                    if args.new_only {
+                        seen::record(&mut seen, &key, &tweet.id);
+                        save_seen(&seen_path, &seen);
+                    }
+                }
+            }
+            Err(e) => eprintln!(
+                "{}  Failed to fetch tweets: {e}",
+                chrono::Local::now().format("%H:%M:%S")
+            ),
+        }
+    }
+}
+
+/// Save what `--new-only` has printed, or exit.
+fn save_seen(path: &Path, seen: &seen::Seen) {
+    if let Err(e) = seen::save(path, seen) {
+        eprintln!("Failed to remember the newest tweet: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn handle_replies(id: &str, limit: usize) {
+    let all = Settings::load().is_ok_and(|s| s.full_archive_search);
+    let client = load_client_or_exit();
+    let conversation = match api::get_conversation(&client, id, all, limit).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to fetch the conversation: {e}");
+            std::process::exit(1);
+        }
+    };
+    let print = |depth: usize, tweet: &api::Tweet| {
+        let indent = "  ".repeat(depth);
+        let (handle, name) = conversation
+            .author(tweet)
+            .map_or(("i", ""), |u| (u.username.as_str(), u.name.as_str()));
+        let posted = tweet
+            .created_at
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| store::format_timestamp(t.timestamp()))
+            .unwrap_or_default();
+        println!("{indent}@{handle} ({name})  {posted}  {}", tweet.id);
+        for line in tweet.text.lines() {
+            println!("{indent}  {line}");
+        }
+        println!();
+    };
+
+    print(0, &conversation.tweets[0]);
+    let replies = replies::tree(id, &conversation.tweets);
+    for &(depth, tweet) in &replies {
+        print(depth, tweet);
+    }
+    eprintln!("{} replies", replies.len());
+}
+
+/// Print the tweets of `timeline` in `format`, newest first. The CSV header
+/// line is left out without `header` (for the later reads of `--watch`).
+fn print_timeline(timeline: &api::Timeline, format: TimelineFormat, header: bool) {
+    let handle = |tweet: &api::Tweet| {
+        timeline
+            .author(tweet)
+            .map_or("i".to_string(), |u| u.username.clone())
+    };
+    let posted = |tweet: &api::Tweet| {
+        tweet
+            .created_at
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| store::format_timestamp(t.timestamp()))
+            .unwrap_or_default()
+    };
+
+    let mut out = String::new();
+    match format {
+        TimelineFormat::Text => {
+            for tweet in &timeline.tweets {
+                let name = timeline.author(tweet).map_or("", |u| u.name.as_str());
+                out.push_str(&format!(
+                    "@{} ({name})  {}  {}\n",
+                    handle(tweet),
+                    posted(tweet),
+                    tweet.id
+                ));
+                for line in tweet.text.lines() {
+                    out.push_str(&format!("  {line}\n"));
+                }
+                out.push('\n');
+            }
+        }
+        TimelineFormat::Markdown => {
+            for tweet in &timeline.tweets {
+                let text = tweet.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                let handle = handle(tweet);
+                out.push_str(&format!(
+                    "- **@{handle}**: {text} ([{}]({}))\n",
+                    posted(tweet),
+                    hooks::tweet_url(Some(&handle), &tweet.id)
+                ));
+            }
+        }
+        TimelineFormat::Csv => {
+            if header {
+                out.push_str("id,handle,created_at,text,likes,retweets,replies\n");
+            }
+            for tweet in &timeline.tweets {
+                let metrics = tweet.public_metrics.unwrap_or_default();
+                out.push_str(&export::csv_row(&[
+                    tweet.id.clone(),
+                    handle(tweet),
+                    tweet.created_at.clone().unwrap_or_default(),
+                    tweet.text.clone(),
+                    metrics.like_count.to_string(),
+                    metrics.retweet_count.to_string(),
+                    metrics.reply_count.to_string(),
+                ]));
+                out.push('\n');
+            }
+        }
+        TimelineFormat::Jsonl => {
+            for tweet in &timeline.tweets {
+                let mut value = serde_json::to_value(tweet).unwrap();
+                value["username"] = handle(tweet).into();
+                out.push_str(&value.to_string());
+                out.push('\n');
+            }
+        }
+    }
+    print!("{out}");
+}
+
+#[cfg(feature = "history")]
+fn handle_archive_import(path: &std::path::Path, account: Option<String>) {
+    let archive = match archive::read_archive(path) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    let account = account
+        .or(archive.account)
+        .or_else(|| Credentials::load().map(|c| c.screen_name))
+        .unwrap_or_default();
+
+    let store = open_store_or_exit();
+    match store.import_archive(&account, &archive.tweets) {
+        Ok(added) => println!(
+            "Imported {added} tweets ({} already in history).",
+            archive.tweets.len() - added
+        ),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_regex(s: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(s).map_err(|e| e.to_string())
+}
+
+async fn handle_bulk_delete(
+    older_than: Option<Duration>,
+    matching: Option<regex::Regex>,
+    dry_run: bool,
+    yes: bool,
+    pace: Duration,
+) {
+    let store = open_store_or_exit();
+    let until = match older_than {
+        Some(age) => store::now() - age.as_secs() as i64,
+        None => i64::MAX,
+    };
+    let candidates: Vec<_> = match store.history_before(until) {
+        Ok(entries) => entries
+            .into_iter()
+            .filter(|e| matching.as_ref().is_none_or(|re| re.is_match(&e.text)))
+            .collect(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if candidates.is_empty() {
+        println!("No tweets match.");
+        return;
+    }
+
+    let archived = candidates
+        .iter()
+        .filter(|e| e.source == store::SOURCE_ARCHIVE)
+        .count();
+    println!(
+        "{} tweets match ({} posted with xcli, {archived} from the archive), {} to {}.",
+        candidates.len(),
+        candidates.len() - archived,
+        store::format_timestamp(candidates[0].posted_at),
+        store::format_timestamp(candidates[candidates.len() - 1].posted_at)
+    );
+
+    if dry_run {
+        for entry in &candidates {
+            println!(
+                "  {}  {}  {}",
+                store::format_timestamp(entry.posted_at),
+                entry.tweet_id,
+                preview(&entry.text, 60)
+            );
+        }
+        return;
+    }
+
+    let total_secs = pace.as_secs() * (candidates.len() as u64 - 1);
+    println!(
+        "Deleting at one tweet every {} will take about {}.",
+        duration::format_duration(pace),
+        duration::format_duration(Duration::from_secs(total_secs))
+    );
+    if !yes && !confirm(&format!("Delete {} tweets?", candidates.len())) {
+        println!("Aborted.");
+        return;
+    }
+
+    let client = load_client_or_exit();
+    let mut deleted = 0;
+    let mut failed = 0;
+    for (i, entry) in candidates.iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(pace).await;
+        }
+        let progress = format!("[{}/{}]", i + 1, candidates.len());
+        match api::delete_tweet(&client, &entry.tweet_id)
+            .await
+            .map(|r| r.data)
+        {
+            Ok(true) => {
+                deleted += 1;
+                println!("{progress} Tweet {} deleted.", entry.tweet_id);
+            }
+            Ok(false) => {
+                failed += 1;
+                eprintln!("{progress} Tweet {} was not deleted.", entry.tweet_id);
+                continue;
+            }
+            // Already gone: nothing left to delete.
+            Err(e) if e.contains("(404") => {
+                println!("{progress} Tweet {} no longer exists.", entry.tweet_id);
+            }
+            Err(e) if e.contains("(429") => {
+                eprintln!("{progress} Rate limited by X: {e}");
+                eprintln!("Stopping. Run the same command later to continue where this left off.");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("{progress} Failed to delete tweet {}: {e}", entry.tweet_id);
+                continue;
+            }
+        }
+        if let Err(e) = store.forget_tweet(&entry.tweet_id) {
+            eprintln!("Warning: {e}");
+        }
+    }
+
+    println!("Deleted {deleted} tweets.");
+    if failed > 0 {
+        eprintln!("{failed} tweets could not be deleted.");
+        std::process::exit(1);
+    }
+}
+
+/// The X API returns at most this many of a user's most recent tweets.
+const USER_TIMELINE_MAX: usize = 3200;
+
+async fn handle_metrics_export(ids_file: &Path, output: Option<PathBuf>) {
+    let content = read_text_or_exit(None, Some(ids_file));
+    let ids = match stats::parse_tweet_ids(&content) {
+        Ok(ids) if ids.is_empty() => {
+            eprintln!("Error: no tweet IDs in {}", ids_file.display());
+            std::process::exit(1);
+        }
+        Ok(ids) => ids,
+        Err(e) => {
+            eprintln!("Error: {}: {e}", ids_file.display());
+            std::process::exit(1);
+        }
+    };
+
+    let client = load_client_or_exit();
+    eprintln!("Fetching metrics of {} tweets...", ids.len());
+    let mut tweets = match api::get_tweets(&client, &ids).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to fetch tweets: {e}");
+            std::process::exit(1);
+        }
+    };
+    let order: HashMap<&str, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    tweets.sort_by_key(|t| order.get(t.id.as_str()).copied().unwrap_or(usize::MAX));
+    let found: HashSet<&str> = tweets.iter().map(|t| t.id.as_str()).collect();
+    for id in ids.iter().filter(|id| !found.contains(id.as_str())) {
+        eprintln!("Not found: {id}");
+    }
+
+    let what = format!("metrics of {} tweets", tweets.len());
+    write_export(output, &stats::metrics_csv(&tweets), &what);
+}
+
+async fn handle_stats_best_time(since: i64, top: usize, min_posts: u32, json: bool) {
+    let store = open_store_or_exit();
+    let entries = match store.history_since(Some(since)) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    // Posts of their own: replies draw a different crowd.
+    let posted: HashMap<String, i64> = entries
+        .iter()
+        .filter(|e| e.position == 0 && e.in_reply_to.is_none())
+        .map(|e| (e.tweet_id.clone(), e.posted_at))
+        .collect();
+    if posted.is_empty() {
+        eprintln!(
+            "No posts in local history since {}",
+            store::format_timestamp(since)
+        );
+        std::process::exit(1);
+    }
+
+    let client = load_client_or_exit();
+    let ids: Vec<String> = posted.keys().cloned().collect();
+    eprintln!("Fetching metrics of {} posts...", ids.len());
+    let tweets = match api::get_tweets(&client, &ids).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to fetch tweets: {e}");
+            std::process::exit(1);
+        }
+    };
+    let posts: Vec<(i64, u64)> = tweets
+        .iter()
+        .map(|t| {
+            let engagement = stats::engagement(&t.public_metrics.unwrap_or_default());
+            (posted[&t.id], engagement)
+        })
+        .collect();
+    if posts.is_empty() {
+        eprintln!("None of these posts exist on X anymore");
+        std::process::exit(1);
+    }
+    let times = stats::PostingTimes::from_posts(&posts);
+    let best = times.best(top, min_posts);
+
+    if json {
+        let out = serde_json::json!({
+            "posts": posts.len(),
+            "best": best,
+            "slots": times.slots(),
+        });
+        println!("{}", serde_json::to_string_pretty(&out).unwrap());
+        return;
+    }
+    println!(
+        "Average engagement by local weekday and hour ({} posts):",
+        posts.len()
+    );
+    print!("{}", times.heatmap());
+    println!();
+    if best.is_empty() {
+        println!(
+            "No hour has {min_posts} or more posts yet; try --min-posts 1 or a longer --since."
+        );
+        return;
+    }
+    println!("Best times to post:");
+    for slot in &best {
+        println!(
+            "  {} {:02}:00-{:02}:00  {:.1} average engagement over {} posts",
+            slot.weekday,
+            slot.hour,
+            (slot.hour + 1) % 24,
+            slot.average_engagement,
+            slot.posts
+        );
+    }
+}
+
+fn handle_stats_activity(days: usize, heatmap: bool) {
+    let days = days.max(1);
+    let today = chrono::Local::now().date_naive();
+    let first_day = today - chrono::Days::new(days as u64 - 1);
+    let since = duration::parse_since(&first_day.format("%Y-%m-%d").to_string()).ok();
+
+    let store = open_store_or_exit();
+    let entries = match store.history_since(since) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    // A thread counts as one post.
+    let timestamps: Vec<i64> = entries
+        .iter()
+        .filter(|e| e.position == 0)
+        .map(|e| e.posted_at)
+        .collect();
+    let counts = stats::daily_counts(&timestamps, today, days);
+    let total: u32 = counts.iter().sum();
+    let active = counts.iter().filter(|&&c| c > 0).count();
+
+    println!(
+        "Last {days} days: {total} posts on {active} days ({:.1} per day)",
+        total as f64 / days as f64
+    );
+    if heatmap {
+        print!("{}", stats::heatmap(&counts, first_day));
+    } else {
+        println!("{}  {}  {}", first_day, stats::sparkline(&counts), today);
+    }
+    let (longest, current) = stats::streaks(&counts);
+    println!("Longest streak: {longest} days, current streak: {current} days");
+}
+
+async fn handle_stats(since: i64, top: usize, json: bool) {
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    let tweets = match api::get_user_tweets(
+        &client,
+        &me.id,
+        &store::format_rfc3339(since),
+        USER_TIMELINE_MAX,
+    )
+    .await
+    {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to fetch tweets: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let summary = stats::summarize(&tweets, top);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+        return;
+    }
+
+    println!(
+        "@{}: {} tweets since {}",
+        me.username,
+        summary.tweets,
+        store::format_timestamp(since)
+    );
+    if summary.tweets == 0 {
+        return;
+    }
+    let t = &summary.totals;
+    let a = &summary.averages;
+    println!();
+    println!("               total        avg");
+    println!(
+        "  Impressions {:>7} {:>10.1}",
+        t.impression_count, a.impressions
+    );
+    println!("  Likes       {:>7} {:>10.1}", t.like_count, a.likes);
+    println!("  Reposts     {:>7} {:>10.1}", t.retweet_count, a.reposts);
+    println!("  Replies     {:>7} {:>10.1}", t.reply_count, a.replies);
+    println!("  Quotes      {:>7} {:>10.1}", t.quote_count, a.quotes);
+
+    println!();
+    println!("Top posts:");
+    for (i, tweet) in summary.top.iter().enumerate() {
+        let m = tweet.public_metrics.unwrap_or_default();
+        println!(
+            "  {}. {}  likes {}, reposts {}, replies {}, impressions {}",
+            i + 1,
+            tweet.id,
+            m.like_count,
+            m.retweet_count,
+            m.reply_count,
+            m.impression_count
+        );
+        println!("     {}", preview(&tweet.text, 70));
+    }
+}
+
+async fn handle_show(id: &str, watch_metrics: bool, interval: u64, json: bool) {
+    let client = load_client_or_exit();
+    let resp = match api::get_tweet(&client, id).await {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Failed to fetch tweet: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&resp).unwrap());
+        return;
+    }
+    print_meta("", &resp.meta);
+    let tweet = resp.data;
+
+    println!("ID: {}", tweet.id);
+    if let Some(created_at) = &tweet.created_at {
+        println!("Posted: {created_at}");
+    }
+    println!("{}", tweet.text);
+    let mut last = tweet.public_metrics.unwrap_or_default();
+    println!();
+    println!("{}", stats::format_metrics(&last, None));
+
+    if !watch_metrics {
+        return;
+    }
+    println!("Watching metrics every {interval}s. Press Ctrl-C to stop.");
+    loop {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let now = chrono::Local::now().format("%H:%M:%S");
+        match api::get_tweet(&client, id).await {
+            Ok(resp) => {
+                print_meta("", &resp.meta);
+                let t = resp.data;
+                let current = t.public_metrics.unwrap_or_default();
+                println!("{now}  {}", stats::format_metrics(&current, Some(&last)));
+                last = current;
+            }
+            Err(e) => eprintln!("{now}  Failed to fetch metrics: {e}"),
+        }
+    }
+}
+
+async fn handle_bulk(
+    action: bulk::Action,
+    handles: Vec<String>,
+    from_file: Option<PathBuf>,
+    pace: Duration,
+    skip_blocked: bool,
+) {
+    let mut all: Vec<String> = handles.iter().map(|h| bulk::normalize_handle(h)).collect();
+    if let Some(path) = &from_file {
+        match fs::read_to_string(path) {
+            Ok(content) => all.extend(bulk::parse_handles(&content)),
+            Err(e) => {
+                eprintln!("Failed to read {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    let progress = from_file.as_deref().map(bulk::progress_path);
+
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if skip_blocked {
+        let blocked = match api::get_blocking(&client, &me.id).await {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("Failed to fetch your block list: {e}");
+                std::process::exit(1);
+            }
+        };
+        let (pending, already) = bulk::exclude_existing(all, &blocked);
+        if already > 0 {
+            println!("Skipping {already} accounts you already block.");
+        }
+        all = pending;
+    }
+
+    let mut known: Vec<api::User> = match open_store_or_exit().contacts(Some(&all)) {
+        Ok(contacts) => contacts.into_iter().map(contact_user).collect(),
+        Err(e) => {
+            eprintln!("Warning: {e}");
+            Vec::new()
+        }
+    };
+    let cache_path = config::user_ids_path();
+    let mut cache = idcache::load(&cache_path);
+    let cached: Vec<String> = all
+        .iter()
+        .filter(|h| !known.iter().any(|u| u.username.eq_ignore_ascii_case(h)))
+        .cloned()
+        .collect();
+    known.extend(idcache::known(&cache, &cached, store::now()));
+
+    let report = match bulk::apply(
+        &client,
+        action,
+        &me.id,
+        &all,
+        known,
+        progress.as_deref(),
+        pace,
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if !report.looked_up.is_empty() {
+        idcache::remember(&mut cache, &report.looked_up, store::now());
+        if let Err(e) = idcache::save(&cache_path, &cache) {
+            eprintln!("Warning: {e}");
+        }
+    }
+    if report.resumed > 0 {
+        println!(
+            "Skipped {} handles already done in a previous run.",
+            report.resumed
+        );
+    }
+    println!("{} accounts {}.", report.applied, action.past_tense());
+    if !report.not_found.is_empty() {
+        eprintln!("Not found: {}", report.not_found.join(", "));
+    }
+    if !report.failed.is_empty() {
+        for (handle, e) in &report.failed {
+            eprintln!("Failed for @{handle}: {e}");
+        }
+        if let Some(path) = &progress {
+            eprintln!(
+                "Progress saved to {}. Run the same command again to retry.",
+                path.display()
+            );
+        }
+        std::process::exit(1);
+    }
+}
+
+fn contact_user(contact: store::Contact) -> api::User {
+    api::User {
+        id: contact.user_id,
+        username: contact.handle,
+        name: contact.name,
+        description: None,
+        public_metrics: None,
+        profile_image_url: None,
+        most_recent_tweet_id: None,
+    }
+}
+
+#[cfg(feature = "history")]
+async fn handle_feed(action: FeedAction) {
+    let store = open_store_or_exit();
+
+    match action {
+        FeedAction::Add { url, template } => {
+            if let Err(e) = feed::validate_template(&template) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+            let entries = match feed::fetch(&*web_transport(), &url).await {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            let ids: Vec<String> = entries.into_iter().map(|e| e.id).collect();
+            match store.add_feed(&url, &template, &ids) {
+                Ok(true) => println!(
+                    "Subscribed to {url}. Its {} current entries will not be posted.",
+                    ids.len()
+                ),
+                Ok(false) => {
+                    eprintln!("Already subscribed to {url}.");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        FeedAction::List => {
+            let feeds = match store.feeds() {
+                Ok(f) => f,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            if feeds.is_empty() {
+                println!("No feeds yet. Subscribe with `xcli feed add <url>`.");
+                return;
+            }
+            for f in feeds {
+                let synced = f
+                    .synced_at
+                    .map_or("never".to_string(), store::format_timestamp);
+                println!(
+                    "{}\n  template: {}\n  added:    {}\n  synced:   {synced}",
+                    f.url,
+                    f.template,
+                    store::format_timestamp(f.added_at)
+                );
+            }
+        }
+        FeedAction::Remove { url } => match store.remove_feed(&url) {
+            Ok(true) => println!("Unsubscribed from {url}."),
+            Ok(false) => {
+                eprintln!("Not subscribed to {url}.");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        },
+        FeedAction::Sync { dry_run } => {
+            let client = load_client_or_exit();
+            if !sync_feeds(&client, &store, dry_run).await {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Feeds and media URLs are fetched over plain HTTP, also under `--sandbox`,
+/// which only fakes the X API.
+fn web_transport() -> Arc<dyn Transport> {
+    traced(Arc::new(HttpTransport::default()))
+}
+
+/// Post the new entries of every feed, oldest first. An entry that fails is
+/// tried again next time. Returns false if anything failed.
+#[cfg(feature = "history")]
+async fn sync_feeds(client: &XClient, store: &Store, dry_run: bool) -> bool {
+    let feeds = match store.feeds() {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return false;
+        }
+    };
+    let transport = web_transport();
+    let mut ok = true;
+    for f in feeds {
+        let entries = match feed::fetch(&*transport, &f.url).await {
+            Ok(e) => e,
+            Err(e) => {
+                applog::warn(&e);
+                eprintln!("Error: {e}");
+                ok = false;
+                continue;
+            }
+        };
+        for entry in entries {
+            match store.feed_entry_seen(&f.url, &entry.id) {
+                Ok(false) => {}
+                Ok(true) => continue,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    return false;
+                }
+            }
+            let chunks = prepare_chunks(feed::render(&f.template, &entry), false, true);
+            if dry_run {
+                println!("Would post from {}:", f.url);
+                for chunk in &chunks {
+                    println!("  ({}/280) {chunk}", thread::weighted_len(chunk));
+                }
+                continue;
+            }
+            match api::create_thread(client, &chunks).await {
+                Ok(posted) => {
+                    let ids = api::ids(&posted);
+                    println!("Posted \"{}\" from {}: {}", entry.title, f.url, ids[0]);
+                    record_history(client, None, &ids, &chunks);
+                    if let Err(e) = store.mark_feed_entry(&f.url, &entry.id, &ids[0]) {
+                        eprintln!("Error: {e}");
+                        return false;
+                    }
+                }
+                Err(e) => {
+                    let message = format!(
+                        "Failed to post \"{}\" from {}: {}",
+                        entry.title, f.url, e.error
+                    );
+                    applog::error(&message);
+                    eprintln!("{message}");
+                    ok = false;
+                    if !e.posted_ids.is_empty() {
+                        // Half a thread is out; posting it again would repeat that half.
+                        record_history(client, None, &e.posted_ids, &chunks);
+                        let _ = store.mark_feed_entry(&f.url, &entry.id, &e.posted_ids[0]);
+                    }
+                }
+            }
+        }
+        if !dry_run {
+            let _ = store.mark_feed_synced(&f.url, store::now());
+        }
+    }
+    ok
+}
+
+async fn handle_contacts(action: ContactsAction) {
+    let store = open_store_or_exit();
+
+    match action {
+        ContactsAction::Add { handles } => {
+            let handles: Vec<String> = handles.iter().map(|h| bulk::normalize_handle(h)).collect();
+            let client = load_client_or_exit();
+            let users = match api::get_users_by_usernames(&client, &handles).await {
+                Ok(u) => u,
+                Err(e) => {
+                    eprintln!("Failed to look up accounts: {e}");
+                    std::process::exit(1);
+                }
+            };
+            for user in &users {
+                if let Err(e) = store.save_contact(&user.username, &user.id, &user.name) {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+                println!("Saved @{} ({}, ID {})", user.username, user.name, user.id);
+            }
+            let missing: Vec<&String> = handles
+                .iter()
+                .filter(|h| !users.iter().any(|u| u.username.eq_ignore_ascii_case(h)))
+                .collect();
+            if !missing.is_empty() {
+                for handle in missing {
+                    eprintln!("Not found: @{handle}");
+                }
+                std::process::exit(1);
+            }
+        }
+        ContactsAction::List => {
+            let contacts = match store.contacts(None) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            if contacts.is_empty() {
+                println!("No contacts yet. Add some with `xcli contacts add <handle>`.");
+                return;
+            }
+            for c in contacts {
+                println!(
+                    "@{:<16} {:<24} {:<20} added {}",
+                    c.handle,
+                    c.name,
+                    c.user_id,
+                    store::format_timestamp(c.added_at)
+                );
+            }
+        }
+        ContactsAction::Remove { handles } => {
+            let mut ok = true;
+            for handle in handles.iter().map(|h| bulk::normalize_handle(h)) {
+                match store.remove_contact(&handle) {
+                    Ok(true) => println!("Removed @{handle}"),
+                    Ok(false) => {
+                        ok = false;
+                        eprintln!("No contact @{handle}");
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            if !ok {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+async fn handle_block_export(output: Option<PathBuf>) {
+    let client = load_client_or_exit();
+    let me = match api::get_me(&client).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to look up your account: {e}");
+            std::process::exit(1);
+        }
+    };
+    eprintln!("Fetching accounts blocked by @{}...", me.username);
+    let blocked = match api::get_blocking(&client, &me.id).await {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("Failed to fetch your block list: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let header = format!(
+        "Blocked by @{}, exported {}",
+        me.username,
+        store::format_timestamp(store::now())
+    );
+    write_export(
+        output,
+        &bulk::format_handles(&header, &blocked),
+        &format!("{} blocked accounts", blocked.len()),
+    );
+}
+
+async fn handle_undo(yes: bool) {
+    let store = open_store_or_exit();
+    let last = match store.recent_posts(1) {
+        Ok(mut posts) => posts.pop(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    let Some(last) = last else {
+        println!("Nothing to undo: no posts recorded yet.");
+        return;
+    };
+    let entries = match store.thread_entries(&last.root_id) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if entries.len() == 1 {
+        println!(
+            "Last post ({}): {}  {}",
+            store::format_timestamp(last.posted_at),
+            last.tweet_id,
+            preview(&last.text, 60)
+        );
+    } else {
+        println!(
+            "Last post ({}): thread of {} tweets",
+            store::format_timestamp(last.posted_at),
+            entries.len()
+        );
+        for entry in &entries {
+            println!(
+                "  [{}/{}] {}  {}",
+                entry.position + 1,
+                entries.len(),
+                entry.tweet_id,
+                preview(&entry.text, 60)
+            );
+        }
+    }
+
+    if !yes && !confirm("Delete it?") {
+        println!("Aborted.");
+        return;
+    }
+
+    let client = load_client_or_exit();
+    let mut failed = false;
+    for entry in entries.iter().rev() {
+        match api::delete_tweet(&client, &entry.tweet_id)
+            .await
+            .map(|r| r.data)
+        {
+            Ok(true) => {
+                println!("Tweet {} deleted.", entry.tweet_id);
+                if let Err(e) = store.forget_tweet(&entry.tweet_id) {
+                    eprintln!("Warning: {e}");
+                }
+            }
+            Ok(false) => {
+                failed = true;
+                eprintln!("Tweet {} was not deleted.", entry.tweet_id);
+            }
+            Err(e) => {
+                failed = true;
+                eprintln!("Failed to delete tweet {}: {e}", entry.tweet_id);
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Single-line preview of a tweet, truncated to `max` characters.
+fn preview(text: &str, max: usize) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= max {
+        flat
+    } else {
+        let cut: String = flat.chars().take(max.saturating_sub(3)).collect();
+        format!("{cut}...")
+    }
+}
+
+/// How far back the duplicate guard looks.
+const DUPLICATE_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Refuse to post text that was already posted recently, unless `allow` is set.
+/// The text given on the command line, or read from `file` ("-" for stdin).
+fn read_text_or_exit(text: Option<String>, file: Option<&Path>) -> String {
+    let Some(path) = file else {
+        return text.unwrap_or_default();
+    };
+    let result = if path == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    };
+    match result {
+        Ok(content) if content.trim().is_empty() => {
+            eprintln!("Error: {} is empty.", path.display());
+            std::process::exit(1);
+        }
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error: could not read {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Output of `--from-command`. Exits if the command cannot run or prints
+/// nothing.
+fn run_command_or_exit(command: &str) -> String {
+    match capture::run(command) {
+        Ok((output, _)) if capture::clean(&output).is_empty() => {
+            eprintln!("Error: `{command}` printed nothing.");
+            std::process::exit(1);
+        }
+        Ok((output, failure)) => {
+            if let Some(failure) = failure {
+                eprintln!("Note: `{command}` failed ({failure}); posting its output anyway.");
+            }
+            output
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The kind of media `source` (a `--media` value) is expected to be, to check
+/// the combination before anything is read. Files must have a known
+/// extension; URLs without one and stdin count as images.
+fn expected_kind(source: &str, stdin_type: Option<&'static str>) -> Result<media::Kind, String> {
+    if source == "-" {
+        Ok(stdin_type.map_or(media::Kind::Image, media::Kind::of))
+    } else if source.starts_with("https://") || source.starts_with("http://") {
+        let path = source.split(['?', '#']).next().unwrap_or_default();
+        Ok(api::media_type(Path::new(path)).map_or(media::Kind::Image, media::Kind::of))
+    } else {
+        api::media_type(Path::new(source)).map(media::Kind::of)
+    }
+}
+
+/// A `--media` source ready to upload: a file, or an image read from stdin
+/// or downloaded.
+enum MediaSource {
+    File(PathBuf),
+    Data {
+        data: Vec<u8>,
+        mime: &'static str,
+        name: String,
+    },
+}
+
+/// Media IDs of the `--media` attachments, in the order given, with the
+/// `--alt` texts (or those of the alt text command) set. Everything is read
+/// (and downloaded) and its combination checked before the first upload, and
+/// uploads go one at a time, so the order is kept. Exits on the first that
+/// fails, before anything is posted.
+async fn upload_media_or_exit(
+    client: &XClient,
+    sources: &[String],
+    alts: &[String],
+    stdin_type: Option<&'static str>,
+    options: &api::MediaOptions,
+) -> Vec<String> {
+    let describe = |source: &str| if source == "-" { "stdin" } else { source }.to_string();
+    let mut ready = Vec::new();
+    for source in sources {
+        let result = if source == "-" {
+            read_media_stdin(stdin_type)
+        } else if source.starts_with("https://") || source.starts_with("http://") {
+            download_media(source).await
+        } else {
+            Ok(MediaSource::File(PathBuf::from(source)))
+        };
+        match result {
+            Ok(media) => ready.push(media),
+            Err(e) => {
+                eprintln!("Failed to upload {}: {e}", describe(source));
+                std::process::exit(1);
+            }
+        }
+    }
+    // What stdin and URLs held is only known now.
+    let kinds: Result<Vec<media::Kind>, String> = ready
+        .iter()
+        .map(|m| match m {
+            MediaSource::File(path) => api::media_type(path).map(media::Kind::of),
+            MediaSource::Data { mime, .. } => Ok(media::Kind::of(mime)),
+        })
+        .collect();
+    let kinds = kinds
+        .and_then(|kinds| media::check_combination(&kinds).map(|()| kinds))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        });
+
+    let command = load_profile_or_exit().alt_text_command;
+    let mut alts: Vec<Option<String>> = alts.iter().cloned().map(Some).collect();
+    alts.resize(sources.len(), None);
+    for (i, alt) in alts.iter_mut().enumerate() {
+        if let (None, Some(command)) = (&alt, &command) {
+            if kinds[i] != media::Kind::Video {
+                *alt = suggest_alt_text(command, &describe(&sources[i]), &ready[i], i);
+            }
+        }
+    }
+    let images: Vec<(String, &str)> = sources
+        .iter()
+        .zip(&alts)
+        .zip(&kinds)
+        .filter(|(_, kind)| **kind != media::Kind::Video)
+        .map(|((source, alt), _)| (describe(source), alt.as_deref().unwrap_or("")))
+        .collect();
+    alt_text_lint_or_exit(&images, false);
+
+    let mut ids = Vec::new();
+    for ((source, media), alt) in sources.iter().zip(ready).zip(alts) {
+        let result = match media {
+            MediaSource::File(path) => api::upload_media_with(client, &path, options).await,
+            MediaSource::Data { data, mime, name } => {
+                api::upload_media_data(client, data, mime, &name, options).await
+            }
+        };
+        let result = match (result, alt) {
+            (Ok(id), Some(alt)) => api::set_alt_text(client, &id, &alt)
+                .await
+                .map(|()| id)
+                .map_err(|e| format!("Failed to set its alt text: {e}")),
+            (result, _) => result,
+        };
+        match result {
+            Ok(id) => ids.push(id),
+            Err(e) => {
+                eprintln!("Failed to upload {}: {e}", describe(source));
+                if let Some(upload) = unfinished_upload(Path::new(source)) {
+                    eprintln!(
+                        "{} of {} parts were sent. Run the command again to carry on, or `xcli media resume {}`.",
+                        upload.segments.len(),
+                        upload.chunk_count(),
+                        upload.media_id
+                    );
+                }
+                std::process::exit(1);
+            }
+        }
+    }
+    ids
+}
+
+/// Alt text for `media` from the alt text `command`, shown for approval when
+/// someone is at the terminal. Images in memory are handed to the command as
+/// a temporary file. A failing command only costs the alt text.
+fn suggest_alt_text(
+    command: &str,
+    source: &str,
+    media: &MediaSource,
+    index: usize,
+) -> Option<String> {
+    let result = match media {
+        MediaSource::File(path) => hooks::alt_text(command, &config::profile_name(), path),
+        MediaSource::Data { data, mime, .. } => {
+            let path = env::temp_dir().join(format!(
+                "xcli_alt_{}_{index}.{}",
+                std::process::id(),
+                media::extension(mime)
+            ));
+            let result = fs::write(&path, data)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+                .and_then(|()| hooks::alt_text(command, &config::profile_name(), &path));
+            let _ = fs::remove_file(&path);
+            result
+        }
+    };
+    let text = match result {
+        Ok(text) if !text.is_empty() => text,
+        Ok(_) => return None,
+        Err(e) => {
+            eprintln!("Warning: no alt text for {source}: {e}");
+            return None;
+        }
+    };
+    let text = if text.chars().count() > api::MAX_ALT_TEXT {
+        eprintln!(
+            "Warning: the alt text for {source} was cut to {} characters",
+            api::MAX_ALT_TEXT
+        );
+        text.chars().take(api::MAX_ALT_TEXT).collect()
+    } else {
+        text
+    };
+    eprintln!("Alt text for {source}: {text}");
+    if !io::stdin().is_terminal() || confirm("Use it?") {
+        return Some(text);
+    }
+    prompt_optional("Alt text")
+}
+
+/// Read the image piped to stdin, of type `mime` or else detected.
+fn read_media_stdin(mime: Option<&'static str>) -> Result<MediaSource, String> {
+    let mut data = Vec::new();
+    io::stdin()
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read stdin: {e}"))?;
+    if data.is_empty() {
+        return Err("Nothing was piped to stdin".to_string());
+    }
+    let mime = mime
+        .or_else(|| media::sniff(&data))
+        .ok_or("Not a JPEG, PNG, WebP or GIF image; pass --media-type if it is one")?;
+    let name = format!("stdin.{}", media::extension(mime));
+    Ok(MediaSource::Data { data, mime, name })
+}
+
+/// Download the image at `url`, named after the last part of the URL's path.
+async fn download_media(url: &str) -> Result<MediaSource, String> {
+    let (data, mime) = media::download(&*web_transport(), url).await?;
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|u| u.split_once("://"))
+        .and_then(|(_, rest)| rest.split_once('/'))
+        .and_then(|(_, path)| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .map_or_else(
+            || format!("media.{}", media::extension(mime)),
+            str::to_string,
+        );
+    Ok(MediaSource::Data { data, mime, name })
+}
+
+/// The `--caption-file` and `--alt-file` of `thread photos`.
+struct PhotoFiles {
+    captions: Option<PathBuf>,
+    alts: Option<PathBuf>,
+}
+
+/// Lines of `path` (none without one), one per photo: exits if there are
+/// more than `photos` or one is longer than `max` characters.
+/// The photos of `thread photos` with their alt texts, for `alt_text_lint_or_exit`.
+fn photo_alts<'a>(photos: &[PathBuf], alts: &'a [String]) -> Vec<(String, &'a str)> {
+    photos
+        .iter()
+        .zip(alts)
+        .map(|(photo, alt)| (photo.display().to_string(), alt.as_str()))
+        .collect()
+}
+
+fn photo_lines_or_exit(path: Option<&Path>, photos: usize, max: usize) -> Vec<String> {
+    let Some(path) = path else {
+        return vec![String::new(); photos];
+    };
+    let mut lines = photos::read_lines(path).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    if lines.len() > photos {
+        eprintln!(
+            "Error: {} has {} lines for {photos} photos",
+            path.display(),
+            lines.len()
+        );
+        std::process::exit(1);
+    }
+    if let Some(i) = lines.iter().position(|l| l.chars().count() > max) {
+        eprintln!(
+            "Error: line {} of {} is longer than {max} characters",
+            i + 1,
+            path.display()
+        );
+        std::process::exit(1);
+    }
+    lines.resize(photos, String::new());
+    lines
+}
+
+async fn handle_thread_photos(
+    sources: &[String],
+    files: &PhotoFiles,
+    one_per_tweet: bool,
+    dry_run: bool,
+    options: &api::MediaOptions,
+    json: bool,
+) {
+    let photos = photos::collect(sources).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let kinds: Vec<media::Kind> = photos
+        .iter()
+        .map(|p| media::Kind::of(api::media_type(p).unwrap_or_default()))
+        .collect();
+    if let Some(i) = kinds.iter().position(|&k| k == media::Kind::Video) {
+        eprintln!(
+            "Error: {} is a video; `thread photos` takes images",
+            photos[i].display()
+        );
+        std::process::exit(1);
+    }
+    let captions = photo_lines_or_exit(
+        files.captions.as_deref(),
+        photos.len(),
+        thread::MAX_WEIGHTED_LEN,
+    );
+    let mut alts = photo_lines_or_exit(files.alts.as_deref(), photos.len(), api::MAX_ALT_TEXT);
+
+    let groups = photos::group(&kinds, if one_per_tweet { 1 } else { media::MAX_IMAGES });
+    let texts: Vec<String> = groups
+        .iter()
+        .map(|g| {
+            let lines: Vec<&str> = g
+                .iter()
+                .map(|&i| captions[i].as_str())
+                .filter(|c| !c.is_empty())
+                .collect();
+            lines.join("\n")
+        })
+        .collect();
+    if let Some(i) = texts
+        .iter()
+        .position(|t| thread::weighted_len(t) > thread::MAX_WEIGHTED_LEN)
+    {
+        eprintln!(
+            "Error: the captions of tweet {} are over 280 characters together; try --one-per-tweet",
+            i + 1
+        );
+        std::process::exit(1);
+    }
+
+    if dry_run {
+        println!(
+            "Thread preview ({} tweets, {} photos):",
+            groups.len(),
+            photos.len()
+        );
+        for (n, (group, text)) in groups.iter().zip(&texts).enumerate() {
+            let line = format!(
+                "  [{}/{}] ({}/280) {text}",
+                n + 1,
+                groups.len(),
+                thread::weighted_len(text)
+            );
+            println!("{}", line.trim_end());
+            for &i in group {
+                match alts[i].as_str() {
+                    "" => println!("      {}", photos[i].display()),
+                    alt => println!("      {} (alt text: {alt})", photos[i].display()),
+                }
+            }
+        }
+        if load_profile_or_exit().alt_text_command.is_none() {
+            alt_text_lint_or_exit(&photo_alts(&photos, &alts), true);
+        }
+        return;
+    }
+
+    let client = load_client_or_exit();
+    if let Some(command) = load_profile_or_exit().alt_text_command {
+        for (i, photo) in photos.iter().enumerate() {
+            if alts[i].is_empty() {
+                let media = MediaSource::File(photo.clone());
+                let source = photo.display().to_string();
+                alts[i] = suggest_alt_text(&command, &source, &media, i).unwrap_or_default();
+            }
+        }
+    }
+    alt_text_lint_or_exit(&photo_alts(&photos, &alts), false);
+    let mut ids = Vec::new();
+    for (i, photo) in photos.iter().enumerate() {
+        let source = photo.display().to_string();
+        let result = match api::upload_media_with(&client, photo, options).await {
+            Ok(id) if !alts[i].is_empty() => api::set_alt_text(&client, &id, &alts[i])
+                .await
+                .map(|()| id)
+                .map_err(|e| format!("Failed to set its alt text: {e}")),
+            result => result,
+        };
+        match result {
+            Ok(id) => ids.push(id),
+            Err(e) => {
+                eprintln!("Failed to upload {source}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    let media: Vec<Vec<String>> = groups
+        .iter()
+        .map(|g| g.iter().map(|&i| ids[i].clone()).collect())
+        .collect();
+
+    match api::create_thread_with_tweet_media(&client, &texts, &media).await {
+        Ok(posted) => {
+            print_posted("Thread", None, &posted, json);
+            let ids = api::ids(&posted);
+            record_history(&client, None, &ids, &texts);
+            run_post_post_hook(&client, &ids);
+            notify_webhook(&client, None, texts.len(), &ids, None).await;
+        }
+        Err(e) => {
+            eprintln!(
+                "Thread failed at tweet [{}/{}]: {}",
+                e.failed_index + 1,
+                texts.len(),
+                e.error
+            );
+            if !e.posted_ids.is_empty() {
+                eprintln!("Already posted:");
+                for (i, id) in e.posted_ids.iter().enumerate() {
+                    eprintln!("  [{}/{}] ID: {id}", i + 1, texts.len());
+                }
+                record_history(&client, None, &e.posted_ids, &texts);
+            }
+            notify_webhook(&client, None, texts.len(), &e.posted_ids, Some(&e.error)).await;
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The recorded, unfinished upload of the file at `path`, if any.
+fn unfinished_upload(path: &Path) -> Option<uploads::Upload> {
+    let path = fs::canonicalize(path).ok()?;
+    uploads::load(&config::uploads_path(), store::now())
+        .into_iter()
+        .find(|u| u.path == path && !u.finished)
+}
+
+/// Posted media can't be used again, so their upload records go.
+fn forget_uploads(media_ids: &[String]) {
+    if media_ids.is_empty() {
+        return;
+    }
+    if let Err(e) = uploads::forget(&config::uploads_path(), media_ids, store::now()) {
+        eprintln!("Warning: {e}");
+    }
+}
+
+async fn handle_media(action: MediaAction) {
+    let now = store::now();
+    let recorded = uploads::load(&config::uploads_path(), now);
+    match action {
+        MediaAction::List => {
+            if recorded.is_empty() {
+                println!("No video uploads in progress.");
+                return;
+            }
+            for upload in recorded {
+                let progress = if upload.finished {
+                    "uploaded, not posted yet".to_string()
+                } else {
+                    format!(
+                        "{} of {} parts sent",
+                        upload.segments.len(),
+                        upload.chunk_count()
+                    )
+                };
+                println!(
+                    "  {} {} ({}, {progress}, expires {})",
+                    upload.media_id,
+                    upload.path.display(),
+                    media::format_size(upload.total_bytes as usize),
+                    store::format_timestamp(upload.expires_at)
+                );
+            }
+        }
+        MediaAction::Resume { media_id } => {
+            let ids: Vec<String> = match media_id {
+                Some(id) => vec![id],
+                None => recorded
+                    .into_iter()
+                    .filter(|u| !u.finished)
+                    .map(|u| u.media_id)
+                    .collect(),
+            };
+            if ids.is_empty() {
+                println!("No interrupted uploads to resume.");
+                return;
+            }
+            let client = load_client_or_exit();
+            let mut ok = true;
+            for id in ids {
+                match api::resume_upload(&client, &id).await {
+                    Ok(id) => println!("Uploaded media ID {id}"),
+                    Err(e) => {
+                        eprintln!("Failed to resume {id}: {e}");
+                        ok = false;
+                    }
+                }
+            }
+            if !ok {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Print the `--dry-run` preview of `chunks` under `single` (one tweet) or
+/// `thread`. With a `[spellcheck]` in the profile, possible misspellings are
+/// underlined (on a terminal) and listed after it.
+fn print_preview(single: &str, thread: &str, chunks: &[String]) {
+    let misspelled = match load_profile_or_exit().spellcheck {
+        Some(config) => spell::misspelled(&config, &chunks.join("\n")).unwrap_or_else(|e| {
+            eprintln!("Warning: spellcheck skipped: {e}");
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let shown = |chunk: &str| {
+        if io::stdout().is_terminal() {
+            spell::underline(chunk, &misspelled)
+        } else {
+            chunk.to_string()
+        }
+    };
+    if chunks.len() == 1 {
+        println!(
+            "{single} ({}/280):\n  {}",
+            thread::weighted_len(&chunks[0]),
+            shown(&chunks[0])
+        );
+    } else {
+        println!("{thread}:");
+        for (i, chunk) in chunks.iter().enumerate() {
+            println!(
+                "  [{}/{}] ({}/280) {}",
+                i + 1,
+                chunks.len(),
+                thread::weighted_len(chunk),
+                shown(chunk)
+            );
+        }
+    }
+    if !misspelled.is_empty() {
+        println!("Possible misspellings: {}", misspelled.join(", "));
+    }
+}
+
+/// Check `chunks` against the profile's lint rules. Problems are errors that
+/// stop the post, or only warnings with `warn_only` or in a dry run.
+fn lint_or_exit(chunks: &[String], dry_run: bool) {
+    let rules = load_profile_or_exit().lint;
+    let problems = lint::check(&rules, chunks).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    let block = !rules.warn_only && !dry_run;
+    for problem in &problems {
+        let label = if block { "Error" } else { "Warning" };
+        eprintln!("{label}: {}", problem.describe(chunks.len()));
+    }
+    if block && !problems.is_empty() {
+        eprintln!("Fix the text, or set warn_only in the profile's [lint] table to post anyway.");
+        std::process::exit(1);
+    }
+}
+
+/// `lint_or_exit` for the images of a post, as (name, alt text): under the
+/// profile's `require_alt_text`, each without alt text stops the post.
+fn alt_text_lint_or_exit(images: &[(String, &str)], dry_run: bool) {
+    let rules = load_profile_or_exit().lint;
+    let problems = lint::check_alt_text(&rules, images);
+    let block = !rules.warn_only && !dry_run;
+    for problem in &problems {
+        let label = if block { "Error" } else { "Warning" };
+        eprintln!("{label}: {}", problem.describe(1));
+    }
+    if block && !problems.is_empty() {
+        eprintln!("Describe every image (--alt, --alt-file or alt_text_command), or set warn_only in the profile's [lint] table to post anyway.");
+        std::process::exit(1);
+    }
+}
+
+fn handle_lint(file: &Path) {
+    let text = read_text_or_exit(None, Some(file));
+    let chunks = prepare_chunks(text, false, true);
+    let rules = load_profile_or_exit().lint;
+    let problems = lint::check(&rules, &chunks).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    if problems.is_empty() {
+        println!("No problems found.");
+        return;
+    }
+    for problem in &problems {
+        println!("{}", problem.describe(chunks.len()));
+    }
+    std::process::exit(1);
+}
+
+/// `text` translated into `lang` by the profile's translate command, after
+/// the original (as the thread's next tweet) if `with_original`.
+fn translate_or_exit(text: String, lang: &str, with_original: bool) -> String {
+    let Some(command) = load_profile_or_exit().translate_command else {
+        eprintln!("Error: --translate needs a translate_command in the profile (config.toml)");
+        std::process::exit(1);
+    };
+    let translated = match hooks::translate(&command, &config::profile_name(), &text, lang) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    if with_original {
+        format!("{}\n---\n{translated}", text.trim_end())
+    } else {
+        translated
+    }
+}
+
+/// Whether to strip image metadata, after `--keep-metadata`,
+/// `--strip-metadata` and the profile's `strip_metadata`.
+fn strips_metadata(keep: bool, strip: bool) -> bool {
+    let flag = (keep || strip).then_some(strip);
+    load_profile_or_exit().strips_metadata(flag)
+}
+
+fn load_profile_or_exit() -> config::Profile {
+    match Settings::load().and_then(|s| s.profile(&config::profile_name())) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Apply `--markdown` and the profile's typography, then split the text,
+/// adding the profile's hashtags (unless `--no-tags`) and signature.
+fn prepare_chunks(text: String, markdown: bool, tags: bool) -> Vec<String> {
+    let profile = load_profile_or_exit();
+    let text = if markdown {
+        markdown::to_unicode(&text)
+    } else {
+        text
+    };
+    let text = if profile.typography {
+        typography::smarten(&text)
+    } else {
+        text
+    };
+    let text = if profile.clean_urls {
+        urls::clean(&text, &profile.tracking_params)
+    } else {
+        text
+    };
+    let hashtags = if tags {
+        thread::missing_hashtags(&text, &profile.default_hashtags).join(" ")
+    } else {
+        String::new()
+    };
+    let (mut prefix, mut suffix) = (String::new(), String::new());
+    if !hashtags.is_empty() {
+        match profile.hashtag_position {
+            HashtagPosition::Prepend => prefix = format!("{hashtags} "),
+            HashtagPosition::Append => suffix = format!(" {hashtags}"),
+        }
+    }
+    suffix.push_str(profile.signature.as_deref().unwrap_or_default());
+    thread::split_with_affixes(&text, &prefix, &suffix)
+}
+
+/// Pass the chunks through the profile's pre-post hook, if any. Exits when
+/// the hook vetoes the post or returns chunks that cannot be posted.
+fn run_pre_post_hook(original: Vec<String>) -> Vec<String> {
+    let Some(command) = load_profile_or_exit().hooks.pre_post else {
+        return original;
+    };
+    let chunks = match hooks::pre_post(&command, &config::profile_name(), &original) {
+        Ok(c) if c.is_empty() => {
+            eprintln!("Error: pre_post hook returned no text. Nothing posted.");
+            std::process::exit(1);
+        }
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: {e}. Nothing posted.");
+            std::process::exit(1);
+        }
+    };
+    if let Err((idx, len)) = thread::validate_chunks(&chunks) {
+        eprintln!(
+            "Error: chunk {} from the pre_post hook exceeds 280 characters ({len}/280). Nothing posted.",
+            idx + 1
+        );
+        std::process::exit(1);
+    }
+    // The hook's text must pass the same rules as the text it was given.
+    if chunks != original {
+        lint_or_exit(&chunks, false);
+    }
+    chunks
+}
+
+fn run_post_post_hook(client: &XClient, ids: &[String]) {
+    let Some(command) = load_profile_or_exit().hooks.post_post else {
+        return;
+    };
+    let screen_name = client.config().screen_name.as_deref();
+    if let Err(e) = hooks::post_post(&command, &config::profile_name(), screen_name, ids) {
+        eprintln!("Warning: {e}");
+    }
+}
+
+/// POST the outcome of a post to the profile's `notify_url`, if any.
+async fn notify_webhook(
+    client: &XClient,
+    in_reply_to: Option<&str>,
+    tweets: usize,
+    ids: &[String],
+    error: Option<&str>,
+) {
+    let Some(url) = load_profile_or_exit().notify_url else {
+        return;
+    };
+    if nothing_really_posted() {
+        eprintln!("Skipping webhook {url}: nothing was really posted.");
+        return;
+    }
+    let account = client.config().screen_name.as_deref();
+    let profile = config::profile_name();
+    let payload = notify::payload(account, &profile, in_reply_to, tweets, ids, error);
+    if let Err(e) = notify::send(&*traced(Arc::new(HttpTransport::default())), &url, &payload).await
+    {
+        eprintln!("Warning: {e}");
+    }
+}
+
+/// True under `--sandbox` and when replaying, where posts are faked and must
+/// not be announced or mirrored.
+fn nothing_really_posted() -> bool {
+    config::is_sandbox()
+        || matches!(
+            CASSETTE.get(),
+            Some(Cassette::Replay(_) | Cassette::Trace(_))
+        )
+}
+
+/// Post `chunks` to the `--also` networks. A failure there is only a warning,
+/// as the tweets are out already.
+async fn mirror_chunks(networks: &[Network], chunks: &[String], json: bool) {
+    if networks.is_empty() {
+        return;
+    }
+    if nothing_really_posted() {
+        let names: Vec<&str> = networks.iter().map(|n| n.name()).collect();
+        eprintln!(
+            "Skipping {}: nothing was really posted.",
+            names.join(" and ")
+        );
+        return;
+    }
+    let profile = load_profile_or_exit();
+    let transport = traced(Arc::new(HttpTransport::default()));
+    for &network in networks {
+        match mirror::post(&*transport, &profile, network, chunks).await {
+            Ok(urls) => {
+                // Keep stdout parseable under --json.
+                let line = format!("Also posted to {}: {}", network.name(), urls.join(" "));
+                if json {
+                    eprintln!("{line}");
+                } else {
+                    println!("{line}");
+                }
+            }
+            Err(e) => {
+                applog::error(&format!("{} cross-post failed: {e}", network.name()));
+                eprintln!("Warning: failed to post to {}: {e}", network.name());
+            }
+        }
+    }
+}
+
+fn check_duplicates(chunks: &[String], allow: bool) {
+    if !store::ENABLED {
+        return;
+    }
+    let since = store::now() - DUPLICATE_WINDOW_SECS;
+    let duplicates = match Store::open().and_then(|store| store.find_duplicates(chunks, since)) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Warning: could not check for duplicates: {e}");
+            return;
+        }
+    };
+    if duplicates.is_empty() {
+        return;
+    }
+
+    let label = if allow { "Warning" } else { "Error" };
+    for (i, entry) in &duplicates {
+        let which = if chunks.len() == 1 {
+            "This text".to_string()
+        } else {
+            format!("Chunk {}", i + 1)
+        };
+        eprintln!(
+            "{label}: {which} was already posted on {} (ID: {}).",
+            store::format_timestamp(entry.posted_at),
+            entry.tweet_id
+        );
+    }
+    if !allow {
+        eprintln!("X rejects duplicate tweets. Use --allow-duplicate to post anyway.");
+        std::process::exit(1);
+    }
+}
+
+fn schedule_deletion(ids: &[String], after: Duration) {
+    let store = open_store_or_exit();
+    let due_at = store::now() + after.as_secs() as i64;
+    for id in ids {
+        if let Err(e) = store.enqueue(store::ACTION_DELETE, id, due_at) {
+            eprintln!("Warning: {e}");
+            return;
+        }
+    }
+    println!(
+        "Scheduled for deletion at {} (run `xcli queue run` to process).",
+        store::format_timestamp(due_at)
+    );
+}
+
+#[cfg(feature = "stream")]
+async fn handle_stream_rules(action: RulesAction) {
+    if let RulesAction::Add { value, .. } = &action {
+        if let Err(e) = stream::check_rule(value) {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+    let client = load_client_or_exit();
+    let bearer = match api::get_bearer_token(&client).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to get an app-only token: {e}");
+            std::process::exit(1);
+        }
+    };
+    let existing = match api::get_stream_rules(&client, &bearer).await {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Failed to fetch stream rules: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    match action {
+        RulesAction::List => {
+            if existing.is_empty() {
+                println!("No stream rules. Add one with `xcli stream rules add`.");
+            }
+            for rule in &existing {
+                let id = rule.id.as_deref().unwrap_or("?");
+                match &rule.tag {
+                    Some(tag) => println!("{id}  {} [{tag}]", rule.value),
+                    None => println!("{id}  {}", rule.value),
+                }
+            }
+        }
+        RulesAction::Add {
+            value,
+            tag,
+            dry_run,
+        } => {
+            if let Some(rule) = existing.iter().find(|r| r.value == value) {
+                println!(
+                    "The rule already exists (ID {}).",
+                    rule.id.as_deref().unwrap_or("?")
+                );
+                return;
+            }
+            let rule = api::StreamRule {
+                id: None,
+                value,
+                tag,
+            };
+            match api::add_stream_rules(&client, &bearer, &[rule], dry_run).await {
+                Ok(_) if dry_run => println!("[dry-run] The rule is valid and would be added."),
+                Ok(added) => {
+                    let id = added.first().and_then(|r| r.id.as_deref()).unwrap_or("?");
+                    println!("Added rule {id}.");
+                }
+                Err(e) => {
+                    eprintln!("Failed to add the rule: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        RulesAction::Delete { ids } => {
+            if let Some(id) = ids
+                .iter()
+                .find(|id| !existing.iter().any(|r| r.id.as_ref() == Some(*id)))
+            {
+                eprintln!(
+                    "Error: there is no stream rule with ID {id} (see `xcli stream rules list`)"
+                );
+                std::process::exit(1);
+            }
+            match api::delete_stream_rules(&client, &bearer, &ids).await {
+                Ok(deleted) => println!("Deleted {deleted} rules."),
+                Err(e) => {
+                    eprintln!("Failed to delete rules: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        RulesAction::Export { file } => {
+            if let Err(e) = fs::write(&file, stream::rules_to_json(&existing)) {
+                eprintln!("Failed to write {}: {e}", file.display());
+                std::process::exit(1);
+            }
+            println!("Exported {} rules to {}", existing.len(), file.display());
+        }
+        RulesAction::Import { file, dry_run } => {
+            let wanted = match fs::read_to_string(&file)
+                .map_err(|e| format!("Failed to read {}: {e}", file.display()))
+                .and_then(|content| stream::parse_rules(&content))
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            };
+            let missing = stream::missing_rules(&wanted, &existing);
+            let skipped = wanted.len() - missing.len();
+            if missing.is_empty() {
+                println!("All {} rules already exist.", wanted.len());
+                return;
+            }
+            match api::add_stream_rules(&client, &bearer, &missing, dry_run).await {
+                Ok(_) if dry_run => {
+                    println!(
+                        "[dry-run] {} rules are valid and would be added.",
+                        missing.len()
+                    )
+                }
+                Ok(added) => {
+                    for rule in &added {
+                        match &rule.tag {
+                            Some(tag) => println!("  + {} [{tag}]", rule.value),
+                            None => println!("  + {}", rule.value),
+                        }
+                    }
+                    println!("Added {} rules.", added.len());
+                }
+                Err(e) => {
+                    eprintln!("Failed to add rules: {e}");
+                    std::process::exit(1);
+                }
+            }
+            if skipped > 0 {
+                println!("Skipped {skipped} rules that already exist.");
+            }
+        }
+    }
+}
+
+/// Forward the filtered stream to `url`. Tweets go through a buffer on disk:
+/// one task reads the stream into it, reconnecting as X asks, and another
+/// delivers from it in order, retrying while the webhook fails.
+#[cfg(feature = "stream")]
+async fn handle_stream_forward(url: &str) {
+    let client = load_client_or_exit();
+    let bearer = stream_bearer_or_exit(&client).await;
+    let spool = stream::Spool::new(config::stream_buffer_path());
+    let buffered = spool.len().unwrap_or(0);
+    println!("Forwarding the filtered stream to {url}. Press Ctrl-C to stop.");
+    if buffered > 0 {
+        println!("{buffered} tweets from an earlier run are still to be delivered.");
+    }
+    let arrived = tokio::sync::Notify::new();
+
+    let mut buffer = |line: &str| match spool.push(line) {
+        Ok(()) => arrived.notify_one(),
+        Err(e) => eprintln!("{e}"),
+    };
+    let read = follow_stream(&client, &bearer, &mut buffer);
+
+    let deliver = async {
+        let transport = web_transport();
+        let mut failures = 0;
+        loop {
+            let line = match spool.first() {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    arrived.notified().await;
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            };
+            let now = chrono::Local::now().format("%H:%M:%S");
+            let id = stream::tweet_id(&line);
+            match stream::deliver(&*transport, url, &line).await {
+                stream::Delivery::Sent => {
+                    failures = 0;
+                    println!("{now}  Forwarded {id}");
+                }
+                stream::Delivery::Retry(e) => {
+                    failures += 1;
+                    let delay =
+                        stream::backoff(Duration::from_secs(1), Duration::from_secs(300), failures);
+                    eprintln!(
+                        "{now}  {e}; retrying {id} in {}",
+                        duration::format_duration(delay)
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                stream::Delivery::Rejected(e) => {
+                    failures = 0;
+                    eprintln!("{now}  {e}; dropping {id}");
+                }
+            }
+            if let Err(e) = spool.pop() {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    };
+
+    tokio::join!(read, deliver);
+}
+
+/// Print each tweet of the filtered stream as a line of JSON, for other
+/// tools to read; everything else goes to stderr.
+#[cfg(feature = "stream")]
+async fn handle_stream_run() {
+    let client = load_client_or_exit();
+    let bearer = stream_bearer_or_exit(&client).await;
+    eprintln!("Reading the filtered stream. Press Ctrl-C to stop.");
+    let mut stdout = io::stdout();
+    follow_stream(&client, &bearer, &mut |line| {
+        if let Err(e) = writeln!(stdout, "{}", line.trim_end()).and_then(|()| stdout.flush()) {
+            // The reader (`head`, say) has what it wants.
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                std::process::exit(0);
+            }
+            eprintln!("Failed to write the tweet: {e}");
+            std::process::exit(1);
+        }
+    })
+    .await;
+}
+
+/// An app-only token to read the filtered stream with, once it is clear
+/// the app has rules to stream.
+#[cfg(feature = "stream")]
+async fn stream_bearer_or_exit(client: &XClient) -> String {
+    let bearer = match api::get_bearer_token(client).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to get an app-only token: {e}");
+            std::process::exit(1);
+        }
+    };
+    match api::get_stream_rules(client, &bearer).await {
+        Ok(rules) if rules.is_empty() => {
+            eprintln!("The app has no stream rules. Add some with `xcli stream rules add`.");
+            std::process::exit(1);
+        }
+        Ok(_) => bearer,
+        Err(e) => {
+            eprintln!("Failed to fetch stream rules: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Read the filtered stream until the process ends, handing each tweet line
+/// to `each`. X closes the stream now and then (and sends keep-alives in
+/// between, which are skipped); it is reconnected with a growing delay while
+/// it keeps failing.
+#[cfg(feature = "stream")]
+async fn follow_stream(client: &XClient, bearer: &str, each: &mut crate::transport::OnLine<'_>) {
+    let mut failures = 0;
+    loop {
+        let mut received = false;
+        let result = api::read_stream(client, bearer, &mut |line| {
+            received = true;
+            each(line);
+        })
+        .await;
+        if received {
+            failures = 0;
+        }
+        failures += 1;
+        let delay = stream::backoff(Duration::from_secs(5), Duration::from_secs(320), failures);
+        let why = result
+            .err()
+            .unwrap_or_else(|| "The stream closed".to_string());
+        eprintln!(
+            "{}  {why}; reconnecting in {}",
+            chrono::Local::now().format("%H:%M:%S"),
+            duration::format_duration(delay)
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(feature = "render")]
+async fn handle_render(
+    id: Option<String>,
+    text: Option<String>,
+    out: &Path,
+    font: Option<&Path>,
+    dark: bool,
+) {
+    let fonts = render::Fonts::load(font).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let client = load_client_or_exit();
+    let (text, author, created_at, metrics) = match (id, text) {
+        (Some(id), _) => match api::get_tweet_card(&client, &id).await {
+            Ok((_, None)) => {
+                eprintln!("X did not say who posted tweet {id}");
+                std::process::exit(1);
+            }
+            Ok((tweet, Some(author))) => {
+                (tweet.text, author, tweet.created_at, tweet.public_metrics)
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch tweet: {e}");
+                std::process::exit(1);
+            }
+        },
+        (None, text) => match api::get_me_card(&client).await {
+            Ok(me) => (text.unwrap_or_default(), me, None, None),
+            Err(e) => {
+                eprintln!("Failed to fetch your profile: {e}");
+                std::process::exit(1);
+            }
+        },
+    };
+
+    // The API hands out the 48x48 avatar; the same URL serves larger ones.
+    let avatar = match &author.profile_image_url {
+        Some(url) => {
+            let url = url.replace("_normal.", "_400x400.");
+            match media::download(&*web_transport(), &url).await {
+                Ok((data, mime)) => match media::decode(&data, mime) {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        eprintln!("Warning: could not read the avatar: {e}");
+                        None
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Warning: could not download the avatar: {e}");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    let posted = created_at
+        .as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%-I:%M %p · %b %-d, %Y")
+                .to_string()
+        });
+    let card = render::Card {
+        name: &author.name,
+        handle: &author.username,
+        text: &text,
+        avatar: avatar.as_ref(),
+        posted,
+        metrics,
+        dark,
+    };
+    let image = render::draw(&card, &fonts);
+    if let Err(e) = fs::write(out, crate::png::encode(&image)) {
+        eprintln!("Failed to write {}: {e}", out.display());
+        std::process::exit(1);
+    }
+    println!("Wrote {} ({}x{})", out.display(), image.width, image.height);
+}
+
+async fn handle_queue(action: QueueAction) {
+    let store = open_store_or_exit();
+
+    match action {
+        QueueAction::List => {
+            let items = match store.queue_items() {
+                Ok(items) => items,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            if items.is_empty() {
+                println!("Queue is empty.");
+                return;
+            }
+            for item in items {
+                print!(
+                    "  {} {} at {}",
+                    item.action,
+                    item.tweet_id,
+                    store::format_timestamp(item.due_at)
+                );
+                match item.last_error {
+                    Some(err) => println!(" (attempts: {}, last error: {err})", item.attempts),
+                    None => println!(),
+                }
+            }
+        }
+        QueueAction::Flush => {
+            #[cfg(feature = "history")]
+            let policy = retention_policy_or_exit();
+            let client = load_client_or_exit();
+            let lock = lock_queue_or_exit();
+            let ok = run_queue_once(&client, &store).await;
+            #[cfg(feature = "history")]
+            let ok = sync_feeds(&client, &store, false).await && ok;
+            #[cfg(feature = "history")]
+            let ok = run_retention(&client, &store, policy.as_ref()).await && ok;
+            drop(lock);
+            if !ok {
+                std::process::exit(1);
+            }
+        }
+        QueueAction::Run { interval } => {
+            #[cfg(feature = "history")]
+            let policy = retention_policy_or_exit();
+            let client = load_client_or_exit();
+            let _lock = lock_queue_or_exit();
+            println!("Queue runner started (checking every {interval}s). Press Ctrl-C to stop.");
+            loop {
+                run_queue_once(&client, &store).await;
+                #[cfg(feature = "history")]
+                sync_feeds(&client, &store, false).await;
+                #[cfg(feature = "history")]
+                run_retention(&client, &store, policy.as_ref()).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+            }
+            println!("Queue runner stopped.");
+        }
+        QueueAction::InstallService { interval, print } => install_queue_service(interval, print),
+    }
+}
+
+fn install_queue_service(interval: u64, print: bool) {
+    let client = load_client_or_exit();
+    let (exe, working_dir) = match (std::env::current_exe(), std::env::current_dir()) {
+        (Ok(exe), Ok(dir)) => (exe, dir),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Failed to locate xcli: {e}");
+            std::process::exit(1);
+        }
+    };
+    let spec = service::ServiceSpec {
+        exe,
+        interval,
+        working_dir,
+        env: std::env::var("XDG_CONFIG_HOME")
+            .map(|v| vec![("XDG_CONFIG_HOME".to_string(), v)])
+            .unwrap_or_default(),
+        account: client.config().screen_name.clone(),
+        profile: config::profile_name(),
+    };
+
+    let macos = cfg!(target_os = "macos");
+    let (content, path) = if macos {
+        let log_dir = dirs::home_dir()
+            .map(|h| h.join("Library/Logs/xcli"))
+            .unwrap_or_else(std::env::temp_dir);
+        let _ = fs::create_dir_all(&log_dir);
+        (
+            service::launchd_plist(&spec, &log_dir),
+            service::launchd_plist_path(),
+        )
+    } else {
+        (service::systemd_unit(&spec), service::systemd_unit_path())
+    };
+
+    if print {
+        print!("{content}");
+        return;
+    }
+    let Some(path) = path else {
+        eprintln!("Could not determine where to install the service.");
+        std::process::exit(1);
+    };
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            eprintln!("Failed to create {}: {e}", parent.display());
+            std::process::exit(1);
+        }
+    }
+    if fs::read_to_string(&path).is_ok_and(|old| old != content) {
+        eprintln!("Replacing the service installed before: one queue runner serves every profile.");
+    }
+    if let Err(e) = fs::write(&path, content) {
+        eprintln!("Failed to write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    println!("Wrote {}", path.display());
+    if macos {
+        println!("Start it with:\n  launchctl load -w {}", path.display());
+    } else {
+        println!(
+            "Start it with:\n  systemctl --user daemon-reload\n  systemctl --user enable --now {}\nTo keep it running while logged out:\n  loginctl enable-linger $USER",
+            service::SYSTEMD_UNIT_NAME
+        );
+    }
+}
+
+/// Only one process may work the queue at a time, or due items could be
+/// posted or deleted twice.
+fn lock_queue_or_exit() -> lock::Lock {
+    match lock::Lock::acquire(&config::queue_lock_path()) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns false if anything failed.
+async fn run_queue_once(client: &XClient, store: &Store) -> bool {
+    let outcomes = match queue::process_due(client, store, store::now()).await {
+        Ok(o) => o,
+        Err(e) => {
+            applog::error(&format!("Queue run failed: {e}"));
+            eprintln!("Error: {e}");
+            return false;
+        }
+    };
+
+    let mut ok = true;
+    for outcome in outcomes {
+        match outcome {
+            queue::Outcome::Done(item) => println!("{}: {} done.", item.action, item.tweet_id),
+            queue::Outcome::Failed(item, e) => {
+                ok = false;
+                let message = format!(
+                    "{}: {} failed (attempt {}/{}): {e}",
+                    item.action,
+                    item.tweet_id,
+                    item.attempts + 1,
+                    queue::MAX_ATTEMPTS
+                );
+                applog::warn(&message);
+                eprintln!("{message}");
+            }
+            queue::Outcome::Dropped(item, e) => {
+                ok = false;
+                let message = format!(
+                    "{}: {} failed {} times, giving up: {e}",
+                    item.action,
+                    item.tweet_id,
+                    queue::MAX_ATTEMPTS
+                );
+                applog::error(&message);
+                eprintln!("{message}");
+            }
+        }
+    }
+    ok
+}
+
+/// The profile's retention policy, if it has one.
+#[cfg(feature = "history")]
+fn retention_policy_or_exit() -> Option<retention::Policy> {
+    let config = load_profile_or_exit().retention?;
+    match retention::Policy::new(&config) {
+        Ok(policy) => Some(policy),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The tweets of the local history past the policy's cutoff, and the IDs of
+/// the tweets you liked if the policy keeps those.
+#[cfg(feature = "history")]
+async fn retention_candidates(
+    client: &XClient,
+    store: &Store,
+    policy: &retention::Policy,
+) -> Result<(Vec<store::HistoryEntry>, std::collections::HashSet<String>), String> {
+    let due = store.history_before(policy.cutoff(store::now()))?;
+    let mut liked = std::collections::HashSet::new();
+    if policy.keep_liked && !due.is_empty() {
+        let me = api::get_me(client)
+            .await
+            .map_err(|e| format!("Failed to look up your account: {e}"))?;
+        liked = api::get_liked_tweets(client, &me.id)
+            .await
+            .map_err(|e| format!("Failed to fetch your likes: {e}"))?
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+    }
+    Ok((due, liked))
+}
+
+/// Delete what the retention policy says is due, unless it ran within the
+/// last `retention::RUN_EVERY` seconds. Returns false if anything failed.
+#[cfg(feature = "history")]
+async fn run_retention(
+    client: &XClient,
+    store: &Store,
+    policy: Option<&retention::Policy>,
+) -> bool {
+    let Some(policy) = policy else {
+        return true;
+    };
+    let state = config::retention_state_path();
+    let now = store::now();
+    if retention::last_run(&state).is_some_and(|at| now - at < retention::RUN_EVERY) {
+        return true;
+    }
+    let (due, liked) = match retention_candidates(client, store, policy).await {
+        Ok(c) => c,
+        Err(e) => {
+            let message = format!("Retention run failed: {e}");
+            applog::error(&message);
+            eprintln!("{message}");
+            return false;
+        }
+    };
+    let plan = retention::plan(policy, &due, &liked);
+    let mut ok = true;
+    for entry in plan.delete.iter().take(retention::MAX_DELETES_PER_RUN) {
+        let id = &entry.tweet_id;
+        match api::delete_tweet(client, id).await.map(|r| r.data) {
+            Ok(true) => println!("retention: {id} deleted."),
+            Ok(false) => {
+                ok = false;
+                eprintln!("retention: {id} was not deleted.");
+                continue;
+            }
+            // Already gone: nothing left to delete.
+            Err(e) if e.contains("(404") => println!("retention: {id} no longer exists."),
+            Err(e) if e.contains("(429") => {
+                applog::warn(&format!("Retention run rate limited by X: {e}"));
+                eprintln!("retention: rate limited by X, continuing next run.");
+                ok = false;
+                break;
+            }
+            Err(e) => {
+                ok = false;
+                let message = format!("retention: {id} failed: {e}");
+                applog::warn(&message);
+                eprintln!("{message}");
+                continue;
+            }
+        }
+        if let Err(e) = store.forget_tweet(id) {
+            eprintln!("Warning: {e}");
+        }
+    }
+    if let Err(e) = retention::record_run(&state, now) {
+        eprintln!("{e}");
+        ok = false;
+    }
+    ok
+}
+
+#[cfg(feature = "history")]
+async fn handle_retention_preview() {
+    let Some(policy) = retention_policy_or_exit() else {
+        eprintln!(
+            "Profile `{}` has no retention policy. Add a [retention] table to {} (see `xcli retention --help`).",
+            config::profile_name(),
+            config::settings_path().display()
+        );
+        std::process::exit(1);
+    };
+    let store = open_store_or_exit();
+    let client = load_client_or_exit();
+    let (due, liked) = match retention_candidates(&client, &store, &policy).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let plan = retention::plan(&policy, &due, &liked);
+    let line = |entry: &store::HistoryEntry| {
+        format!(
+            "  {}  {}  {}",
+            store::format_timestamp(entry.posted_at),
+            entry.tweet_id,
+            preview(&entry.text, 60)
+        )
+    };
+
+    println!(
+        "Tweets posted before {} ({} ago) are due.",
+        store::format_timestamp(policy.cutoff(store::now())),
+        duration::format_duration(Duration::from_secs(policy.max_age as u64))
+    );
+    if plan.delete.is_empty() {
+        println!("The next run deletes nothing.");
+    } else {
+        let total = plan.delete.len();
+        let next = total.min(retention::MAX_DELETES_PER_RUN);
+        if next < total {
+            println!("The next run deletes {next} of {total} tweets (the oldest; the rest follow in later runs):");
+        } else {
+            println!("The next run deletes {total} tweets:");
+        }
+        for entry in plan.delete.iter().take(next) {
+            println!("{}", line(entry));
+        }
+    }
+    if !plan.kept.is_empty() {
+        println!("Kept ({}):", plan.kept.len());
+        for (entry, reason) in &plan.kept {
+            println!("{}  [{reason}]", line(entry));
+        }
+    }
+    match retention::last_run(&config::retention_state_path()) {
+        Some(at) => println!(
+            "Last run {}; the next is due from {} on, with `xcli queue run` or `xcli queue flush`.",
+            store::format_timestamp(at),
+            store::format_timestamp(at + retention::RUN_EVERY)
+        ),
+        None => {
+            println!("The next run is the next round of `xcli queue run` or `xcli queue flush`.")
+        }
+    }
+}
+
+/// Where `--record` / `--replay` point, if given, or the trace that
+/// `xcli debug replay` answers from.
+enum Cassette {
+    Record(PathBuf),
+    Replay(PathBuf),
+    Trace(Vec<trace::Exchange>),
+}
+
+static CASSETTE: OnceLock<Cassette> = OnceLock::new();
+
+fn load_client_or_exit() -> XClient {
+    let config = if config::is_sandbox() {
+        Ok(sandbox::config())
+    } else {
+        Config::load()
+    };
+    let result = match CASSETTE.get() {
+        // A replay needs no account, so CI can run without secrets.
+        Some(Cassette::Replay(dir)) => ReplayTransport::load(dir).map(|replay| {
+            let transport: Arc<dyn Transport> = Arc::new(replay);
+            (config.unwrap_or_else(|_| sandbox::config()), transport)
+        }),
+        Some(Cassette::Trace(exchanges)) => {
+            let replay: Arc<dyn Transport> =
+                Arc::new(trace::TraceReplayTransport::new(exchanges.clone()));
+            Ok((config.unwrap_or_else(|_| sandbox::config()), replay))
+        }
+        Some(Cassette::Record(dir)) => config.and_then(|config| {
+            let inner = default_transport();
+            let recorder: Arc<dyn Transport> = Arc::new(RecordingTransport::create(inner, dir)?);
+            Ok((config, recorder))
+        }),
+        None => config.map(|config| (config, default_transport())),
+    };
+    match result {
+        Ok((config, transport)) => XClient::with_transport(config, traced(transport)),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The `--trace-file` of this run.
+static TRACE: OnceLock<Arc<trace::Trace>> = OnceLock::new();
+
+/// Log `inner`'s exchanges to the `--trace-file`, if one was given, and
+/// remember the rate limits X reports (see `xcli limits`).
+fn traced(inner: Arc<dyn Transport>) -> Arc<dyn Transport> {
+    // Replayed answers say nothing about the account's limits now.
+    let inner: Arc<dyn Transport> = match CASSETTE.get() {
+        Some(Cassette::Replay(_) | Cassette::Trace(_)) => inner,
+        _ => Arc::new(ratelimits::TrackingTransport::new(
+            inner,
+            config::rate_limits_path(),
+        )),
+    };
+    let inner: Arc<dyn Transport> = if applog::enabled() {
+        Arc::new(applog::LoggingTransport::new(inner))
+    } else {
+        inner
+    };
+    match TRACE.get() {
+        Some(t) => Arc::new(trace::TracingTransport::new(inner, Arc::clone(t))),
+        None => inner,
+    }
+}
+
+fn default_transport() -> Arc<dyn Transport> {
+    if config::is_sandbox() {
+        Arc::new(SandboxTransport::new())
+    } else {
+        Arc::new(HttpTransport::default())
+    }
+}
+
+const PORTAL_URL: &str = "https://developer.x.com/en/portal/dashboard";
+
+async fn handle_init() {
+    if !io::stdin().is_terminal() {
+        eprintln!("`xcli init` asks questions; run it in a terminal.");
+        eprintln!(
+            "To set up non-interactively, use `xcli auth setup --api-key KEY --api-secret SECRET`."
+        );
+        std::process::exit(1);
+    }
+    if config::profile_name() != config::DEFAULT_PROFILE {
+        eprintln!(
+            "Warning: profiles only hold settings; the keys and login set up here are used by every profile."
+        );
+    }
+    println!("Welcome to xcli! This sets up posting from the command line in four steps.");
+
+    println!("\nStep 1/4: Create a developer app");
+    let existing = ApiKeys::load();
+    let keep_keys = existing.is_some()
+        && confirm(&format!(
+            "API keys are already saved in {}. Keep them?",
+            config::keys_path().display()
+        ));
+    if !keep_keys {
+        println!("  1. Open {PORTAL_URL} and sign in with the X account that owns the app.");
+        println!("  2. Create a project and an app in it (the free tier is enough).");
+        println!("  3. In the app's settings, under \"User authentication settings\", click");
+        println!("     Set up and enter:");
+        println!("       App permissions:  Read and write");
+        println!("       Type of App:      Web App, Automated App or Bot");
+        println!("       Callback URI:     {}", oauth::CALLBACK_URL);
+        println!("       Website URL:      any page of yours, e.g. your profile or homepage");
+        println!("     and save.");
+        println!("  4. Under \"Keys and tokens\", generate the API Key and Secret (the");
+        println!("     consumer keys) and keep the page open for the next step.");
+        pause("Press Enter when the app is ready...");
+
+        println!("\nStep 2/4: Save the API keys");
+        let keys = ApiKeys {
+            api_key: prompt("API Key"),
+            api_secret: prompt("API Secret"),
+            access_token: None,
+            access_token_secret: None,
+        };
+        if let Err(e) = keys.save() {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        println!("Keys saved to {}", config::keys_path().display());
+    } else {
+        println!("\nStep 2/4: Save the API keys (kept)");
+    }
+
+    println!("\nStep 3/4: Log in");
+    let relogin = match Credentials::load() {
+        Some(creds) if keep_keys => !confirm(&format!(
+            "Already logged in as @{}. Keep it?",
+            creds.screen_name
+        )),
+        _ => true,
+    };
+    if relogin {
+        let (api_key, api_secret) = match Config::load_consumer_only() {
+            Ok(keys) => keys,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        };
+        let transport = traced(Arc::new(HttpTransport::default()));
+        match oauth::start_login(&*transport, &api_key, &api_secret).await {
+            Ok(creds) => {
+                if let Err(e) = creds.save() {
+                    eprintln!("Failed to save credentials: {e}");
+                    std::process::exit(1);
+                }
+                println!("Logged in as @{}", creds.screen_name);
+            }
+            Err(e) => {
+                eprintln!("Login failed: {e}");
+                eprintln!(
+                    "Check that {} is the app's callback URI and run `xcli init` again.",
+                    oauth::CALLBACK_URL
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    println!("\nStep 4/4: Check the login");
+    let client = load_client_or_exit();
+    match api::get_me(&client).await {
+        Ok(me) => println!("Verified: @{} ({})", me.username, me.name),
+        Err(e) => {
+            eprintln!("Could not read your account: {e}");
+            eprintln!("Check the keys and the app permissions (Read and write), then run `xcli init` again.");
+            std::process::exit(1);
+        }
+    }
+
+    if confirm("Post a test tweet (you can delete it right away)?") {
+        let text = format!(
+            "Testing xcli, a command line client for X ({})",
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        );
+        match api::create_tweet(&client, &text, None).await {
+            Ok(resp) => {
+                println!("Test tweet posted! ID: {}", resp.data);
+                if confirm("Delete it now?") {
+                    match api::delete_tweet(&client, &resp.data).await {
+                        Ok(_) => println!("Test tweet deleted."),
+                        Err(e) => eprintln!("Failed to delete the test tweet: {e}"),
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to post the test tweet: {e}");
+                eprintln!(
+                    "If X says the app is not permitted, set App permissions to Read and write,"
+                );
+                eprintln!("regenerate the keys if asked, and run `xcli init` again.");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    println!("\nAll set. Try:");
+    println!("  xcli tweet \"Hello from xcli!\"");
+    println!("  xcli timeline");
+    println!("  xcli --help");
+}
+
+async fn handle_auth(action: AuthAction) {
+    if config::is_sandbox() {
+        eprintln!(
+            "Error: `xcli auth` manages real credentials and is not available with --sandbox."
+        );
+        std::process::exit(1);
+    }
+    match action {
+        AuthAction::Login => {
+            let (api_key, api_secret) = match Config::load_consumer_only() {
+                Ok(keys) => keys,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    eprintln!("Run `xcli auth setup` or set X_API_KEY and X_API_SECRET in .env.");
+                    std::process::exit(1);
+                }
+            };
+
+            let transport = traced(Arc::new(HttpTransport::default()));
+            match oauth::start_login(&*transport, &api_key, &api_secret).await {
+                Ok(creds) => {
+                    let name = creds.screen_name.clone();
+                    if let Err(e) = creds.save() {
+                        eprintln!("Failed to save credentials: {e}");
+                        std::process::exit(1);
+                    }
+                    println!("Logged in as @{name}");
+                    println!(
+                        "Credentials saved to {}",
+                        config::credentials_path().display()
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Login failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        AuthAction::Logout => {
+            if let Err(e) = Credentials::delete() {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+            println!("Logged out. Credentials removed.");
+        }
+        AuthAction::Status => match Credentials::load() {
+            Some(creds) => {
+                println!("Logged in as @{}", creds.screen_name);
+                println!("Credentials: {}", config::credentials_path().display());
+            }
+            None => {
+                println!("Not logged in.");
+                println!("Run `xcli auth login` to authenticate.");
+            }
+        },
+        AuthAction::Setup {
+            api_key,
+            api_secret,
+            access_token,
+            access_token_secret,
+        } => {
+            let api_key = api_key.unwrap_or_else(|| prompt("API Key"));
+            let api_secret = api_secret.unwrap_or_else(|| prompt("API Secret"));
+            let access_token = access_token.or_else(|| prompt_optional("Access Token"));
+            let access_token_secret =
+                access_token_secret.or_else(|| prompt_optional("Access Token Secret"));
+
+            let keys = ApiKeys {
+                api_key,
+                api_secret,
+                access_token,
+                access_token_secret,
+            };
+
+            if let Err(e) = keys.save() {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+            println!("Keys saved to {}", config::keys_path().display());
+        }
+    }
+}
+
+async fn handle_whoami() {
+    let client = load_client_or_exit();
+    let identity = match api::whoami(&client).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            eprintln!("X rejected the credentials: {e}");
+            eprintln!("Run `xcli auth login` to log in again.");
+            std::process::exit(1);
+        }
+    };
+    let user = &identity.user;
+    println!("Logged in as @{} ({})", user.username, user.name);
+    println!("User ID: {}", user.id);
+    let Some(level) = identity.access_level.as_deref() else {
+        println!("Access:  not reported by X");
+        return;
+    };
+    let scopes = api::access_scopes(level);
+    println!("Access:  {}", scopes.join(", "));
+    if !scopes.contains(&"write") {
+        eprintln!(
+            "Warning: this token cannot post. Give the app Read and Write permissions \
+             in the Developer Portal, then run `xcli auth login` again."
+        );
+    }
+}
+
+async fn handle_usage(days: u32, json: bool) {
+    let client = load_client_or_exit();
+    let bearer = match api::get_bearer_token(&client).await {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Failed to get an app-only token: {e}");
+            std::process::exit(1);
+        }
+    };
+    let usage = match api::get_usage(&client, &bearer, days).await {
+        Ok(u) => u,
+        Err(e) => {
+            eprintln!("Failed to fetch usage: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&usage).unwrap());
+        return;
+    }
+
+    let percent = usage.used * 100 / usage.cap.max(1);
+    println!(
+        "Posts read this month: {} of {} ({percent}%)",
+        usage.used, usage.cap
+    );
+    let today = chrono::Utc::now().date_naive();
+    let reset = usage.next_reset(today);
+    println!("Resets on {reset} (in {} days)", (reset - today).num_days());
+    if !usage.daily.usage.is_empty() {
+        println!();
+        println!("Posts read per day:");
+        for day in &usage.daily.usage {
+            println!(
+                "  {}  {}",
+                day.date.get(..10).unwrap_or(&day.date),
+                day.usage
+            );
+        }
+    }
+    if percent >= 90 {
+        eprintln!(
+            "Warning: {percent}% of the monthly cap is used; reads will fail once it runs out."
+        );
+    }
+}
+
+async fn handle_limits(probe: bool, json: bool) {
+    if probe {
+        let client = load_client_or_exit();
+        if let Err(e) = api::get_me(&client).await {
+            eprintln!("Failed to reach X: {e}");
+            std::process::exit(1);
+        }
+    }
+    let limits = ratelimits::load(&config::rate_limits_path());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&limits).unwrap());
+        return;
+    }
+    if limits.is_empty() {
+        println!("No rate limits recorded yet. Run a command, or `xcli limits --probe`.");
+        return;
+    }
+
+    let now = store::now();
+    let ago = |secs: i64| duration::format_duration(Duration::from_secs(secs.max(0) as u64));
+    let width = limits.keys().map(|k| k.len()).max().unwrap_or(0);
+    println!("{:<width$}  {:>9}  RESETS", "ENDPOINT", "LEFT");
+    for (endpoint, limit) in &limits {
+        let window = limit.window;
+        let reset = window.reset as i64;
+        let (left, resets) = if reset > now {
+            (
+                format!("{}/{}", window.remaining, window.limit),
+                format!("in {}", ago(reset - now)),
+            )
+        } else {
+            // The window has started again since X last said.
+            (
+                format!("{}/{}", window.limit, window.limit),
+                format!("{} ago", ago(now - reset)),
+            )
+        };
+        println!(
+            "{endpoint:<width$}  {left:>9}  {resets} (seen {} ago)",
+            ago(now - limit.seen_at)
+        );
+    }
+}
+
+fn prompt(label: &str) -> String {
+    loop {
+        print!("{label}: ");
+        io::stdout().flush().unwrap();
+        let mut buf = String::new();
+        io::stdin().read_line(&mut buf).unwrap();
+        let val = buf.trim().to_string();
+        if !val.is_empty() {
+            return val;
+        }
+        eprintln!("{label} is required.");
+    }
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{question} [y/N]: ");
+    io::stdout().flush().unwrap();
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf).unwrap();
+    matches!(buf.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Wait for Enter.
+fn pause(message: &str) {
+    print!("{message}");
+    io::stdout().flush().unwrap();
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf).unwrap();
+}
+
+fn prompt_optional(label: &str) -> Option<String> {
+    print!("{label} (optional, press Enter to skip): ");
+    io::stdout().flush().unwrap();
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf).unwrap();
+    let val = buf.trim().to_string();
+    if val.is_empty() {
+        None
+    } else {
+        Some(val)
+    }
+}
@@ -0,0 +1,118 @@
+//! [`XClient`], the entry point for using xcli as a library.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::api::{self, MediaOptions, ThreadError, Tweet, User};
+use crate::config::Config;
+use crate::transport::{HttpTransport, Request, Response, Transport};
+
+/// An X API v2 client acting as one account (OAuth 1.0a user context).
+///
+/// ```no_run
+/// # async fn demo() -> Result<(), String> {
+/// let client = xcli::XClient::from_env()?;
+/// let chunks = xcli::thread::split_text("A long announcement...");
+/// let ids = client.thread(&chunks).await.map_err(|e| e.error)?;
+/// client.delete(&ids[0]).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct XClient {
+    config: Config,
+    transport: Arc<dyn Transport>,
+}
+
+impl XClient {
+    pub fn new(config: Config) -> Self {
+        Self::with_transport(config, Arc::new(HttpTransport::default()))
+    }
+
+    /// A client that sends everything through `transport`, e.g. a
+    /// [`crate::transport::MockTransport`] in tests.
+    pub fn with_transport(config: Config, transport: Arc<dyn Transport>) -> Self {
+        XClient { config, transport }
+    }
+
+    /// Load credentials the way the `xcli` binary does: credentials.json,
+    /// then keys.json, then `X_*` environment variables (and `.env`).
+    pub fn from_env() -> Result<Self, String> {
+        Config::load().map(Self::new)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
+    }
+
+    pub(crate) async fn send(&self, req: Request) -> Result<Response, String> {
+        self.transport.send(req).await
+    }
+
+    /// Post a single tweet and return its ID. The text is not split; use
+    /// [`XClient::thread`] with [`crate::thread::split_text`] for long text.
+    pub async fn tweet(&self, text: &str) -> Result<String, String> {
+        api::create_tweet(self, text, None).await.map(|r| r.data)
+    }
+
+    /// Post a reply to `tweet_id` and return its ID.
+    pub async fn reply(&self, tweet_id: &str, text: &str) -> Result<String, String> {
+        api::create_tweet(self, text, Some(tweet_id))
+            .await
+            .map(|r| r.data)
+    }
+
+    /// Post a tweet with media uploaded by [`XClient::upload_media`].
+    pub async fn tweet_with_media(
+        &self,
+        text: &str,
+        media_ids: &[String],
+    ) -> Result<String, String> {
+        api::create_tweet_with_media(self, text, None, media_ids)
+            .await
+            .map(|r| r.data)
+    }
+
+    /// Post `chunks` as a thread, each replying to the previous one. On
+    /// failure the error lists the IDs that were already posted.
+    pub async fn thread(&self, chunks: &[String]) -> Result<Vec<String>, ThreadError> {
+        api::create_thread(self, chunks)
+            .await
+            .map(|posted| api::ids(&posted))
+    }
+
+    /// Delete a tweet. Returns whether X reports it deleted.
+    pub async fn delete(&self, tweet_id: &str) -> Result<bool, String> {
+        api::delete_tweet(self, tweet_id).await.map(|r| r.data)
+    }
+
+    /// Tweets from the last 7 days matching `query` (X search syntax), newest
+    /// first, up to `max_results`.
+    pub async fn search(&self, query: &str, max_results: usize) -> Result<Vec<Tweet>, String> {
+        api::search_recent(self, query, max_results).await
+    }
+
+    /// Upload an image (JPEG, PNG, WebP or GIF) or video (MP4 or MOV) and
+    /// return its media ID. Image metadata is removed, and a JPEG or PNG over
+    /// X's limits is scaled down first.
+    pub async fn upload_media(&self, path: &Path) -> Result<String, String> {
+        api::upload_media(self, path).await
+    }
+
+    /// [`XClient::upload_media`], with a say in how the image is prepared.
+    pub async fn upload_media_with(
+        &self,
+        path: &Path,
+        options: &MediaOptions,
+    ) -> Result<String, String> {
+        api::upload_media_with(self, path, options).await
+    }
+
+    /// The authenticated account.
+    pub async fn me(&self) -> Result<User, String> {
+        api::get_me(self).await
+    }
+}
@@ -1,13 +1,26 @@
+//! Credentials and file locations under `~/.config/xcli`.
+
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+static SANDBOX: AtomicBool = AtomicBool::new(false);
+static PROFILE: OnceLock<String> = OnceLock::new();
+static ISOLATED: OnceLock<PathBuf> = OnceLock::new();
+
+pub const DEFAULT_PROFILE: &str = "default";
 
 pub struct Config {
     pub api_key: String,
     pub api_secret: String,
     pub access_token: String,
     pub access_token_secret: String,
+    /// Screen name of the logged-in account, when known (OAuth login only).
+    pub screen_name: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -27,12 +40,40 @@ pub struct ApiKeys {
     pub access_token_secret: Option<String>,
 }
 
-fn config_dir() -> PathBuf {
+fn base_config_dir() -> PathBuf {
+    if let Some(dir) = ISOLATED.get() {
+        return dir.clone();
+    }
     dirs::config_dir()
         .expect("Could not determine config directory")
         .join("xcli")
 }
 
+pub fn config_dir() -> PathBuf {
+    if is_sandbox() {
+        base_config_dir().join("sandbox")
+    } else {
+        base_config_dir()
+    }
+}
+
+/// Switch this process to sandbox mode: local state (history, queue, lock)
+/// moves to `~/.config/xcli/sandbox` so rehearsals never touch the real store.
+pub fn enable_sandbox() {
+    SANDBOX.store(true, Ordering::Relaxed);
+}
+
+pub fn is_sandbox() -> bool {
+    SANDBOX.load(Ordering::Relaxed)
+}
+
+/// Keep all configuration and state of this process in `dir` instead of
+/// `~/.config/xcli`, e.g. so `xcli debug replay` cannot touch real history
+/// or run the user's hooks.
+pub fn isolate(dir: PathBuf) {
+    let _ = ISOLATED.set(dir);
+}
+
 pub fn credentials_path() -> PathBuf {
     config_dir().join("credentials.json")
 }
@@ -41,6 +82,301 @@ pub fn keys_path() -> PathBuf {
     config_dir().join("keys.json")
 }
 
+pub fn store_path() -> PathBuf {
+    config_dir().join("xcli.db")
+}
+
+/// Where `xcli bot run` got to.
+pub fn bot_state_path() -> PathBuf {
+    config_dir().join("bot.json")
+}
+
+/// The newest tweets `--new-only` reads printed.
+pub fn seen_path() -> PathBuf {
+    config_dir().join("seen.json")
+}
+
+/// The account IDs handles were last resolved to.
+pub fn user_ids_path() -> PathBuf {
+    config_dir().join("user-ids.json")
+}
+
+/// The rate limits X last reported, by endpoint.
+pub fn rate_limits_path() -> PathBuf {
+    config_dir().join("rate-limits.json")
+}
+
+/// Tweets `xcli stream forward` has read but not yet delivered.
+pub fn stream_buffer_path() -> PathBuf {
+    config_dir().join("stream-buffer.jsonl")
+}
+
+/// When the retention policy last ran.
+pub fn retention_state_path() -> PathBuf {
+    config_dir().join("retention.json")
+}
+
+/// Follower counts logged by `xcli followers watch`.
+pub fn follower_log_path() -> PathBuf {
+    config_dir().join("followers.csv")
+}
+
+/// Video uploads that can be resumed.
+pub fn uploads_path() -> PathBuf {
+    config_dir().join("uploads.json")
+}
+
+pub fn queue_lock_path() -> PathBuf {
+    config_dir().join("queue.lock")
+}
+
+/// Cached answer of the once-a-day release check.
+pub fn update_cache_path() -> PathBuf {
+    base_config_dir().join("update-check.json")
+}
+
+/// The application log is state rather than configuration, so it goes under
+/// `~/.local/state/xcli` (the local data directory where there is no such
+/// thing).
+pub fn log_path() -> PathBuf {
+    if let Some(dir) = ISOLATED.get() {
+        return dir.join("xcli.log");
+    }
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .expect("Could not determine state directory")
+        .join("xcli")
+        .join("xcli.log")
+}
+
+/// Preferences apply in sandbox mode too, so hooks can be rehearsed.
+pub fn settings_path() -> PathBuf {
+    base_config_dir().join("config.toml")
+}
+
+/// Select the profile of `config.toml` used by this process. Without a call,
+/// `XCLI_PROFILE` or else `default` is used.
+pub fn select_profile(name: &str) {
+    let _ = PROFILE.set(name.to_string());
+}
+
+pub fn profile_name() -> String {
+    PROFILE
+        .get()
+        .cloned()
+        .or_else(|| env::var("XCLI_PROFILE").ok().filter(|p| !p.is_empty()))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Preferences from `~/.config/xcli/config.toml`, grouped in profiles:
+///
+/// ```toml
+/// [profiles.default.hooks]
+/// pre_post = "~/bin/lint-tweet"
+/// post_post = "echo posted >> ~/posted.log"
+///
+/// [profiles.work]
+/// notify_url = "https://hooks.slack.com/services/..."
+/// ```
+///
+/// Top-level keys apply to every profile.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Print a line when a newer release is out (checked once a day).
+    #[serde(default)]
+    pub check_updates: bool,
+    /// Keep an application log of commands, retries and errors.
+    #[serde(default)]
+    pub log: bool,
+    /// The X API access (Pro or Enterprise) includes full-archive search,
+    /// for `xcli search --all`.
+    #[serde(default)]
+    pub full_archive_search: bool,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Gets a JSON summary of every post, successful or not.
+    pub notify_url: Option<String>,
+    /// Curl quotes and turn `--` and `...` into dashes and ellipses.
+    #[serde(default)]
+    pub typography: bool,
+    /// Remove tracking parameters (`utm_*`, `fbclid` and the like) from
+    /// links.
+    #[serde(default)]
+    pub clean_urls: bool,
+    /// More parameters for `clean_urls` to remove; `*` ends a prefix.
+    #[serde(default)]
+    pub tracking_params: Vec<String>,
+    /// Appended as is to single tweets and the last tweet of threads.
+    pub signature: Option<String>,
+    /// Added to every post unless `--no-tags` is given, skipping those the
+    /// text already has.
+    #[serde(default)]
+    pub default_hashtags: Vec<String>,
+    #[serde(default)]
+    pub hashtag_position: HashtagPosition,
+    /// Account posted to with `--also mastodon`.
+    pub mastodon: Option<MastodonAccount>,
+    /// Account posted to with `--also bluesky`.
+    pub bluesky: Option<BlueskyAccount>,
+    /// Gets the path of an image without `--alt` on stdin and prints alt
+    /// text for it, e.g. from a local captioning model.
+    pub alt_text_command: Option<String>,
+    /// Gets the text of a `--translate` post on stdin and prints it in the
+    /// language in `XCLI_TRANSLATE_LANG`.
+    pub translate_command: Option<String>,
+    #[serde(default)]
+    pub lint: Lint,
+    /// Flag misspellings in `--dry-run` previews.
+    pub spellcheck: Option<Spellcheck>,
+    /// Old tweets the queue runner deletes.
+    pub retention: Option<Retention>,
+    /// Remove EXIF and other metadata from images before upload (the
+    /// default); `false` keeps it unless `--strip-metadata` is given.
+    pub strip_metadata: Option<bool>,
+}
+
+impl Profile {
+    /// Whether images lose their metadata before upload: as `--keep-metadata`
+    /// or `--strip-metadata` say (`flag`), else as the profile says.
+    pub fn strips_metadata(&self, flag: Option<bool>) -> bool {
+        flag.or(self.strip_metadata).unwrap_or(true)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MastodonAccount {
+    /// e.g. `https://mastodon.social`
+    pub instance: String,
+    /// From Preferences → Development, with the `write:statuses` scope.
+    pub access_token: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlueskyAccount {
+    /// e.g. `me.bsky.social`
+    pub handle: String,
+    /// From Settings → Privacy and security → App passwords.
+    pub app_password: String,
+    /// The account's PDS, if not `https://bsky.social`.
+    pub service: Option<String>,
+}
+
+/// Where `default_hashtags` go: after the last tweet or before the first.
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashtagPosition {
+    #[default]
+    Append,
+    Prepend,
+}
+
+/// Content rules checked before posting and by `xcli lint`.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Lint {
+    /// Words that may not appear (whole words, in any case).
+    #[serde(default)]
+    pub banned_words: Vec<String>,
+    /// Regular expressions the text may not match.
+    #[serde(default)]
+    pub banned_patterns: Vec<String>,
+    /// Hashtags every post must carry, e.g. `#ad` on a sponsored account.
+    #[serde(default)]
+    pub required_tags: Vec<String>,
+    /// Most hashtags in one post.
+    pub max_hashtags: Option<usize>,
+    /// Refuse tweets that are nothing but a link.
+    #[serde(default)]
+    pub no_bare_urls: bool,
+    /// Refuse images without alt text.
+    #[serde(default)]
+    pub require_alt_text: bool,
+    /// Only warn about problems instead of refusing to post.
+    #[serde(default)]
+    pub warn_only: bool,
+}
+
+/// How `--dry-run` previews are spellchecked.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Spellcheck {
+    /// Dictionary language, e.g. `en_US`.
+    pub language: Option<String>,
+    /// Reads the text on stdin and prints unknown words one per line
+    /// (default: `hunspell -l`, with the language's dictionary).
+    pub command: Option<String>,
+}
+
+/// Which old tweets the queue runner deletes (see `xcli retention preview`).
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Retention {
+    /// Delete tweets older than this, e.g. `90d`.
+    pub delete_after: String,
+    /// Keep tweets you liked yourself.
+    #[serde(default)]
+    pub keep_liked: bool,
+    /// Keep tweets with any of these hashtags, e.g. `#keep`.
+    #[serde(default)]
+    pub keep_tags: Vec<String>,
+    /// Keep tweets matching any of these regular expressions.
+    #[serde(default)]
+    pub keep_patterns: Vec<String>,
+}
+
+/// Shell commands run around posting.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Gets the chunks on stdin; may rewrite them on stdout or veto the post
+    /// by exiting non-zero.
+    pub pre_post: Option<String>,
+    /// Gets one `ID URL` line per posted tweet on stdin.
+    pub post_post: Option<String>,
+}
+
+impl Settings {
+    /// Load `config.toml`; a missing file means no settings.
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(&settings_path())
+    }
+
+    pub fn load_from(path: &PathBuf) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(data) => Self::parse(&data).map_err(|e| format!("{}: {e}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn parse(data: &str) -> Result<Self, String> {
+        toml::from_str(data).map_err(|e| format!("Invalid config: {e}"))
+    }
+
+    /// The named profile. `default` may be left out of the file; any other
+    /// name must exist.
+    pub fn profile(&self, name: &str) -> Result<Profile, String> {
+        match self.profiles.get(name) {
+            Some(p) => Ok(p.clone()),
+            None if name == DEFAULT_PROFILE => Ok(Profile::default()),
+            None => Err(format!(
+                "Profile `{name}` is not defined in {}",
+                settings_path().display()
+            )),
+        }
+    }
+}
+
 impl Credentials {
     pub fn load() -> Option<Self> {
         Self::load_from(&credentials_path())
@@ -104,6 +440,80 @@ impl ApiKeys {
     }
 }
 
+impl Config {
+    /// Load config with priority: credentials.json → keys.json → .env
+    pub fn load() -> Result<Self, String> {
+        dotenvy::dotenv().ok();
+
+        let keys = ApiKeys::load();
+
+        let api_key = env::var("X_API_KEY")
+            .ok()
+            .or_else(|| keys.as_ref().map(|k| k.api_key.clone()))
+            .ok_or("X_API_KEY not set. Run `xcli auth setup` or set it in .env")?;
+        let api_secret = env::var("X_API_SECRET")
+            .ok()
+            .or_else(|| keys.as_ref().map(|k| k.api_secret.clone()))
+            .ok_or("X_API_SECRET not set. Run `xcli auth setup` or set it in .env")?;
+
+        // 1) credentials.json (OAuth tokens)
+        if let Some(creds) = Credentials::load() {
+            return Ok(Config {
+                api_key,
+                api_secret,
+                access_token: creds.access_token,
+                access_token_secret: creds.access_token_secret,
+                screen_name: Some(creds.screen_name),
+            });
+        }
+
+        // 2) keys.json access tokens
+        if let Some(ref k) = keys {
+            if let (Some(at), Some(ats)) = (&k.access_token, &k.access_token_secret) {
+                return Ok(Config {
+                    api_key,
+                    api_secret,
+                    access_token: at.clone(),
+                    access_token_secret: ats.clone(),
+                    screen_name: None,
+                });
+            }
+        }
+
+        // 3) .env access tokens
+        let access_token = env::var("X_ACCESS_TOKEN")
+            .map_err(|_| "Not logged in. Run `xcli auth login` or set X_ACCESS_TOKEN in .env")?;
+        let access_token_secret = env::var("X_ACCESS_TOKEN_SECRET").map_err(|_| {
+            "Not logged in. Run `xcli auth login` or set X_ACCESS_TOKEN_SECRET in .env"
+        })?;
+
+        Ok(Config {
+            api_key,
+            api_secret,
+            access_token,
+            access_token_secret,
+            screen_name: None,
+        })
+    }
+
+    /// Load only api_key and api_secret (for OAuth flow before user tokens exist).
+    /// Priority: keys.json → .env
+    pub fn load_consumer_only() -> Result<(String, String), String> {
+        dotenvy::dotenv().ok();
+
+        if let Some(keys) = ApiKeys::load() {
+            return Ok((keys.api_key, keys.api_secret));
+        }
+
+        let api_key = env::var("X_API_KEY")
+            .map_err(|_| "X_API_KEY not set. Run `xcli auth setup` or set it in .env")?;
+        let api_secret = env::var("X_API_SECRET")
+            .map_err(|_| "X_API_SECRET not set. Run `xcli auth setup` or set it in .env")?;
+
+        Ok((api_key, api_secret))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,79 +620,83 @@ mod tests {
     }
 
     #[test]
-    fn api_keys_load_missing_returns_none() {
-        let path = temp_dir().join("xcli_keys_missing_999.json");
-        assert!(ApiKeys::load_from(&path).is_none());
+    fn settings_profiles_and_hooks() {
+        let settings = Settings::parse(
+            r#"
+            check_updates = true
+            log = true
+            full_archive_search = true
+
+            [profiles.work]
+            notify_url = "https://hooks.test/x"
+            typography = true
+            signature = " — via xcli"
+            default_hashtags = ["rustlang"]
+            hashtag_position = "prepend"
+            strip_metadata = false
+
+            [profiles.work.hooks]
+            pre_post = "lint"
+
+            [profiles.work.bluesky]
+            handle = "me.bsky.social"
+            app_password = "abcd-efgh"
+
+            [profiles.work.retention]
+            delete_after = "90d"
+            keep_liked = true
+            keep_tags = ["keep"]
+            "#,
+        )
+        .unwrap();
+        assert!(settings.check_updates);
+        assert!(settings.log);
+        assert!(settings.full_archive_search);
+        let work = settings.profile("work").unwrap();
+        assert_eq!(work.hooks.pre_post.as_deref(), Some("lint"));
+        assert!(work.hooks.post_post.is_none());
+        assert_eq!(work.notify_url.as_deref(), Some("https://hooks.test/x"));
+        assert!(work.typography);
+        assert_eq!(work.signature.as_deref(), Some(" — via xcli"));
+        assert_eq!(work.default_hashtags, vec!["rustlang"]);
+        assert_eq!(work.hashtag_position, HashtagPosition::Prepend);
+        assert!(!work.strips_metadata(None));
+        assert!(work.strips_metadata(Some(true)));
+        assert_eq!(work.bluesky.unwrap().handle, "me.bsky.social");
+        assert!(work.mastodon.is_none());
+        let retention = work.retention.unwrap();
+        assert_eq!(retention.delete_after, "90d");
+        assert!(retention.keep_liked);
+        assert_eq!(retention.keep_tags, vec!["keep"]);
+        assert!(retention.keep_patterns.is_empty());
+
+        assert!(settings
+            .profile(DEFAULT_PROFILE)
+            .unwrap()
+            .hooks
+            .pre_post
+            .is_none());
+        let default = settings.profile(DEFAULT_PROFILE).unwrap();
+        assert!(default.strips_metadata(None));
+        assert!(!default.strips_metadata(Some(false)));
+        assert!(settings.profile("missing").is_err());
     }
-}
-
-impl Config {
-    /// Load config with priority: credentials.json → keys.json → .env
-    pub fn load() -> Result<Self, String> {
-        dotenvy::dotenv().ok();
-
-        let keys = ApiKeys::load();
-
-        let api_key = env::var("X_API_KEY")
-            .ok()
-            .or_else(|| keys.as_ref().map(|k| k.api_key.clone()))
-            .ok_or("X_API_KEY not set. Run `xcli auth setup` or set it in .env")?;
-        let api_secret = env::var("X_API_SECRET")
-            .ok()
-            .or_else(|| keys.as_ref().map(|k| k.api_secret.clone()))
-            .ok_or("X_API_SECRET not set. Run `xcli auth setup` or set it in .env")?;
 
-        // 1) credentials.json (OAuth tokens)
-        if let Some(creds) = Credentials::load() {
-            return Ok(Config {
-                api_key,
-                api_secret,
-                access_token: creds.access_token,
-                access_token_secret: creds.access_token_secret,
-            });
-        }
-
-        // 2) keys.json access tokens
-        if let Some(ref k) = keys {
-            if let (Some(at), Some(ats)) = (&k.access_token, &k.access_token_secret) {
-                return Ok(Config {
-                    api_key,
-                    api_secret,
-                    access_token: at.clone(),
-                    access_token_secret: ats.clone(),
-                });
-            }
-        }
-
-        // 3) .env access tokens
-        let access_token = env::var("X_ACCESS_TOKEN")
-            .map_err(|_| "Not logged in. Run `xcli auth login` or set X_ACCESS_TOKEN in .env")?;
-        let access_token_secret = env::var("X_ACCESS_TOKEN_SECRET").map_err(|_| {
-            "Not logged in. Run `xcli auth login` or set X_ACCESS_TOKEN_SECRET in .env"
-        })?;
-
-        Ok(Config {
-            api_key,
-            api_secret,
-            access_token,
-            access_token_secret,
-        })
+    #[test]
+    fn settings_reject_typos() {
+        let err = Settings::parse("[profiles.default.hooks]\npre_posts = \"x\"").unwrap_err();
+        assert!(err.contains("pre_posts"), "{err}");
     }
 
-    /// Load only api_key and api_secret (for OAuth flow before user tokens exist).
-    /// Priority: keys.json → .env
-    pub fn load_consumer_only() -> Result<(String, String), String> {
-        dotenvy::dotenv().ok();
-
-        if let Some(keys) = ApiKeys::load() {
-            return Ok((keys.api_key, keys.api_secret));
-        }
-
-        let api_key = env::var("X_API_KEY")
-            .map_err(|_| "X_API_KEY not set. Run `xcli auth setup` or set it in .env")?;
-        let api_secret = env::var("X_API_SECRET")
-            .map_err(|_| "X_API_SECRET not set. Run `xcli auth setup` or set it in .env")?;
+    #[test]
+    fn settings_missing_file_is_empty() {
+        let path = temp_dir().join("xcli_settings_missing_999.toml");
+        assert!(Settings::load_from(&path).unwrap().profiles.is_empty());
+    }
 
-        Ok((api_key, api_secret))
+    #[test]
+    fn api_keys_load_missing_returns_none() {
+        let path = temp_dir().join("xcli_keys_missing_999.json");
+        assert!(ApiKeys::load_from(&path).is_none());
     }
 }
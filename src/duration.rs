@@ -0,0 +1,167 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Parse a human-friendly duration such as `90s`, `30m`, `24h`, `7d`, `2w`, `1y`
+/// or a combination like `1h30m`. A bare number is treated as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let s = input.trim();
+    if s.is_empty() {
+        return Err("Duration is empty".to_string());
+    }
+
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        if digits.is_empty() {
+            return Err(format!(
+                "Invalid duration '{input}': expected a number before '{c}'"
+            ));
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            'y' => 365 * 24 * 60 * 60,
+            _ => {
+                return Err(format!(
+                    "Invalid duration '{input}': unknown unit '{c}' (use s, m, h, d, w or y)"
+                ))
+            }
+        };
+        let n: u64 = digits
+            .parse()
+            .map_err(|_| format!("Invalid duration '{input}'"))?;
+        total = n
+            .checked_mul(unit)
+            .and_then(|v| total.checked_add(v))
+            .ok_or_else(|| format!("Duration '{input}' is too large"))?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() {
+        return Err(format!(
+            "Invalid duration '{input}': missing unit after '{digits}'"
+        ));
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Inverse of `parse_duration`: render as compact units, e.g. `1d12h`.
+pub fn format_duration(d: Duration) -> String {
+    let mut secs = d.as_secs();
+    if secs == 0 {
+        return "0s".to_string();
+    }
+    let mut out = String::new();
+    for (unit, size) in [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)] {
+        if secs >= size {
+            out.push_str(&format!("{}{unit}", secs / size));
+            secs %= size;
+        }
+    }
+    out
+}
+
+/// Parse a point in time for `--since`-style flags into unix seconds. Accepts a
+/// date (`2024-05-01`, local midnight), an RFC 3339 timestamp, or a duration
+/// meaning "that long ago" (`30d`).
+pub fn parse_since(input: &str) -> Result<i64, String> {
+    let s = input.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+        return Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|dt| dt.timestamp())
+            .ok_or_else(|| format!("Invalid date '{input}'"));
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.timestamp());
+    }
+    let ago = parse_duration(s).map_err(|_| {
+        format!("Invalid time '{input}': use YYYY-MM-DD, RFC 3339, or a duration like 30d")
+    })?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Ok(now.saturating_sub(ago.as_secs()) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_units() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_duration("24h").unwrap(), Duration::from_secs(86400));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(604800));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::from_secs(1209600));
+        assert_eq!(parse_duration("1y").unwrap(), Duration::from_secs(31536000));
+    }
+
+    #[test]
+    fn combined_units() {
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(
+            parse_duration("1d12h").unwrap(),
+            Duration::from_secs(129600)
+        );
+    }
+
+    #[test]
+    fn bare_number_is_seconds() {
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+    }
+
+    #[test]
+    fn format_round_trips() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(86400)), "1d");
+        assert_eq!(format_duration(Duration::from_secs(129600)), "1d12h");
+        assert_eq!(format_duration(Duration::from_secs(5430)), "1h30m30s");
+    }
+
+    #[test]
+    fn since_accepts_dates_and_durations() {
+        let date = parse_since("2024-05-01").unwrap();
+        let expected = Local
+            .with_ymd_and_hms(2024, 5, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(date, expected);
+
+        assert_eq!(parse_since("2024-05-01T00:00:00Z").unwrap(), 1714521600);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let ago = parse_since("1d").unwrap();
+        assert!((now - 86400 - ago).abs() <= 1);
+
+        assert!(parse_since("yesterday").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("1h30").is_err());
+    }
+}
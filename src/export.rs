@@ -0,0 +1,94 @@
+/// Join fields into one CSV record (RFC 4180), quoting only where needed.
+pub fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|f| csv_field(f.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split CSV text (RFC 4180, as `csv_row` writes it) into records of fields.
+pub fn parse_csv(data: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_unquoted() {
+        assert_eq!(csv_row(&["a", "b", "123"]), "a,b,123");
+    }
+
+    #[test]
+    fn special_fields_quoted() {
+        assert_eq!(
+            csv_row(&["a,b", "say \"hi\""]),
+            "\"a,b\",\"say \"\"hi\"\"\""
+        );
+        assert_eq!(csv_row(&["line1\nline2"]), "\"line1\nline2\"");
+    }
+
+    #[test]
+    fn empty_fields() {
+        assert_eq!(csv_row(&["", "x", ""]), ",x,");
+    }
+
+    #[test]
+    fn parses_what_it_writes() {
+        let rows = [
+            csv_row(&["handle", "bio"]),
+            csv_row(&["a", "say \"hi\", then\nleave"]),
+            csv_row(&["b", ""]),
+        ];
+        let parsed = parse_csv(&(rows.join("\r\n") + "\n")).unwrap();
+        assert_eq!(
+            parsed,
+            [
+                vec!["handle", "bio"],
+                vec!["a", "say \"hi\", then\nleave"],
+                vec!["b", ""],
+            ]
+        );
+        assert!(parse_csv("\"open").is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! RSS and Atom cross-posting (`xcli feed`): new entries of subscribed feeds
+//! are posted through a template, once each.
+//!
+//! The entries a feed has when it is added count as seen, so subscribing
+//! does not flood the timeline with a blog's archive. `xcli feed sync`, and
+//! the queue runner on every round, post whatever appeared since.
+
+use regex::Regex;
+use roxmltree::{Document, Node};
+
+use crate::transport::{Method, Request, Transport};
+
+/// Used when `xcli feed add` gets no `--template`.
+pub const DEFAULT_TEMPLATE: &str = "{title} {link}";
+
+/// `{summary}` is cut to this many characters, so one entry is one tweet
+/// unless the template says otherwise.
+const MAX_SUMMARY: usize = 200;
+
+/// Placeholders a template may use.
+pub const PLACEHOLDERS: &[&str] = &["{title}", "{link}", "{summary}"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// RSS `guid` or Atom `id`, falling back to the link.
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    /// Plain text of the description, summary or content.
+    pub summary: String,
+}
+
+/// Check that `template` uses only known placeholders.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let placeholder = Regex::new(r"\{[a-z_]+\}").unwrap();
+    for found in placeholder.find_iter(template) {
+        if !PLACEHOLDERS.contains(&found.as_str()) {
+            return Err(format!(
+                "Unknown placeholder {} in template (expected {})",
+                found.as_str(),
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children()
+        .find(|c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> String {
+    child(node, name)
+        .and_then(|c| c.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Text of an HTML fragment (as found in descriptions), on one line.
+fn plain_text(html: &str) -> String {
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = tags
+        .replace_all(html, " ")
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The `href` of an Atom entry's alternate link.
+fn atom_link(entry: Node) -> String {
+    entry
+        .children()
+        .filter(|c| c.is_element() && c.tag_name().name() == "link")
+        .find(|l| l.attribute("rel").is_none_or(|rel| rel == "alternate"))
+        .and_then(|l| l.attribute("href"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn entry(id: String, title: String, link: String, summary: &str) -> Entry {
+    Entry {
+        id: if id.is_empty() { link.clone() } else { id },
+        title: plain_text(&title),
+        link,
+        summary: plain_text(summary),
+    }
+}
+
+/// Entries of an RSS 2.0 or Atom document, oldest first.
+pub fn parse(xml: &str) -> Result<Vec<Entry>, String> {
+    let doc = Document::parse(xml).map_err(|e| format!("Invalid feed: {e}"))?;
+    let root = doc.root_element();
+    let mut entries: Vec<Entry> = match root.tag_name().name() {
+        "rss" => {
+            let channel = child(root, "channel").ok_or("Invalid feed: RSS without a channel")?;
+            channel
+                .children()
+                .filter(|c| c.is_element() && c.tag_name().name() == "item")
+                .map(|item| {
+                    entry(
+                        child_text(item, "guid"),
+                        child_text(item, "title"),
+                        child_text(item, "link"),
+                        &child_text(item, "description"),
+                    )
+                })
+                .collect()
+        }
+        "feed" => root
+            .children()
+            .filter(|c| c.is_element() && c.tag_name().name() == "entry")
+            .map(|e| {
+                let summary = match child_text(e, "summary") {
+                    s if s.is_empty() => child_text(e, "content"),
+                    s => s,
+                };
+                entry(
+                    child_text(e, "id"),
+                    child_text(e, "title"),
+                    atom_link(e),
+                    &summary,
+                )
+            })
+            .collect(),
+        other => return Err(format!("Not an RSS or Atom feed (root element <{other}>)")),
+    };
+    entries.retain(|e| !e.id.is_empty());
+    // Feeds list the newest entry first.
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Fill `template` in with `entry`.
+pub fn render(template: &str, entry: &Entry) -> String {
+    let summary = if entry.summary.chars().count() > MAX_SUMMARY {
+        let cut: String = entry.summary.chars().take(MAX_SUMMARY - 1).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        entry.summary.clone()
+    };
+    template
+        .replace("{title}", &entry.title)
+        .replace("{link}", &entry.link)
+        .replace("{summary}", &summary)
+        .trim()
+        .to_string()
+}
+
+/// Download and parse the feed at `url`.
+pub async fn fetch(transport: &dyn Transport, url: &str) -> Result<Vec<Entry>, String> {
+    let resp = transport
+        .send(Request::new(Method::GET, url))
+        .await
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+    if !resp.status.is_success() {
+        return Err(format!("Failed to fetch {url}: {}", resp.status));
+    }
+    parse(&resp.text()).map_err(|e| format!("{url}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Blog</title>
+  <item><title>Second post</title><link>https://blog.example/2</link>
+    <guid isPermaLink="false">post-2</guid>
+    <description><![CDATA[<p>Hello &amp; <b>welcome</b></p>]]></description></item>
+  <item><title>First post</title><link>https://blog.example/1</link></item>
+</channel></rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+  <entry><title type="html">Release 1.0</title><id>tag:blog.example,2025:1</id>
+    <link rel="self" href="https://blog.example/1.atom"/>
+    <link href="https://blog.example/1"/>
+    <content type="html">&lt;p&gt;It is out.&lt;/p&gt;</content></entry>
+</feed>"#;
+
+    #[test]
+    fn parses_rss_oldest_first() {
+        let entries = parse(RSS).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "https://blog.example/1");
+        assert_eq!(entries[0].title, "First post");
+        assert_eq!(entries[1].id, "post-2");
+        assert_eq!(entries[1].summary, "Hello & welcome");
+    }
+
+    #[test]
+    fn parses_atom() {
+        let entries = parse(ATOM).unwrap();
+        assert_eq!(
+            entries,
+            vec![Entry {
+                id: "tag:blog.example,2025:1".to_string(),
+                title: "Release 1.0".to_string(),
+                link: "https://blog.example/1".to_string(),
+                summary: "It is out.".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse("<html></html>").unwrap_err().contains("<html>"));
+        assert!(parse("not xml").is_err());
+    }
+
+    #[test]
+    fn renders_templates() {
+        let entry = &parse(RSS).unwrap()[1];
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, entry),
+            "Second post https://blog.example/2"
+        );
+        assert_eq!(
+            render("New: {title}\n\n{summary} {link}", entry),
+            "New: Second post\n\nHello & welcome https://blog.example/2"
+        );
+
+        let long = Entry {
+            summary: "word ".repeat(100),
+            ..entry.clone()
+        };
+        let summary = render("{summary}", &long);
+        assert_eq!(summary.chars().count(), MAX_SUMMARY);
+        assert!(summary.ends_with("word…"));
+
+        assert!(validate_template("{title} {link}").is_ok());
+        assert!(validate_template("{titel}")
+            .unwrap_err()
+            .contains("{titel}"));
+    }
+
+    #[tokio::test]
+    async fn fetch_reports_http_errors() {
+        let mock = MockTransport::new();
+        mock.respond(200, ATOM);
+        assert_eq!(
+            fetch(&mock, "https://blog.example/feed")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        mock.respond(404, "gone");
+        let err = fetch(&mock, "https://blog.example/feed").await.unwrap_err();
+        assert!(err.contains("404"), "{err}");
+    }
+}
@@ -0,0 +1,225 @@
+//! Pre- and post-post hooks: shell commands from the profile's `[hooks]`
+//! table, run around posting.
+//!
+//! The pre-post hook reads the chunks on stdin, separated by `---` lines. It
+//! vetoes the post by exiting non-zero, and may print replacement chunks in
+//! the same format (printing nothing keeps them unchanged). The post-post hook
+//! reads one `ID URL` line per posted tweet. The alt text command reads the
+//! path of an image and prints a description of it, and the translate command
+//! reads the text and prints it in the language named by
+//! `XCLI_TRANSLATE_LANG`.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const SEPARATOR: &str = "---";
+
+pub fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// Run `command` with `input` on stdin and return its stdout. Its stderr
+/// goes straight to the terminal, so hooks can explain a veto.
+fn run(command: &str, hook: &str, profile: &str, input: &str) -> Result<String, String> {
+    run_with(command, hook, profile, &[], input)
+}
+
+/// `run`, with extra environment variables.
+fn run_with(
+    command: &str,
+    hook: &str,
+    profile: &str,
+    env: &[(&str, &str)],
+    input: &str,
+) -> Result<String, String> {
+    let mut child = shell(command)
+        .env("XCLI_HOOK", hook)
+        .env("XCLI_PROFILE", profile)
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("Failed to run {hook} hook `{command}`: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that does not read its input is fine.
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {hook} hook `{command}`: {e}"))?;
+    if !output.status.success() {
+        let code = output
+            .status
+            .code()
+            .map_or("killed".to_string(), |c| format!("exit code {c}"));
+        return Err(format!("{hook} hook `{command}` failed ({code})"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn format_chunks(chunks: &[String]) -> String {
+    let mut out = chunks.join(&format!("\n{SEPARATOR}\n"));
+    out.push('\n');
+    out
+}
+
+/// Split hook output back into chunks on `---` lines.
+pub fn parse_chunks(output: &str) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    for line in output.lines() {
+        if line.trim() == SEPARATOR {
+            chunks.push(String::new());
+        } else {
+            let current = chunks.last_mut().unwrap();
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(line);
+        }
+    }
+    chunks
+        .into_iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Run the pre-post hook over `chunks` and return the chunks to post.
+pub fn pre_post(command: &str, profile: &str, chunks: &[String]) -> Result<Vec<String>, String> {
+    let output = run(command, "pre_post", profile, &format_chunks(chunks))?;
+    if output.trim().is_empty() {
+        return Ok(chunks.to_vec());
+    }
+    Ok(parse_chunks(&output))
+}
+
+pub fn tweet_url(screen_name: Option<&str>, id: &str) -> String {
+    format!("https://x.com/{}/status/{id}", screen_name.unwrap_or("i"))
+}
+
+/// Run the post-post hook with the posted tweets.
+pub fn post_post(
+    command: &str,
+    profile: &str,
+    screen_name: Option<&str>,
+    ids: &[String],
+) -> Result<(), String> {
+    let input: String = ids
+        .iter()
+        .map(|id| format!("{id} {}\n", tweet_url(screen_name, id)))
+        .collect();
+    run(command, "post_post", profile, &input).map(|_| ())
+}
+
+/// Run the alt text command on the image at `path` and return what it
+/// printed, trimmed (empty if it had nothing to say).
+pub fn alt_text(command: &str, profile: &str, path: &Path) -> Result<String, String> {
+    let output = run(
+        command,
+        "alt_text",
+        profile,
+        &format!("{}\n", path.display()),
+    )?;
+    Ok(output.trim().to_string())
+}
+
+/// Run the translate command over `text` and return its translation into
+/// `lang`.
+pub fn translate(command: &str, profile: &str, text: &str, lang: &str) -> Result<String, String> {
+    let env = [("XCLI_TRANSLATE_LANG", lang)];
+    let output = run_with(command, "translate", profile, &env, text)?;
+    let translated = output.trim();
+    if translated.is_empty() {
+        return Err(format!("translate hook `{command}` printed nothing"));
+    }
+    Ok(translated.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let original = chunks(&["first\nline two", "second"]);
+        let text = format_chunks(&original);
+        assert_eq!(text, "first\nline two\n---\nsecond\n");
+        assert_eq!(parse_chunks(&text), original);
+        assert_eq!(parse_chunks("\n---\n only \n---\n"), chunks(&["only"]));
+    }
+
+    #[test]
+    fn tweet_urls() {
+        assert_eq!(tweet_url(Some("me"), "1"), "https://x.com/me/status/1");
+        assert_eq!(tweet_url(None, "1"), "https://x.com/i/status/1");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pre_post_can_rewrite_keep_or_veto() {
+        let original = chunks(&["hello", "world"]);
+        assert_eq!(
+            pre_post("tr a-z A-Z", "default", &original).unwrap(),
+            chunks(&["HELLO", "WORLD"])
+        );
+        assert_eq!(
+            pre_post("cat >/dev/null", "default", &original).unwrap(),
+            original
+        );
+        // No empty tweets, whatever the hook prints.
+        assert_eq!(
+            pre_post(
+                "printf 'a\\n---\\n\\n---\\nb\\n---\\n'",
+                "default",
+                &original
+            )
+            .unwrap(),
+            chunks(&["a", "b"])
+        );
+        let err = pre_post("exit 3", "default", &original).unwrap_err();
+        assert!(err.contains("exit code 3"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn post_post_gets_ids_and_urls() {
+        let ids = chunks(&["1", "2"]);
+        let check = r#"test "$(cat)" = "1 https://x.com/me/status/1
+2 https://x.com/me/status/2" && test "$XCLI_HOOK" = post_post"#;
+        post_post(check, "default", Some("me"), &ids).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn alt_text_gets_the_image_path() {
+        let command = r#"read path; echo "  A chart from $path "; test "$XCLI_HOOK" = alt_text"#;
+        let alt = alt_text(command, "default", Path::new("/tmp/chart.png")).unwrap();
+        assert_eq!(alt, "A chart from /tmp/chart.png");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn translate_gets_text_and_language() {
+        let command = r#"printf '[%s] ' "$XCLI_TRANSLATE_LANG"; tr a-z A-Z"#;
+        assert_eq!(
+            translate(command, "default", "hello\n", "ko").unwrap(),
+            "[ko] HELLO"
+        );
+        let err = translate("cat >/dev/null", "default", "hello", "ko").unwrap_err();
+        assert!(err.contains("printed nothing"), "{err}");
+    }
+}
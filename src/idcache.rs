@@ -0,0 +1,109 @@
+//! The account IDs handles were last resolved to, kept in `user-ids.json` so
+//! `xcli follow` and friends skip the lookup for handles seen recently.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::User;
+
+/// How long a lookup is trusted (30 days): a handle given up can be taken
+/// by another account.
+const MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub id: String,
+    /// The handle as X spells it.
+    pub username: String,
+    pub name: String,
+    /// When it was looked up (Unix seconds).
+    pub resolved_at: i64,
+}
+
+/// Lookups by lowercased handle.
+pub type Cache = BTreeMap<String, Entry>;
+
+/// The accounts of `handles` looked up less than 30 days before `now`.
+pub fn known(cache: &Cache, handles: &[String], now: i64) -> Vec<User> {
+    handles
+        .iter()
+        .filter_map(|h| cache.get(&h.to_lowercase()))
+        .filter(|e| now - e.resolved_at < MAX_AGE_SECS)
+        .map(|e| User {
+            id: e.id.clone(),
+            username: e.username.clone(),
+            name: e.name.clone(),
+            description: None,
+            public_metrics: None,
+            profile_image_url: None,
+            most_recent_tweet_id: None,
+        })
+        .collect()
+}
+
+/// Remember `users` as looked up at `now`.
+pub fn remember(cache: &mut Cache, users: &[User], now: i64) {
+    for user in users {
+        cache.insert(
+            user.username.to_lowercase(),
+            Entry {
+                id: user.id.clone(),
+                username: user.username.clone(),
+                name: user.name.clone(),
+                resolved_at: now,
+            },
+        );
+    }
+}
+
+/// What is saved at `path`, or nothing.
+pub fn load(path: &Path) -> Cache {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(path: &Path, cache: &Cache) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string_pretty(cache).expect("cached IDs serialize to JSON");
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str, username: &str) -> User {
+        User {
+            id: id.to_string(),
+            username: username.to_string(),
+            name: username.to_string(),
+            description: None,
+            public_metrics: None,
+            profile_image_url: None,
+            most_recent_tweet_id: None,
+        }
+    }
+
+    #[test]
+    fn trusts_recent_lookups_only() {
+        let mut cache = Cache::new();
+        remember(&mut cache, &[user("1", "Alice")], 1_000);
+        remember(&mut cache, &[user("2", "bob")], 1_000 + MAX_AGE_SECS);
+
+        let handles = ["alice".to_string(), "BOB".to_string(), "carol".to_string()];
+        let found = known(&cache, &handles, 1_000 + MAX_AGE_SECS);
+        let found: Vec<(&str, &str)> = found
+            .iter()
+            .map(|u| (u.id.as_str(), u.username.as_str()))
+            .collect();
+        assert_eq!(found, [("2", "bob")]);
+        assert_eq!(known(&cache, &handles, 2_000).len(), 2);
+    }
+}
@@ -0,0 +1,302 @@
+//! JPEG metadata: the size and orientation of a photo, and stripping what it
+//! says about where it was taken. Decoding and encoding, to scale photos down
+//! before upload (see [`crate::media`]), go through the `image` crate.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::ExtendedColorType;
+
+use crate::media::Image;
+
+fn u16_at(data: &[u8], pos: usize) -> Result<usize, String> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| "Truncated JPEG".to_string())
+}
+
+/// Width and height from the frame header, without decoding.
+pub fn dimensions(data: &[u8]) -> Option<(usize, usize)> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let len = u16_at(data, pos + 2).ok()?;
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16_at(data, pos + 5).ok()?;
+            let width = u16_at(data, pos + 7).ok()?;
+            return Some((width, height));
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// The EXIF orientation (1 to 8) of a JPEG, 1 if it has none.
+pub fn orientation(data: &[u8]) -> u16 {
+    segments(data)
+        .find(|(marker, body)| *marker == 0xE1 && body.starts_with(b"Exif\0\0"))
+        .and_then(|(_, body)| exif_orientation(&body[6..]))
+        .unwrap_or(1)
+}
+
+/// The `(marker, body)` of every segment before the scan data.
+pub fn segments(data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut pos = 2;
+    std::iter::from_fn(move || {
+        while data.get(pos) == Some(&0xFF) && data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        if data.get(pos) != Some(&0xFF) {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        if marker == 0xD9 || marker == 0xDA {
+            return None;
+        }
+        let len = u16_at(data, pos + 2).ok()?;
+        let body = data.get(pos + 4..pos + 2 + len)?;
+        pos += 2 + len;
+        Some((marker, body))
+    })
+}
+
+/// Whether a segment may be private: EXIF and XMP (APP1), maker and vendor
+/// blocks, IPTC (APP13), multi-picture data and comments. JFIF (APP0), color
+/// profiles (APP2 `ICC_PROFILE`) and Adobe's color transform (APP14) stay,
+/// as decoding depends on them.
+fn is_metadata(marker: u8, body: &[u8]) -> bool {
+    match marker {
+        0xE0 | 0xEE => false,
+        0xE2 => !body.starts_with(b"ICC_PROFILE\0"),
+        0xE1..=0xEF | 0xFE => true,
+        _ => false,
+    }
+}
+
+/// A copy without EXIF (GPS position, camera, date), XMP, IPTC, comments
+/// and anything after the image, keeping only the orientation so the photo
+/// still shows upright. `None` if there was nothing to remove.
+pub fn strip_metadata(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err("Not a JPEG file".to_string());
+    }
+    let orientation = orientation(data);
+    let mut out = vec![0xFF, 0xD8];
+    let mut removed = false;
+    let mut pos = 2;
+    loop {
+        while data.get(pos) == Some(&0xFF) && data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        if data.get(pos) != Some(&0xFF) {
+            return Err("Corrupt JPEG: expected a marker".to_string());
+        }
+        let marker = *data.get(pos + 1).ok_or("Truncated JPEG")?;
+        if marker == 0xDA {
+            break;
+        }
+        if marker == 0xD9 {
+            return Err("Corrupt JPEG: no image data".to_string());
+        }
+        let len = u16_at(data, pos + 2)?;
+        if len < 2 {
+            return Err("Corrupt JPEG: bad segment length".to_string());
+        }
+        let segment = data.get(pos..pos + 2 + len).ok_or("Truncated JPEG")?;
+        if is_metadata(marker, &segment[4..]) {
+            removed = true;
+        } else {
+            out.extend_from_slice(segment);
+        }
+        pos += 2 + len;
+    }
+    // The scans run to the end of image; `FF D9` cannot occur inside them.
+    let end = data[pos..]
+        .windows(2)
+        .position(|w| w == [0xFF, 0xD9])
+        .map_or(data.len(), |i| pos + i + 2);
+    removed |= end < data.len();
+    if !removed {
+        return Ok(None);
+    }
+    out.extend_from_slice(&data[pos..end]);
+    if orientation != 1 {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend(orientation.to_be_bytes());
+        exif.extend([0; 6]);
+        let mut app1 = Vec::new();
+        segment(&mut app1, 0xE1, &exif);
+        out.splice(2..2, app1);
+    }
+    Ok(Some(out))
+}
+
+/// Orientation tag (0x0112) of the first IFD of a TIFF structure.
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    let little = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |p: usize| -> Option<u16> {
+        let b = tiff.get(p..p + 2)?;
+        Some(if little {
+            u16::from_le_bytes([b[0], b[1]])
+        } else {
+            u16::from_be_bytes([b[0], b[1]])
+        })
+    };
+    let u32_at = |p: usize| -> Option<u32> {
+        let b = tiff.get(p..p + 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Some(if little {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    };
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|o| (1..=8).contains(o))
+}
+
+fn segment(out: &mut Vec<u8>, marker: u8, body: &[u8]) {
+    out.extend([0xFF, marker]);
+    out.extend(((body.len() + 2) as u16).to_be_bytes());
+    out.extend(body);
+}
+
+/// Encode `image` (alpha ignored) as a baseline JPEG.
+pub fn encode(image: &Image, quality: u8) -> Vec<u8> {
+    let rgb: Vec<u8> = image
+        .pixels
+        .chunks(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect();
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, quality)
+        .encode(
+            &rgb,
+            image.width as u32,
+            image.height as u32,
+            ExtendedColorType::Rgb8,
+        )
+        .expect("encoding to memory does not fail");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media;
+
+    fn decode(data: &[u8]) -> Result<Image, String> {
+        media::decode(data, "image/jpeg")
+    }
+
+    fn gradient(width: usize, height: usize) -> Image {
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend([(x * 255 / width) as u8, (y * 255 / height) as u8, 100, 255]);
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let image = gradient(37, 21);
+        let data = encode(&image, 90);
+        assert_eq!(dimensions(&data), Some((37, 21)));
+        let decoded = decode(&data).unwrap();
+        assert_eq!((decoded.width, decoded.height), (37, 21));
+        let worst = image
+            .pixels
+            .iter()
+            .zip(&decoded.pixels)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(worst < 16, "pixels off by {worst}");
+    }
+
+    #[test]
+    fn reads_exif_orientation() {
+        let mut data = vec![0xFF, 0xD8];
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        exif.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0]);
+        segment(&mut data, 0xE1, &exif);
+        data.extend(&encode(&gradient(8, 8), 50)[2..]);
+        assert_eq!(orientation(&data), 6);
+        assert_eq!(orientation(&encode(&gradient(8, 8), 50)), 1);
+    }
+
+    #[test]
+    fn strips_metadata_but_orientation() {
+        let image = encode(&gradient(8, 8), 50);
+        let mut data = vec![0xFF, 0xD8];
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x02".to_vec();
+        exif.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 8, 0, 0]);
+        exif.extend([0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 0x26, 0, 0, 0, 0]);
+        exif.extend(b"GPS SECRET");
+        segment(&mut data, 0xE1, &exif);
+        segment(&mut data, 0xE2, b"ICC_PROFILE\0\x01\x01");
+        segment(&mut data, 0xFE, b"SECRET comment");
+        data.extend(&image[2..]);
+        data.extend(b"SECRET trailer");
+
+        let stripped = strip_metadata(&data).unwrap().unwrap();
+        assert!(!stripped.windows(6).any(|w| w == b"SECRET"));
+        assert!(stripped.windows(11).any(|w| w == b"ICC_PROFILE"));
+        assert_eq!(orientation(&stripped), 8);
+        assert_eq!(
+            decode(&stripped).unwrap(),
+            decode(&image).unwrap().oriented(8)
+        );
+        assert_eq!(strip_metadata(&image).unwrap(), None);
+
+        let corrupt = [0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x00, 0xFF, 0xDA, 0x00];
+        assert_eq!(
+            strip_metadata(&corrupt).unwrap_err(),
+            "Corrupt JPEG: bad segment length"
+        );
+    }
+
+    #[test]
+    fn rejects_huge_and_corrupt_images() {
+        let mut huge = vec![0xFF, 0xD8];
+        segment(&mut huge, 0xC0, &[8, 0xFF, 0xFF, 0xFF, 0xFF, 1, 1, 0x11, 0]);
+        assert_eq!(decode(&huge).unwrap_err(), "Image too large to resize");
+
+        // A DC table whose only code stands for a 200-bit difference.
+        let mut data = vec![0xFF, 0xD8];
+        let mut dqt = vec![0];
+        dqt.extend([1; 64]);
+        segment(&mut data, 0xDB, &dqt);
+        let mut dht = vec![0x00, 1];
+        dht.extend([0; 15]);
+        dht.push(200);
+        dht.extend([0x10, 1]);
+        dht.extend([0; 15]);
+        dht.push(0);
+        segment(&mut data, 0xC4, &dht);
+        segment(&mut data, 0xC0, &[8, 0, 8, 0, 8, 1, 1, 0x11, 0]);
+        segment(&mut data, 0xDA, &[1, 1, 0x00, 0, 63, 0]);
+        data.extend([0x00, 0xFF, 0xD9]);
+        assert!(decode(&data).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Post and manage tweets on X from Rust.
+//!
+//! This crate powers the `xcli` command-line tool and exposes its core as a
+//! library, so other tools can embed it instead of shelling out:
+//!
+//! - [`XClient`]: tweet, reply, thread, delete, search and media upload.
+//! - [`thread`]: the weighted-length thread splitter.
+//! - [`auth`]: the OAuth 1.0a request signer.
+//! - [`config`]: credentials, loaded from the same places as the CLI.
+//!
+//! ```no_run
+//! # async fn demo() -> Result<(), String> {
+//! let client = xcli::XClient::from_env()?;
+//! let id = client.tweet("Hello from Rust!").await?;
+//! println!("posted {id}");
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod api;
+pub mod auth;
+pub mod client;
+pub mod config;
+pub mod thread;
+pub mod transport;
+
+#[doc(hidden)]
+pub mod cli;
+
+mod applog;
+#[cfg(feature = "history")]
+mod archive;
+mod bot;
+mod bulk;
+mod capture;
+mod cassette;
+mod duration;
+mod export;
+#[cfg(feature = "history")]
+mod feed;
+mod hooks;
+mod idcache;
+mod jpeg;
+mod lint;
+mod lock;
+mod markdown;
+mod media;
+mod mirror;
+mod mutuals;
+mod notify;
+mod oauth;
+mod photos;
+mod plugin;
+mod png;
+mod progress;
+mod prune;
+mod queue;
+mod ratelimits;
+#[cfg(feature = "render")]
+mod render;
+mod replies;
+#[cfg(feature = "history")]
+mod retention;
+mod roster;
+mod sandbox;
+mod seen;
+mod serve;
+mod service;
+mod spell;
+mod stats;
+mod store;
+#[cfg(feature = "stream")]
+mod stream;
+mod trace;
+mod typography;
+mod update;
+mod uploads;
+mod urls;
+
+pub use client::XClient;
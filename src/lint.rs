@@ -0,0 +1,186 @@
+//! Content rules from the profile's `[lint]` table, checked before posting
+//! and by `xcli lint`: banned words and patterns, required disclosure tags,
+//! a hashtag limit, tweets that are nothing but a link and images without
+//! alt text.
+
+use regex::Regex;
+
+use crate::config::Lint;
+use crate::thread::missing_hashtags;
+
+/// A rule the text breaks, in one tweet or in the post as a whole.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub tweet: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    /// The problem as a sentence, for a post of `tweets` tweets.
+    pub fn describe(&self, tweets: usize) -> String {
+        match self.tweet {
+            Some(i) if tweets > 1 => format!("Tweet {}/{tweets} {}", i + 1, self.message),
+            Some(_) => format!("The tweet {}", self.message),
+            None => format!("The post {}", self.message),
+        }
+    }
+}
+
+/// Check `chunks` (the tweets of one post) against `rules`. Fails only if a
+/// rule itself is broken, such as an invalid pattern.
+pub fn check(rules: &Lint, chunks: &[String]) -> Result<Vec<Problem>, String> {
+    let mut problems = Vec::new();
+    let mut found =
+        |tweet: Option<usize>, message: String| problems.push(Problem { tweet, message });
+
+    let words = rules
+        .banned_words
+        .iter()
+        .map(|w| {
+            let pattern = format!(r"(?i)(?:^|[^\w#@]){}(?:$|\W)", regex::escape(w.trim()));
+            (w.trim(), Regex::new(&pattern).unwrap())
+        })
+        .collect::<Vec<_>>();
+    let patterns = rules
+        .banned_patterns
+        .iter()
+        .map(|p| Regex::new(p).map_err(|e| format!("Invalid banned_patterns entry `{p}`: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let link = Regex::new(r"^https?://\S+$").unwrap();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        for (word, re) in &words {
+            if re.is_match(chunk) {
+                found(Some(i), format!("contains the banned word \"{word}\""));
+            }
+        }
+        for re in &patterns {
+            if let Some(m) = re.find(chunk) {
+                found(
+                    Some(i),
+                    format!("matches the banned pattern /{re}/ (\"{}\")", m.as_str()),
+                );
+            }
+        }
+        if rules.no_bare_urls && link.is_match(chunk.trim()) {
+            found(Some(i), "is nothing but a link".to_string());
+        }
+    }
+
+    let text = chunks.join("\n");
+    for tag in missing_hashtags(&text, &rules.required_tags) {
+        found(None, format!("is missing the required tag {tag}"));
+    }
+    if let Some(max) = rules.max_hashtags {
+        let count = hashtags(&text);
+        if count > max {
+            found(None, format!("has {count} hashtags (at most {max})"));
+        }
+    }
+    Ok(problems)
+}
+
+/// Check the images of a post, as (name, alt text) with an empty alt text for
+/// none, against `require_alt_text`.
+pub fn check_alt_text(rules: &Lint, images: &[(String, &str)]) -> Vec<Problem> {
+    if !rules.require_alt_text {
+        return Vec::new();
+    }
+    images
+        .iter()
+        .filter(|(_, alt)| alt.trim().is_empty())
+        .map(|(name, _)| Problem {
+            tweet: None,
+            message: format!("attaches {name} without alt text"),
+        })
+        .collect()
+}
+
+/// How many hashtags `text` has.
+fn hashtags(text: &str) -> usize {
+    let tag = Regex::new(r"(?:^|\s)#[^\s#[:punct:]]").unwrap();
+    tag.find_iter(text).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn messages(rules: &Lint, texts: &[&str]) -> Vec<String> {
+        let chunks = chunks(texts);
+        check(rules, &chunks)
+            .unwrap()
+            .iter()
+            .map(|p| p.describe(chunks.len()))
+            .collect()
+    }
+
+    #[test]
+    fn flags_banned_words_and_patterns() {
+        let rules = Lint {
+            banned_words: vec!["moon".to_string()],
+            banned_patterns: vec![r"\d+x returns".to_string()],
+            ..Lint::default()
+        };
+        assert_eq!(
+            messages(&rules, &["To the MOON!", "Expect 10x returns"]),
+            [
+                "Tweet 1/2 contains the banned word \"moon\"",
+                "Tweet 2/2 matches the banned pattern /\\d+x returns/ (\"10x returns\")",
+            ]
+        );
+        // Whole words only, and hashtags or mentions are something else.
+        assert!(messages(&rules, &["Honeymoon photos from #moon and @moon"]).is_empty());
+
+        let broken = Lint {
+            banned_patterns: vec!["(".to_string()],
+            ..Lint::default()
+        };
+        assert!(check(&broken, &chunks(&["hi"])).is_err());
+    }
+
+    #[test]
+    fn checks_tags_and_links() {
+        let rules = Lint {
+            required_tags: vec!["ad".to_string()],
+            max_hashtags: Some(2),
+            no_bare_urls: true,
+            ..Lint::default()
+        };
+        assert_eq!(
+            messages(&rules, &["#one #two #three"]),
+            [
+                "The post is missing the required tag #ad",
+                "The post has 3 hashtags (at most 2)",
+            ]
+        );
+        assert_eq!(
+            messages(&rules, &["New video #ad", " https://example.com/v "]),
+            ["Tweet 2/2 is nothing but a link"]
+        );
+        assert!(messages(&rules, &["Watch https://example.com/v #AD"]).is_empty());
+    }
+
+    #[test]
+    fn requires_alt_text_when_asked() {
+        let images = [
+            ("cat.jpg".to_string(), "A cat asleep on a keyboard"),
+            ("chart.png".to_string(), " "),
+        ];
+        assert!(check_alt_text(&Lint::default(), &images).is_empty());
+
+        let rules = Lint {
+            require_alt_text: true,
+            ..Lint::default()
+        };
+        let problems: Vec<String> = check_alt_text(&rules, &images)
+            .iter()
+            .map(|p| p.describe(1))
+            .collect();
+        assert_eq!(problems, ["The post attaches chart.png without alt text"]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+/// An advisory lock on a file, held until drop (or until the process dies,
+/// which releases it by itself). Used so only one queue runner processes the
+/// queue at a time. The file holds the owner's PID, for error messages only:
+/// it is never removed, as a runner may be waiting on it.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Take the lock, or fail if another process holds it.
+    pub fn acquire(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {e}"))?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+
+        if !try_lock(&file).map_err(|e| format!("Failed to lock {}: {e}", path.display()))? {
+            let mut owner = String::new();
+            let _ = file.read_to_string(&mut owner);
+            let owner = owner
+                .trim()
+                .parse::<u32>()
+                .map(|p| format!(" (pid {p})"))
+                .unwrap_or_default();
+            return Err(format!("Another queue runner is already active{owner}"));
+        }
+
+        let written = file
+            .set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| writeln!(file, "{}", std::process::id()));
+        written.map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(Lock { _file: file })
+    }
+}
+
+/// Whether the lock on `file` was taken; `false` if another holds it.
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let rc = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if rc == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(false),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::path::PathBuf;
+
+    fn lock_path(name: &str) -> PathBuf {
+        temp_dir().join(format!("xcli_test_{}_{name}.lock", std::process::id()))
+    }
+
+    #[test]
+    fn second_acquire_fails_until_released() {
+        let path = lock_path("exclusive");
+        let _ = fs::remove_file(&path);
+
+        let lock = Lock::acquire(&path).unwrap();
+        let err = Lock::acquire(&path).err().unwrap();
+        assert!(err.contains(&format!("pid {}", std::process::id())));
+
+        drop(lock);
+        assert!(path.exists());
+        let lock = Lock::acquire(&path).unwrap();
+        drop(lock);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn contents_do_not_decide_who_holds_it() {
+        // What a dead runner, or one halfway through writing, leaves behind.
+        let path = lock_path("garbage");
+        fs::write(&path, "not a pid").unwrap();
+        let lock = Lock::acquire(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+
+        // A live holder is respected even when the file says nothing.
+        fs::write(&path, "").unwrap();
+        let err = Lock::acquire(&path).err().unwrap();
+        assert_eq!(err, "Another queue runner is already active");
+        drop(lock);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! `--markdown`: X has no text formatting, so `**bold**` and `*italic*` are
+//! rendered with look-alike letters from the Mathematical Alphanumeric
+//! Symbols block (sans-serif, which reads best). They weigh 2 each, like any
+//! character outside the light ranges.
+//!
+//! Emphasis follows the usual markdown rules closely enough for tweets: the
+//! markers must hug the text (`2 * 3 * 4` is left alone), do not span lines
+//! and do not nest, and `\*` is a literal asterisk. Mentions, hashtags,
+//! cashtags and URLs inside emphasis keep their letters, or X would no longer
+//! recognize them.
+
+#[derive(Clone, Copy)]
+enum Style {
+    Italic,
+    Bold,
+    BoldItalic,
+    Monospace,
+}
+
+impl Style {
+    /// First code points of the styled `A`, `a` and `0`.
+    fn bases(self) -> (u32, u32, Option<u32>) {
+        match self {
+            Style::Bold => (0x1D5D4, 0x1D5EE, Some(0x1D7EC)),
+            Style::Italic => (0x1D608, 0x1D622, None),
+            Style::BoldItalic => (0x1D63C, 0x1D656, Some(0x1D7EC)),
+            Style::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+        }
+    }
+
+    fn apply(self, c: char) -> char {
+        let (upper, lower, digit) = self.bases();
+        let offset = |base: u32, first: char| char::from_u32(base + (c as u32 - first as u32));
+        let styled = match c {
+            'A'..='Z' => offset(upper, 'A'),
+            'a'..='z' => offset(lower, 'a'),
+            '0'..='9' => digit.and_then(|base| offset(base, '0')),
+            _ => None,
+        };
+        styled.unwrap_or(c)
+    }
+}
+
+/// Words that X links, and that must keep plain letters to stay links.
+fn is_entity(word: &str) -> bool {
+    word.starts_with(['@', '#', '$']) || word.contains("://")
+}
+
+fn push_styled(out: &mut String, text: &str, style: Style) {
+    for word in text.split_inclusive(char::is_whitespace) {
+        if is_entity(word) {
+            out.push_str(word);
+        } else {
+            out.extend(word.chars().map(|c| style.apply(c)));
+        }
+    }
+}
+
+/// Index of the `n` asterisks closing emphasis opened just before `start`.
+fn closing(chars: &[char], start: usize, n: usize) -> Option<usize> {
+    if chars
+        .get(start)
+        .is_none_or(|c| c.is_whitespace() || *c == '*')
+    {
+        return None;
+    }
+    let mut i = start + 1;
+    while i + n <= chars.len() {
+        match chars[i] {
+            '\n' => return None,
+            '\\' => i += 2,
+            '*' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '*').count();
+                if run == n && !chars[i - 1].is_whitespace() {
+                    return Some(i);
+                }
+                i += run;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Set all of `text` in monospace letters, for code and command output.
+pub fn monospace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 4);
+    push_styled(&mut out, text, Style::Monospace);
+    out
+}
+
+/// Replace `**bold**`, `*italic*` and `***both***` with styled letters.
+pub fn to_unicode(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'*') => {
+                out.push('*');
+                i += 2;
+            }
+            '*' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '*').count();
+                let n = run.min(3);
+                let start = i + n;
+                match closing(&chars, start, n) {
+                    Some(end) if run == n => {
+                        let style = match n {
+                            1 => Style::Italic,
+                            2 => Style::Bold,
+                            _ => Style::BoldItalic,
+                        };
+                        let inner: String = chars[start..end].iter().collect();
+                        push_styled(&mut out, &inner.replace("\\*", "*"), style);
+                        i = end + n;
+                    }
+                    _ => {
+                        out.extend(&chars[i..i + run]);
+                        i += run;
+                    }
+                }
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thread::weighted_len;
+
+    #[test]
+    fn bold_and_italic() {
+        assert_eq!(to_unicode("a **bold** move"), "a 𝗯𝗼𝗹𝗱 move");
+        assert_eq!(to_unicode("*Really* 2x"), "𝘙𝘦𝘢𝘭𝘭𝘺 2x");
+        assert_eq!(to_unicode("***Top 10***"), "𝙏𝙤𝙥 𝟭𝟬");
+        assert_eq!(to_unicode("**안녕 AB!**"), "안녕 𝗔𝗕!");
+    }
+
+    #[test]
+    fn monospace_keeps_layout_and_entities() {
+        assert_eq!(
+            monospace("ok 12\n  see https://x.com"),
+            "𝚘𝚔 𝟷𝟸\n  𝚜𝚎𝚎 https://x.com"
+        );
+    }
+
+    #[test]
+    fn styled_letters_weigh_two() {
+        assert_eq!(weighted_len(&to_unicode("**bold**")), 8);
+        assert_eq!(weighted_len(&to_unicode("*it* ok")), 7);
+    }
+
+    #[test]
+    fn leaves_non_emphasis_alone() {
+        for text in [
+            "2 * 3 * 4",
+            "** not bold **",
+            "**unclosed",
+            "*across\nlines*",
+            "****",
+            "**mismatched*",
+        ] {
+            assert_eq!(to_unicode(text), text);
+        }
+        assert_eq!(to_unicode(r"\*not italic\*"), "*not italic*");
+    }
+
+    #[test]
+    fn keeps_entities_plain() {
+        assert_eq!(
+            to_unicode("**thanks @rustlang #rust https://x.com/a**"),
+            "𝘁𝗵𝗮𝗻𝗸𝘀 @rustlang #rust https://x.com/a"
+        );
+    }
+}
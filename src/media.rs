@@ -0,0 +1,681 @@
+//! Getting images within X's upload limits. A JPEG or PNG over 5 MB, or over
+//! 8192 pixels on a side, is scaled down and re-encoded locally (as JPEG, or
+//! PNG if it has transparency) instead of being rejected by X after the
+//! whole file was sent. WebP and GIF files are uploaded as they are, but a
+//! GIF over X's GIF limits is refused locally.
+//!
+//! Before either, metadata is stripped: photos carry the GPS position they
+//! were taken at in their EXIF data.
+
+use std::io::Cursor;
+
+use image::{ImageError, ImageFormat, ImageReader, Limits};
+
+use crate::transport::{Method, Request, Transport};
+use crate::{jpeg, png};
+
+/// Largest image X accepts.
+pub const MAX_BYTES: usize = 5 * 1024 * 1024;
+/// Largest width or height X accepts.
+pub const MAX_SIDE: usize = 8192;
+/// Longest side to start from when an image is too heavy. X shows nothing
+/// larger anyway.
+const TARGET_SIDE: usize = 4096;
+const QUALITY: u8 = 85;
+/// Largest GIF X accepts (those over `MAX_BYTES` only in chunks).
+pub const MAX_GIF_BYTES: usize = 15 * 1024 * 1024;
+/// Largest GIF width and height X accepts.
+const MAX_GIF_SIZE: (usize, usize) = (1280, 1080);
+/// Most frames X accepts in a GIF.
+const MAX_GIF_FRAMES: usize = 350;
+/// Largest image decoded for resizing (100 megapixels). Bigger ones would
+/// take gigabytes of memory, and are uploaded as they are.
+pub const MAX_PIXELS: usize = 100_000_000;
+/// Largest image `download` accepts. Above X's own limits, as oversized
+/// photos are scaled down.
+const MAX_DOWNLOAD_BYTES: usize = 50 * 1024 * 1024;
+
+/// Decoded pixels, 4 bytes (RGBA) each, row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn has_alpha(&self) -> bool {
+        self.pixels.chunks(4).any(|p| p[3] != 255)
+    }
+
+    /// Apply an EXIF orientation (1 to 8), so the pixels are upright without
+    /// it.
+    pub fn oriented(self, orientation: u16) -> Image {
+        let (w, h) = (self.width, self.height);
+        let source: fn(usize, usize, usize, usize) -> (usize, usize) = match orientation {
+            2 => |x, y, w, _| (w - 1 - x, y),
+            3 => |x, y, w, h| (w - 1 - x, h - 1 - y),
+            4 => |x, y, _, h| (x, h - 1 - y),
+            5 => |x, y, _, _| (y, x),
+            6 => |x, y, _, h| (y, h - 1 - x),
+            7 => |x, y, w, h| (w - 1 - y, h - 1 - x),
+            8 => |x, y, w, _| (w - 1 - y, x),
+            _ => return self,
+        };
+        let (width, height) = if orientation >= 5 { (h, w) } else { (w, h) };
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(x, y, w, h);
+                let i = (sy * w + sx) * 4;
+                pixels.extend_from_slice(&self.pixels[i..i + 4]);
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Scale down to `width` by `height`, averaging the pixels each one
+    /// covers.
+    pub fn resized(&self, width: usize, height: usize) -> Image {
+        let columns = coverage(self.width, width);
+        let rows = coverage(self.height, height);
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        let mut acc = vec![0f32; width * 4];
+        for sources in &rows {
+            acc.fill(0.0);
+            for &(sy, wy) in sources {
+                let row = &self.pixels[sy * self.width * 4..(sy + 1) * self.width * 4];
+                for (x, sources) in columns.iter().enumerate() {
+                    for &(sx, wx) in sources {
+                        for c in 0..4 {
+                            acc[x * 4 + c] += row[sx * 4 + c] as f32 * wx * wy;
+                        }
+                    }
+                }
+            }
+            pixels.extend(acc.iter().map(|v| v.round().clamp(0.0, 255.0) as u8));
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Scaled so the longest side is at most `side`.
+    fn fitted(&self, side: usize) -> Image {
+        let longest = self.width.max(self.height);
+        if longest <= side {
+            return self.clone();
+        }
+        let scale = |n: usize| (n * side).div_ceil(longest).max(1);
+        self.resized(scale(self.width), scale(self.height))
+    }
+}
+
+/// For each of `dst` pixels, the `src` pixels it covers and their weights.
+fn coverage(src: usize, dst: usize) -> Vec<Vec<(usize, f32)>> {
+    let scale = src as f32 / dst as f32;
+    (0..dst)
+        .map(|i| {
+            let (start, end) = (i as f32 * scale, (i + 1) as f32 * scale);
+            (start.floor() as usize..(end.ceil() as usize).min(src))
+                .map(|s| {
+                    let covered = end.min(s as f32 + 1.0) - start.max(s as f32);
+                    (s, covered / scale)
+                })
+                .filter(|&(_, w)| w > 0.0)
+                .collect()
+        })
+        .collect()
+}
+
+/// An image made to fit, ready to upload instead of the original.
+pub struct Resized {
+    pub data: Vec<u8>,
+    pub mime: &'static str,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Width and height of a JPEG or PNG, without decoding it.
+pub fn dimensions(data: &[u8], mime: &str) -> Option<(usize, usize)> {
+    match mime {
+        "image/jpeg" => jpeg::dimensions(data),
+        "image/png" => png::dimensions(data),
+        _ => None,
+    }
+}
+
+/// Decode a JPEG (turned upright) or PNG.
+pub fn decode(data: &[u8], mime: &str) -> Result<Image, String> {
+    if dimensions(data, mime).is_some_and(|(w, h)| w.saturating_mul(h) > MAX_PIXELS) {
+        return Err("Image too large to resize".to_string());
+    }
+    match mime {
+        "image/jpeg" => Ok(read(data, ImageFormat::Jpeg)?.oriented(jpeg::orientation(data))),
+        "image/png" => read(data, ImageFormat::Png),
+        _ => Err(format!("Cannot decode {mime} images")),
+    }
+}
+
+/// Decode `data` as `format` to RGBA, with the decoder allowed no more
+/// memory than the pixels of `MAX_PIXELS` take.
+fn read(data: &[u8], format: ImageFormat) -> Result<Image, String> {
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_PIXELS as u64 * 4);
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(decode_error)?.into_rgba8();
+    Ok(Image {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        pixels: image.into_raw(),
+    })
+}
+
+fn decode_error(e: ImageError) -> String {
+    match e {
+        ImageError::Limits(_) => "Image too large to resize".to_string(),
+        e => format!("Could not decode the image: {}", e.to_string().trim_end()),
+    }
+}
+
+/// `None` if `data` is within X's limits (or not a JPEG or PNG), otherwise
+/// the image scaled down until it is. Errors tell why it cannot be.
+pub fn fit(data: &[u8], mime: &str) -> Result<Option<Resized>, String> {
+    if !matches!(mime, "image/jpeg" | "image/png") {
+        return Ok(None);
+    }
+    let (width, height) = dimensions(data, mime).ok_or("Could not read the image size")?;
+    if data.len() <= MAX_BYTES && width <= MAX_SIDE && height <= MAX_SIDE {
+        return Ok(None);
+    }
+    let image = decode(data, mime)?;
+    let alpha = image.has_alpha();
+    let longest = image.width.max(image.height);
+    let mut side = if data.len() > MAX_BYTES {
+        longest.min(TARGET_SIDE)
+    } else {
+        longest.min(MAX_SIDE)
+    };
+    loop {
+        let scaled = image.fitted(side);
+        let (encoded, mime) = if alpha {
+            (png::encode(&scaled), "image/png")
+        } else {
+            (jpeg::encode(&scaled, QUALITY), "image/jpeg")
+        };
+        if encoded.len() <= MAX_BYTES {
+            return Ok(Some(Resized {
+                data: encoded,
+                mime,
+                width: scaled.width,
+                height: scaled.height,
+            }));
+        }
+        // Bytes grow with the area; aim a little under the limit.
+        let ratio = (MAX_BYTES as f64 / encoded.len() as f64).sqrt() * 0.9;
+        side = (side as f64 * ratio) as usize;
+        if side < 64 {
+            return Err("It does not get under 5 MB even scaled down".to_string());
+        }
+    }
+}
+
+/// The MIME type of an image X accepts, from its first bytes.
+pub fn sniff(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Parse a `--media-type` value: `png`, `jpeg`, `webp`, `gif` or the full
+/// MIME type.
+pub fn parse_media_type(s: &str) -> Result<&'static str, String> {
+    let lower = s.to_ascii_lowercase();
+    match lower.strip_prefix("image/").unwrap_or(&lower) {
+        "jpeg" | "jpg" => Ok("image/jpeg"),
+        "png" => Ok("image/png"),
+        "webp" => Ok("image/webp"),
+        "gif" => Ok("image/gif"),
+        _ => Err(format!(
+            "unsupported media type `{s}` (expected png, jpeg, webp or gif)"
+        )),
+    }
+}
+
+/// Parse a `--subtitles-lang` value, a two-letter ISO 639-1 code.
+pub fn parse_language(s: &str) -> Result<String, String> {
+    if s.len() == 2 && s.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(s.to_ascii_lowercase())
+    } else {
+        Err(format!(
+            "`{s}` is not a two-letter language code (e.g. en, ko)"
+        ))
+    }
+}
+
+/// The name X shows in the captions menu for language `code`.
+pub fn language_name(code: &str) -> &str {
+    match code {
+        "ar" => "العربية",
+        "de" => "Deutsch",
+        "en" => "English",
+        "es" => "Español",
+        "fr" => "Français",
+        "hi" => "हिन्दी",
+        "it" => "Italiano",
+        "ja" => "日本語",
+        "ko" => "한국어",
+        "nl" => "Nederlands",
+        "pt" => "Português",
+        "ru" => "Русский",
+        "tr" => "Türkçe",
+        "zh" => "中文",
+        _ => code,
+    }
+}
+
+/// File extension for an image of MIME type `mime`.
+pub fn extension(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "jpg",
+    }
+}
+
+/// Most images X attaches to one tweet.
+pub const MAX_IMAGES: usize = 4;
+
+/// The kinds of media X tells apart when several go on one tweet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Image,
+    Gif,
+    Video,
+}
+
+impl Kind {
+    pub fn of(mime: &str) -> Kind {
+        match mime {
+            "image/gif" => Kind::Gif,
+            m if m.starts_with("video/") => Kind::Video,
+            _ => Kind::Image,
+        }
+    }
+}
+
+/// Check that media of `kinds` can go on one tweet together: up to four
+/// images, or a single GIF or video on its own.
+pub fn check_combination(kinds: &[Kind]) -> Result<(), String> {
+    if kinds.len() > 1 {
+        if kinds.contains(&Kind::Video) {
+            return Err("a video cannot be attached together with other media".to_string());
+        }
+        if kinds.contains(&Kind::Gif) {
+            return Err("a GIF cannot be attached together with other media".to_string());
+        }
+    }
+    if kinds.len() > MAX_IMAGES {
+        return Err(format!(
+            "at most {MAX_IMAGES} images can be attached to a tweet"
+        ));
+    }
+    Ok(())
+}
+
+/// Download the image at `url`, returning it with its MIME type. Anything
+/// but a JPEG, PNG, WebP or GIF (an error page, say) is refused.
+pub async fn download(
+    transport: &dyn Transport,
+    url: &str,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let resp = transport
+        .send(Request::new(Method::GET, url))
+        .await
+        .map_err(|e| format!("Download failed: {e}"))?;
+    if !resp.status.is_success() {
+        return Err(format!("Download failed: {}", resp.status));
+    }
+    if resp.body.len() > MAX_DOWNLOAD_BYTES {
+        return Err(format!(
+            "Too large to attach ({}, at most {})",
+            format_size(resp.body.len()),
+            format_size(MAX_DOWNLOAD_BYTES)
+        ));
+    }
+    match sniff(&resp.body) {
+        Some(mime) => Ok((resp.body, mime)),
+        None => Err(format!(
+            "Not a JPEG, PNG, WebP or GIF image (Content-Type: {})",
+            resp.header("content-type").unwrap_or("none")
+        )),
+    }
+}
+
+/// A copy of a JPEG, PNG or WebP image without its EXIF and other metadata
+/// (see [`jpeg::strip_metadata`] and [`png::strip_metadata`]). `None` if
+/// there was none, or the format has no place for it (GIF).
+pub fn strip_metadata(data: &[u8], mime: &str) -> Result<Option<Vec<u8>>, String> {
+    match mime {
+        "image/jpeg" => jpeg::strip_metadata(data),
+        "image/png" => png::strip_metadata(data),
+        "image/webp" => strip_webp_metadata(data),
+        _ => Ok(None),
+    }
+}
+
+/// Drop the `EXIF` and `XMP ` chunks of a WebP file, and their flags in the
+/// `VP8X` header.
+fn strip_webp_metadata(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err("Not a WebP file".to_string());
+    }
+    let mut out = data[..12].to_vec();
+    let mut removed = false;
+    let mut image = false;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let kind = &data[pos..pos + 4];
+        let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let end = pos + 8 + len + len % 2;
+        let chunk = data
+            .get(pos..end.min(data.len()))
+            .filter(|c| c.len() >= 8 + len)
+            .ok_or("Truncated WebP file")?;
+        if kind == b"EXIF" || kind == b"XMP " {
+            removed = true;
+        } else {
+            image |= matches!(kind, b"VP8 " | b"VP8L" | b"ANMF");
+            out.extend_from_slice(chunk);
+        }
+        pos = end;
+    }
+    if !image {
+        return Err("Corrupt WebP file: no image data".to_string());
+    }
+    if !removed {
+        return Ok(None);
+    }
+    if out.get(12..16) == Some(b"VP8X") && out.len() > 20 {
+        out[20] &= !0b1100;
+    }
+    let riff_len = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    Ok(Some(out))
+}
+
+/// `1.5 MB` or `820 KB`, for messages.
+pub fn format_size(bytes: usize) -> String {
+    if bytes < 1024 * 1024 {
+        format!("{} KB", bytes.div_ceil(1024))
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Width, height and frame count of a GIF, read from its blocks without
+/// decoding them. `None` if `data` is not a GIF.
+pub fn gif_info(data: &[u8]) -> Option<(usize, usize, usize)> {
+    if !data.starts_with(b"GIF87a") && !data.starts_with(b"GIF89a") {
+        return None;
+    }
+    let u16_at = |i: usize| Some(u16::from_le_bytes([*data.get(i)?, *data.get(i + 1)?]) as usize);
+    let (width, height) = (u16_at(6)?, u16_at(8)?);
+    // A color table follows the block when the top bit of `packed` is set.
+    let table = |packed: u8| {
+        if packed & 0x80 != 0 {
+            3 << ((packed & 7) + 1)
+        } else {
+            0
+        }
+    };
+    let skip_sub_blocks = |mut pos: usize| {
+        while let Some(&len) = data.get(pos) {
+            pos += 1 + len as usize;
+            if len == 0 {
+                break;
+            }
+        }
+        pos
+    };
+    let mut pos = 13 + table(*data.get(10)?);
+    let mut frames = 0;
+    // A file cut short still counts the frames it has.
+    while let Some(&block) = data.get(pos) {
+        match block {
+            0x21 => pos = skip_sub_blocks(pos + 2),
+            0x2C => {
+                frames += 1;
+                pos += 10 + table(*data.get(pos + 9)?);
+                pos = skip_sub_blocks(pos + 1);
+            }
+            0x3B => break,
+            _ => return None,
+        }
+    }
+    Some((width, height, frames))
+}
+
+/// Check a GIF against X's limits for GIFs: 15 MB, 1280 by 1080 pixels and
+/// 350 frames.
+pub fn check_gif(data: &[u8]) -> Result<(), String> {
+    if data.len() > MAX_GIF_BYTES {
+        return Err(format!(
+            "X accepts GIFs up to {} (this one is {})",
+            format_size(MAX_GIF_BYTES),
+            format_size(data.len())
+        ));
+    }
+    let (width, height, frames) = gif_info(data).ok_or("not a valid GIF")?;
+    let (max_width, max_height) = MAX_GIF_SIZE;
+    if width > max_width || height > max_height {
+        return Err(format!(
+            "X accepts GIFs up to {max_width}x{max_height} (this one is {width}x{height})"
+        ));
+    }
+    if frames > MAX_GIF_FRAMES {
+        return Err(format!(
+            "X accepts GIFs of up to {MAX_GIF_FRAMES} frames (this one has {frames})"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A GIF of `frames` frames of 2x2 pixels, `width` wide.
+    fn gif(width: u16, frames: usize) -> Vec<u8> {
+        let mut data = b"GIF89a".to_vec();
+        data.extend(width.to_le_bytes());
+        data.extend([2, 0, 0x80, 0, 0]);
+        data.extend([0; 6]); // global color table of 2 colors
+        for _ in 0..frames {
+            data.extend([0x21, 0xF9, 4, 0, 10, 0, 0, 0]);
+            data.extend([0x2C, 0, 0, 0, 0, 2, 0, 2, 0, 0]);
+            data.extend([2, 2, 0x44, 0x01, 0]);
+        }
+        data.push(0x3B);
+        data
+    }
+
+    #[test]
+    fn reads_gif_size_and_frames() {
+        assert_eq!(gif_info(&gif(2, 3)), Some((2, 2, 3)));
+        assert_eq!(gif_info(b"\x89PNG\r\n\x1a\n"), None);
+        // Cut short in the second frame.
+        let data = gif(2, 2);
+        assert_eq!(gif_info(&data[..data.len() - 4]), Some((2, 2, 2)));
+    }
+
+    #[test]
+    fn checks_gifs_against_x_limits() {
+        assert!(check_gif(&gif(2, 3)).is_ok());
+        assert_eq!(
+            check_gif(&gif(2000, 1)).unwrap_err(),
+            "X accepts GIFs up to 1280x1080 (this one is 2000x2)"
+        );
+        assert_eq!(
+            check_gif(&gif(2, 351)).unwrap_err(),
+            "X accepts GIFs of up to 350 frames (this one has 351)"
+        );
+        let mut heavy = gif(2, 1);
+        heavy.resize(MAX_GIF_BYTES + 1, 0);
+        assert!(check_gif(&heavy)
+            .unwrap_err()
+            .starts_with("X accepts GIFs up to 15.0 MB"));
+    }
+    use crate::transport::{MockTransport, Response};
+
+    /// 2x3 pixels numbered 0 to 5 in their red channel.
+    fn numbered() -> Image {
+        Image {
+            width: 2,
+            height: 3,
+            pixels: (0..6).flat_map(|n| [n, 0, 0, 255]).collect(),
+        }
+    }
+
+    fn reds(image: &Image) -> Vec<u8> {
+        image.pixels.chunks(4).map(|p| p[0]).collect()
+    }
+
+    #[test]
+    fn applies_exif_orientations() {
+        // 0 1
+        // 2 3
+        // 4 5
+        assert_eq!(reds(&numbered().oriented(1)), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(reds(&numbered().oriented(3)), [5, 4, 3, 2, 1, 0]);
+        let rotated = numbered().oriented(6);
+        assert_eq!((rotated.width, rotated.height), (3, 2));
+        assert_eq!(reds(&rotated), [4, 2, 0, 5, 3, 1]);
+        assert_eq!(reds(&numbered().oriented(8)), [1, 3, 5, 0, 2, 4]);
+    }
+
+    #[test]
+    fn averages_when_scaling_down() {
+        let image = Image {
+            width: 4,
+            height: 2,
+            pixels: [0, 100, 200, 40, 0, 100, 200, 40]
+                .iter()
+                .flat_map(|&v| [v, v, v, 255])
+                .collect(),
+        };
+        let half = image.resized(2, 1);
+        assert_eq!(reds(&half), [50, 120]);
+        assert_eq!(image.resized(3, 1).width, 3);
+        assert!(!half.has_alpha());
+    }
+
+    #[test]
+    fn leaves_small_images_alone() {
+        let data = png::encode(&numbered());
+        assert!(fit(&data, "image/png").unwrap().is_none());
+        assert!(fit(b"GIF89a", "image/gif").unwrap().is_none());
+    }
+
+    #[test]
+    fn parses_media_types() {
+        assert_eq!(parse_media_type("PNG"), Ok("image/png"));
+        assert_eq!(parse_media_type("jpg"), Ok("image/jpeg"));
+        assert_eq!(parse_media_type("image/webp"), Ok("image/webp"));
+        assert!(parse_media_type("image/tiff").is_err());
+        assert_eq!(sniff(b"GIF89a..."), Some("image/gif"));
+        assert_eq!(sniff(b"<html>"), None);
+        assert_eq!(parse_language("EN"), Ok("en".to_string()));
+        assert!(parse_language("eng").is_err());
+        assert_eq!(language_name("ko"), "한국어");
+    }
+
+    #[test]
+    fn checks_media_combinations() {
+        use Kind::*;
+        assert!(check_combination(&[Image; 4]).is_ok());
+        assert!(check_combination(&[Gif]).is_ok());
+        assert!(check_combination(&[Video]).is_ok());
+        assert!(check_combination(&[]).is_ok());
+        assert!(check_combination(&[Image; 5])
+            .unwrap_err()
+            .contains("at most 4"));
+        assert!(check_combination(&[Image, Video])
+            .unwrap_err()
+            .contains("video"));
+        assert!(check_combination(&[Gif, Gif]).unwrap_err().contains("GIF"));
+        assert!(check_combination(&[Image, Gif])
+            .unwrap_err()
+            .contains("GIF"));
+        assert_eq!(Kind::of("video/quicktime"), Video);
+        assert_eq!(Kind::of("image/webp"), Image);
+    }
+
+    #[tokio::test]
+    async fn downloads_images_only() {
+        let mock = MockTransport::new();
+        mock.push(Ok(Response::new(200, png::encode(&numbered()))))
+            .respond(200, "<html>Not found</html>")
+            .respond(404, "");
+
+        let (data, mime) = download(&mock, "https://example.com/a.png").await.unwrap();
+        assert_eq!((mime, dimensions(&data, mime)), ("image/png", Some((2, 3))));
+        let err = download(&mock, "https://example.com/b.png")
+            .await
+            .unwrap_err();
+        assert!(err.starts_with("Not a JPEG, PNG, WebP or GIF"), "{err}");
+        assert!(download(&mock, "https://example.com/c.png").await.is_err());
+        assert_eq!(mock.requests()[0].url, "https://example.com/a.png");
+    }
+
+    #[test]
+    fn strips_webp_metadata() {
+        let mut data = b"RIFF\0\0\0\0WEBP".to_vec();
+        data.extend(b"VP8X\x0a\0\0\0\x0c\0\0\0\0\0\0\0\0\0");
+        data.extend(b"VP8L\x01\0\0\0\x2f\0");
+        data.extend(b"EXIF\x06\0\0\0SECRET");
+        let len = (data.len() - 8) as u32;
+        data[4..8].copy_from_slice(&len.to_le_bytes());
+
+        let stripped = strip_metadata(&data, "image/webp").unwrap().unwrap();
+        assert_eq!(stripped.len(), data.len() - 14);
+        assert_eq!(&stripped[4..8], &(stripped.len() as u32 - 8).to_le_bytes());
+        assert_eq!(stripped[20], 0);
+        assert!(!stripped.windows(6).any(|w| w == b"SECRET"));
+        assert_eq!(strip_metadata(&stripped, "image/webp").unwrap(), None);
+        assert_eq!(strip_metadata(b"GIF89a", "image/gif").unwrap(), None);
+
+        let mut exif_only = b"RIFF\x0e\0\0\0WEBP".to_vec();
+        exif_only.extend(b"EXIF\x06\0\0\0SECRET");
+        assert!(strip_metadata(&exif_only, "image/webp").is_err());
+    }
+
+    #[test]
+    fn scales_down_oversized_images() {
+        let image = Image {
+            width: MAX_SIDE + 8,
+            height: 2,
+            pixels: [200, 200, 200, 255].repeat((MAX_SIDE + 8) * 2),
+        };
+        let resized = fit(&png::encode(&image), "image/png").unwrap().unwrap();
+        assert_eq!(resized.mime, "image/jpeg");
+        assert_eq!((resized.width, resized.height), (MAX_SIDE, 2));
+        assert_eq!(jpeg::dimensions(&resized.data), Some((MAX_SIDE, 2)));
+    }
+}
@@ -0,0 +1,393 @@
+//! Cross-posting to Mastodon and Bluesky (`xcli tweet --also`): the chunks
+//! posted to X are posted to the other networks too, as a thread of their
+//! own, with the accounts of the profile:
+//!
+//! ```toml
+//! [profiles.default.mastodon]
+//! instance = "https://mastodon.social"
+//! access_token = "..."
+//!
+//! [profiles.default.bluesky]
+//! handle = "me.bsky.social"
+//! app_password = "xxxx-xxxx-xxxx-xxxx"
+//! ```
+//!
+//! Each network has its own length limit. A chunk over it is split again
+//! with the thread splitter; X weights are never below a character count,
+//! so what fits by X's count fits by theirs.
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::config::{BlueskyAccount, MastodonAccount, Profile};
+use crate::thread;
+use crate::transport::{Method, Request, Response, Transport};
+
+const BLUESKY_SERVICE: &str = "https://bsky.social";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Network {
+    Mastodon,
+    Bluesky,
+}
+
+impl Network {
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mastodon => "Mastodon",
+            Network::Bluesky => "Bluesky",
+        }
+    }
+
+    /// Characters per post (graphemes on Bluesky, which are never more).
+    pub fn limit(self) -> usize {
+        match self {
+            Network::Mastodon => 500,
+            Network::Bluesky => 300,
+        }
+    }
+}
+
+/// Parse a `--also` value.
+pub fn parse_network(s: &str) -> Result<Network, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "mastodon" => Ok(Network::Mastodon),
+        "bluesky" | "bsky" => Ok(Network::Bluesky),
+        _ => Err(format!(
+            "unknown network `{s}` (expected mastodon or bluesky)"
+        )),
+    }
+}
+
+/// Check that `profile` has an account on every network.
+pub fn check_accounts(profile: &Profile, networks: &[Network]) -> Result<(), String> {
+    for &network in networks {
+        let (configured, key) = match network {
+            Network::Mastodon => (profile.mastodon.is_some(), "mastodon"),
+            Network::Bluesky => (profile.bluesky.is_some(), "bluesky"),
+        };
+        if !configured {
+            return Err(format!(
+                "No {} account configured. Add a [profiles.<name>.{key}] table to config.toml.",
+                network.name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `chunks`, each split again if it is over `network`'s limit.
+pub fn fit(network: Network, chunks: &[String]) -> Vec<String> {
+    chunks
+        .iter()
+        .flat_map(|chunk| {
+            if chunk.chars().count() <= network.limit() {
+                vec![chunk.clone()]
+            } else {
+                thread::split_limited(chunk, network.limit()).collect()
+            }
+        })
+        .collect()
+}
+
+fn check(resp: &Response, what: &str) -> Result<(), String> {
+    if resp.status.is_success() {
+        return Ok(());
+    }
+    Err(format!("{what} returned {}: {}", resp.status, resp.text()))
+}
+
+/// Post `chunks` as a thread on `network` with the profile's account.
+/// Returns the URLs of the posts.
+pub async fn post(
+    transport: &dyn Transport,
+    profile: &Profile,
+    network: Network,
+    chunks: &[String],
+) -> Result<Vec<String>, String> {
+    check_accounts(profile, &[network])?;
+    let chunks = fit(network, chunks);
+    match network {
+        Network::Mastodon => {
+            post_mastodon(transport, profile.mastodon.as_ref().unwrap(), &chunks).await
+        }
+        Network::Bluesky => {
+            post_bluesky(transport, profile.bluesky.as_ref().unwrap(), &chunks).await
+        }
+    }
+}
+
+/// Describe a failure after `posted` of `total` posts.
+fn failed_after(posted: usize, total: usize, error: String) -> String {
+    if posted == 0 {
+        error
+    } else {
+        format!("{error} (after {posted} of {total} posts)")
+    }
+}
+
+#[derive(Deserialize)]
+struct Status {
+    id: String,
+    url: String,
+}
+
+async fn post_mastodon(
+    transport: &dyn Transport,
+    account: &MastodonAccount,
+    chunks: &[String],
+) -> Result<Vec<String>, String> {
+    let endpoint = format!("{}/api/v1/statuses", account.instance.trim_end_matches('/'));
+    let mut urls = Vec::new();
+    let mut reply_to: Option<String> = None;
+    for chunk in chunks {
+        let req = Request::new(Method::POST, &endpoint)
+            .header("Authorization", format!("Bearer {}", account.access_token))
+            .json(&json!({ "status": chunk, "in_reply_to_id": reply_to }));
+        let status: Status = async {
+            let resp = transport.send(req).await?;
+            check(&resp, &endpoint)?;
+            resp.json::<Status>()
+        }
+        .await
+        .map_err(|e| failed_after(urls.len(), chunks.len(), e))?;
+        urls.push(status.url);
+        reply_to = Some(status.id);
+    }
+    Ok(urls)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    access_jwt: String,
+    did: String,
+}
+
+#[derive(Deserialize, Clone)]
+struct StrongRef {
+    uri: String,
+    cid: String,
+}
+
+/// Bluesky shows no links or hashtags that are not marked up as facets,
+/// which address the text by UTF-8 byte offsets.
+fn facets(text: &str) -> Vec<Value> {
+    let link = Regex::new(r"https?://[^\s]+").unwrap();
+    let tag = Regex::new(r"(?:^|\s)(#[^\s#[:punct:]][^\s#]*)").unwrap();
+    let trailing = |s: &str| s.trim_end_matches(|c: char| ".,;:!?)\"'".contains(c)).len();
+    let span = |start: usize, end: usize| json!({ "byteStart": start, "byteEnd": end });
+
+    let mut out = Vec::new();
+    for m in link.find_iter(text) {
+        let end = m.start() + trailing(m.as_str());
+        out.push(json!({
+            "index": span(m.start(), end),
+            "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": &text[m.start()..end] }],
+        }));
+    }
+    for c in tag.captures_iter(text) {
+        let m = c.get(1).unwrap();
+        let end = m.start() + trailing(m.as_str());
+        out.push(json!({
+            "index": span(m.start(), end),
+            "features": [{ "$type": "app.bsky.richtext.facet#tag", "tag": &text[m.start() + 1..end] }],
+        }));
+    }
+    out
+}
+
+/// `at://did/app.bsky.feed.post/<rkey>` → its bsky.app URL.
+fn bluesky_url(handle: &str, uri: &str) -> String {
+    let rkey = uri.rsplit('/').next().unwrap_or_default();
+    format!("https://bsky.app/profile/{handle}/post/{rkey}")
+}
+
+async fn post_bluesky(
+    transport: &dyn Transport,
+    account: &BlueskyAccount,
+    chunks: &[String],
+) -> Result<Vec<String>, String> {
+    let service = account
+        .service
+        .as_deref()
+        .unwrap_or(BLUESKY_SERVICE)
+        .trim_end_matches('/');
+
+    let endpoint = format!("{service}/xrpc/com.atproto.server.createSession");
+    let resp = transport
+        .send(Request::new(Method::POST, &endpoint).json(&json!({
+            "identifier": account.handle,
+            "password": account.app_password,
+        })))
+        .await?;
+    check(&resp, &endpoint)?;
+    let session: Session = resp.json()?;
+
+    let endpoint = format!("{service}/xrpc/com.atproto.repo.createRecord");
+    let mut urls = Vec::new();
+    let mut thread: Option<(StrongRef, StrongRef)> = None;
+    for chunk in chunks {
+        let mut record = json!({
+            "$type": "app.bsky.feed.post",
+            "text": chunk,
+            "createdAt": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "facets": facets(chunk),
+        });
+        if let Some((root, parent)) = &thread {
+            record["reply"] = json!({
+                "root": { "uri": root.uri, "cid": root.cid },
+                "parent": { "uri": parent.uri, "cid": parent.cid },
+            });
+        }
+        let req = Request::new(Method::POST, &endpoint)
+            .header("Authorization", format!("Bearer {}", session.access_jwt))
+            .json(&json!({
+                "repo": session.did,
+                "collection": "app.bsky.feed.post",
+                "record": record,
+            }));
+        let posted: StrongRef = async {
+            let resp = transport.send(req).await?;
+            check(&resp, &endpoint)?;
+            resp.json::<StrongRef>()
+        }
+        .await
+        .map_err(|e| failed_after(urls.len(), chunks.len(), e))?;
+        urls.push(bluesky_url(&account.handle, &posted.uri));
+        let root = thread.map_or(posted.clone(), |(root, _)| root);
+        thread = Some((root, posted));
+    }
+    Ok(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    fn profile() -> Profile {
+        Profile {
+            mastodon: Some(MastodonAccount {
+                instance: "https://mastodon.test/".to_string(),
+                access_token: "tok".to_string(),
+            }),
+            bluesky: Some(BlueskyAccount {
+                handle: "me.bsky.social".to_string(),
+                app_password: "pw".to_string(),
+                service: None,
+            }),
+            ..Profile::default()
+        }
+    }
+
+    fn body(req: &Request) -> Value {
+        serde_json::from_str(&req.body_text()).unwrap()
+    }
+
+    #[test]
+    fn parses_networks_and_checks_accounts() {
+        assert_eq!(parse_network("Mastodon"), Ok(Network::Mastodon));
+        assert_eq!(parse_network("bsky"), Ok(Network::Bluesky));
+        assert!(parse_network("threads").is_err());
+
+        let err = check_accounts(&Profile::default(), &[Network::Bluesky]).unwrap_err();
+        assert!(err.contains("[profiles.<name>.bluesky]"), "{err}");
+        assert!(check_accounts(&profile(), &[Network::Mastodon, Network::Bluesky]).is_ok());
+    }
+
+    #[test]
+    fn refits_chunks_over_the_limit() {
+        let long = "word ".repeat(70).trim().to_string();
+        assert_eq!(
+            fit(Network::Mastodon, std::slice::from_ref(&long)),
+            vec![long.clone()]
+        );
+        let fitted = fit(Network::Bluesky, &["short".to_string(), long]);
+        assert_eq!(fitted.len(), 3);
+        assert!(fitted.iter().all(|c| c.chars().count() <= 300));
+    }
+
+    #[test]
+    fn facets_use_byte_offsets() {
+        let text = "새 글 https://blog.example/a. #rust";
+        let facets = facets(text);
+        assert_eq!(facets.len(), 2);
+        let link = &facets[0];
+        let (start, end) = (
+            link["index"]["byteStart"].as_u64().unwrap() as usize,
+            link["index"]["byteEnd"].as_u64().unwrap() as usize,
+        );
+        assert_eq!(&text[start..end], "https://blog.example/a");
+        assert_eq!(facets[1]["features"][0]["tag"], "rust");
+    }
+
+    #[tokio::test]
+    async fn posts_mastodon_threads() {
+        let mock = MockTransport::new();
+        mock.respond(200, r#"{"id":"1","url":"https://mastodon.test/@me/1"}"#)
+            .respond(200, r#"{"id":"2","url":"https://mastodon.test/@me/2"}"#);
+        let chunks = vec!["one".to_string(), "two".to_string()];
+        let urls = post(&mock, &profile(), Network::Mastodon, &chunks)
+            .await
+            .unwrap();
+        assert_eq!(urls[1], "https://mastodon.test/@me/2");
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].url, "https://mastodon.test/api/v1/statuses");
+        assert_eq!(
+            requests[0].header_value("authorization"),
+            Some("Bearer tok")
+        );
+        assert_eq!(body(&requests[0])["in_reply_to_id"], Value::Null);
+        assert_eq!(body(&requests[1])["in_reply_to_id"], "1");
+    }
+
+    #[tokio::test]
+    async fn posts_bluesky_threads() {
+        let mock = MockTransport::new();
+        mock.respond(200, r#"{"accessJwt":"jwt","did":"did:plc:me"}"#)
+            .respond(
+                200,
+                r#"{"uri":"at://did:plc:me/app.bsky.feed.post/a1","cid":"c1"}"#,
+            )
+            .respond(
+                200,
+                r#"{"uri":"at://did:plc:me/app.bsky.feed.post/b2","cid":"c2"}"#,
+            )
+            .respond(
+                200,
+                r#"{"uri":"at://did:plc:me/app.bsky.feed.post/c3","cid":"c3"}"#,
+            );
+        let chunks = vec!["one".to_string(), "two".to_string(), "three".to_string()];
+        let urls = post(&mock, &profile(), Network::Bluesky, &chunks)
+            .await
+            .unwrap();
+        assert_eq!(urls[0], "https://bsky.app/profile/me.bsky.social/post/a1");
+
+        let requests = mock.requests();
+        assert_eq!(body(&requests[0])["identifier"], "me.bsky.social");
+        assert_eq!(
+            requests[1].header_value("authorization"),
+            Some("Bearer jwt")
+        );
+        assert!(body(&requests[1])["record"].get("reply").is_none());
+        let reply = &body(&requests[3])["record"]["reply"];
+        assert_eq!(reply["root"]["cid"], "c1");
+        assert_eq!(reply["parent"]["cid"], "c2");
+    }
+
+    #[tokio::test]
+    async fn reports_partial_threads() {
+        let mock = MockTransport::new();
+        mock.respond(200, r#"{"id":"1","url":"u1"}"#)
+            .respond(422, r#"{"error":"Validation failed"}"#);
+        let chunks = vec!["one".to_string(), "two".to_string()];
+        let err = post(&mock, &profile(), Network::Mastodon, &chunks)
+            .await
+            .unwrap_err();
+        assert!(err.contains("422"), "{err}");
+        assert!(err.ends_with("(after 1 of 2 posts)"), "{err}");
+    }
+}
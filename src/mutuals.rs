@@ -0,0 +1,52 @@
+//! `xcli mutuals`: the connections you share with another account, such as
+//! the people you follow who already follow them.
+
+use std::collections::HashSet;
+
+use crate::api::User;
+
+/// The accounts of `a` that are also in `b`, most followed first.
+pub fn shared(a: &[User], b: &[User]) -> Vec<User> {
+    let ids: HashSet<&str> = b.iter().map(|u| u.id.as_str()).collect();
+    let mut found: Vec<User> = a
+        .iter()
+        .filter(|u| ids.contains(u.id.as_str()))
+        .cloned()
+        .collect();
+    // A stable sort keeps X's order among accounts of the same reach.
+    found.sort_by_key(|u| std::cmp::Reverse(u.public_metrics.map_or(0, |m| m.followers_count)));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::UserMetrics;
+
+    fn user(id: &str, followers: u64) -> User {
+        User {
+            id: id.to_string(),
+            username: format!("user{id}"),
+            name: String::new(),
+            description: None,
+            public_metrics: Some(UserMetrics {
+                followers_count: followers,
+                ..UserMetrics::default()
+            }),
+            profile_image_url: None,
+            most_recent_tweet_id: None,
+        }
+    }
+
+    #[test]
+    fn keeps_shared_accounts_by_reach() {
+        let following = [user("1", 10), user("2", 500), user("3", 10), user("4", 90)];
+        let followers = [user("4", 90), user("3", 10), user("9", 1), user("1", 10)];
+        let ids: Vec<String> = shared(&following, &followers)
+            .into_iter()
+            .map(|u| u.id)
+            .collect();
+        assert_eq!(ids, ["4", "1", "3"]);
+        assert!(shared(&following, &[]).is_empty());
+    }
+}
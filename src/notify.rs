@@ -0,0 +1,160 @@
+//! Webhook notification after posting (`notify_url` in a profile).
+//!
+//! The payload carries a `text` summary, so Slack-style incoming webhooks
+//! work as is, plus structured fields for other receivers. Watch commands
+//! can also raise a desktop notification.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::hooks::tweet_url;
+use crate::transport::{Method, Request, Transport};
+
+/// A slow receiver must not hold the terminal for long after posting.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug)]
+pub struct Payload {
+    pub text: String,
+    /// `posted`, or `failed` (possibly after part of a thread was posted).
+    pub status: &'static str,
+    pub account: Option<String>,
+    pub profile: String,
+    pub in_reply_to: Option<String>,
+    /// Number of tweets that were to be posted.
+    pub tweets: usize,
+    pub ids: Vec<String>,
+    pub urls: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub fn payload(
+    account: Option<&str>,
+    profile: &str,
+    in_reply_to: Option<&str>,
+    tweets: usize,
+    ids: &[String],
+    error: Option<&str>,
+) -> Payload {
+    let urls: Vec<String> = ids.iter().map(|id| tweet_url(account, id)).collect();
+    let what = match (in_reply_to.is_some(), tweets > 1) {
+        (false, false) => "Tweet",
+        (false, true) => "Thread",
+        (true, false) => "Reply",
+        (true, true) => "Reply thread",
+    };
+    let text = match (error, urls.first()) {
+        (None, Some(url)) => format!("{what} posted: {url}"),
+        (None, None) => format!("{what} posted"),
+        (Some(e), None) => format!("{what} failed: {e}"),
+        (Some(e), Some(url)) => format!(
+            "{what} failed after {} of {tweets} tweets ({url}): {e}",
+            ids.len()
+        ),
+    };
+    Payload {
+        text,
+        status: if error.is_none() { "posted" } else { "failed" },
+        account: account.map(str::to_string),
+        profile: profile.to_string(),
+        in_reply_to: in_reply_to.map(str::to_string),
+        tweets,
+        ids: ids.to_vec(),
+        urls,
+        error: error.map(str::to_string),
+    }
+}
+
+/// Show a desktop notification: with `osascript` on macOS, `notify-send`
+/// elsewhere.
+pub fn desktop(title: &str, body: &str) -> Result<(), String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut c = std::process::Command::new("osascript");
+        c.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(title)
+        ));
+        c
+    } else {
+        let mut c = std::process::Command::new("notify-send");
+        c.arg(title).arg(body);
+        c
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+    if !status.success() {
+        return Err(format!("{program} failed ({status})"));
+    }
+    Ok(())
+}
+
+/// POST `payload` as JSON to `url`.
+pub async fn send(transport: &dyn Transport, url: &str, payload: &Payload) -> Result<(), String> {
+    let req = Request::new(Method::POST, url).json(payload);
+    let resp = tokio::time::timeout(TIMEOUT, transport.send(req))
+        .await
+        .map_err(|_| format!("Webhook {url} did not answer within {}s", TIMEOUT.as_secs()))??;
+    if !resp.status.is_success() {
+        return Err(format!(
+            "Webhook {url} returned {}: {}",
+            resp.status,
+            resp.text()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn summaries() {
+        let p = payload(Some("me"), "default", None, 1, &ids(&["1"]), None);
+        assert_eq!(p.status, "posted");
+        assert_eq!(p.text, "Tweet posted: https://x.com/me/status/1");
+
+        let p = payload(None, "work", Some("9"), 3, &ids(&["1"]), Some("boom"));
+        assert_eq!(p.status, "failed");
+        assert_eq!(
+            p.text,
+            "Reply thread failed after 1 of 3 tweets (https://x.com/i/status/1): boom"
+        );
+
+        let p = payload(None, "default", None, 2, &[], Some("boom"));
+        assert_eq!(p.text, "Thread failed: boom");
+    }
+
+    #[tokio::test]
+    async fn posts_json() {
+        let mock = MockTransport::new();
+        mock.respond(200, "ok");
+        let p = payload(Some("me"), "default", None, 1, &ids(&["1"]), None);
+        send(&mock, "https://hooks.test/x", &p).await.unwrap();
+
+        let req = &mock.requests()[0];
+        assert_eq!(req.url, "https://hooks.test/x");
+        let body: serde_json::Value = serde_json::from_str(&req.body_text()).unwrap();
+        assert_eq!(body["ids"], serde_json::json!(["1"]));
+        assert_eq!(body["urls"][0], "https://x.com/me/status/1");
+    }
+
+    #[tokio::test]
+    async fn rejects_error_statuses() {
+        let mock = MockTransport::new();
+        mock.respond(404, "no_service");
+        let p = payload(None, "default", None, 1, &ids(&["1"]), None);
+        let err = send(&mock, "https://hooks.test/x", &p).await.unwrap_err();
+        assert!(err.contains("no_service"), "{err}");
+    }
+}
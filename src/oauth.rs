@@ -1,15 +1,24 @@
 use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::TcpListener;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
 
 use crate::auth::build_flexible_oauth_header;
 use crate::config::Credentials;
+use crate::transport::{Method, Request, Transport};
 
 const REQUEST_TOKEN_URL: &str = "https://api.x.com/oauth/request_token";
 const AUTHORIZE_URL: &str = "https://api.x.com/oauth/authorize";
 const ACCESS_TOKEN_URL: &str = "https://api.x.com/oauth/access_token";
 const CALLBACK_PORT: u16 = 18923;
-const CALLBACK_URL: &str = "http://127.0.0.1:18923/callback";
+/// The callback URL to register for the app in the developer portal.
+pub const CALLBACK_URL: &str = "http://127.0.0.1:18923/callback";
+/// How long to wait for the user to approve the app in the browser.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long one browser connection may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub fn parse_form_body(body: &str) -> HashMap<String, String> {
     body.split('&')
@@ -20,9 +29,14 @@ pub fn parse_form_body(body: &str) -> HashMap<String, String> {
         .collect()
 }
 
-pub async fn start_login(api_key: &str, api_secret: &str) -> Result<Credentials, String> {
+pub async fn start_login(
+    transport: &dyn Transport,
+    api_key: &str,
+    api_secret: &str,
+) -> Result<Credentials, String> {
     // 1. Bind to fixed port
     let listener = TcpListener::bind(format!("127.0.0.1:{CALLBACK_PORT}"))
+        .await
         .map_err(|e| format!("Failed to bind local server on port {CALLBACK_PORT}: {e}"))?;
     let callback_url = CALLBACK_URL;
 
@@ -37,16 +51,13 @@ pub async fn start_login(api_key: &str, api_secret: &str) -> Result<Credentials,
         &[("oauth_callback", callback_url)],
     );
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .post(REQUEST_TOKEN_URL)
-        .header("Authorization", &auth_header)
-        .send()
+    let resp = transport
+        .send(Request::new(Method::POST, REQUEST_TOKEN_URL).header("Authorization", auth_header))
         .await
         .map_err(|e| format!("Request token request failed: {e}"))?;
 
-    let status = resp.status();
-    let body = resp.text().await.unwrap_or_default();
+    let status = resp.status;
+    let body = resp.text();
     if !status.is_success() {
         return Err(format!("Request token failed ({status}): {body}"));
     }
@@ -68,8 +79,18 @@ pub async fn start_login(api_key: &str, api_secret: &str) -> Result<Credentials,
     let _ = open::that(&authorize_url);
 
     // 4. Wait for callback
-    println!("Waiting for authorization callback...");
-    let (oauth_token, oauth_verifier) = wait_for_callback(&listener)?;
+    println!("Waiting for authorization callback... (Ctrl-C to cancel)");
+    let (oauth_token, oauth_verifier) = tokio::select! {
+        result = tokio::time::timeout(LOGIN_TIMEOUT, wait_for_callback(&listener)) => {
+            result.map_err(|_| {
+                format!(
+                    "No authorization within {} minutes. Run `xcli auth login` again.",
+                    LOGIN_TIMEOUT.as_secs() / 60
+                )
+            })??
+        }
+        _ = tokio::signal::ctrl_c() => return Err("Login cancelled.".to_string()),
+    };
 
     if oauth_token != request_token {
         return Err("OAuth token mismatch".to_string());
@@ -86,15 +107,13 @@ pub async fn start_login(api_key: &str, api_secret: &str) -> Result<Credentials,
         &[("oauth_verifier", &oauth_verifier)],
     );
 
-    let resp = client
-        .post(ACCESS_TOKEN_URL)
-        .header("Authorization", &auth_header)
-        .send()
+    let resp = transport
+        .send(Request::new(Method::POST, ACCESS_TOKEN_URL).header("Authorization", auth_header))
         .await
         .map_err(|e| format!("Access token request failed: {e}"))?;
 
-    let status = resp.status();
-    let body = resp.text().await.unwrap_or_default();
+    let status = resp.status;
+    let body = resp.text();
     if !status.is_success() {
         return Err(format!("Access token failed ({status}): {body}"));
     }
@@ -120,15 +139,35 @@ pub async fn start_login(api_key: &str, api_secret: &str) -> Result<Credentials,
     })
 }
 
-pub fn wait_for_callback(listener: &TcpListener) -> Result<(String, String), String> {
-    let (mut stream, _) = listener
-        .accept()
-        .map_err(|e| format!("Failed to accept connection: {e}"))?;
+/// Serve the callback until a browser delivers the OAuth verifier. Each
+/// connection is handled on its own task, so a stray request (a favicon, a
+/// preconnect that never sends anything) cannot block the real callback.
+pub async fn wait_for_callback(listener: &TcpListener) -> Result<(String, String), String> {
+    let (tx, mut rx) = mpsc::channel(1);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) =
+                    accepted.map_err(|e| format!("Failed to accept connection: {e}"))?;
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Ok(Some(result)) =
+                        tokio::time::timeout(REQUEST_TIMEOUT, handle_connection(stream)).await
+                    {
+                        let _ = tx.send(result).await;
+                    }
+                });
+            }
+            Some(result) = rx.recv() => return result,
+        }
+    }
+}
 
+/// Answer one browser request. Returns `None` for requests other than the
+/// callback.
+async fn handle_connection(mut stream: TcpStream) -> Option<Result<(String, String), String>> {
     let mut buf = [0u8; 4096];
-    let n = stream
-        .read(&mut buf)
-        .map_err(|e| format!("Failed to read request: {e}"))?;
+    let n = stream.read(&mut buf).await.ok()?;
     let request = String::from_utf8_lossy(&buf[..n]);
 
     // Parse GET /callback?oauth_token=...&oauth_verifier=... HTTP/1.1
@@ -136,14 +175,53 @@ pub fn wait_for_callback(listener: &TcpListener) -> Result<(String, String), Str
         .lines()
         .next()
         .and_then(|line| line.split_whitespace().nth(1))
-        .ok_or("Invalid HTTP request")?;
+        .unwrap_or_default();
+    if path.split('?').next() != Some("/callback") {
+        let _ = stream
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await;
+        return None;
+    }
+
+    let result = parse_callback(path);
+    let (title, message) = match &result {
+        Ok(_) => (
+            "Authorized!",
+            "You can close this tab and return to the terminal.",
+        ),
+        Err(_) => (
+            "Authorization failed",
+            "Return to the terminal for details.",
+        ),
+    };
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html><body style="font-family:system-ui;text-align:center;padding:60px">
+<h1>{title}</h1>
+<p>{message}</p>
+</body></html>"#
+    );
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        html.len(),
+        html
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
 
+    Some(result)
+}
+
+fn parse_callback(path: &str) -> Result<(String, String), String> {
     let query = path
         .split('?')
         .nth(1)
         .ok_or("No query string in callback")?;
 
     let params = parse_form_body(query);
+    if params.contains_key("denied") {
+        return Err("Authorization was denied in the browser.".to_string());
+    }
     let oauth_token = params
         .get("oauth_token")
         .ok_or("Missing oauth_token in callback")?
@@ -152,29 +230,12 @@ pub fn wait_for_callback(listener: &TcpListener) -> Result<(String, String), Str
         .get("oauth_verifier")
         .ok_or("Missing oauth_verifier in callback")?
         .clone();
-
-    // Respond with success page
-    let html = r#"<!DOCTYPE html>
-<html><body style="font-family:system-ui;text-align:center;padding:60px">
-<h1>Authorized!</h1>
-<p>You can close this tab and return to the terminal.</p>
-</body></html>"#;
-
-    let response = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-        html.len(),
-        html
-    );
-    let _ = stream.write_all(response.as_bytes());
-
     Ok((oauth_token, oauth_verifier))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write as IoWrite;
-    use std::net::TcpStream;
 
     #[test]
     fn parse_form_body_basic() {
@@ -203,38 +264,71 @@ mod tests {
         assert_eq!(result.get("key").unwrap(), "val=ue");
     }
 
-    #[test]
-    fn wait_for_callback_parses_request() {
-        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    async fn callback(request: &'static [u8]) -> Result<(String, String), String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let port = listener.local_addr().unwrap().port();
 
-        let handle = std::thread::spawn(move || wait_for_callback(&listener));
+        let handle = tokio::spawn(async move { wait_for_callback(&listener).await });
 
         // Simulate browser callback
-        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}")).unwrap();
-        stream
-            .write_all(b"GET /callback?oauth_token=tok123&oauth_verifier=ver456 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))
+            .await
             .unwrap();
+        stream.write_all(request).await.unwrap();
 
-        let (token, verifier) = handle.join().unwrap().unwrap();
+        handle.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn wait_for_callback_parses_request() {
+        let (token, verifier) = callback(
+            b"GET /callback?oauth_token=tok123&oauth_verifier=ver456 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        )
+        .await
+        .unwrap();
         assert_eq!(token, "tok123");
         assert_eq!(verifier, "ver456");
     }
 
-    #[test]
-    fn wait_for_callback_missing_verifier() {
-        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
-        let port = listener.local_addr().unwrap().port();
+    #[tokio::test]
+    async fn wait_for_callback_missing_verifier() {
+        let result =
+            callback(b"GET /callback?oauth_token=tok123 HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("oauth_verifier"));
+    }
 
-        let handle = std::thread::spawn(move || wait_for_callback(&listener));
+    #[tokio::test]
+    async fn wait_for_callback_reports_denial() {
+        let result =
+            callback(b"GET /callback?denied=tok123 HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(result.unwrap_err().contains("denied"));
+    }
 
-        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}")).unwrap();
-        stream
-            .write_all(b"GET /callback?oauth_token=tok123 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+    #[tokio::test]
+    async fn idle_and_unrelated_connections_do_not_block() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move { wait_for_callback(&listener).await });
+        let addr = format!("127.0.0.1:{port}");
+
+        // A preconnect that never sends anything, and a favicon request.
+        let _idle = TcpStream::connect(&addr).await.unwrap();
+        let mut favicon = TcpStream::connect(&addr).await.unwrap();
+        favicon
+            .write_all(b"GET /favicon.ico HTTP/1.1\r\n\r\n")
+            .await
             .unwrap();
+        let mut response = String::new();
+        favicon.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
 
-        let result = handle.join().unwrap();
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("oauth_verifier"));
+        let mut stream = TcpStream::connect(&addr).await.unwrap();
+        stream
+            .write_all(b"GET /callback?oauth_token=t&oauth_verifier=v HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let (token, verifier) = handle.await.unwrap().unwrap();
+        assert_eq!((token.as_str(), verifier.as_str()), ("t", "v"));
     }
 }
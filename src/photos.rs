@@ -0,0 +1,196 @@
+//! `xcli thread photos`: the images of directories and file patterns, in
+//! natural order (`2.jpg` before `10.jpg`), grouped into the tweets of a
+//! thread.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::api;
+use crate::media::{Kind, MAX_IMAGES};
+
+/// The images named by `sources`: files, directories (their images) and
+/// patterns with `*` or `?` in the file name. Each source is expanded in
+/// natural order; the sources keep theirs.
+pub fn collect(sources: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut photos = Vec::new();
+    for source in sources {
+        let path = Path::new(source);
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let found = if path.is_dir() {
+            images_in(path, |_| true)?
+        } else if name.contains(['*', '?']) {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            images_in(dir, |file| wildcard(&name, file))?
+        } else {
+            api::media_type(path)?;
+            vec![path.to_path_buf()]
+        };
+        if found.is_empty() {
+            return Err(format!("No images match {source}"));
+        }
+        photos.extend(found);
+    }
+    Ok(photos)
+}
+
+/// Images (by extension) in `dir` whose name passes `keep`, in natural
+/// order. Videos and other files are skipped.
+fn images_in(dir: &Path, keep: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+    let mut found: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| keep(&p.file_name().unwrap_or_default().to_string_lossy()))
+        .filter(|p| api::media_type(p).is_ok_and(|m| Kind::of(m) != Kind::Video))
+        .collect();
+    found.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(found)
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for one.
+fn wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // matched[j]: the pattern so far matches the first j characters.
+    let mut matched = vec![false; name.len() + 1];
+    matched[0] = true;
+    for p in pattern {
+        let mut next = vec![false; name.len() + 1];
+        for j in 0..=name.len() {
+            next[j] = match p {
+                '*' => matched[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && name[j - 1] == c,
+            };
+        }
+        matched = next;
+    }
+    matched[name.len()]
+}
+
+/// Compare names the way people count: runs of digits by their value, the
+/// rest case-insensitively.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |s: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(d) = s.next_if(char::is_ascii_digit) {
+                        digits.push(d);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Split media of `kinds` into consecutive tweets of at most `per_tweet`
+/// (and at most four) images each. A GIF gets a tweet of its own.
+pub fn group(kinds: &[Kind], per_tweet: usize) -> Vec<Vec<usize>> {
+    let per_tweet = per_tweet.clamp(1, MAX_IMAGES);
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, &kind) in kinds.iter().enumerate() {
+        let fits = groups.last().is_some_and(|g| {
+            g.len() < per_tweet && kind == Kind::Image && kinds[g[0]] == Kind::Image
+        });
+        if fits {
+            groups.last_mut().unwrap().push(i);
+        } else {
+            groups.push(vec![i]);
+        }
+    }
+    groups
+}
+
+/// The lines of `path`, trimmed; a blank line is an empty entry.
+pub fn read_lines(path: &Path) -> Result<Vec<String>, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    Ok(text.lines().map(|l| l.trim().to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn orders_numbers_by_value() {
+        let mut names = vec!["img10.jpg", "IMG2.jpg", "img1.jpg", "img02b.jpg", "a.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["a.jpg", "img1.jpg", "IMG2.jpg", "img02b.jpg", "img10.jpg"]
+        );
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(wildcard("*.jpg", "beach.jpg"));
+        assert!(wildcard("day?-*.png", "day3-sunset.png"));
+        assert!(!wildcard("*.jpg", "beach.jpeg"));
+        assert!(!wildcard("day?.png", "day10.png"));
+    }
+
+    #[test]
+    fn groups_images_and_gifs() {
+        use Kind::*;
+        let kinds = [Image, Image, Image, Image, Image, Gif, Image];
+        assert_eq!(
+            group(&kinds, 4),
+            vec![vec![0, 1, 2, 3], vec![4], vec![5], vec![6]]
+        );
+        assert_eq!(group(&kinds[..3], 1), vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn collects_directories_and_patterns() {
+        let dir = temp_dir().join(format!("xcli_test_{}_photos", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["10.jpg", "2.png", "1.jpg", "clip.mp4", "notes.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let names = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        let all = collect(&[dir.display().to_string()]).unwrap();
+        assert_eq!(names(all), ["1.jpg", "2.png", "10.jpg"]);
+        let jpegs = collect(&[dir.join("*.jpg").display().to_string()]).unwrap();
+        assert_eq!(names(jpegs), ["1.jpg", "10.jpg"]);
+        assert!(collect(&[dir.join("*.gif").display().to_string()]).is_err());
+        assert!(collect(&[dir.join("notes.txt").display().to_string()]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
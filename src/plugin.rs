@@ -0,0 +1,169 @@
+//! External subcommands: `xcli foo` runs an executable named `xcli-foo` found
+//! on `PATH`, the way cargo and git dispatch theirs.
+//!
+//! Plugins get context through environment variables:
+//! - `XCLI_BIN`: path of the running xcli, for calling back into it
+//! - `XCLI_CONFIG_DIR`: the config directory (sandbox-aware)
+//! - `XCLI_ACCOUNT`: handle of the logged-in account, when known
+//! - `XCLI_PROFILE`: the selected `config.toml` profile
+//! - `XCLI_OUTPUT_FORMAT`: `text` on a terminal, `json` when piped
+//! - `XCLI_SANDBOX`: `1` under `--sandbox`
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+pub const PREFIX: &str = "xcli-";
+
+/// Find the `xcli-<name>` executable in the directories of `path_var`.
+pub fn find(name: &str, path_var: &OsStr) -> Option<PathBuf> {
+    let file_name = format!("{PREFIX}{name}{}", env::consts::EXE_SUFFIX);
+    env::split_paths(path_var)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Context handed to a plugin.
+pub struct Context {
+    pub exe: Option<PathBuf>,
+    pub config_dir: PathBuf,
+    pub account: Option<String>,
+    pub profile: String,
+    pub json_output: bool,
+    pub sandbox: bool,
+}
+
+impl Context {
+    pub fn env(&self) -> Vec<(&'static str, OsString)> {
+        let mut vars = vec![
+            ("XCLI_CONFIG_DIR", self.config_dir.clone().into_os_string()),
+            ("XCLI_PROFILE", self.profile.clone().into()),
+            (
+                "XCLI_OUTPUT_FORMAT",
+                if self.json_output { "json" } else { "text" }.into(),
+            ),
+        ];
+        if let Some(exe) = &self.exe {
+            vars.push(("XCLI_BIN", exe.clone().into_os_string()));
+        }
+        if let Some(account) = &self.account {
+            vars.push(("XCLI_ACCOUNT", account.into()));
+        }
+        if self.sandbox {
+            vars.push(("XCLI_SANDBOX", "1".into()));
+        }
+        vars
+    }
+}
+
+/// Run the plugin with `args` and wait for it, returning its exit code.
+pub fn run(program: &Path, args: &[OsString], context: &Context) -> Result<i32, String> {
+    let status = std::process::Command::new(program)
+        .args(args)
+        .envs(context.env())
+        .status()
+        .map_err(|e| format!("Failed to run {}: {e}", program.display()))?;
+    // Killed by a signal: report failure like a shell would.
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Names of all `xcli-*` plugins on `path_var`, sorted and deduplicated.
+pub fn list(path_var: &OsStr) -> Vec<String> {
+    let mut names: Vec<String> = env::split_paths(path_var)
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let stem = path.file_stem()?.to_str()?;
+            let name = stem.strip_prefix(PREFIX)?;
+            let expected = format!("{stem}{}", env::consts::EXE_SUFFIX);
+            (path.file_name()? == expected.as_str() && is_executable(&path))
+                .then(|| name.to_string())
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs;
+
+    fn plugin_dir(name: &str, plugins: &[&str]) -> PathBuf {
+        let dir = temp_dir().join(format!("xcli_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for plugin in plugins {
+            let path = dir.join(format!("{plugin}{}", env::consts::EXE_SUFFIX));
+            fs::write(&path, "#!/bin/sh\n").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn finds_plugins_on_path() {
+        let first = plugin_dir("plugins_a", &["xcli-schedule"]);
+        let second = plugin_dir("plugins_b", &["xcli-schedule", "xcli-lint", "other"]);
+        let path = env::join_paths([&first, &second]).unwrap();
+
+        assert_eq!(
+            find("schedule", &path),
+            Some(first.join(format!("xcli-schedule{}", env::consts::EXE_SUFFIX)))
+        );
+        assert_eq!(find("missing", &path), None);
+        assert_eq!(list(&path), vec!["lint", "schedule"]);
+
+        let _ = fs::remove_dir_all(&first);
+        let _ = fs::remove_dir_all(&second);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_non_executable_files() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = plugin_dir("plugins_noexec", &["xcli-draft"]);
+        let path = dir.join("xcli-draft");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(find("draft", dir.as_os_str()), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn context_env() {
+        let context = Context {
+            exe: None,
+            config_dir: PathBuf::from("/cfg"),
+            account: Some("me".to_string()),
+            profile: "default".to_string(),
+            json_output: true,
+            sandbox: false,
+        };
+        let vars = context.env();
+        let get = |k: &str| vars.iter().find(|(n, _)| *n == k).map(|(_, v)| v.clone());
+        assert_eq!(get("XCLI_CONFIG_DIR"), Some("/cfg".into()));
+        assert_eq!(get("XCLI_OUTPUT_FORMAT"), Some("json".into()));
+        assert_eq!(get("XCLI_ACCOUNT"), Some("me".into()));
+        assert_eq!(get("XCLI_SANDBOX"), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! PNG chunks: the size of a screenshot, and stripping its metadata.
+//! Decoding and encoding, to scale screenshots down before upload (see
+//! [`crate::media`]), go through the `image` crate.
+
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+
+use crate::media::Image;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The `(type, data)` of every chunk.
+pub fn chunks(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+        let kind = data.get(pos + 4..pos + 8)?;
+        let body = data.get(pos + 8..pos + 8 + len)?;
+        pos += 12 + len;
+        Some((kind, body))
+    })
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8], body: &[u8]) {
+    out.extend((body.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(body);
+    let crc = crc32fast::hash(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// Width and height from the header, without decoding.
+pub fn dimensions(data: &[u8]) -> Option<(usize, usize)> {
+    if !data.starts_with(SIGNATURE) {
+        return None;
+    }
+    let (kind, header) = chunks(data).next()?;
+    if kind != b"IHDR" || header.len() < 8 {
+        return None;
+    }
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+    Some((width, height))
+}
+
+/// A copy without EXIF (`eXIf`), text (`tEXt`, `zTXt`, `iTXt`, where
+/// editors leave author, software and XMP) and the modification time. `None`
+/// if there was nothing to remove.
+pub fn strip_metadata(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    if !data.starts_with(SIGNATURE) {
+        return Err("Not a PNG file".to_string());
+    }
+    let mut out = SIGNATURE.to_vec();
+    let mut removed = false;
+    for (kind, body) in chunks(data) {
+        if matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            removed = true;
+        } else {
+            push_chunk(&mut out, kind, body);
+        }
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    Ok(removed.then_some(out))
+}
+/// Encode `image` as an 8-bit PNG, without alpha if it is fully opaque.
+pub fn encode(image: &Image) -> Vec<u8> {
+    let (rows, color): (Vec<u8>, _) = if image.has_alpha() {
+        (image.pixels.clone(), ExtendedColorType::Rgba8)
+    } else {
+        let rgb = image.pixels.chunks(4).flat_map(|p| [p[0], p[1], p[2]]);
+        (rgb.collect(), ExtendedColorType::Rgb8)
+    };
+    let mut out = Vec::new();
+    PngEncoder::new(&mut out)
+        .write_image(&rows, image.width as u32, image.height as u32, color)
+        .expect("encoding to memory does not fail");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn decode(data: &[u8]) -> Result<Image, String> {
+        media::decode(data, "image/png")
+    }
+
+    fn compressed(rows: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(rows).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn checkerboard(alpha: u8) -> Image {
+        let (width, height) = (13, 7);
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let v = if (x + y) % 2 == 0 { 250 } else { 10 };
+                pixels.extend([v, x as u8 * 10, y as u8 * 20, alpha]);
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn round_trips_with_and_without_alpha() {
+        for alpha in [255, 128] {
+            let image = checkerboard(alpha);
+            let data = encode(&image);
+            assert_eq!(dimensions(&data), Some((13, 7)));
+            assert_eq!(decode(&data).unwrap().pixels, image.pixels);
+        }
+    }
+
+    #[test]
+    fn decodes_palettes_and_low_bit_depths() {
+        // 3x1, 2 bits per pixel, palette black/white/red with red half transparent.
+        let mut data = SIGNATURE.to_vec();
+        push_chunk(&mut data, b"IHDR", &[0, 0, 0, 3, 0, 0, 0, 1, 2, 3, 0, 0, 0]);
+        push_chunk(&mut data, b"PLTE", &[0, 0, 0, 255, 255, 255, 255, 0, 0]);
+        push_chunk(&mut data, b"tRNS", &[255, 255, 128]);
+        push_chunk(&mut data, b"IDAT", &compressed(&[0, 0b0001_1000]));
+        push_chunk(&mut data, b"IEND", &[]);
+
+        let image = decode(&data).unwrap();
+        assert_eq!(
+            image.pixels,
+            [0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 128]
+        );
+    }
+
+    #[test]
+    fn refuses_huge_images() {
+        let mut data = SIGNATURE.to_vec();
+        push_chunk(&mut data, b"IHDR", &[0, 1, 0, 0, 0, 1, 0, 0, 8, 6, 0, 0, 0]);
+        push_chunk(&mut data, b"IEND", &[]);
+        assert_eq!(decode(&data).unwrap_err(), "Image too large to resize");
+    }
+
+    #[test]
+    fn strips_metadata() {
+        let image = encode(&checkerboard(255));
+        let mut data = image[..image.len() - 12].to_vec();
+        push_chunk(&mut data, b"tEXt", b"Author\0SECRET");
+        push_chunk(&mut data, b"eXIf", b"MM\0\x2aSECRET");
+        push_chunk(&mut data, b"IEND", &[]);
+
+        let stripped = strip_metadata(&data).unwrap().unwrap();
+        assert_eq!(stripped, image);
+        assert_eq!(strip_metadata(&image).unwrap(), None);
+    }
+
+    #[test]
+    fn decodes_interlaced() {
+        // 1x1 RGB: only the first Adam7 pass has a pixel.
+        let mut data = SIGNATURE.to_vec();
+        push_chunk(&mut data, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 1]);
+        push_chunk(&mut data, b"IDAT", &compressed(&[0, 10, 20, 30]));
+        push_chunk(&mut data, b"IEND", &[]);
+        assert_eq!(decode(&data).unwrap().pixels, [10, 20, 30, 255]);
+    }
+}
@@ -0,0 +1,94 @@
+//! A one-line progress bar on stderr for chunked uploads, so a large video
+//! does not look stuck. Drawn only at a terminal: logs and pipes get nothing.
+
+use std::io::{self, IsTerminal, Write};
+
+use crate::media::format_size;
+
+/// Characters between the brackets of the bar.
+const WIDTH: usize = 24;
+
+pub struct Bar {
+    label: String,
+    total: u64,
+    done: u64,
+    shown: bool,
+}
+
+impl Bar {
+    /// A bar for sending `total` bytes of `label` (a file name, say).
+    pub fn new(label: &str, total: u64) -> Bar {
+        Bar {
+            label: label.to_string(),
+            total,
+            done: 0,
+            shown: io::stderr().is_terminal(),
+        }
+    }
+
+    /// `done` bytes are sent.
+    pub fn set(&mut self, done: u64) {
+        self.done = done.min(self.total);
+        self.draw(&self.line());
+    }
+
+    /// Everything is sent and X is processing it, `percent` done if it says.
+    pub fn processing(&self, percent: Option<u64>) {
+        let line = match percent {
+            Some(percent) => format!("Processing {} on X... {percent}%", self.label),
+            None => format!("Processing {} on X...", self.label),
+        };
+        self.draw(&line);
+    }
+
+    fn line(&self) -> String {
+        let (done, total) = (self.done as usize, self.total.max(1) as usize);
+        let filled = done * WIDTH / total;
+        format!(
+            "Uploading {} [{}{}] {} / {} {:>3}%",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            format_size(done),
+            format_size(self.total as usize),
+            done * 100 / total
+        )
+    }
+
+    fn draw(&self, line: &str) {
+        if self.shown {
+            // Back to the start of the line, then clear what the last one left.
+            eprint!("\r{line}\x1b[K");
+            let _ = io::stderr().flush();
+        }
+    }
+}
+
+/// Leaves the bar (how much was sent) on a line of its own, so what is
+/// printed next (an error, say) does not run into it.
+impl Drop for Bar {
+    fn drop(&mut self) {
+        if self.shown {
+            self.draw(&self.line());
+            eprintln!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_bytes_sent_of_total() {
+        let mut bar = Bar::new("clip.mp4", 48 * 1024 * 1024);
+        bar.shown = false;
+        bar.set(12 * 1024 * 1024);
+        assert_eq!(
+            bar.line(),
+            "Uploading clip.mp4 [######                  ] 12.0 MB / 48.0 MB  25%"
+        );
+        bar.set(u64::MAX);
+        assert!(bar.line().ends_with("48.0 MB / 48.0 MB 100%"));
+    }
+}
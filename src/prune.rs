@@ -0,0 +1,125 @@
+//! `xcli following prune`: the accounts you follow that don't follow you
+//! back, or have not posted for a while.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::api::User;
+
+/// X's snowflake epoch (2010-11-04), in Unix milliseconds.
+const SNOWFLAKE_EPOCH_MS: i64 = 1_288_834_974_657;
+
+/// When a tweet was posted (Unix seconds), read from its ID.
+pub fn posted_at(tweet_id: &str) -> Option<i64> {
+    let id: u64 = tweet_id.parse().ok()?;
+    Some(((id >> 22) as i64 + SNOWFLAKE_EPOCH_MS) / 1000)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Reason {
+    NotFollowingBack,
+    /// Last posted at this time (Unix seconds), or never.
+    Inactive(Option<i64>),
+}
+
+impl Reason {
+    pub fn describe(&self) -> String {
+        match self {
+            Reason::NotFollowingBack => "doesn't follow you back".to_string(),
+            Reason::Inactive(None) => "has never posted".to_string(),
+            Reason::Inactive(Some(ts)) => match chrono::DateTime::from_timestamp(*ts, 0) {
+                Some(t) => format!("last posted {}", t.format("%Y-%m-%d")),
+                None => "last posted long ago".to_string(),
+            },
+        }
+    }
+}
+
+/// An account to unfollow, and why.
+pub struct Candidate {
+    pub user: User,
+    pub reasons: Vec<Reason>,
+}
+
+/// The accounts in `following` that don't follow back (when `followers` is
+/// given) or have not posted since `active_since` (Unix seconds, when given;
+/// `last_posted` holds when each account last posted, by ID). Accounts
+/// missing from `last_posted` count as active.
+pub fn select(
+    following: Vec<User>,
+    followers: Option<&[User]>,
+    active_since: Option<i64>,
+    last_posted: &HashMap<String, Option<i64>>,
+) -> Vec<Candidate> {
+    let followers: Option<HashSet<&str>> =
+        followers.map(|f| f.iter().map(|u| u.id.as_str()).collect());
+    following
+        .into_iter()
+        .filter_map(|user| {
+            let mut reasons = Vec::new();
+            if followers
+                .as_ref()
+                .is_some_and(|f| !f.contains(user.id.as_str()))
+            {
+                reasons.push(Reason::NotFollowingBack);
+            }
+            if let (Some(since), Some(&last)) = (active_since, last_posted.get(&user.id)) {
+                if last.is_none_or(|ts| ts < since) {
+                    reasons.push(Reason::Inactive(last));
+                }
+            }
+            (!reasons.is_empty()).then_some(Candidate { user, reasons })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> User {
+        User {
+            id: id.to_string(),
+            username: format!("user{id}"),
+            name: id.to_string(),
+            description: None,
+            public_metrics: None,
+            profile_image_url: None,
+            most_recent_tweet_id: None,
+        }
+    }
+
+    #[test]
+    fn reads_the_time_from_a_tweet_id() {
+        assert_eq!(posted_at("1212092628029698048"), Some(1_577_820_376));
+        assert_eq!(posted_at("not an id"), None);
+    }
+
+    #[test]
+    fn selects_non_mutual_and_inactive_accounts() {
+        let following = vec![user("1"), user("2"), user("3"), user("4")];
+        let followers = [user("1"), user("2"), user("4")];
+        let last_posted = HashMap::from([
+            ("1".to_string(), Some(2_000)),
+            ("2".to_string(), Some(500)),
+            ("3".to_string(), Some(2_000)),
+            ("4".to_string(), None),
+        ]);
+
+        let picked = select(following, Some(&followers), Some(1_000), &last_posted);
+        let picked: Vec<(&str, &[Reason])> = picked
+            .iter()
+            .map(|c| (c.user.id.as_str(), c.reasons.as_slice()))
+            .collect();
+        assert_eq!(
+            picked,
+            [
+                ("2", &[Reason::Inactive(Some(500))][..]),
+                ("3", &[Reason::NotFollowingBack][..]),
+                ("4", &[Reason::Inactive(None)][..]),
+            ]
+        );
+
+        let only_inactive = select(vec![user("3")], None, Some(1_000), &last_posted);
+        assert!(only_inactive.is_empty());
+    }
+}
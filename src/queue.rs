@@ -0,0 +1,50 @@
+use crate::api;
+use crate::client::XClient;
+use crate::store::{QueueItem, Store, ACTION_DELETE};
+
+/// Give up on an item after this many failed attempts.
+pub const MAX_ATTEMPTS: i64 = 5;
+
+pub enum Outcome {
+    Done(QueueItem),
+    Failed(QueueItem, String),
+    Dropped(QueueItem, String),
+}
+
+/// Process every item that is due at `now`. Successful items are removed;
+/// failures are retried on the next run until `MAX_ATTEMPTS` is reached.
+pub async fn process_due(
+    client: &XClient,
+    store: &Store,
+    now: i64,
+) -> Result<Vec<Outcome>, String> {
+    let mut outcomes = Vec::new();
+
+    for item in store.due_items(now)? {
+        let result = match item.action.as_str() {
+            ACTION_DELETE => match api::delete_tweet(client, &item.tweet_id).await {
+                Ok(resp) if resp.data => Ok(()),
+                Ok(_) => Err("tweet was not deleted".to_string()),
+                Err(e) => Err(e),
+            },
+            other => Err(format!("unknown action '{other}'")),
+        };
+
+        match result {
+            Ok(()) => {
+                store.remove_queue_item(item.id)?;
+                outcomes.push(Outcome::Done(item));
+            }
+            Err(e) if item.attempts + 1 >= MAX_ATTEMPTS => {
+                store.remove_queue_item(item.id)?;
+                outcomes.push(Outcome::Dropped(item, e));
+            }
+            Err(e) => {
+                store.record_queue_failure(item.id, &e)?;
+                outcomes.push(Outcome::Failed(item, e));
+            }
+        }
+    }
+
+    Ok(outcomes)
+}
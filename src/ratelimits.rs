@@ -0,0 +1,190 @@
+//! `xcli limits`: the rate limits X last reported for each endpoint, taken
+//! from the `x-rate-limit-*` headers of every response and kept in
+//! `rate-limits.json`, so they can be checked without spending requests.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::api::RateLimit;
+use crate::transport::{Method, OnLine, Request, Response, Transport};
+
+const API_ORIGIN: &str = "https://api.x.com";
+
+/// One rate-limit window of an endpoint, as X last reported it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    #[serde(flatten)]
+    pub window: RateLimit,
+    /// When X reported it (Unix seconds).
+    pub seen_at: i64,
+}
+
+/// Limits by endpoint, e.g. `GET /2/users/:id/tweets`. Endpoints with a
+/// daily cap besides (posting) also have a `... (24h)` entry.
+pub type Limits = BTreeMap<String, Limit>;
+
+/// The endpoint `url` belongs to, with IDs and handles replaced by `:id`
+/// and `:username`. `None` for URLs outside the X API.
+pub fn endpoint(method: &Method, url: &str) -> Option<String> {
+    let path = url.strip_prefix(API_ORIGIN)?;
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        let segment = if segments.ends_with(&["by", "username"]) {
+            ":username"
+        } else if segment.bytes().any(|b| b.is_ascii_digit()) && segment != "2" {
+            ":id"
+        } else {
+            segment
+        };
+        segments.push(segment);
+    }
+    Some(format!("{method} {}", segments.join("/")))
+}
+
+/// The windows `resp` reports, labelled with the suffix of their entry (the
+/// daily cap gets ` (24h)`).
+pub fn from_response(resp: &Response, now: i64) -> Vec<(&'static str, Limit)> {
+    [("", "x-rate-limit"), (" (24h)", "x-user-limit-24hour")]
+        .into_iter()
+        .filter_map(|(suffix, prefix)| {
+            let window = RateLimit::from_headers(resp, prefix)?;
+            Some((
+                suffix,
+                Limit {
+                    window,
+                    seen_at: now,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// What is saved at `path`, or nothing.
+pub fn load(path: &Path) -> Limits {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(path: &Path, limits: &Limits) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string_pretty(limits).expect("rate limits serialize to JSON");
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Remember the limits `resp` (to `req`) reports in the file at `path`.
+fn record(path: &Path, req: &Request, resp: &Response) {
+    let Some(endpoint) = endpoint(&req.method, &req.url) else {
+        return;
+    };
+    let windows = from_response(resp, crate::store::now());
+    if windows.is_empty() {
+        return;
+    }
+    let mut limits = load(path);
+    for (suffix, limit) in windows {
+        limits.insert(format!("{endpoint}{suffix}"), limit);
+    }
+    // Only a cache: a failure to write it must not fail the request.
+    let _ = save(path, &limits);
+}
+
+/// Records the rate limits of every response to a file.
+pub struct TrackingTransport {
+    inner: Arc<dyn Transport>,
+    path: PathBuf,
+}
+
+impl TrackingTransport {
+    pub fn new(inner: Arc<dyn Transport>, path: PathBuf) -> Self {
+        TrackingTransport { inner, path }
+    }
+}
+
+#[async_trait]
+impl Transport for TrackingTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let sent = req.clone();
+        let result = self.inner.send(req).await;
+        if let Ok(resp) = &result {
+            record(&self.path, &sent, resp);
+        }
+        result
+    }
+
+    async fn send_lines(&self, req: Request, line: &mut OnLine<'_>) -> Result<Response, String> {
+        let sent = req.clone();
+        let result = self.inner.send_lines(req, line).await;
+        if let Ok(resp) = &result {
+            record(&self.path, &sent, resp);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use std::env::temp_dir;
+
+    #[test]
+    fn names_endpoints_without_ids() {
+        let get = |url: &str| endpoint(&Method::GET, url);
+        assert_eq!(
+            get("https://api.x.com/2/users/123/tweets?max_results=5").as_deref(),
+            Some("GET /2/users/:id/tweets")
+        );
+        assert_eq!(
+            get("https://api.x.com/2/users/by/username/jack2").as_deref(),
+            Some("GET /2/users/by/username/:username")
+        );
+        assert_eq!(
+            get("https://api.x.com/2/spaces/1YqKDqWqdPLsV").as_deref(),
+            Some("GET /2/spaces/:id")
+        );
+        assert_eq!(
+            endpoint(&Method::POST, "https://api.x.com/2/tweets").as_deref(),
+            Some("POST /2/tweets")
+        );
+        assert_eq!(get("https://upload.example/2/tweets"), None);
+    }
+
+    #[tokio::test]
+    async fn records_the_limits_of_each_response() {
+        let path = temp_dir().join(format!("xcli_test_{}_limits.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mock = Arc::new(MockTransport::new());
+        mock.push(Ok(Response::new(201, "{}")
+            .with_header("x-rate-limit-limit", "100")
+            .with_header("x-rate-limit-remaining", "99")
+            .with_header("x-rate-limit-reset", "1700000900")
+            .with_header("x-user-limit-24hour-limit", "17")
+            .with_header("x-user-limit-24hour-remaining", "16")
+            .with_header("x-user-limit-24hour-reset", "1700080000")))
+            .respond(200, "{}");
+        let transport = TrackingTransport::new(mock, path.clone());
+
+        let post = || Request::new(Method::POST, "https://api.x.com/2/tweets");
+        transport.send(post()).await.unwrap();
+        // A response without the headers leaves what was recorded.
+        transport.send(post()).await.unwrap();
+
+        let limits = load(&path);
+        let keys: Vec<&str> = limits.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["POST /2/tweets", "POST /2/tweets (24h)"]);
+        let daily = limits["POST /2/tweets (24h)"].window;
+        assert_eq!((daily.limit, daily.remaining), (17, 16));
+        assert_eq!(limits["POST /2/tweets"].window.reset, 1_700_000_900);
+        fs::remove_file(&path).unwrap();
+    }
+}
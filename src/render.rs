@@ -0,0 +1,448 @@
+//! `xcli render`: a tweet drawn as a card (avatar, name and handle, text,
+//! time and metrics) in a PNG, for blogs and slides without a screenshot.
+//! Text is set in a TrueType font: `--font`, or a common system font.
+
+use std::fs;
+use std::path::Path;
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+
+use crate::api::PublicMetrics;
+use crate::media::Image;
+
+/// Width of a card in pixels; the height follows from the text.
+pub const WIDTH: usize = 1200;
+const PADDING: usize = 64;
+const AVATAR: usize = 112;
+const NAME_SIZE: f32 = 38.0;
+const TEXT_SIZE: f32 = 44.0;
+const SMALL_SIZE: f32 = 32.0;
+/// Line height as a multiple of the font size.
+const LEADING: f32 = 1.35;
+
+/// Fonts tried when none is given, as (regular, bold).
+const SYSTEM_FONTS: &[(&str, &str)] = &[
+    (
+        "/System/Library/Fonts/Supplemental/Arial.ttf",
+        "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
+    ),
+    ("/Library/Fonts/Arial.ttf", "/Library/Fonts/Arial Bold.ttf"),
+    (
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    ),
+    (
+        "/usr/share/fonts/TTF/DejaVuSans.ttf",
+        "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    ),
+    (
+        "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/dejavu/DejaVuSans-Bold.ttf",
+    ),
+    (
+        "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+        "/usr/share/fonts/truetype/noto/NotoSans-Bold.ttf",
+    ),
+    (
+        "C:\\Windows\\Fonts\\segoeui.ttf",
+        "C:\\Windows\\Fonts\\segoeuib.ttf",
+    ),
+    (
+        "C:\\Windows\\Fonts\\arial.ttf",
+        "C:\\Windows\\Fonts\\arialbd.ttf",
+    ),
+];
+
+type Rgb = [u8; 3];
+
+struct Palette {
+    background: Rgb,
+    text: Rgb,
+    muted: Rgb,
+    divider: Rgb,
+    accent: Rgb,
+}
+
+const LIGHT: Palette = Palette {
+    background: [255, 255, 255],
+    text: [15, 20, 25],
+    muted: [83, 100, 113],
+    divider: [239, 243, 244],
+    accent: [29, 155, 240],
+};
+
+const DARK: Palette = Palette {
+    background: [0, 0, 0],
+    text: [231, 233, 234],
+    muted: [113, 118, 123],
+    divider: [47, 51, 54],
+    accent: [29, 155, 240],
+};
+
+pub struct Fonts {
+    regular: FontVec,
+    bold: FontVec,
+}
+
+impl Fonts {
+    /// The font at `path` (for bold text too), or the first system font
+    /// found.
+    pub fn load(path: Option<&Path>) -> Result<Fonts, String> {
+        let read = |path: &Path| -> Result<FontVec, String> {
+            let data =
+                fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            FontVec::try_from_vec(data)
+                .map_err(|_| format!("{} is not a TrueType or OpenType font", path.display()))
+        };
+        if let Some(path) = path {
+            let regular = read(path)?;
+            return Ok(Fonts {
+                bold: read(path)?,
+                regular,
+            });
+        }
+        let (regular, bold) = SYSTEM_FONTS
+            .iter()
+            .find(|(regular, _)| Path::new(regular).exists())
+            .ok_or("No system font found; pass one with --font FILE.ttf")?;
+        let bold = read(Path::new(bold)).or_else(|_| read(Path::new(regular)))?;
+        Ok(Fonts {
+            regular: read(Path::new(regular))?,
+            bold,
+        })
+    }
+}
+
+/// What goes on a card.
+pub struct Card<'a> {
+    pub name: &'a str,
+    pub handle: &'a str,
+    pub text: &'a str,
+    pub avatar: Option<&'a Image>,
+    /// When it was posted, as it should read.
+    pub posted: Option<String>,
+    pub metrics: Option<PublicMetrics>,
+    pub dark: bool,
+}
+
+/// `n` the way X shows counts: `987`, `12.3K`, `4.5M`.
+pub fn compact(n: u64) -> String {
+    let short = |n: u64, unit: u64, suffix: &str| {
+        let tenths = n * 10 / unit;
+        if tenths.is_multiple_of(10) || tenths >= 1000 {
+            format!("{}{suffix}", tenths / 10)
+        } else {
+            format!("{}.{}{suffix}", tenths / 10, tenths % 10)
+        }
+    };
+    match n {
+        0..=9_999 => n.to_string(),
+        10_000..=999_999 => short(n, 1_000, "K"),
+        _ => short(n, 1_000_000, "M"),
+    }
+}
+
+/// `text` broken into lines no wider than `max` as measured by `width`: at
+/// spaces where possible, inside a word too long for a line (or text
+/// without spaces) otherwise. Line breaks in `text` are kept.
+pub fn wrap(text: &str, max: f32, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ').filter(|w| !w.is_empty()) {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if width(&candidate) <= max {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            line = word.to_string();
+            while width(&line) > max {
+                let mut cut = line.len();
+                while width(&line[..cut]) > max {
+                    cut = line[..cut].char_indices().last().map_or(0, |(i, _)| i);
+                }
+                // At least one character per line, however narrow.
+                if cut == 0 {
+                    cut = line.chars().next().map_or(line.len(), char::len_utf8);
+                }
+                let rest = line.split_off(cut);
+                lines.push(std::mem::replace(&mut line, rest));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// How wide `text` is set in `font` at `size` pixels.
+fn measure(font: &FontVec, size: f32, text: &str) -> f32 {
+    let font = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut last = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(last) = last {
+            width += font.kern(last, id);
+        }
+        width += font.h_advance(id);
+        last = Some(id);
+    }
+    width
+}
+
+/// Blend `color` into the pixel at (`x`, `y`) by `coverage` (0 to 1).
+fn blend(canvas: &mut Image, x: i64, y: i64, color: Rgb, coverage: f32) {
+    if x < 0 || y < 0 || x as usize >= canvas.width || y as usize >= canvas.height {
+        return;
+    }
+    let i = (y as usize * canvas.width + x as usize) * 4;
+    let coverage = coverage.clamp(0.0, 1.0);
+    for (c, &value) in color.iter().enumerate() {
+        let under = canvas.pixels[i + c] as f32;
+        canvas.pixels[i + c] = (under + (value as f32 - under) * coverage).round() as u8;
+    }
+}
+
+/// Draw `text` with its baseline at `y`, starting at `x`. Returns its width.
+fn draw_text(
+    canvas: &mut Image,
+    font: &FontVec,
+    size: f32,
+    (x, y): (f32, f32),
+    text: &str,
+    color: Rgb,
+) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut caret = x;
+    let mut last = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(last) = last {
+            caret += scaled.kern(last, id);
+        }
+        let glyph = id.with_scale_and_position(size, point(caret, y));
+        caret += scaled.h_advance(id);
+        last = Some(id);
+        if let Some(outline) = font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i64 + gx as i64;
+                let py = bounds.min.y as i64 + gy as i64;
+                blend(canvas, px, py, color, coverage);
+            });
+        }
+    }
+    caret - x
+}
+
+/// Draw a round avatar `size` pixels across at (`x`, `y`): `avatar`, or the
+/// first letter of `name` on the accent color.
+fn draw_avatar(
+    canvas: &mut Image,
+    fonts: &Fonts,
+    (x, y): (usize, usize),
+    avatar: Option<&Image>,
+    name: &str,
+    palette: &Palette,
+) {
+    let size = AVATAR;
+    let scaled = avatar.map(|a| {
+        if a.width > size && a.height > size {
+            a.resized(size, size)
+        } else {
+            a.clone()
+        }
+    });
+    let r = size as f32 / 2.0;
+    for dy in 0..size {
+        for dx in 0..size {
+            let distance = ((dx as f32 + 0.5 - r).powi(2) + (dy as f32 + 0.5 - r).powi(2)).sqrt();
+            let coverage = r - distance + 0.5;
+            if coverage <= 0.0 {
+                continue;
+            }
+            let color = match &scaled {
+                Some(a) => {
+                    let i = ((dy * a.height / size) * a.width + dx * a.width / size) * 4;
+                    [a.pixels[i], a.pixels[i + 1], a.pixels[i + 2]]
+                }
+                None => palette.accent,
+            };
+            blend(canvas, (x + dx) as i64, (y + dy) as i64, color, coverage);
+        }
+    }
+    if scaled.is_none() {
+        let initial: String = name.chars().take(1).flat_map(char::to_uppercase).collect();
+        let size = AVATAR as f32 * 0.5;
+        let width = measure(&fonts.bold, size, &initial);
+        let ascent = fonts.bold.as_scaled(PxScale::from(size)).ascent();
+        let origin = (
+            x as f32 + (AVATAR as f32 - width) / 2.0,
+            y as f32 + (AVATAR as f32 + ascent * 0.72) / 2.0,
+        );
+        draw_text(canvas, &fonts.bold, size, origin, &initial, [255, 255, 255]);
+    }
+}
+
+/// Draw `card`.
+pub fn draw(card: &Card, fonts: &Fonts) -> Image {
+    let palette = if card.dark { &DARK } else { &LIGHT };
+    let inner = (WIDTH - 2 * PADDING) as f32;
+    let lines = wrap(card.text, inner, |s| measure(&fonts.regular, TEXT_SIZE, s));
+    let line_height = TEXT_SIZE * LEADING;
+    let small_height = SMALL_SIZE * LEADING;
+
+    let text_top = (PADDING + AVATAR) as f32 + 32.0;
+    let mut height = text_top + lines.len() as f32 * line_height;
+    if card.posted.is_some() {
+        height += 16.0 + small_height;
+    }
+    if card.metrics.is_some() {
+        height += 32.0 + small_height;
+    }
+    let height = height as usize + PADDING;
+
+    let mut canvas = Image {
+        width: WIDTH,
+        height,
+        pixels: palette
+            .background
+            .iter()
+            .copied()
+            .chain([255])
+            .cycle()
+            .take(WIDTH * height * 4)
+            .collect(),
+    };
+
+    draw_avatar(
+        &mut canvas,
+        fonts,
+        (PADDING, PADDING),
+        card.avatar,
+        card.name,
+        palette,
+    );
+    let left = (PADDING + AVATAR + 24) as f32;
+    let middle = (PADDING + AVATAR / 2) as f32;
+    draw_text(
+        &mut canvas,
+        &fonts.bold,
+        NAME_SIZE,
+        (left, middle - 8.0),
+        card.name,
+        palette.text,
+    );
+    draw_text(
+        &mut canvas,
+        &fonts.regular,
+        SMALL_SIZE,
+        (left, middle + SMALL_SIZE + 4.0),
+        &format!("@{}", card.handle),
+        palette.muted,
+    );
+
+    let ascent = fonts.regular.as_scaled(PxScale::from(TEXT_SIZE)).ascent();
+    let mut y = text_top;
+    for line in &lines {
+        draw_text(
+            &mut canvas,
+            &fonts.regular,
+            TEXT_SIZE,
+            (PADDING as f32, y + ascent),
+            line,
+            palette.text,
+        );
+        y += line_height;
+    }
+
+    let small_ascent = fonts.regular.as_scaled(PxScale::from(SMALL_SIZE)).ascent();
+    if let Some(posted) = &card.posted {
+        y += 16.0;
+        draw_text(
+            &mut canvas,
+            &fonts.regular,
+            SMALL_SIZE,
+            (PADDING as f32, y + small_ascent),
+            posted,
+            palette.muted,
+        );
+        y += small_height;
+    }
+    if let Some(m) = &card.metrics {
+        y += 16.0;
+        for x in PADDING..WIDTH - PADDING {
+            blend(&mut canvas, x as i64, y as i64, palette.divider, 1.0);
+            blend(&mut canvas, x as i64, y as i64 + 1, palette.divider, 1.0);
+        }
+        y += 16.0;
+        let counts = [
+            (m.reply_count, "Replies"),
+            (m.retweet_count, "Reposts"),
+            (m.like_count, "Likes"),
+            (m.impression_count, "Views"),
+        ];
+        let mut x = PADDING as f32;
+        for (count, label) in counts {
+            let baseline = (x, y + small_ascent);
+            x += draw_text(
+                &mut canvas,
+                &fonts.bold,
+                SMALL_SIZE,
+                baseline,
+                &compact(count),
+                palette.text,
+            );
+            x += draw_text(
+                &mut canvas,
+                &fonts.regular,
+                SMALL_SIZE,
+                (x, y + small_ascent),
+                &format!(" {label}"),
+                palette.muted,
+            );
+            x += 40.0;
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compacts_counts() {
+        assert_eq!(compact(987), "987");
+        assert_eq!(compact(9_999), "9999");
+        assert_eq!(compact(12_345), "12.3K");
+        assert_eq!(compact(40_000), "40K");
+        assert_eq!(compact(999_999), "999K");
+        assert_eq!(compact(4_560_000), "4.5M");
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_inside_long_words() {
+        // One unit per character.
+        let width = |s: &str| s.chars().count() as f32;
+        assert_eq!(
+            wrap("the quick brown fox\n\njumps", 10.0, width),
+            ["the quick", "brown fox", "", "jumps"]
+        );
+        assert_eq!(
+            wrap("a supercalifragilistic word", 10.0, width),
+            ["a", "supercalif", "ragilistic", "word"]
+        );
+        assert_eq!(
+            wrap("안녕하세요반갑습니다", 4.0, width),
+            ["안녕하세", "요반갑습", "니다"]
+        );
+    }
+}
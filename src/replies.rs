@@ -0,0 +1,73 @@
+//! `xcli replies`: the replies to a tweet as a tree, built from the tweets
+//! of its conversation.
+
+use std::collections::HashMap;
+
+use crate::api::Tweet;
+
+/// The replies under `root_id` among `tweets`, depth first with the oldest
+/// reply first at each level, each with its depth (1 for a direct reply).
+/// Replies whose parent is missing (deleted, or too old for the search)
+/// count as direct replies.
+pub fn tree<'a>(root_id: &str, tweets: &'a [Tweet]) -> Vec<(usize, &'a Tweet)> {
+    let known: HashMap<&str, &Tweet> = tweets.iter().map(|t| (t.id.as_str(), t)).collect();
+    let mut children: HashMap<&str, Vec<&Tweet>> = HashMap::new();
+    for tweet in tweets.iter().filter(|t| t.id != root_id) {
+        let parent = match tweet.in_reply_to() {
+            Some(parent) if known.contains_key(parent) => parent,
+            _ => root_id,
+        };
+        children.entry(parent).or_default().push(tweet);
+    }
+    for replies in children.values_mut() {
+        // IDs grow over time; compare them as numbers.
+        replies.sort_by(|a, b| (a.id.len(), &a.id).cmp(&(b.id.len(), &b.id)));
+        replies.dedup_by(|a, b| a.id == b.id);
+    }
+
+    let mut out = Vec::new();
+    let mut stack: Vec<(usize, &Tweet)> = children
+        .get(root_id)
+        .map(|r| r.iter().rev().map(|&t| (1, t)).collect())
+        .unwrap_or_default();
+    while let Some((depth, tweet)) = stack.pop() {
+        out.push((depth, tweet));
+        if let Some(replies) = children.get(tweet.id.as_str()) {
+            stack.extend(replies.iter().rev().map(|&t| (depth + 1, t)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: &str, parent: Option<&str>) -> Tweet {
+        let referenced = match parent {
+            Some(p) => format!(r#","referenced_tweets":[{{"type":"replied_to","id":"{p}"}}]"#),
+            None => String::new(),
+        };
+        serde_json::from_str(&format!(r#"{{"id":"{id}","text":"t{id}"{referenced}}}"#)).unwrap()
+    }
+
+    #[test]
+    fn nests_replies_oldest_first() {
+        let tweets = [
+            tweet("100", None),
+            tweet("110", Some("100")),
+            tweet("99", Some("100")),
+            tweet("120", Some("110")),
+            tweet("130", Some("999")),
+            tweet("125", Some("99")),
+        ];
+        let shape: Vec<(usize, &str)> = tree("100", &tweets)
+            .into_iter()
+            .map(|(depth, t)| (depth, t.id.as_str()))
+            .collect();
+        assert_eq!(
+            shape,
+            [(1, "99"), (2, "125"), (1, "110"), (2, "120"), (1, "130")]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Retention policy (`[retention]` in a profile): tweets in the local
+//! history older than `delete_after` are deleted by the queue runner, unless
+//! you liked them yourself or they carry a tag or match a pattern to keep.
+//! `xcli retention preview` shows what the next run would delete.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Retention;
+use crate::duration;
+use crate::store::HistoryEntry;
+use crate::thread::missing_hashtags;
+
+/// Most tweets deleted in one run, within X's limit of 50 deletions per 15
+/// minutes. The rest wait for the next run.
+pub const MAX_DELETES_PER_RUN: usize = 50;
+
+/// Seconds between two runs from the queue runner.
+pub const RUN_EVERY: i64 = 3600;
+
+/// A `[retention]` table, checked and ready to apply.
+#[derive(Debug)]
+pub struct Policy {
+    /// In seconds.
+    pub max_age: i64,
+    pub keep_liked: bool,
+    keep_tags: Vec<String>,
+    keep_patterns: Vec<Regex>,
+}
+
+impl Policy {
+    pub fn new(config: &Retention) -> Result<Self, String> {
+        let max_age = duration::parse_duration(&config.delete_after)
+            .map_err(|e| format!("Invalid retention delete_after: {e}"))?;
+        let keep_patterns = config
+            .keep_patterns
+            .iter()
+            .map(|p| Regex::new(p).map_err(|e| format!("Invalid keep_patterns entry `{p}`: {e}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Policy {
+            max_age: max_age.as_secs() as i64,
+            keep_liked: config.keep_liked,
+            keep_tags: config.keep_tags.clone(),
+            keep_patterns,
+        })
+    }
+
+    /// Tweets posted before this are due.
+    pub fn cutoff(&self, now: i64) -> i64 {
+        now - self.max_age
+    }
+
+    /// Why `entry` is kept despite its age, if it is. `liked` holds the IDs
+    /// of the tweets you liked.
+    pub fn keep_reason(&self, entry: &HistoryEntry, liked: &HashSet<String>) -> Option<String> {
+        if self.keep_liked && liked.contains(&entry.tweet_id) {
+            return Some("liked".to_string());
+        }
+        let missing = missing_hashtags(&entry.text, &self.keep_tags);
+        if let Some(tag) = self
+            .keep_tags
+            .iter()
+            .map(|t| format!("#{}", t.trim().trim_start_matches('#')))
+            .find(|t| t.len() > 1 && !missing.iter().any(|m| m.eq_ignore_ascii_case(t)))
+        {
+            return Some(tag);
+        }
+        self.keep_patterns
+            .iter()
+            .find(|re| re.is_match(&entry.text))
+            .map(|re| format!("/{re}/"))
+    }
+}
+
+/// What a run does with the tweets past the cutoff.
+pub struct Plan<'a> {
+    /// Oldest first.
+    pub delete: Vec<&'a HistoryEntry>,
+    pub kept: Vec<(&'a HistoryEntry, String)>,
+}
+
+/// Sort `due`, the tweets past the cutoff, into those to delete and those
+/// kept.
+pub fn plan<'a>(policy: &Policy, due: &'a [HistoryEntry], liked: &HashSet<String>) -> Plan<'a> {
+    let mut plan = Plan {
+        delete: Vec::new(),
+        kept: Vec::new(),
+    };
+    for entry in due {
+        match policy.keep_reason(entry, liked) {
+            Some(reason) => plan.kept.push((entry, reason)),
+            None => plan.delete.push(entry),
+        }
+    }
+    plan.delete.sort_by_key(|e| e.posted_at);
+    plan
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct State {
+    /// Unix seconds.
+    last_run: Option<i64>,
+}
+
+/// When the policy last ran, according to the state at `path`.
+pub fn last_run(path: &Path) -> Option<i64> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<State>(&data).ok())
+        .and_then(|s| s.last_run)
+}
+
+pub fn record_run(path: &Path, at: i64) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string(&State { last_run: Some(at) }).unwrap();
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::SOURCE_XCLI;
+    use std::env::temp_dir;
+
+    fn entry(id: &str, text: &str, posted_at: i64) -> HistoryEntry {
+        HistoryEntry {
+            tweet_id: id.to_string(),
+            root_id: id.to_string(),
+            position: 0,
+            text: text.to_string(),
+            account: "me".to_string(),
+            in_reply_to: None,
+            posted_at,
+            source: SOURCE_XCLI.to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_liked_tagged_and_matching_tweets() {
+        let policy = Policy::new(&Retention {
+            delete_after: "90d".to_string(),
+            keep_liked: true,
+            keep_tags: vec!["keep".to_string()],
+            keep_patterns: vec!["(?i)release".to_string()],
+        })
+        .unwrap();
+        assert_eq!(policy.cutoff(100 * 86400), 10 * 86400);
+
+        let due = vec![
+            entry("3", "hot take", 30),
+            entry("1", "liked one", 10),
+            entry("2", "Worth it #Keep.", 20),
+            entry("4", "v1.0 Release notes", 40),
+            entry("5", "older hot take", 5),
+        ];
+        let liked: HashSet<String> = ["1".to_string()].into();
+        let plan = plan(&policy, &due, &liked);
+        let ids: Vec<&str> = plan.delete.iter().map(|e| e.tweet_id.as_str()).collect();
+        assert_eq!(ids, ["5", "3"]);
+        let kept: Vec<(&str, &str)> = plan
+            .kept
+            .iter()
+            .map(|(e, why)| (e.tweet_id.as_str(), why.as_str()))
+            .collect();
+        assert_eq!(
+            kept,
+            [("1", "liked"), ("2", "#keep"), ("4", "/(?i)release/")]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_policies() {
+        let mut config = Retention {
+            delete_after: "soon".to_string(),
+            ..Retention::default()
+        };
+        assert!(Policy::new(&config).is_err());
+        config.delete_after = "30d".to_string();
+        config.keep_patterns = vec!["(".to_string()];
+        assert!(Policy::new(&config).unwrap_err().contains("keep_patterns"));
+    }
+
+    #[test]
+    fn remembers_the_last_run() {
+        let path = temp_dir().join(format!("xcli_test_{}_retention.json", std::process::id()));
+        assert_eq!(last_run(&path), None);
+        record_run(&path, 1234).unwrap();
+        assert_eq!(last_run(&path), Some(1234));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+//! `--diff`: what changed in a list of accounts (the ones you follow, say)
+//! since it was exported, from the export itself in any of its formats.
+
+use std::collections::HashSet;
+
+use crate::api::User;
+use crate::export;
+
+/// The accounts in an export written by `xcli following export` or
+/// `xcli followers export`: CSV, a JSON array or JSON lines.
+pub fn read_export(data: &str) -> Result<Vec<User>, String> {
+    let data = data.trim_start_matches('\u{feff}').trim();
+    if data.starts_with('[') {
+        return serde_json::from_str(data).map_err(|e| format!("not a JSON list of accounts: {e}"));
+    }
+    if data.starts_with('{') {
+        return data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(n, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {e}", n + 1)))
+            .collect();
+    }
+    let mut records = export::parse_csv(data)?.into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("no `{name}` column in the CSV header"))
+    };
+    let (handle, name, id, bio) = (
+        column("handle")?,
+        column("name")?,
+        column("id")?,
+        column("bio"),
+    );
+    records
+        .enumerate()
+        .map(|(n, record)| {
+            let field = |i: usize| {
+                record
+                    .get(i)
+                    .cloned()
+                    .ok_or_else(|| format!("row {}: too few fields", n + 2))
+            };
+            Ok(User {
+                id: field(id)?,
+                username: field(handle)?,
+                name: field(name)?,
+                description: bio
+                    .as_ref()
+                    .ok()
+                    .and_then(|&i| record.get(i))
+                    .filter(|b| !b.is_empty())
+                    .cloned(),
+                public_metrics: None,
+                profile_image_url: None,
+                most_recent_tweet_id: None,
+            })
+        })
+        .collect()
+}
+
+/// Accounts that joined and left a list between two reads of it.
+pub struct Changes {
+    pub added: Vec<User>,
+    pub removed: Vec<User>,
+}
+
+/// What changed from `old` to `new`, matching accounts by ID (handles can
+/// change), each side in the order of its list.
+pub fn compare(old: &[User], new: &[User]) -> Changes {
+    let old_ids: HashSet<&str> = old.iter().map(|u| u.id.as_str()).collect();
+    let new_ids: HashSet<&str> = new.iter().map(|u| u.id.as_str()).collect();
+    let added = new
+        .iter()
+        .filter(|u| !old_ids.contains(u.id.as_str()))
+        .cloned()
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|u| !new_ids.contains(u.id.as_str()))
+        .cloned()
+        .collect();
+    Changes { added, removed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> User {
+        User {
+            id: id.to_string(),
+            username: format!("user{id}"),
+            name: format!("User {id}"),
+            description: None,
+            public_metrics: None,
+            profile_image_url: None,
+            most_recent_tweet_id: None,
+        }
+    }
+
+    #[test]
+    fn reads_every_export_format() {
+        let csv = "handle,name,id,followers_count,bio\n\
+                   user1,User 1,1,10,\n\
+                   user2,\"User 2\",2,,\"Says \"\"hi\"\"\"\n";
+        let users = read_export(csv).unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1].id, "2");
+        assert_eq!(users[1].description.as_deref(), Some("Says \"hi\""));
+
+        let json = serde_json::to_string_pretty(&[user("1"), user("2")]).unwrap();
+        assert_eq!(read_export(&json).unwrap().len(), 2);
+
+        let jsonl = format!(
+            "{}\n{}\n",
+            serde_json::to_string(&user("1")).unwrap(),
+            serde_json::to_string(&user("3")).unwrap()
+        );
+        assert_eq!(read_export(&jsonl).unwrap()[1].username, "user3");
+
+        assert!(read_export("name,bio\nx,y\n").is_err());
+    }
+
+    #[test]
+    fn compares_by_id() {
+        let mut renamed = user("2");
+        renamed.username = "newname".to_string();
+        let changes = compare(&[user("1"), user("2")], &[renamed, user("3")]);
+        let ids = |users: &[User]| users.iter().map(|u| u.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&changes.added), ["3"]);
+        assert_eq!(ids(&changes.removed), ["1"]);
+    }
+}
@@ -0,0 +1,367 @@
+//! An in-process fake of the X API behind `xcli --sandbox`, so scripts can be
+//! rehearsed without posting anything real.
+//!
+//! Only the endpoints xcli calls are emulated, and state lives for one
+//! process: a tweet posted by one `xcli --sandbox` run is unknown to the next.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::transport::{Method, Request, Response, Transport};
+
+const API_ORIGIN: &str = "https://api.x.com";
+
+/// The account every sandbox request acts as.
+pub const SANDBOX_USER_ID: &str = "1000";
+pub const SANDBOX_USERNAME: &str = "sandbox";
+
+/// X's snowflake epoch (2010-11-04), in Unix milliseconds.
+const SNOWFLAKE_EPOCH_MS: i64 = 1_288_834_974_657;
+
+/// Placeholder credentials for sandbox runs without an account configured.
+pub fn config() -> Config {
+    Config {
+        api_key: "sandbox-key".to_string(),
+        api_secret: "sandbox-secret".to_string(),
+        access_token: "sandbox-token".to_string(),
+        access_token_secret: "sandbox-token-secret".to_string(),
+        screen_name: Some(SANDBOX_USERNAME.to_string()),
+    }
+}
+
+struct State {
+    last_id: u64,
+    tweets: BTreeMap<String, Value>,
+    rules: Vec<Value>,
+}
+
+impl State {
+    /// IDs are snowflakes of the current time, so they look real and do not
+    /// collide with those of earlier sandbox runs in the local history.
+    fn next_id(&mut self) -> String {
+        let millis = chrono::Utc::now().timestamp_millis() - SNOWFLAKE_EPOCH_MS;
+        self.last_id = ((millis as u64) << 22).max(self.last_id + 1);
+        self.last_id.to_string()
+    }
+}
+
+/// Answers requests to `api.x.com` from memory. Anything it does not
+/// emulate gets a 404 naming the endpoint.
+pub struct SandboxTransport {
+    state: Mutex<State>,
+}
+
+impl SandboxTransport {
+    pub fn new() -> Self {
+        SandboxTransport {
+            state: Mutex::new(State {
+                last_id: 0,
+                tweets: BTreeMap::new(),
+                rules: Vec::new(),
+            }),
+        }
+    }
+
+    fn route(&self, req: &Request) -> Response {
+        let Some(rest) = req.url.strip_prefix(API_ORIGIN) else {
+            return not_emulated(req);
+        };
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let mut state = self.state.lock().unwrap();
+
+        match (&req.method, segments.as_slice()) {
+            (&Method::POST, ["2", "tweets"]) => {
+                let body: Value = match serde_json::from_str(&req.body_text()) {
+                    Ok(v) => v,
+                    Err(e) => return error(400, &format!("Invalid JSON body: {e}")),
+                };
+                // X takes a tweet without text if it has media.
+                let Some(text) = body["text"]
+                    .as_str()
+                    .or(body["media"].is_object().then_some(""))
+                else {
+                    return error(400, "The `text` field is required");
+                };
+                let id = state.next_id();
+                let tweet = json!({
+                    "id": id,
+                    "text": text,
+                    "author_id": SANDBOX_USER_ID,
+                    "created_at": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                    "public_metrics": {},
+                });
+                state.tweets.insert(id.clone(), tweet);
+                ok(201, json!({"data": {"id": id, "text": text}}))
+            }
+            (&Method::DELETE, ["2", "tweets", id]) => {
+                state.tweets.remove(*id);
+                ok(200, json!({"data": {"deleted": true}}))
+            }
+            (&Method::GET, ["2", "tweets", "search", "recent" | "all"]) => ok(200, empty_page()),
+            // Every list holds just the sandbox account.
+            (&Method::GET, ["2", "lists", _, "tweets"]) => {
+                let tweets: Vec<&Value> = state.tweets.values().rev().collect();
+                ok(
+                    200,
+                    json!({
+                        "data": tweets,
+                        "includes": {"users": [sandbox_user()]},
+                        "meta": {"result_count": tweets.len()},
+                    }),
+                )
+            }
+            // A stream that sends a keep-alive and closes.
+            (&Method::GET, ["2", "tweets", "search", "stream"]) => Response::new(200, "\r\n"),
+            (&Method::GET, ["2", "tweets", "search", "stream", "rules"]) => {
+                ok(200, json!({"data": state.rules}))
+            }
+            (&Method::POST, ["2", "tweets", "search", "stream", "rules"]) => {
+                let body: Value = serde_json::from_str(&req.body_text()).unwrap_or_default();
+                if let Some(ids) = body["delete"]["ids"].as_array() {
+                    let before = state.rules.len();
+                    state.rules.retain(|rule| !ids.contains(&rule["id"]));
+                    let deleted = before - state.rules.len();
+                    return ok(
+                        200,
+                        json!({"meta": {"summary": {"deleted": deleted, "not_deleted": ids.len() - deleted}}}),
+                    );
+                }
+                let dry_run = query.split('&').any(|p| p == "dry_run=true");
+                let mut added = Vec::new();
+                for rule in body["add"].as_array().cloned().unwrap_or_default() {
+                    let mut rule = rule;
+                    rule["id"] = json!(state.next_id());
+                    added.push(rule);
+                }
+                if !dry_run {
+                    state.rules.extend(added.iter().cloned());
+                }
+                ok(201, json!({"data": added}))
+            }
+            (&Method::GET, ["2", "tweets"]) => {
+                let ids = query_param(query, "ids").unwrap_or_default();
+                let tweets: Vec<&Value> = ids
+                    .split(',')
+                    .filter_map(|id| state.tweets.get(id))
+                    .collect();
+                ok(200, json!({"data": tweets}))
+            }
+            (&Method::GET, ["2", "tweets", id]) => match state.tweets.get(*id) {
+                Some(tweet) => ok(200, json!({"data": tweet})),
+                None => error(404, &format!("Could not find tweet with id: [{id}].")),
+            },
+            (&Method::GET, ["2", "usage", "tweets"]) => ok(
+                200,
+                json!({"data": {
+                    "cap_reset_day": 1,
+                    "project_id": "1",
+                    "project_cap": "10000",
+                    "project_usage": "0",
+                    "daily_project_usage": {"project_id": "1", "usage": []},
+                }}),
+            ),
+            (&Method::GET, ["2", "spaces", "search"]) => {
+                ok(200, json!({"meta": {"result_count": 0}}))
+            }
+            (&Method::GET, ["2", "spaces", id]) => ok(
+                200,
+                json!({
+                    "data": {
+                        "id": id,
+                        "state": "live",
+                        "title": "Sandbox Space",
+                        "host_ids": [SANDBOX_USER_ID],
+                        "started_at": "2024-06-01T12:00:00.000Z",
+                        "participant_count": 3,
+                    },
+                    "includes": {"users": [sandbox_user()]},
+                }),
+            ),
+            (&Method::GET, ["2", "users", "me"]) => {
+                ok(200, json!({"data": sandbox_user()})).with_header("x-access-level", "read-write")
+            }
+            (&Method::GET, ["2", "users", "by", "username", name]) => ok(
+                200,
+                json!({"data": {
+                    "id": "2000",
+                    "username": name,
+                    "name": name,
+                    "created_at": "2010-11-04T00:00:00.000Z",
+                    "verified_type": "none",
+                    "public_metrics": {"followers_count": 0, "following_count": 0, "tweet_count": 0, "listed_count": 0},
+                }}),
+            ),
+            (&Method::GET, ["2", "users", "by"]) => {
+                let users: Vec<Value> = query_param(query, "usernames")
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|u| !u.is_empty())
+                    .enumerate()
+                    .map(|(i, name)| {
+                        json!({"id": (2000 + i).to_string(), "username": name, "name": name})
+                    })
+                    .collect();
+                ok(200, json!({"data": users}))
+            }
+            (
+                &Method::GET,
+                ["2", "users", _, "tweets" | "mentions" | "followers" | "following" | "blocking" | "muting"
+                | "liked_tweets"],
+            ) => ok(200, empty_page()),
+            (&Method::POST, ["2", "users", _, "blocking"]) => {
+                ok(200, json!({"data": {"blocking": true}}))
+            }
+            (&Method::POST, ["2", "users", _, "muting"]) => {
+                ok(200, json!({"data": {"muting": true}}))
+            }
+            (&Method::POST, ["2", "users", _, "following"]) => ok(
+                200,
+                json!({"data": {"following": true, "pending_follow": false}}),
+            ),
+            (&Method::DELETE, ["2", "users", _, "following", _]) => {
+                ok(200, json!({"data": {"following": false}}))
+            }
+            (&Method::POST, ["2", "users", _, "followed_lists"]) => {
+                ok(200, json!({"data": {"following": true}}))
+            }
+            (&Method::DELETE, ["2", "users", _, "followed_lists", _]) => {
+                ok(200, json!({"data": {"following": false}}))
+            }
+            (&Method::POST, ["2", "lists"]) => {
+                let body: Value = serde_json::from_str(&req.body_text()).unwrap_or_default();
+                let name = body["name"].as_str().unwrap_or_default();
+                ok(201, json!({"data": {"id": state.next_id(), "name": name}}))
+            }
+            (&Method::PUT, ["2", "lists", _]) => ok(200, json!({"data": {"updated": true}})),
+            (&Method::DELETE, ["2", "lists", _]) => ok(200, json!({"data": {"deleted": true}})),
+            (&Method::POST, ["2", "lists", _, "members"]) => {
+                ok(200, json!({"data": {"is_member": true}}))
+            }
+            (&Method::DELETE, ["2", "lists", _, "members", _]) => {
+                ok(200, json!({"data": {"is_member": false}}))
+            }
+            (&Method::POST, ["2", "users", _, "pinned_lists"]) => {
+                ok(200, json!({"data": {"pinned": true}}))
+            }
+            (&Method::POST, ["2", "media", "upload"]) => {
+                ok(200, json!({"data": {"id": state.next_id()}}))
+            }
+            (&Method::POST, ["2", "media", "upload", "initialize"]) => ok(
+                200,
+                json!({"data": {"id": state.next_id(), "expires_after_secs": 86400}}),
+            ),
+            (&Method::POST, ["2", "media", "upload", _, "append"]) => ok(200, json!({"data": {}})),
+            (&Method::POST, ["2", "media", "upload", id, "finalize"]) => ok(
+                200,
+                json!({"data": {"id": id, "processing_info": {"state": "succeeded"}}}),
+            ),
+            (&Method::GET, ["2", "media", "upload"]) => ok(
+                200,
+                json!({"data": {
+                    "id": query_param(query, "media_id"),
+                    "processing_info": {"state": "succeeded"},
+                }}),
+            ),
+            (&Method::POST, ["2", "media", "subtitles"]) => {
+                ok(200, json!({"data": {"associated": true}}))
+            }
+            (&Method::POST, ["2", "media", "metadata"]) => {
+                ok(200, json!({"data": {"associated": true}}))
+            }
+            (&Method::POST, ["oauth2", "token"]) => ok(
+                200,
+                json!({"token_type": "bearer", "access_token": "sandbox-bearer"}),
+            ),
+            _ => not_emulated(req),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for SandboxTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        Ok(self.route(&req))
+    }
+}
+
+fn sandbox_user() -> Value {
+    json!({"id": SANDBOX_USER_ID, "username": SANDBOX_USERNAME, "name": "xcli sandbox"})
+}
+
+fn empty_page() -> Value {
+    json!({"meta": {"result_count": 0}})
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == name).then(|| v.replace("%2C", ","))
+    })
+}
+
+fn ok(status: u16, body: Value) -> Response {
+    Response::new(status, body.to_string()).with_header("content-type", "application/json")
+}
+
+fn error(status: u16, detail: &str) -> Response {
+    ok(status, json!({"title": "Sandbox error", "detail": detail}))
+}
+
+fn not_emulated(req: &Request) -> Response {
+    error(
+        404,
+        &format!("{} {} is not emulated in sandbox mode", req.method, req.url),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XClient;
+    use std::sync::Arc;
+
+    fn client() -> XClient {
+        XClient::with_transport(config(), Arc::new(SandboxTransport::new()))
+    }
+
+    #[tokio::test]
+    async fn posts_and_deletes_tweets() {
+        let client = client();
+        let a = client.tweet("hello").await.unwrap();
+        let b = client.reply(&a, "again").await.unwrap();
+        assert_ne!(a, b);
+        assert!(a.len() >= 19);
+
+        let tweet = crate::api::get_tweet(&client, &a).await.unwrap().data;
+        assert_eq!(tweet.text, "hello");
+
+        client.delete(&a).await.unwrap();
+        assert!(crate::api::get_tweet(&client, &a).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn me_and_thread() {
+        let client = client();
+        assert_eq!(client.me().await.unwrap().username, SANDBOX_USERNAME);
+        let ids = client
+            .thread(&["one".to_string(), "two".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_endpoints_are_404() {
+        let resp = SandboxTransport::new()
+            .send(Request::new(Method::GET, "https://api.x.com/2/spaces"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status.as_u16(), 404);
+        assert!(resp.text().contains("not emulated"));
+    }
+}
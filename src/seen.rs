@@ -0,0 +1,70 @@
+//! `--new-only`: the newest tweet each read (your mentions, an account's
+//! timeline, a search query) printed last time, kept in `seen.json` so the
+//! next run (from cron, say) prints only what came after it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The newest tweet ID printed, by read (see `timeline_key` and
+/// `search_key`).
+pub type Seen = BTreeMap<String, String>;
+
+/// The read of the tweets of (`endpoint` is `tweets`) or mentioning
+/// (`mentions`) the account `user_id`.
+pub fn timeline_key(endpoint: &str, user_id: &str) -> String {
+    format!("{endpoint}:{user_id}")
+}
+
+pub fn search_key(query: &str) -> String {
+    format!("search:{query}")
+}
+
+/// Remember `id` as the newest of `key` if it is newer than the last.
+pub fn record(seen: &mut Seen, key: &str, id: &str) {
+    // IDs grow over time; compare them as numbers.
+    let newer = seen
+        .get(key)
+        .is_none_or(|last| (id.len(), id) > (last.len(), last.as_str()));
+    if newer {
+        seen.insert(key.to_string(), id.to_string());
+    }
+}
+
+/// What is saved at `path`, or nothing.
+pub fn load(path: &Path) -> Seen {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(path: &Path, seen: &Seen) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string_pretty(seen).expect("seen IDs serialize to JSON");
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    #[test]
+    fn keeps_the_newest_id_of_each_read() {
+        let path = temp_dir().join(format!("xcli_test_{}_seen.json", std::process::id()));
+        let mut seen = load(&path);
+        let mentions = timeline_key("mentions", "7");
+        record(&mut seen, &mentions, "99");
+        record(&mut seen, &mentions, "100");
+        record(&mut seen, &mentions, "98");
+        record(&mut seen, &search_key("rust"), "5");
+        assert_eq!(seen.get(&mentions).map(String::as_str), Some("100"));
+        assert_eq!(seen.get("search:rust").map(String::as_str), Some("5"));
+        save(&path, &seen).unwrap();
+        assert_eq!(load(&path), seen);
+        fs::remove_file(&path).unwrap();
+    }
+}
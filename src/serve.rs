@@ -0,0 +1,376 @@
+//! `xcli serve`: a long-running daemon that answers JSON-RPC 2.0 over a unix
+//! socket, one request per line, keeping credentials and connections warm.
+//!
+//! Methods:
+//! - `post` `{"text", "reply_to"?}` → `{"ids": [...]}` (long text is threaded)
+//! - `delete` `{"id"}` → `{"deleted": true}`
+//! - `search` `{"query", "max_results"?}` → `{"tweets": [...]}`
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::client::XClient;
+use crate::store::{self, Store};
+use crate::{api, applog, thread};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any failure of the requested operation itself (API errors included).
+const OPERATION_FAILED: i64 = -32000;
+
+const DEFAULT_SEARCH_RESULTS: usize = 10;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: Option<String>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct PostParams {
+    text: String,
+    reply_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DeleteParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+    max_results: Option<usize>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+fn params<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, RpcError> {
+    serde_json::from_value(value).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, err: RpcError) -> String {
+    json!({"jsonrpc": "2.0", "error": {"code": err.code, "message": err.message}, "id": id})
+        .to_string()
+}
+
+/// Executes JSON-RPC requests against one account.
+pub struct Server {
+    client: XClient,
+    /// Store that posts are recorded in, like `xcli tweet` does.
+    history: Option<PathBuf>,
+}
+
+impl Server {
+    pub fn new(client: XClient, history: Option<PathBuf>) -> Self {
+        Server { client, history }
+    }
+
+    /// Handle one line of input. Returns the response line, or `None` for
+    /// notifications (requests without an `id`).
+    pub async fn handle_line(&self, line: &str) -> Option<String> {
+        let request: RpcRequest = match serde_json::from_str(line) {
+            Ok(r) => r,
+            Err(e) => {
+                let code = if serde_json::from_str::<Value>(line).is_ok() {
+                    INVALID_REQUEST
+                } else {
+                    PARSE_ERROR
+                };
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(code, e.to_string()),
+                ));
+            }
+        };
+        if request.jsonrpc.as_deref() != Some("2.0") {
+            let id = request.id.unwrap_or(Value::Null);
+            let err = RpcError::new(INVALID_REQUEST, "Expected \"jsonrpc\": \"2.0\"");
+            return Some(error_response(id, err));
+        }
+
+        let result = self.dispatch(&request.method, request.params).await;
+        if let Err(err) = &result {
+            applog::error(&format!(
+                "serve: {} failed: {}",
+                request.method, err.message
+            ));
+        }
+        let id = request.id?;
+        Some(match result {
+            Ok(value) => json!({"jsonrpc": "2.0", "result": value, "id": id}).to_string(),
+            Err(err) => error_response(id, err),
+        })
+    }
+
+    async fn dispatch(&self, method: &str, params_value: Value) -> Result<Value, RpcError> {
+        match method {
+            "post" => self.post(params(params_value)?).await,
+            "delete" => {
+                let p: DeleteParams = params(params_value)?;
+                let deleted = self
+                    .client
+                    .delete(&p.id)
+                    .await
+                    .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+                if !deleted {
+                    return Err(RpcError::new(
+                        OPERATION_FAILED,
+                        format!("X did not delete tweet {}", p.id),
+                    ));
+                }
+                if let Some(path) = &self.history {
+                    let _ = Store::open_at(path).and_then(|s| s.forget_tweet(&p.id));
+                }
+                Ok(json!({"deleted": true}))
+            }
+            "search" => {
+                let p: SearchParams = params(params_value)?;
+                let max = p.max_results.unwrap_or(DEFAULT_SEARCH_RESULTS);
+                let tweets = self
+                    .client
+                    .search(&p.query, max)
+                    .await
+                    .map_err(|e| RpcError::new(OPERATION_FAILED, e))?;
+                Ok(json!({ "tweets": tweets }))
+            }
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method `{other}` (expected post, delete or search)"),
+            )),
+        }
+    }
+
+    async fn post(&self, p: PostParams) -> Result<Value, RpcError> {
+        let chunks = thread::split_text(&p.text);
+        if let Err((idx, len)) = thread::validate_chunks(&chunks) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Chunk {} exceeds 280 characters ({len}/280)", idx + 1),
+            ));
+        }
+
+        let reply_to = p.reply_to.as_deref();
+        let result = match reply_to {
+            Some(parent) => api::create_reply_thread(&self.client, parent, &chunks).await,
+            None => api::create_thread(&self.client, &chunks).await,
+        };
+        let (ids, error) = match result {
+            Ok(posted) => (api::ids(&posted), None),
+            Err(e) => (e.posted_ids, Some(e.error)),
+        };
+        self.record(reply_to, &ids, &chunks);
+
+        match error {
+            None => Ok(json!({ "ids": ids })),
+            Some(e) if ids.is_empty() => Err(RpcError::new(OPERATION_FAILED, e)),
+            Some(e) => Err(RpcError::new(
+                OPERATION_FAILED,
+                format!(
+                    "Thread failed after {} of {} tweets ({}): {e}",
+                    ids.len(),
+                    chunks.len(),
+                    ids.join(", ")
+                ),
+            )),
+        }
+    }
+
+    fn record(&self, in_reply_to: Option<&str>, ids: &[String], chunks: &[String]) {
+        let Some(path) = &self.history else {
+            return;
+        };
+        let account = self.client.config().screen_name.as_deref().unwrap_or("");
+        let result = Store::open_at(path)
+            .and_then(|s| s.record_post(account, in_reply_to, ids, chunks, store::now()));
+        if let Err(e) = result {
+            eprintln!("Warning: could not record history: {e}");
+        }
+    }
+}
+
+/// Accept connections on `socket` until Ctrl-C, serving each one
+/// concurrently. The socket is only accessible to the current user.
+#[cfg(unix)]
+pub async fn run(server: Server, socket: &std::path::Path) -> Result<(), String> {
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    if socket.exists() {
+        if UnixStream::connect(socket).await.is_ok() {
+            return Err(format!(
+                "Another xcli serve is already listening on {}",
+                socket.display()
+            ));
+        }
+        // Left behind by a daemon that did not shut down cleanly.
+        std::fs::remove_file(socket)
+            .map_err(|e| format!("Failed to remove stale {}: {e}", socket.display()))?;
+    }
+    // Created 0600 rather than restricted after `bind`, so it is never open
+    // to other users, not even for a moment.
+    let umask = unsafe { libc::umask(0o177) };
+    let bound = UnixListener::bind(socket);
+    unsafe { libc::umask(umask) };
+    let listener = bound.map_err(|e| format!("Failed to listen on {}: {e}", socket.display()))?;
+
+    let server = Arc::new(server);
+    let result = loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => break Err(format!("Failed to accept connection: {e}")),
+            },
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        };
+        let server = Arc::clone(&server);
+        tokio::spawn(async move {
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Some(mut response) = server.handle_line(&line).await {
+                    response.push('\n');
+                    if write.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    };
+    let _ = std::fs::remove_file(socket);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::transport::MockTransport;
+    use std::sync::Arc;
+
+    fn server(mock: &Arc<MockTransport>) -> Server {
+        let config = Config {
+            api_key: "k".to_string(),
+            api_secret: "s".to_string(),
+            access_token: "t".to_string(),
+            access_token_secret: "ts".to_string(),
+            screen_name: None,
+        };
+        Server::new(XClient::with_transport(config, mock.clone()), None)
+    }
+
+    async fn call(server: &Server, request: Value) -> Value {
+        let line = server.handle_line(&request.to_string()).await.unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn post_returns_ids() {
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(201, r#"{"data":{"id":"10"}}"#);
+        let resp = call(
+            &server(&mock),
+            json!({"jsonrpc": "2.0", "method": "post", "params": {"text": "hi"}, "id": 1}),
+        )
+        .await;
+        assert_eq!(resp["result"]["ids"], json!(["10"]));
+        assert_eq!(resp["id"], json!(1));
+    }
+
+    #[tokio::test]
+    async fn api_failures_are_rpc_errors() {
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(403, "forbidden");
+        let resp = call(
+            &server(&mock),
+            json!({"jsonrpc": "2.0", "method": "delete", "params": {"id": "5"}, "id": "a"}),
+        )
+        .await;
+        assert_eq!(resp["error"]["code"], json!(OPERATION_FAILED));
+        assert!(resp["error"]["message"].as_str().unwrap().contains("403"));
+
+        mock.respond(200, r#"{"data":{"deleted":false}}"#);
+        let resp = call(
+            &server(&mock),
+            json!({"jsonrpc": "2.0", "method": "delete", "params": {"id": "5"}, "id": "b"}),
+        )
+        .await;
+        assert_eq!(resp["error"]["code"], json!(OPERATION_FAILED));
+        assert_eq!(resp["error"]["message"], json!("X did not delete tweet 5"));
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_requests() {
+        let mock = Arc::new(MockTransport::new());
+        let server = server(&mock);
+
+        let resp: Value =
+            serde_json::from_str(&server.handle_line("{not json").await.unwrap()).unwrap();
+        assert_eq!(resp["error"]["code"], json!(PARSE_ERROR));
+
+        let resp = call(
+            &server,
+            json!({"jsonrpc": "2.0", "method": "follow", "id": 2}),
+        )
+        .await;
+        assert_eq!(resp["error"]["code"], json!(METHOD_NOT_FOUND));
+
+        let resp = call(
+            &server,
+            json!({"jsonrpc": "2.0", "method": "post", "params": {}, "id": 3}),
+        )
+        .await;
+        assert_eq!(resp["error"]["code"], json!(INVALID_PARAMS));
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn notifications_get_no_response() {
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(200, r#"{"data":{"deleted":true}}"#);
+        let line = json!({"jsonrpc": "2.0", "method": "delete", "params": {"id": "5"}});
+        assert!(server(&mock).handle_line(&line.to_string()).await.is_none());
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn socket_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let socket = std::env::temp_dir().join(format!("xcli_test_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let mock = Arc::new(MockTransport::new());
+        let (daemon_server, path) = (server(&mock), socket.clone());
+        let daemon = tokio::spawn(async move { run(daemon_server, &path).await });
+        while !socket.exists() {
+            tokio::task::yield_now().await;
+        }
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        daemon.abort();
+        let _ = std::fs::remove_file(&socket);
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::DEFAULT_PROFILE;
+
+/// One runner for the queue, whatever the profile: the queue (like the
+/// account and the lock that guards it) is shared by every profile.
+pub const SYSTEMD_UNIT_NAME: &str = "xcli-queue.service";
+pub const LAUNCHD_LABEL: &str = "com.xcli.queue";
+
+/// What the background queue runner needs to behave exactly like the
+/// `xcli queue run` the user would start by hand.
+pub struct ServiceSpec {
+    pub exe: PathBuf,
+    pub interval: u64,
+    /// xcli reads `.env` from the working directory, so this pins the account.
+    pub working_dir: PathBuf,
+    /// Environment to carry over, e.g. `XDG_CONFIG_HOME`.
+    pub env: Vec<(String, String)>,
+    pub account: Option<String>,
+    /// Profile of config.toml whose settings (retention, hooks) apply.
+    pub profile: String,
+}
+
+impl ServiceSpec {
+    fn args(&self) -> Vec<String> {
+        vec![
+            self.exe.display().to_string(),
+            "--profile".to_string(),
+            self.profile.clone(),
+            "queue".to_string(),
+            "run".to_string(),
+            "--interval".to_string(),
+            self.interval.to_string(),
+        ]
+    }
+
+    fn description(&self) -> String {
+        let mut about = Vec::new();
+        if let Some(account) = &self.account {
+            about.push(format!("@{account}"));
+        }
+        if self.profile != DEFAULT_PROFILE {
+            about.push(format!("profile {}", self.profile));
+        }
+        if about.is_empty() {
+            "xcli queue runner".to_string()
+        } else {
+            format!("xcli queue runner ({})", about.join(", "))
+        }
+    }
+}
+
+/// `~/.config/systemd/user/xcli-queue.service`
+pub fn systemd_unit_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("systemd/user").join(SYSTEMD_UNIT_NAME))
+}
+
+/// `~/Library/LaunchAgents/com.xcli.queue.plist`
+pub fn launchd_plist_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|d| {
+        d.join("Library/LaunchAgents")
+            .join(format!("{LAUNCHD_LABEL}.plist"))
+    })
+}
+
+/// Quote a value for systemd unit files when it contains spaces or quotes.
+fn systemd_quote(value: &str) -> String {
+    if value.is_empty() || value.contains([' ', '"', '\\', '\'']) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn systemd_unit(spec: &ServiceSpec) -> String {
+    let exec = spec
+        .args()
+        .iter()
+        .map(|a| systemd_quote(a))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut out = format!(
+        "[Unit]\nDescription={}\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nType=simple\nExecStart={exec}\nWorkingDirectory={}\n",
+        spec.description(),
+        systemd_quote(&spec.working_dir.display().to_string())
+    );
+    for (key, value) in &spec.env {
+        out.push_str(&format!(
+            "Environment={}\n",
+            systemd_quote(&format!("{key}={value}"))
+        ));
+    }
+    out.push_str("Restart=on-failure\nRestartSec=30\n\n[Install]\nWantedBy=default.target\n");
+    out
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn launchd_plist(spec: &ServiceSpec, log_dir: &Path) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist version=\"1.0\">\n<dict>\n",
+    );
+    out.push_str(&format!(
+        "  <key>Label</key>\n  <string>{LAUNCHD_LABEL}</string>\n  <key>ProgramArguments</key>\n  <array>\n"
+    ));
+    for arg in spec.args() {
+        out.push_str(&format!("    <string>{}</string>\n", xml_escape(&arg)));
+    }
+    out.push_str("  </array>\n");
+    out.push_str(&format!(
+        "  <key>WorkingDirectory</key>\n  <string>{}</string>\n",
+        xml_escape(&spec.working_dir.display().to_string())
+    ));
+    if !spec.env.is_empty() {
+        out.push_str("  <key>EnvironmentVariables</key>\n  <dict>\n");
+        for (key, value) in &spec.env {
+            out.push_str(&format!(
+                "    <key>{}</key>\n    <string>{}</string>\n",
+                xml_escape(key),
+                xml_escape(value)
+            ));
+        }
+        out.push_str("  </dict>\n");
+    }
+    out.push_str(&format!(
+        "  <key>RunAtLoad</key>\n  <true/>\n  <key>KeepAlive</key>\n  <true/>\n  <key>StandardOutPath</key>\n  <string>{}</string>\n  <key>StandardErrorPath</key>\n  <string>{}</string>\n",
+        xml_escape(&log_dir.join("queue.log").display().to_string()),
+        xml_escape(&log_dir.join("queue.err.log").display().to_string())
+    ));
+    out.push_str("</dict>\n</plist>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            exe: PathBuf::from("/usr/local/bin/xcli"),
+            interval: 60,
+            working_dir: PathBuf::from("/home/me/my bot"),
+            env: vec![("XDG_CONFIG_HOME".to_string(), "/home/me/.cfg".to_string())],
+            account: Some("mybot".to_string()),
+            profile: DEFAULT_PROFILE.to_string(),
+        }
+    }
+
+    #[test]
+    fn systemd_unit_runs_queue() {
+        let unit = systemd_unit(&spec());
+        assert!(unit.contains("Description=xcli queue runner (@mybot)\n"));
+        assert!(unit
+            .contains("ExecStart=/usr/local/bin/xcli --profile default queue run --interval 60\n"));
+        assert!(unit.contains("WorkingDirectory=\"/home/me/my bot\"\n"));
+        assert!(unit.contains("Environment=XDG_CONFIG_HOME=/home/me/.cfg\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+
+    #[test]
+    fn service_runs_with_its_profile() {
+        let mut spec = spec();
+        spec.profile = "work".to_string();
+        let unit = systemd_unit(&spec);
+        assert!(unit.contains("Description=xcli queue runner (@mybot, profile work)\n"));
+        assert!(
+            unit.contains("ExecStart=/usr/local/bin/xcli --profile work queue run --interval 60\n")
+        );
+
+        // Still the one runner of the queue, under the usual label.
+        let plist = launchd_plist(&spec, Path::new("/logs"));
+        assert!(plist.contains("<string>com.xcli.queue</string>"));
+        assert!(plist.contains("    <string>--profile</string>\n    <string>work</string>\n"));
+        assert!(plist.contains("<string>/logs/queue.log</string>"));
+    }
+
+    #[test]
+    fn systemd_quotes_values() {
+        assert_eq!(systemd_quote("/usr/bin/xcli"), "/usr/bin/xcli");
+        assert_eq!(systemd_quote("/a b/xcli"), "\"/a b/xcli\"");
+        assert_eq!(systemd_quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn launchd_plist_escapes_and_lists_args() {
+        let mut spec = spec();
+        spec.working_dir = PathBuf::from("/Users/me/R&D");
+        let plist = launchd_plist(&spec, Path::new("/Users/me/Library/Logs/xcli"));
+        assert!(plist.contains("<string>com.xcli.queue</string>"));
+        assert!(plist.contains(
+            "    <string>/usr/local/bin/xcli</string>\n    <string>--profile</string>\n    <string>default</string>\n    <string>queue</string>\n    <string>run</string>\n    <string>--interval</string>\n    <string>60</string>\n"
+        ));
+        assert!(plist.contains("<string>/Users/me/R&amp;D</string>"));
+        assert!(plist.contains("<key>XDG_CONFIG_HOME</key>"));
+        assert!(plist.contains("<string>/Users/me/Library/Logs/xcli/queue.log</string>"));
+    }
+}
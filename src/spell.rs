@@ -0,0 +1,117 @@
+//! Spellchecking for `--dry-run` previews, with the profile's
+//! `[spellcheck]` table: a command reads the text on stdin and prints the
+//! words it does not know, one per line, like `hunspell -l` (the default) or
+//! `aspell list`.
+
+use std::io::Write;
+use std::process::Stdio;
+
+use regex::Regex;
+
+use crate::config::Spellcheck;
+use crate::hooks::shell;
+
+/// The command `config` runs: its own, or hunspell with the dictionary of
+/// its language.
+pub fn command(config: &Spellcheck) -> String {
+    match (&config.command, &config.language) {
+        (Some(command), _) => command.clone(),
+        (None, Some(language)) => format!("hunspell -l -d {language}"),
+        (None, None) => "hunspell -l".to_string(),
+    }
+}
+
+/// The words of `text` the spellchecker flags, each once, in order. The
+/// language is in `XCLI_SPELLCHECK_LANG` for commands of one's own.
+pub fn misspelled(config: &Spellcheck, text: &str) -> Result<Vec<String>, String> {
+    let command = command(config);
+    let mut child = shell(&command)
+        .env(
+            "XCLI_SPELLCHECK_LANG",
+            config.language.as_deref().unwrap_or(""),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run `{command}`: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run `{command}`: {e}"))?;
+    if !output.status.success() {
+        let code = output
+            .status
+            .code()
+            .map_or("killed".to_string(), |c| format!("exit code {c}"));
+        return Err(format!("`{command}` failed ({code})"));
+    }
+    let mut words: Vec<String> = Vec::new();
+    for word in String::from_utf8_lossy(&output.stdout).lines() {
+        let word = word.trim();
+        if !word.is_empty() && !words.iter().any(|w| w == word) {
+            words.push(word.to_string());
+        }
+    }
+    Ok(words)
+}
+
+/// `text` with every whole-word occurrence of `words` underlined with
+/// terminal escapes.
+pub fn underline(text: &str, words: &[String]) -> String {
+    if words.is_empty() {
+        return text.to_string();
+    }
+    let alternatives: Vec<String> = words.iter().map(|w| regex::escape(w)).collect();
+    let re = Regex::new(&format!(r"\b(?:{})\b", alternatives.join("|"))).unwrap();
+    re.replace_all(text, "\x1b[4m$0\x1b[24m").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn underlines_whole_words() {
+        let words = vec!["teh".to_string(), "recieve".to_string()];
+        assert_eq!(
+            underline("teh cat will recieve tehran", &words),
+            "\x1b[4mteh\x1b[24m cat will \x1b[4mrecieve\x1b[24m tehran"
+        );
+        assert_eq!(underline("fine", &[]), "fine");
+    }
+
+    #[test]
+    fn picks_the_command() {
+        let mut config = Spellcheck::default();
+        assert_eq!(command(&config), "hunspell -l");
+        config.language = Some("de_DE".to_string());
+        assert_eq!(command(&config), "hunspell -l -d de_DE");
+        config.command = Some("aspell list".to_string());
+        assert_eq!(command(&config), "aspell list");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lists_flagged_words_once() {
+        let config = Spellcheck {
+            language: Some("en".to_string()),
+            command: Some(
+                r#"tr ' ' '\n' | grep -x 'teh'; echo "$XCLI_SPELLCHECK_LANG""#.to_string(),
+            ),
+        };
+        assert_eq!(
+            misspelled(&config, "teh cat and teh dog").unwrap(),
+            ["teh", "en"]
+        );
+        let failing = Spellcheck {
+            command: Some("exit 2".to_string()),
+            ..Spellcheck::default()
+        };
+        assert!(misspelled(&failing, "x")
+            .unwrap_err()
+            .contains("exit code 2"));
+    }
+}
@@ -0,0 +1,555 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{Datelike, Local, NaiveDate, TimeZone, Timelike};
+use serde::Serialize;
+
+use crate::api::{PublicMetrics, Tweet};
+use crate::{api, export, store};
+
+#[derive(Serialize)]
+pub struct Summary {
+    pub tweets: usize,
+    pub totals: PublicMetrics,
+    pub averages: Averages,
+    pub top: Vec<Tweet>,
+}
+
+#[derive(Serialize, Default, PartialEq, Debug)]
+pub struct Averages {
+    pub impressions: f64,
+    pub likes: f64,
+    pub reposts: f64,
+    pub replies: f64,
+    pub quotes: f64,
+}
+
+/// Likes + reposts + replies + quotes. Used to rank top posts.
+pub fn engagement(m: &PublicMetrics) -> u64 {
+    m.like_count + m.retweet_count + m.reply_count + m.quote_count
+}
+
+/// Totals, per-tweet averages, and the `top_n` most engaging tweets.
+pub fn summarize(tweets: &[Tweet], top_n: usize) -> Summary {
+    let mut totals = PublicMetrics::default();
+    for m in tweets.iter().filter_map(|t| t.public_metrics.as_ref()) {
+        totals.impression_count += m.impression_count;
+        totals.like_count += m.like_count;
+        totals.retweet_count += m.retweet_count;
+        totals.reply_count += m.reply_count;
+        totals.quote_count += m.quote_count;
+        totals.bookmark_count += m.bookmark_count;
+    }
+
+    let averages = if tweets.is_empty() {
+        Averages::default()
+    } else {
+        let n = tweets.len() as f64;
+        Averages {
+            impressions: totals.impression_count as f64 / n,
+            likes: totals.like_count as f64 / n,
+            reposts: totals.retweet_count as f64 / n,
+            replies: totals.reply_count as f64 / n,
+            quotes: totals.quote_count as f64 / n,
+        }
+    };
+
+    let mut ranked: Vec<&Tweet> = tweets.iter().collect();
+    ranked
+        .sort_by_key(|t| std::cmp::Reverse(t.public_metrics.as_ref().map(engagement).unwrap_or(0)));
+    let top = ranked.into_iter().take(top_n).cloned().collect();
+
+    Summary {
+        tweets: tweets.len(),
+        totals,
+        averages,
+        top,
+    }
+}
+
+/// One line of metrics, with the change since `prev` when given, e.g.
+/// `impressions 1200 (+150)  likes 30 (+4)  ...`.
+pub fn format_metrics(cur: &PublicMetrics, prev: Option<&PublicMetrics>) -> String {
+    let prev = prev.copied();
+    let fields = [
+        (
+            "impressions",
+            cur.impression_count,
+            prev.map(|p| p.impression_count),
+        ),
+        ("likes", cur.like_count, prev.map(|p| p.like_count)),
+        ("reposts", cur.retweet_count, prev.map(|p| p.retweet_count)),
+        ("replies", cur.reply_count, prev.map(|p| p.reply_count)),
+        ("quotes", cur.quote_count, prev.map(|p| p.quote_count)),
+    ];
+    fields
+        .iter()
+        .map(|(name, now, before)| match before {
+            Some(before) => format!("{name} {now} ({:+})", *now as i64 - *before as i64),
+            None => format!("{name} {now}"),
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const HEAT_LEVELS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Posts per local calendar day for the `days` days ending with `today`,
+/// oldest first.
+pub fn daily_counts(timestamps: &[i64], today: NaiveDate, days: usize) -> Vec<u32> {
+    let mut counts = vec![0; days];
+    for &ts in timestamps {
+        let Some(date) = Local
+            .timestamp_opt(ts, 0)
+            .single()
+            .map(|dt| dt.date_naive())
+        else {
+            continue;
+        };
+        let ago = (today - date).num_days();
+        if (0..days as i64).contains(&ago) {
+            counts[days - 1 - ago as usize] += 1;
+        }
+    }
+    counts
+}
+
+/// Scale `count` into `0..levels`, where 0 is kept for "none" when `zero_level`.
+fn level(count: u32, max: u32, levels: usize, zero_level: bool) -> usize {
+    if max == 0 || (zero_level && count == 0) {
+        return 0;
+    }
+    let (low, span) = if zero_level {
+        (1, levels - 1)
+    } else {
+        (0, levels)
+    };
+    low + ((count as usize * span).saturating_sub(1) / max as usize).min(span - 1)
+}
+
+/// One character per day, taller for busier days.
+pub fn sparkline(counts: &[u32]) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    counts
+        .iter()
+        .map(|&c| SPARK_LEVELS[level(c, max, SPARK_LEVELS.len(), false)])
+        .collect()
+}
+
+/// A GitHub-style grid: one row per weekday (Monday first), one column per
+/// week. `first_day` is the date of `counts[0]`.
+pub fn heatmap(counts: &[u32], first_day: NaiveDate) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    let offset = first_day.weekday().num_days_from_monday() as usize;
+    let weeks = (offset + counts.len()).div_ceil(7);
+
+    let mut out = String::new();
+    for (weekday, name) in WEEKDAYS.iter().enumerate() {
+        out.push_str(name);
+        out.push(' ');
+        for week in 0..weeks {
+            let cell = match (week * 7 + weekday).checked_sub(offset) {
+                Some(i) if i < counts.len() => {
+                    HEAT_LEVELS[level(counts[i], max, HEAT_LEVELS.len(), true)]
+                }
+                _ => ' ',
+            };
+            out.push(cell);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Longest run of consecutive days with at least one post, and the run
+/// ending on the last day.
+pub fn streaks(counts: &[u32]) -> (usize, usize) {
+    let mut longest = 0;
+    let mut current = 0;
+    for &c in counts {
+        current = if c > 0 { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    (longest, current)
+}
+
+/// Posts and their engagement by local weekday (Monday first) and hour.
+#[derive(Default)]
+pub struct PostingTimes {
+    posts: [[u32; 24]; 7],
+    engagement: [[u64; 24]; 7],
+}
+
+/// One weekday and hour, with the average engagement of its posts.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Slot {
+    pub weekday: &'static str,
+    pub hour: u32,
+    pub posts: u32,
+    pub average_engagement: f64,
+}
+
+impl PostingTimes {
+    /// Bucket posts given as (unix time, engagement).
+    pub fn from_posts(posts: &[(i64, u64)]) -> Self {
+        let mut times = PostingTimes::default();
+        for &(ts, engagement) in posts {
+            let Some(at) = Local.timestamp_opt(ts, 0).single() else {
+                continue;
+            };
+            let (day, hour) = (
+                at.weekday().num_days_from_monday() as usize,
+                at.hour() as usize,
+            );
+            times.posts[day][hour] += 1;
+            times.engagement[day][hour] += engagement;
+        }
+        times
+    }
+
+    /// Every weekday and hour with at least one post, Monday 0:00 first.
+    pub fn slots(&self) -> Vec<Slot> {
+        let mut slots = Vec::new();
+        for (day, name) in WEEKDAYS.iter().enumerate() {
+            for hour in 0..24 {
+                let posts = self.posts[day][hour];
+                if posts > 0 {
+                    slots.push(Slot {
+                        weekday: name,
+                        hour: hour as u32,
+                        posts,
+                        average_engagement: self.engagement[day][hour] as f64 / posts as f64,
+                    });
+                }
+            }
+        }
+        slots
+    }
+
+    /// The `n` slots with the highest average engagement among those with
+    /// at least `min_posts` posts, best first.
+    pub fn best(&self, n: usize, min_posts: u32) -> Vec<Slot> {
+        let mut slots: Vec<Slot> = self
+            .slots()
+            .into_iter()
+            .filter(|s| s.posts >= min_posts)
+            .collect();
+        slots.sort_by(|a, b| b.average_engagement.total_cmp(&a.average_engagement));
+        slots.truncate(n);
+        slots
+    }
+
+    /// Weekdays by hours, shaded by average engagement; hours without posts
+    /// are blank.
+    pub fn heatmap(&self) -> String {
+        // Tenths, so low averages still shade apart.
+        let average = |day: usize, hour: usize| match self.posts[day][hour] {
+            0 => None,
+            n => Some((self.engagement[day][hour] * 10 / n as u64) as u32),
+        };
+        let max = (0..7)
+            .flat_map(|day| (0..24).filter_map(move |hour| average(day, hour)))
+            .max()
+            .unwrap_or(0);
+        let mut out = String::from("    0     6     12    18   \n");
+        for (day, name) in WEEKDAYS.iter().enumerate() {
+            out.push_str(name);
+            out.push(' ');
+            for hour in 0..24 {
+                out.push(match average(day, hour) {
+                    Some(avg) => HEAT_LEVELS[level(avg, max, HEAT_LEVELS.len(), false)],
+                    None => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// The alert threshold a follower count moving from `baseline` to `current`
+/// reaches: a negative threshold is a loss of at least that many, a positive
+/// one a gain. The largest one reached wins.
+pub fn crossed(baseline: u64, current: u64, thresholds: &[i64]) -> Option<i64> {
+    let change = current as i64 - baseline as i64;
+    thresholds
+        .iter()
+        .copied()
+        .filter(|&t| (t < 0 && change <= t) || (t > 0 && change >= t))
+        .max_by_key(|t| t.abs())
+}
+
+/// Append a follower count of `handle` at `at` to the CSV log at `path`.
+pub fn log_followers(path: &Path, at: i64, handle: &str, count: u64) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let new = !path.exists();
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    let mut line = String::new();
+    if new {
+        line.push_str("time,handle,followers\n");
+    }
+    line.push_str(&format!("{},{handle},{count}\n", store::format_rfc3339(at)));
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Parse a tweet IDs file: one ID or tweet URL per line, `#` comments and
+/// blank lines ignored, duplicates dropped while keeping order.
+pub fn parse_tweet_ids(content: &str) -> Result<Vec<String>, String> {
+    let mut seen = HashSet::new();
+    let mut ids = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let id = api::parse_tweet_id(line).map_err(|e| format!("line {}: {e}", n + 1))?;
+        if seen.insert(id.clone()) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// The public metrics of `tweets` as CSV, one row per tweet.
+pub fn metrics_csv(tweets: &[Tweet]) -> String {
+    let mut out =
+        String::from("id,created_at,impressions,likes,reposts,replies,quotes,bookmarks,text\n");
+    for tweet in tweets {
+        let m = tweet.public_metrics.unwrap_or_default();
+        out.push_str(&export::csv_row(&[
+            tweet.id.clone(),
+            tweet.created_at.clone().unwrap_or_default(),
+            m.impression_count.to_string(),
+            m.like_count.to_string(),
+            m.retweet_count.to_string(),
+            m.reply_count.to_string(),
+            m.quote_count.to_string(),
+            m.bookmark_count.to_string(),
+            tweet.text.clone(),
+        ]));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: &str, likes: u64, retweets: u64, impressions: u64) -> Tweet {
+        Tweet {
+            id: id.to_string(),
+            text: format!("tweet {id}"),
+            created_at: None,
+            author_id: None,
+            conversation_id: None,
+            referenced_tweets: Vec::new(),
+            public_metrics: Some(PublicMetrics {
+                impression_count: impressions,
+                like_count: likes,
+                retweet_count: retweets,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn totals_and_averages() {
+        let tweets = vec![tweet("1", 10, 2, 100), tweet("2", 4, 0, 300)];
+        let summary = summarize(&tweets, 5);
+        assert_eq!(summary.tweets, 2);
+        assert_eq!(summary.totals.like_count, 14);
+        assert_eq!(summary.totals.retweet_count, 2);
+        assert_eq!(summary.totals.impression_count, 400);
+        assert_eq!(summary.averages.likes, 7.0);
+        assert_eq!(summary.averages.impressions, 200.0);
+    }
+
+    #[test]
+    fn top_posts_ranked_by_engagement() {
+        let tweets = vec![
+            tweet("low", 1, 0, 1000),
+            tweet("high", 10, 5, 10),
+            tweet("mid", 5, 0, 10),
+        ];
+        let summary = summarize(&tweets, 2);
+        let ids: Vec<_> = summary.top.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["high", "mid"]);
+    }
+
+    #[test]
+    fn metrics_delta_shows_signed_changes() {
+        let prev = PublicMetrics {
+            impression_count: 100,
+            like_count: 5,
+            ..Default::default()
+        };
+        let cur = PublicMetrics {
+            impression_count: 150,
+            like_count: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_metrics(&cur, Some(&prev)),
+            "impressions 150 (+50)  likes 4 (-1)  reposts 0 (+0)  replies 0 (+0)  quotes 0 (+0)"
+        );
+        assert_eq!(
+            format_metrics(&cur, None),
+            "impressions 150  likes 4  reposts 0  replies 0  quotes 0"
+        );
+    }
+
+    fn local_ts(y: i32, m: u32, d: u32, h: u32) -> i64 {
+        Local
+            .with_ymd_and_hms(y, m, d, h, 0, 0)
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn daily_counts_buckets_by_local_day() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let ts = [
+            local_ts(2024, 5, 10, 9),
+            local_ts(2024, 5, 10, 23),
+            local_ts(2024, 5, 8, 0),
+            local_ts(2024, 5, 1, 12), // outside the window
+        ];
+        assert_eq!(daily_counts(&ts, today, 3), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn sparkline_scales_to_max() {
+        assert_eq!(sparkline(&[0, 1, 2, 4, 8]), "▁▁▂▄█");
+        assert_eq!(sparkline(&[0, 0]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn heatmap_aligns_weeks_to_monday() {
+        // 2024-05-08 is a Wednesday.
+        let first = NaiveDate::from_ymd_opt(2024, 5, 8).unwrap();
+        let map = heatmap(&[4, 0, 1, 2, 3, 4, 4, 4], first);
+        let rows: Vec<&str> = map.lines().collect();
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[0], "Mon  █");
+        assert_eq!(rows[1], "Tue  █");
+        assert_eq!(rows[2], "Wed ██");
+        assert_eq!(rows[3], "Thu · ");
+        assert_eq!(rows[4], "Fri ░ ");
+        assert_eq!(rows[5], "Sat ▒ ");
+    }
+
+    #[test]
+    fn streaks_longest_and_current() {
+        assert_eq!(streaks(&[1, 1, 0, 1, 1, 1, 0]), (3, 0));
+        assert_eq!(streaks(&[0, 2, 1]), (2, 2));
+        assert_eq!(streaks(&[]), (0, 0));
+    }
+
+    #[test]
+    fn empty_input() {
+        let summary = summarize(&[], 5);
+        assert_eq!(summary.tweets, 0);
+        assert_eq!(summary.averages, Averages::default());
+        assert!(summary.top.is_empty());
+    }
+
+    #[test]
+    fn best_posting_times() {
+        // Monday 2024-01-01 at 9:00 and 18:00, local time.
+        let at = |day: u32, hour: u32| {
+            Local
+                .with_ymd_and_hms(2024, 1, day, hour, 0, 0)
+                .unwrap()
+                .timestamp()
+        };
+        let posts = [
+            (at(1, 9), 10),
+            (at(8, 9), 30),
+            (at(1, 18), 100),
+            (at(2, 9), 5),
+            (at(9, 9), 7),
+        ];
+        let times = PostingTimes::from_posts(&posts);
+        assert_eq!(
+            times.best(2, 2),
+            vec![
+                Slot {
+                    weekday: "Mon",
+                    hour: 9,
+                    posts: 2,
+                    average_engagement: 20.0
+                },
+                Slot {
+                    weekday: "Tue",
+                    hour: 9,
+                    posts: 2,
+                    average_engagement: 6.0
+                },
+            ]
+        );
+        assert_eq!(times.best(1, 1)[0].hour, 18);
+
+        let heatmap = times.heatmap();
+        let rows: Vec<&str> = heatmap.lines().collect();
+        assert_eq!(rows.len(), 8);
+        let monday: Vec<char> = rows[1].chars().collect();
+        assert_eq!(monday[4 + 9], '·');
+        assert_eq!(monday[4 + 18], '█');
+        assert_eq!(monday[4 + 10], ' ');
+    }
+
+    #[test]
+    fn follower_alerts() {
+        let thresholds = [-50, -10, 100];
+        assert_eq!(crossed(1000, 995, &thresholds), None);
+        assert_eq!(crossed(1000, 990, &thresholds), Some(-10));
+        assert_eq!(crossed(1000, 900, &thresholds), Some(-50));
+        assert_eq!(crossed(1000, 1100, &thresholds), Some(100));
+        assert_eq!(crossed(1000, 1099, &thresholds), None);
+        assert_eq!(crossed(0, 5, &[]), None);
+    }
+
+    #[test]
+    fn follower_log_appends_rows() {
+        let path =
+            std::env::temp_dir().join(format!("xcli_test_{}_followers.csv", std::process::id()));
+        log_followers(&path, 0, "jack", 10).unwrap();
+        log_followers(&path, 60, "jack", 9).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "time,handle,followers\n1970-01-01T00:00:00Z,jack,10\n1970-01-01T00:01:00Z,jack,9\n"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reads_tweet_ids_and_writes_their_metrics() {
+        let content = "# week 42\n111\nhttps://x.com/jack/status/222?s=20  # launch\n\n111\n";
+        assert_eq!(parse_tweet_ids(content).unwrap(), ["111", "222"]);
+        assert!(parse_tweet_ids("111\nnot-an-id\n")
+            .unwrap_err()
+            .starts_with("line 2:"));
+
+        let mut quoted = tweet("2", 4, 0, 300);
+        quoted.text = "Hello, \"world\"".to_string();
+        assert_eq!(
+            metrics_csv(&[tweet("1", 10, 2, 100), quoted]),
+            "id,created_at,impressions,likes,reposts,replies,quotes,bookmarks,text\n\
+             1,,100,10,2,0,0,0,tweet 1\n\
+             2,,300,4,0,0,0,0,\"Hello, \"\"world\"\"\"\n"
+        );
+    }
+}
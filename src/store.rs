@@ -0,0 +1,1226 @@
+#[cfg(feature = "history")]
+use rusqlite::{params, Connection};
+#[cfg(feature = "history")]
+use sha1::{Digest, Sha1};
+#[cfg(feature = "history")]
+use std::collections::HashMap;
+#[cfg(feature = "history")]
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "history")]
+use crate::archive::ArchiveTweet;
+#[cfg(feature = "history")]
+use crate::config::store_path;
+
+/// Whether this build has the local store (the `history` feature).
+pub const ENABLED: bool = cfg!(feature = "history");
+
+#[cfg(not(feature = "history"))]
+const DISABLED: &str = "this xcli was built without the `history` feature";
+
+/// Schema as of version 1. Later changes go in `MIGRATIONS`, never here.
+#[cfg(feature = "history")]
+const SCHEMA_V1: &str = "
+CREATE TABLE IF NOT EXISTS queue (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    action      TEXT NOT NULL,
+    tweet_id    TEXT NOT NULL,
+    due_at      INTEGER NOT NULL,
+    attempts    INTEGER NOT NULL DEFAULT 0,
+    last_error  TEXT
+);
+CREATE INDEX IF NOT EXISTS queue_due_at ON queue (due_at);
+
+CREATE TABLE IF NOT EXISTS history (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    tweet_id    TEXT NOT NULL UNIQUE,
+    root_id     TEXT NOT NULL,
+    position    INTEGER NOT NULL,
+    text        TEXT NOT NULL,
+    account     TEXT NOT NULL,
+    in_reply_to TEXT,
+    posted_at   INTEGER NOT NULL,
+    source      TEXT NOT NULL DEFAULT 'xcli'
+);
+CREATE INDEX IF NOT EXISTS history_root ON history (root_id, position);
+CREATE INDEX IF NOT EXISTS history_posted_at ON history (posted_at);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS history_fts
+    USING fts5(text, content='history', content_rowid='id');
+CREATE TRIGGER IF NOT EXISTS history_fts_insert AFTER INSERT ON history BEGIN
+    INSERT INTO history_fts (rowid, text) VALUES (new.id, new.text);
+END;
+CREATE TRIGGER IF NOT EXISTS history_fts_delete AFTER DELETE ON history BEGIN
+    INSERT INTO history_fts (history_fts, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+CREATE TRIGGER IF NOT EXISTS history_fts_update AFTER UPDATE ON history BEGIN
+    INSERT INTO history_fts (history_fts, rowid, text) VALUES ('delete', old.id, old.text);
+    INSERT INTO history_fts (rowid, text) VALUES (new.id, new.text);
+END;
+";
+
+#[cfg(feature = "history")]
+type Migration = fn(&rusqlite::Transaction) -> rusqlite::Result<()>;
+
+/// Schema migrations, applied in order on open. Entry `i` upgrades the store
+/// from version `i` to `i + 1`, tracked in SQLite's `user_version`. Append new
+/// entries; never edit or reorder released ones.
+#[cfg(feature = "history")]
+const MIGRATIONS: &[Migration] = &[migrate_v1, migrate_v2, migrate_v3];
+
+/// Version 1 is the baseline. Stores created before versioning (version 0)
+/// may be missing later additions, so this also fills those in.
+#[cfg(feature = "history")]
+fn migrate_v1(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    let had_fts: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'history_fts')",
+        [],
+        |row| row.get(0),
+    )?;
+    let had_history: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'history')",
+        [],
+        |row| row.get(0),
+    )?;
+    if had_history {
+        let has_source: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('history') WHERE name = 'source')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_source {
+            // History recorded before archive import existed.
+            tx.execute(
+                "ALTER TABLE history ADD COLUMN source TEXT NOT NULL DEFAULT 'xcli'",
+                [],
+            )?;
+        }
+    }
+    tx.execute_batch(SCHEMA_V1)?;
+    if !had_fts {
+        // Index history recorded before full-text search existed.
+        tx.execute(
+            "INSERT INTO history_fts (history_fts) VALUES ('rebuild')",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Version 2: local contact book.
+#[cfg(feature = "history")]
+fn migrate_v2(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE contacts (
+            handle      TEXT PRIMARY KEY COLLATE NOCASE,
+            user_id     TEXT NOT NULL,
+            name        TEXT NOT NULL,
+            added_at    INTEGER NOT NULL
+        );",
+    )
+}
+
+/// Feeds to cross-post, and the entries already handled.
+#[cfg(feature = "history")]
+fn migrate_v3(tx: &rusqlite::Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE feeds (
+            url         TEXT PRIMARY KEY,
+            template    TEXT NOT NULL,
+            added_at    INTEGER NOT NULL,
+            synced_at   INTEGER
+        );
+        CREATE TABLE feed_entries (
+            feed_url    TEXT NOT NULL,
+            entry_id    TEXT NOT NULL,
+            seen_at     INTEGER NOT NULL,
+            tweet_id    TEXT,
+            PRIMARY KEY (feed_url, entry_id)
+        );",
+    )
+}
+
+/// Bring the store up to the latest schema version, one transaction per step.
+#[cfg(feature = "history")]
+fn migrate(conn: &mut Connection) -> Result<(), String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read local store version: {e}"))?;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "Local store is version {version}, but this xcli only knows up to {}. Upgrade xcli.",
+            MIGRATIONS.len()
+        ));
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let next = i + 1;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to upgrade local store: {e}"))?;
+        migration(&tx)
+            .and_then(|_| tx.pragma_update(None, "user_version", next))
+            .and_then(|_| tx.commit())
+            .map_err(|e| format!("Failed to upgrade local store to version {next}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// History source for tweets posted through xcli.
+#[cfg(feature = "history")]
+pub const SOURCE_XCLI: &str = "xcli";
+/// History source for tweets imported from the official X archive.
+pub const SOURCE_ARCHIVE: &str = "archive";
+
+/// Queued action for the runner. Only `delete` exists today.
+pub const ACTION_DELETE: &str = "delete";
+
+/// Local SQLite store under the config directory.
+#[cfg(feature = "history")]
+pub struct Store {
+    conn: Connection,
+}
+
+/// Without the `history` feature a store cannot be opened, so none of its
+/// methods can be reached.
+#[cfg(not(feature = "history"))]
+pub struct Store {
+    never: std::convert::Infallible,
+}
+
+pub struct QueueItem {
+    pub id: i64,
+    pub action: String,
+    pub tweet_id: String,
+    pub due_at: i64,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+}
+
+/// One posted tweet. Threads share a `root_id` (the first tweet's ID) and are
+/// ordered by `position`, starting at 0.
+pub struct HistoryEntry {
+    pub tweet_id: String,
+    pub root_id: String,
+    pub position: i64,
+    pub text: String,
+    pub account: String,
+    pub in_reply_to: Option<String>,
+    pub posted_at: i64,
+    /// `SOURCE_XCLI` or `SOURCE_ARCHIVE`.
+    pub source: String,
+}
+
+/// A saved account, so handles resolve without an API call.
+pub struct Contact {
+    pub handle: String,
+    pub user_id: String,
+    pub name: String,
+    pub added_at: i64,
+}
+
+/// A subscribed RSS or Atom feed.
+#[cfg(feature = "history")]
+pub struct Feed {
+    pub url: String,
+    /// How entries are turned into posts, see `feed::render`.
+    pub template: String,
+    pub added_at: i64,
+    pub synced_at: Option<i64>,
+}
+
+#[cfg(feature = "history")]
+const HISTORY_COLUMNS: &str =
+    "tweet_id, root_id, position, text, account, in_reply_to, posted_at, source";
+
+/// Stable fingerprint of a tweet's text. Whitespace differences are ignored so
+/// reformatted copies of the same text are still recognized.
+#[cfg(feature = "history")]
+pub fn text_fingerprint(text: &str) -> String {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    Sha1::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Format a unix timestamp as RFC 3339 in UTC, for machine-readable output.
+pub fn format_rfc3339(ts: i64) -> String {
+    match chrono::DateTime::from_timestamp(ts, 0) {
+        Some(dt) => dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        None => ts.to_string(),
+    }
+}
+
+/// Current time as unix seconds.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Format a unix timestamp in local time, e.g. `2024-05-01 13:37`.
+pub fn format_timestamp(ts: i64) -> String {
+    use chrono::{Local, TimeZone};
+    match Local.timestamp_opt(ts, 0).single() {
+        Some(dt) => dt.format("%Y-%m-%d %H:%M").to_string(),
+        None => ts.to_string(),
+    }
+}
+
+#[cfg(feature = "history")]
+impl Store {
+    pub fn open() -> Result<Self, String> {
+        Self::open_at(&store_path())
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {e}"))?;
+        }
+        let mut conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open local store {}: {e}", path.display()))?;
+        migrate(&mut conn)?;
+        Ok(Store { conn })
+    }
+
+    pub fn enqueue(&self, action: &str, tweet_id: &str, due_at: i64) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO queue (action, tweet_id, due_at) VALUES (?1, ?2, ?3)",
+                params![action, tweet_id, due_at],
+            )
+            .map_err(|e| format!("Failed to queue {action} for {tweet_id}: {e}"))?;
+        Ok(())
+    }
+
+    /// All queued items, soonest first.
+    pub fn queue_items(&self) -> Result<Vec<QueueItem>, String> {
+        self.select_queue("SELECT id, action, tweet_id, due_at, attempts, last_error FROM queue ORDER BY due_at, id", [])
+    }
+
+    /// Items whose due time is at or before `now`, soonest first.
+    pub fn due_items(&self, now: i64) -> Result<Vec<QueueItem>, String> {
+        self.select_queue(
+            "SELECT id, action, tweet_id, due_at, attempts, last_error FROM queue WHERE due_at <= ?1 ORDER BY due_at, id",
+            [now],
+        )
+    }
+
+    pub fn remove_queue_item(&self, id: i64) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM queue WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to remove queue item {id}: {e}"))?;
+        Ok(())
+    }
+
+    pub fn record_queue_failure(&self, id: i64, error: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "UPDATE queue SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+                params![id, error],
+            )
+            .map_err(|e| format!("Failed to update queue item {id}: {e}"))?;
+        Ok(())
+    }
+
+    /// Record a posted tweet or thread. `ids` and `texts` are matched by index;
+    /// the first ID becomes the root of the thread.
+    pub fn record_post(
+        &self,
+        account: &str,
+        in_reply_to: Option<&str>,
+        ids: &[String],
+        texts: &[String],
+        posted_at: i64,
+    ) -> Result<(), String> {
+        let Some(root_id) = ids.first() else {
+            return Ok(());
+        };
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to record history: {e}"))?;
+        for (i, (id, text)) in ids.iter().zip(texts).enumerate() {
+            // Only the first tweet replies to an outside tweet; the rest reply to the previous one.
+            let parent = if i == 0 {
+                in_reply_to.map(str::to_string)
+            } else {
+                Some(ids[i - 1].clone())
+            };
+            tx.execute(
+                "INSERT INTO history (tweet_id, root_id, position, text, account, in_reply_to, posted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (tweet_id) DO UPDATE SET
+                     root_id = excluded.root_id, position = excluded.position, text = excluded.text,
+                     account = excluded.account, in_reply_to = excluded.in_reply_to,
+                     posted_at = excluded.posted_at",
+                params![id, root_id, i as i64, text, account, parent, posted_at],
+            )
+            .map_err(|e| format!("Failed to record history: {e}"))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to record history: {e}"))
+    }
+
+    /// Forget a tweet that no longer exists: drop it from history and cancel
+    /// any queued actions for it.
+    pub fn forget_tweet(&self, tweet_id: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM history WHERE tweet_id = ?1", [tweet_id])
+            .map_err(|e| format!("Failed to update history: {e}"))?;
+        self.conn
+            .execute("DELETE FROM queue WHERE tweet_id = ?1", [tweet_id])
+            .map_err(|e| format!("Failed to update queue: {e}"))?;
+        Ok(())
+    }
+
+    /// Find texts that were already posted at or after `since`. Returns the
+    /// index into `texts` and the matching history entry for each duplicate.
+    pub fn find_duplicates(
+        &self,
+        texts: &[String],
+        since: i64,
+    ) -> Result<Vec<(usize, HistoryEntry)>, String> {
+        let recent = self.select_history(
+            &format!(
+                "SELECT {HISTORY_COLUMNS} FROM history WHERE posted_at >= ?1 ORDER BY posted_at DESC"
+            ),
+            [since],
+        )?;
+        let mut by_fingerprint: HashMap<String, HistoryEntry> = HashMap::new();
+        for entry in recent {
+            by_fingerprint
+                .entry(text_fingerprint(&entry.text))
+                .or_insert(entry);
+        }
+
+        let mut duplicates = Vec::new();
+        for (i, text) in texts.iter().enumerate() {
+            if let Some(entry) = by_fingerprint.remove(&text_fingerprint(text)) {
+                duplicates.push((i, entry));
+            }
+        }
+        Ok(duplicates)
+    }
+
+    /// Every recorded tweet posted before `until`, oldest first.
+    pub fn history_before(&self, until: i64) -> Result<Vec<HistoryEntry>, String> {
+        self.select_history(
+            &format!(
+                "SELECT {HISTORY_COLUMNS} FROM history WHERE posted_at < ?1
+                 ORDER BY posted_at, root_id, position"
+            ),
+            [until],
+        )
+    }
+
+    /// Full-text search over posted tweets, best matches first. Every term must
+    /// appear; terms are matched as words, not as FTS query syntax.
+    pub fn search_history(&self, terms: &str, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        let query = fts_query(terms);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let columns = HISTORY_COLUMNS
+            .split(", ")
+            .map(|c| format!("h.{c}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.select_history(
+            &format!(
+                "SELECT {columns} FROM history_fts f JOIN history h ON h.id = f.rowid
+                 WHERE history_fts MATCH ?1 ORDER BY f.rank LIMIT ?2"
+            ),
+            params![query, limit as i64],
+        )
+    }
+
+    /// Every recorded tweet posted at or after `since` (all of history when
+    /// `None`), oldest first with threads kept in order.
+    pub fn history_since(&self, since: Option<i64>) -> Result<Vec<HistoryEntry>, String> {
+        self.select_history(
+            &format!(
+                "SELECT {HISTORY_COLUMNS} FROM history WHERE posted_at >= ?1
+                 ORDER BY posted_at, root_id, position"
+            ),
+            [since.unwrap_or(i64::MIN)],
+        )
+    }
+
+    /// Import tweets from the X archive. Tweets already in history (including
+    /// ones posted through xcli) are left untouched. Returns how many were added.
+    pub fn import_archive(&self, account: &str, tweets: &[ArchiveTweet]) -> Result<usize, String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to import archive: {e}"))?;
+        let mut added = 0;
+        for t in tweets {
+            added += tx
+                .execute(
+                    "INSERT INTO history (tweet_id, root_id, position, text, account, in_reply_to, posted_at, source)
+                     VALUES (?1, ?1, 0, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (tweet_id) DO NOTHING",
+                    params![t.id, t.text, account, t.in_reply_to, t.created_at, SOURCE_ARCHIVE],
+                )
+                .map_err(|e| format!("Failed to import tweet {}: {e}", t.id))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to import archive: {e}"))?;
+        Ok(added)
+    }
+
+    /// First tweet of each of the most recent xcli posts, newest first.
+    pub fn recent_posts(&self, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        self.select_history(
+            &format!(
+                "SELECT {HISTORY_COLUMNS} FROM history WHERE position = 0 AND source = ?1
+                 ORDER BY posted_at DESC, id DESC LIMIT ?2"
+            ),
+            params![SOURCE_XCLI, limit as i64],
+        )
+    }
+
+    /// All tweets of the thread rooted at `root_id`, in order.
+    pub fn thread_entries(&self, root_id: &str) -> Result<Vec<HistoryEntry>, String> {
+        self.select_history(
+            &format!("SELECT {HISTORY_COLUMNS} FROM history WHERE root_id = ?1 ORDER BY position"),
+            [root_id],
+        )
+    }
+
+    /// Add a contact, or refresh its ID and name if the handle is known.
+    pub fn save_contact(&self, handle: &str, user_id: &str, name: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO contacts (handle, user_id, name, added_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (handle) DO UPDATE SET
+                     handle = excluded.handle, user_id = excluded.user_id, name = excluded.name",
+                params![handle, user_id, name, now()],
+            )
+            .map_err(|e| format!("Failed to save contact @{handle}: {e}"))?;
+        Ok(())
+    }
+
+    /// Returns false if there was no such contact.
+    pub fn remove_contact(&self, handle: &str) -> Result<bool, String> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM contacts WHERE handle = ?1", [handle])
+            .map_err(|e| format!("Failed to remove contact @{handle}: {e}"))?;
+        Ok(removed > 0)
+    }
+
+    /// Contacts sorted by handle. With `handles`, only those (case-insensitive).
+    pub fn contacts(&self, handles: Option<&[String]>) -> Result<Vec<Contact>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT handle, user_id, name, added_at FROM contacts ORDER BY handle")
+            .map_err(|e| format!("Failed to read contacts: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Contact {
+                    handle: row.get(0)?,
+                    user_id: row.get(1)?,
+                    name: row.get(2)?,
+                    added_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| format!("Failed to read contacts: {e}"))?;
+        let all = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read contacts: {e}"))?;
+        Ok(match handles {
+            Some(handles) => all
+                .into_iter()
+                .filter(|c| handles.iter().any(|h| h.eq_ignore_ascii_case(&c.handle)))
+                .collect(),
+            None => all,
+        })
+    }
+
+    /// Subscribe to a feed. `entry_ids` (its current entries) count as seen,
+    /// so only later entries are posted. Returns false if already subscribed.
+    pub fn add_feed(
+        &self,
+        url: &str,
+        template: &str,
+        entry_ids: &[String],
+    ) -> Result<bool, String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to add feed: {e}"))?;
+        let added = tx
+            .execute(
+                "INSERT INTO feeds (url, template, added_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (url) DO NOTHING",
+                params![url, template, now()],
+            )
+            .map_err(|e| format!("Failed to add feed: {e}"))?;
+        if added == 0 {
+            return Ok(false);
+        }
+        for id in entry_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO feed_entries (feed_url, entry_id, seen_at) VALUES (?1, ?2, ?3)",
+                params![url, id, now()],
+            )
+            .map_err(|e| format!("Failed to add feed: {e}"))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to add feed: {e}"))?;
+        Ok(true)
+    }
+
+    /// Unsubscribe, forgetting the feed's entries. Returns false if there was
+    /// no such feed.
+    pub fn remove_feed(&self, url: &str) -> Result<bool, String> {
+        self.conn
+            .execute("DELETE FROM feed_entries WHERE feed_url = ?1", [url])
+            .map_err(|e| format!("Failed to remove feed {url}: {e}"))?;
+        let removed = self
+            .conn
+            .execute("DELETE FROM feeds WHERE url = ?1", [url])
+            .map_err(|e| format!("Failed to remove feed {url}: {e}"))?;
+        Ok(removed > 0)
+    }
+
+    /// Subscribed feeds, oldest first.
+    pub fn feeds(&self) -> Result<Vec<Feed>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT url, template, added_at, synced_at FROM feeds ORDER BY added_at, url")
+            .map_err(|e| format!("Failed to read feeds: {e}"))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Feed {
+                    url: row.get(0)?,
+                    template: row.get(1)?,
+                    added_at: row.get(2)?,
+                    synced_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| format!("Failed to read feeds: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read feeds: {e}"))
+    }
+
+    /// Whether an entry of the feed was seen (posted, or there when the feed
+    /// was added).
+    pub fn feed_entry_seen(&self, url: &str, entry_id: &str) -> Result<bool, String> {
+        self.conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM feed_entries WHERE feed_url = ?1 AND entry_id = ?2)",
+                params![url, entry_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read feed entries: {e}"))
+    }
+
+    /// Remember a posted entry, with the ID of the (first) tweet.
+    pub fn mark_feed_entry(&self, url: &str, entry_id: &str, tweet_id: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO feed_entries (feed_url, entry_id, seen_at, tweet_id)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![url, entry_id, now(), tweet_id],
+            )
+            .map_err(|e| format!("Failed to record feed entry: {e}"))?;
+        Ok(())
+    }
+
+    pub fn mark_feed_synced(&self, url: &str, at: i64) -> Result<(), String> {
+        self.conn
+            .execute(
+                "UPDATE feeds SET synced_at = ?2 WHERE url = ?1",
+                params![url, at],
+            )
+            .map_err(|e| format!("Failed to update feed {url}: {e}"))?;
+        Ok(())
+    }
+
+    fn select_history<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| format!("Failed to read history: {e}"))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok(HistoryEntry {
+                    tweet_id: row.get(0)?,
+                    root_id: row.get(1)?,
+                    position: row.get(2)?,
+                    text: row.get(3)?,
+                    account: row.get(4)?,
+                    in_reply_to: row.get(5)?,
+                    posted_at: row.get(6)?,
+                    source: row.get(7)?,
+                })
+            })
+            .map_err(|e| format!("Failed to read history: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read history: {e}"))
+    }
+
+    fn select_queue<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<Vec<QueueItem>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| format!("Failed to read queue: {e}"))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok(QueueItem {
+                    id: row.get(0)?,
+                    action: row.get(1)?,
+                    tweet_id: row.get(2)?,
+                    due_at: row.get(3)?,
+                    attempts: row.get(4)?,
+                    last_error: row.get(5)?,
+                })
+            })
+            .map_err(|e| format!("Failed to read queue: {e}"))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read queue: {e}"))
+    }
+}
+
+#[cfg(not(feature = "history"))]
+impl Store {
+    pub fn open() -> Result<Self, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn open_at(_path: &Path) -> Result<Self, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn enqueue(&self, _action: &str, _tweet_id: &str, _due_at: i64) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub fn queue_items(&self) -> Result<Vec<QueueItem>, String> {
+        match self.never {}
+    }
+
+    pub fn due_items(&self, _now: i64) -> Result<Vec<QueueItem>, String> {
+        match self.never {}
+    }
+
+    pub fn remove_queue_item(&self, _id: i64) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub fn record_queue_failure(&self, _id: i64, _error: &str) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub fn record_post(
+        &self,
+        _account: &str,
+        _in_reply_to: Option<&str>,
+        _ids: &[String],
+        _texts: &[String],
+        _posted_at: i64,
+    ) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub fn forget_tweet(&self, _tweet_id: &str) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub fn find_duplicates(
+        &self,
+        _texts: &[String],
+        _since: i64,
+    ) -> Result<Vec<(usize, HistoryEntry)>, String> {
+        match self.never {}
+    }
+
+    pub fn history_before(&self, _until: i64) -> Result<Vec<HistoryEntry>, String> {
+        match self.never {}
+    }
+
+    pub fn search_history(&self, _terms: &str, _limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        match self.never {}
+    }
+
+    pub fn history_since(&self, _since: Option<i64>) -> Result<Vec<HistoryEntry>, String> {
+        match self.never {}
+    }
+
+    pub fn recent_posts(&self, _limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        match self.never {}
+    }
+
+    pub fn thread_entries(&self, _root_id: &str) -> Result<Vec<HistoryEntry>, String> {
+        match self.never {}
+    }
+
+    pub fn save_contact(&self, _handle: &str, _user_id: &str, _name: &str) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub fn remove_contact(&self, _handle: &str) -> Result<bool, String> {
+        match self.never {}
+    }
+
+    pub fn contacts(&self, _handles: Option<&[String]>) -> Result<Vec<Contact>, String> {
+        match self.never {}
+    }
+}
+
+/// Quote each term so user input is never parsed as FTS5 operators.
+#[cfg(feature = "history")]
+fn fts_query(terms: &str) -> String {
+    terms
+        .split_whitespace()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(all(test, feature = "history"))]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::path::PathBuf;
+
+    fn temp_store(name: &str) -> (Store, PathBuf) {
+        let path = temp_dir().join(format!("xcli_test_{}_{name}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        (Store::open_at(&path).unwrap(), path)
+    }
+
+    #[test]
+    fn enqueue_and_list() {
+        let (store, path) = temp_store("enqueue");
+        store.enqueue(ACTION_DELETE, "222", 200).unwrap();
+        store.enqueue(ACTION_DELETE, "111", 100).unwrap();
+
+        let items = store.queue_items().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].tweet_id, "111");
+        assert_eq!(items[1].tweet_id, "222");
+        assert_eq!(items[0].action, ACTION_DELETE);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn due_items_filters_by_time() {
+        let (store, path) = temp_store("due");
+        store.enqueue(ACTION_DELETE, "1", 100).unwrap();
+        store.enqueue(ACTION_DELETE, "2", 300).unwrap();
+
+        let due = store.due_items(200).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].tweet_id, "1");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn failure_and_removal() {
+        let (store, path) = temp_store("failure");
+        store.enqueue(ACTION_DELETE, "1", 100).unwrap();
+        let id = store.queue_items().unwrap()[0].id;
+
+        store.record_queue_failure(id, "boom").unwrap();
+        let item = &store.queue_items().unwrap()[0];
+        assert_eq!(item.attempts, 1);
+        assert_eq!(item.last_error.as_deref(), Some("boom"));
+
+        store.remove_queue_item(id).unwrap();
+        assert!(store.queue_items().unwrap().is_empty());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn record_single_tweet() {
+        let (store, path) = temp_store("history_single");
+        store
+            .record_post("alice", None, &strings(&["10"]), &strings(&["hello"]), 1000)
+            .unwrap();
+
+        let posts = store.recent_posts(10).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].tweet_id, "10");
+        assert_eq!(posts[0].root_id, "10");
+        assert_eq!(posts[0].text, "hello");
+        assert_eq!(posts[0].account, "alice");
+        assert!(posts[0].in_reply_to.is_none());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn record_thread_links_replies() {
+        let (store, path) = temp_store("history_thread");
+        store
+            .record_post(
+                "alice",
+                Some("5"),
+                &strings(&["10", "11", "12"]),
+                &strings(&["one", "two", "three"]),
+                1000,
+            )
+            .unwrap();
+
+        let posts = store.recent_posts(10).unwrap();
+        assert_eq!(posts.len(), 1);
+
+        let thread = store.thread_entries("10").unwrap();
+        assert_eq!(thread.len(), 3);
+        assert_eq!(thread[0].in_reply_to.as_deref(), Some("5"));
+        assert_eq!(thread[1].in_reply_to.as_deref(), Some("10"));
+        assert_eq!(thread[2].in_reply_to.as_deref(), Some("11"));
+        assert_eq!(thread[2].position, 2);
+        assert_eq!(thread[2].root_id, "10");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn recent_posts_newest_first_with_limit() {
+        let (store, path) = temp_store("history_recent");
+        for (i, id) in ["1", "2", "3"].iter().enumerate() {
+            store
+                .record_post("a", None, &strings(&[id]), &strings(&["x"]), i as i64)
+                .unwrap();
+        }
+
+        let posts = store.recent_posts(2).unwrap();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].tweet_id, "3");
+        assert_eq!(posts[1].tweet_id, "2");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn forget_tweet_removes_history_and_queue() {
+        let (store, path) = temp_store("forget");
+        store
+            .record_post("a", None, &strings(&["1", "2"]), &strings(&["x", "y"]), 10)
+            .unwrap();
+        store.enqueue(ACTION_DELETE, "2", 100).unwrap();
+
+        store.forget_tweet("2").unwrap();
+        assert_eq!(store.thread_entries("1").unwrap().len(), 1);
+        assert!(store.queue_items().unwrap().is_empty());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn fingerprint_ignores_whitespace() {
+        assert_eq!(
+            text_fingerprint("hello  world\n"),
+            text_fingerprint("hello world")
+        );
+        assert_ne!(
+            text_fingerprint("hello world"),
+            text_fingerprint("hello world!")
+        );
+    }
+
+    #[test]
+    fn find_duplicates_within_window() {
+        let (store, path) = temp_store("duplicates");
+        store
+            .record_post("a", None, &strings(&["1"]), &strings(&["old news"]), 100)
+            .unwrap();
+        store
+            .record_post("a", None, &strings(&["2"]), &strings(&["fresh news"]), 500)
+            .unwrap();
+
+        let texts = strings(&["something else", "fresh  news", "old news"]);
+        let dups = store.find_duplicates(&texts, 200).unwrap();
+        assert_eq!(dups.len(), 1);
+        assert_eq!(dups[0].0, 1);
+        assert_eq!(dups[0].1.tweet_id, "2");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn fts_query_quotes_terms() {
+        assert_eq!(fts_query("rust  release"), "\"rust\" \"release\"");
+        assert_eq!(fts_query("say \"hi\""), "\"say\" \"\"\"hi\"\"\"");
+        assert_eq!(fts_query("   "), "");
+    }
+
+    #[test]
+    fn search_history_matches_all_terms() {
+        let (store, path) = temp_store("search");
+        store
+            .record_post(
+                "a",
+                None,
+                &strings(&["1"]),
+                &strings(&["Rust 1.80 released"]),
+                1,
+            )
+            .unwrap();
+        store
+            .record_post(
+                "a",
+                None,
+                &strings(&["2", "3"]),
+                &strings(&["Thread about Go", "and Rust too"]),
+                2,
+            )
+            .unwrap();
+
+        let hits = store.search_history("rust", 10).unwrap();
+        assert_eq!(hits.len(), 2);
+
+        let hits = store.search_history("rust released", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].tweet_id, "1");
+
+        let hits = store.search_history("AND OR (", 10).unwrap();
+        assert!(hits.is_empty());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn search_index_follows_updates_and_deletes() {
+        let (store, path) = temp_store("search_updates");
+        store
+            .record_post("a", None, &strings(&["1"]), &strings(&["first draft"]), 1)
+            .unwrap();
+        store
+            .record_post("a", None, &strings(&["1"]), &strings(&["final text"]), 1)
+            .unwrap();
+        assert!(store.search_history("draft", 10).unwrap().is_empty());
+        assert_eq!(store.search_history("final", 10).unwrap().len(), 1);
+
+        store.forget_tweet("1").unwrap();
+        assert!(store.search_history("final", 10).unwrap().is_empty());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn history_since_filters_and_orders() {
+        let (store, path) = temp_store("since");
+        store
+            .record_post("a", None, &strings(&["1"]), &strings(&["old"]), 100)
+            .unwrap();
+        store
+            .record_post("a", None, &strings(&["2", "3"]), &strings(&["x", "y"]), 200)
+            .unwrap();
+
+        assert_eq!(store.history_since(None).unwrap().len(), 3);
+        let recent = store.history_since(Some(150)).unwrap();
+        let ids: Vec<_> = recent.iter().map(|e| e.tweet_id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "3"]);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn history_before_excludes_recent() {
+        let (store, path) = temp_store("before");
+        store
+            .record_post("a", None, &strings(&["1"]), &strings(&["old"]), 100)
+            .unwrap();
+        store
+            .record_post("a", None, &strings(&["2"]), &strings(&["new"]), 200)
+            .unwrap();
+
+        let old = store.history_before(200).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(old[0].tweet_id, "1");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rfc3339_is_utc() {
+        assert_eq!(format_rfc3339(1714521600), "2024-05-01T00:00:00Z");
+    }
+
+    fn archive_tweet(id: &str, text: &str, created_at: i64) -> ArchiveTweet {
+        ArchiveTweet {
+            id: id.to_string(),
+            text: text.to_string(),
+            created_at,
+            in_reply_to: None,
+        }
+    }
+
+    #[test]
+    fn import_archive_skips_known_tweets() {
+        let (store, path) = temp_store("archive");
+        store
+            .record_post("a", None, &strings(&["1"]), &strings(&["via xcli"]), 100)
+            .unwrap();
+
+        let tweets = vec![
+            archive_tweet("1", "via xcli (archive copy)", 100),
+            archive_tweet("2", "old archived tweet", 50),
+        ];
+        assert_eq!(store.import_archive("a", &tweets).unwrap(), 1);
+        assert_eq!(store.import_archive("a", &tweets).unwrap(), 0);
+
+        let all = store.history_since(None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].source, SOURCE_ARCHIVE);
+        assert_eq!(all[1].source, SOURCE_XCLI);
+        assert_eq!(all[1].text, "via xcli");
+
+        // Archive tweets are searchable but not treated as recent xcli posts.
+        assert_eq!(store.search_history("archived", 10).unwrap().len(), 1);
+        let recent = store.recent_posts(10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].tweet_id, "1");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn contacts_save_update_and_remove() {
+        let (store, path) = temp_store("contacts");
+        store.save_contact("alice", "1", "Alice").unwrap();
+        store.save_contact("bob", "2", "Bob").unwrap();
+        store.save_contact("Alice", "1", "Alice A.").unwrap();
+
+        let all = store.contacts(None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].handle, "Alice");
+        assert_eq!(all[0].name, "Alice A.");
+
+        let some = store.contacts(Some(&strings(&["BOB", "carol"]))).unwrap();
+        assert_eq!(some.len(), 1);
+        assert_eq!(some[0].user_id, "2");
+
+        assert!(store.remove_contact("ALICE").unwrap());
+        assert!(!store.remove_contact("alice").unwrap());
+        assert_eq!(store.contacts(None).unwrap().len(), 1);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn feeds_and_seen_entries() {
+        let (store, path) = temp_store("feeds");
+        let url = "https://blog.example/feed.xml";
+        assert!(store
+            .add_feed(url, "{title} {link}", &strings(&["old"]))
+            .unwrap());
+        assert!(!store.add_feed(url, "other", &[]).unwrap());
+
+        let feeds = store.feeds().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].template, "{title} {link}");
+        assert_eq!(feeds[0].synced_at, None);
+
+        assert!(store.feed_entry_seen(url, "old").unwrap());
+        assert!(!store.feed_entry_seen(url, "new").unwrap());
+        store.mark_feed_entry(url, "new", "10").unwrap();
+        assert!(store.feed_entry_seen(url, "new").unwrap());
+        store.mark_feed_synced(url, 500).unwrap();
+        assert_eq!(store.feeds().unwrap()[0].synced_at, Some(500));
+
+        assert!(store.remove_feed(url).unwrap());
+        assert!(!store.remove_feed(url).unwrap());
+        assert!(!store.feed_entry_seen(url, "old").unwrap());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn new_store_is_at_latest_version() {
+        let (store, path) = temp_store("version");
+        assert_eq!(user_version(&store.conn), MIGRATIONS.len());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn unversioned_store_is_upgraded_in_place() {
+        let path = temp_dir().join(format!("xcli_test_{}_legacy.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        {
+            // History as recorded before archive import and search existed.
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    tweet_id TEXT NOT NULL UNIQUE,
+                    root_id TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    text TEXT NOT NULL,
+                    account TEXT NOT NULL,
+                    in_reply_to TEXT,
+                    posted_at INTEGER NOT NULL
+                );
+                INSERT INTO history (tweet_id, root_id, position, text, account, posted_at)
+                    VALUES ('1', '1', 0, 'shipping the release today', 'me', 100);",
+            )
+            .unwrap();
+        }
+
+        let store = Store::open_at(&path).unwrap();
+        assert_eq!(user_version(&store.conn), MIGRATIONS.len());
+        let found = store.search_history("release", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, SOURCE_XCLI);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn newer_store_is_rejected() {
+        let (store, path) = temp_store("newer");
+        store
+            .conn
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        drop(store);
+
+        let err = Store::open_at(&path).err().unwrap();
+        assert!(err.contains("Upgrade xcli"));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reopen_keeps_data() {
+        let (store, path) = temp_store("reopen");
+        store.enqueue(ACTION_DELETE, "1", 100).unwrap();
+        drop(store);
+
+        let store = Store::open_at(&path).unwrap();
+        assert_eq!(store.queue_items().unwrap().len(), 1);
+
+        let _ = fs::remove_file(&path);
+    }
+}
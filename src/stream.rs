@@ -0,0 +1,347 @@
+//! Filtered-stream rule backups, and the disk-backed buffer and webhook
+//! delivery of `xcli stream forward`.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::api::StreamRule;
+use crate::transport::{Body, Method, Request, Transport};
+
+/// A slow receiver must not stall forwarding for long.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest rule X accepts, in characters (with Pro access; Basic allows 512).
+pub const MAX_RULE_CHARS: usize = 1024;
+
+/// Check a rule before it goes to X: not empty, not too long, and with its
+/// quotes and parentheses closed. X checks the rest of the syntax.
+pub fn check_rule(value: &str) -> Result<(), String> {
+    let chars = value.chars().count();
+    if value.trim().is_empty() {
+        return Err("A rule cannot be empty".to_string());
+    }
+    if chars > MAX_RULE_CHARS {
+        return Err(format!(
+            "Rule is {chars} characters long (X allows at most {MAX_RULE_CHARS})"
+        ));
+    }
+    let (mut quoted, mut depth, mut escaped) = (false, 0usize, false);
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => {
+                if depth == 0 {
+                    return Err(format!(
+                        "Rule `{value}` closes a parenthesis it never opened"
+                    ));
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted {
+        return Err(format!("Rule `{value}` has an unclosed quote"));
+    }
+    if depth > 0 {
+        return Err(format!("Rule `{value}` has an unclosed parenthesis"));
+    }
+    Ok(())
+}
+
+/// Serialize rules for a backup file: pretty JSON without X's rule IDs, so the
+/// file diffs cleanly and can be imported into another app.
+pub fn rules_to_json(rules: &[StreamRule]) -> String {
+    let mut rules: Vec<StreamRule> = rules
+        .iter()
+        .map(|r| StreamRule {
+            id: None,
+            ..r.clone()
+        })
+        .collect();
+    rules.sort_by(|a, b| a.tag.cmp(&b.tag).then_with(|| a.value.cmp(&b.value)));
+    let mut out = serde_json::to_string_pretty(&rules).unwrap();
+    out.push('\n');
+    out
+}
+
+/// Parse a backup file written by `rules_to_json`.
+pub fn parse_rules(content: &str) -> Result<Vec<StreamRule>, String> {
+    let rules: Vec<StreamRule> =
+        serde_json::from_str(content).map_err(|e| format!("Invalid rules file: {e}"))?;
+    for rule in &rules {
+        check_rule(&rule.value).map_err(|e| {
+            format!(
+                "Invalid rules file: {e} (rule with tag '{}')",
+                rule.tag.as_deref().unwrap_or("")
+            )
+        })?;
+    }
+    Ok(rules)
+}
+
+/// Rules from `wanted` whose value is not already in `existing`, without IDs.
+pub fn missing_rules(wanted: &[StreamRule], existing: &[StreamRule]) -> Vec<StreamRule> {
+    let mut seen: HashSet<&str> = existing.iter().map(|r| r.value.as_str()).collect();
+    wanted
+        .iter()
+        .filter(|r| seen.insert(r.value.as_str()))
+        .map(|r| StreamRule {
+            id: None,
+            ..r.clone()
+        })
+        .collect()
+}
+
+/// Tweets read from the stream and not yet delivered, one JSON line each in
+/// a file, so none are lost while the webhook is down or xcli restarts.
+/// Lines are added at the end and taken from the front.
+pub struct Spool {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl Spool {
+    pub fn new(path: PathBuf) -> Self {
+        Spool {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn lines(&self) -> Result<Vec<String>, String> {
+        match fs::read_to_string(&self.path) {
+            Ok(data) => Ok(data.lines().map(str::to_string).collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Failed to read {}: {e}", self.path.display())),
+        }
+    }
+
+    pub fn push(&self, line: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| writeln!(f, "{}", line.trim()))
+            .map_err(|e| format!("Failed to write {}: {e}", self.path.display()))
+    }
+
+    /// The oldest line, if any.
+    pub fn first(&self) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.lines()?.into_iter().next())
+    }
+
+    pub fn len(&self) -> Result<usize, String> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.lines()?.len())
+    }
+
+    /// Drop the oldest line; the file goes once it is empty.
+    pub fn pop(&self) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let lines = self.lines()?;
+        let result = if lines.len() <= 1 {
+            match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        } else {
+            fs::write(&self.path, lines[1..].join("\n") + "\n")
+        };
+        result.map_err(|e| format!("Failed to write {}: {e}", self.path.display()))
+    }
+}
+
+/// How a delivery went.
+#[derive(Debug, PartialEq)]
+pub enum Delivery {
+    Sent,
+    /// Worth trying again: the receiver is down, busy or failing.
+    Retry(String),
+    /// The receiver will not take it, ever.
+    Rejected(String),
+}
+
+/// POST `line`, a tweet as X streamed it, to the webhook at `url`.
+pub async fn deliver(transport: &dyn Transport, url: &str, line: &str) -> Delivery {
+    let mut req = Request::new(Method::POST, url).header("Content-Type", "application/json");
+    req.body = Body::Bytes(line.as_bytes().to_vec());
+    let resp = match tokio::time::timeout(TIMEOUT, transport.send(req)).await {
+        Ok(Ok(resp)) => resp,
+        Ok(Err(e)) => return Delivery::Retry(e),
+        Err(_) => {
+            return Delivery::Retry(format!(
+                "Webhook {url} did not answer within {}s",
+                TIMEOUT.as_secs()
+            ))
+        }
+    };
+    let status = resp.status;
+    if status.is_success() {
+        return Delivery::Sent;
+    }
+    let message = format!("Webhook {url} returned {status}: {}", resp.text());
+    if status.is_client_error() && status.as_u16() != 408 && status.as_u16() != 429 {
+        Delivery::Rejected(message)
+    } else {
+        Delivery::Retry(message)
+    }
+}
+
+/// The ID of the tweet in a streamed line.
+pub fn tweet_id(line: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()
+        .and_then(|v| v["data"]["id"].as_str().map(str::to_string))
+        .unwrap_or_else(|| "?".to_string())
+}
+
+/// How long to wait after `failures` failures in a row: `first`, doubling
+/// each time, at most `max`.
+pub fn backoff(first: Duration, max: Duration, failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    (first * 2u32.pow(doublings)).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use std::env::temp_dir;
+
+    fn rule(id: Option<&str>, value: &str, tag: Option<&str>) -> StreamRule {
+        StreamRule {
+            id: id.map(str::to_string),
+            value: value.to_string(),
+            tag: tag.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn export_drops_ids_and_round_trips() {
+        let rules = vec![
+            rule(Some("2"), "from:rustlang", Some("rust")),
+            rule(Some("1"), "cats has:images", Some("cats")),
+            rule(Some("3"), "xcli", None),
+        ];
+        let json = rules_to_json(&rules);
+        assert!(!json.contains("\"id\""));
+
+        let parsed = parse_rules(&json).unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                rule(None, "xcli", None),
+                rule(None, "cats has:images", Some("cats")),
+                rule(None, "from:rustlang", Some("rust")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(parse_rules("{}").is_err());
+        assert!(parse_rules(r#"[{"value": "  ", "tag": "x"}]"#).is_err());
+        assert!(parse_rules(r#"[{"value": "cats"}]"#).is_ok());
+    }
+
+    #[test]
+    fn checks_rules_before_sending_them() {
+        assert!(check_rule(r#"(from:rustlang OR #rustlang) -is:retweet "rust \"lang\"""#).is_ok());
+        assert!(check_rule(" ").is_err());
+        assert!(check_rule(&"a".repeat(MAX_RULE_CHARS + 1)).is_err());
+        assert!(check_rule("(cats OR dogs")
+            .unwrap_err()
+            .contains("unclosed parenthesis"));
+        assert!(check_rule("cats)").unwrap_err().contains("never opened"));
+        assert!(check_rule(r#""cats ("#)
+            .unwrap_err()
+            .contains("unclosed quote"));
+    }
+
+    #[test]
+    fn missing_rules_skips_existing_and_duplicates() {
+        let existing = vec![rule(Some("1"), "cats", Some("cats"))];
+        let wanted = vec![
+            rule(None, "cats", Some("other tag")),
+            rule(None, "dogs", Some("dogs")),
+            rule(None, "dogs", Some("dogs again")),
+        ];
+        assert_eq!(
+            missing_rules(&wanted, &existing),
+            vec![rule(None, "dogs", Some("dogs"))]
+        );
+    }
+
+    #[test]
+    fn spool_keeps_lines_in_order() {
+        let path = temp_dir().join(format!("xcli_test_{}_spool.jsonl", std::process::id()));
+        let spool = Spool::new(path.clone());
+        assert_eq!(spool.first().unwrap(), None);
+        spool.push(r#"{"data":{"id":"1"}}"#).unwrap();
+        spool.push(r#"{"data":{"id":"2"}}"#).unwrap();
+        assert_eq!(spool.len().unwrap(), 2);
+        assert_eq!(tweet_id(&spool.first().unwrap().unwrap()), "1");
+        spool.pop().unwrap();
+        // A restart picks up what is left.
+        let spool = Spool::new(path.clone());
+        assert_eq!(tweet_id(&spool.first().unwrap().unwrap()), "2");
+        spool.pop().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn delivery_tells_retries_from_rejections() {
+        let mock = MockTransport::new();
+        mock.respond(204, "")
+            .respond(503, "down")
+            .respond(400, "bad")
+            .respond(429, "slow down");
+        mock.push(Err("Request failed: connection refused".to_string()));
+        let line = r#"{"data":{"id":"1","text":"hi"}}"#;
+        let url = "https://hooks.test/x";
+        assert_eq!(deliver(&mock, url, line).await, Delivery::Sent);
+        assert!(matches!(
+            deliver(&mock, url, line).await,
+            Delivery::Retry(_)
+        ));
+        assert!(matches!(
+            deliver(&mock, url, line).await,
+            Delivery::Rejected(_)
+        ));
+        assert!(matches!(
+            deliver(&mock, url, line).await,
+            Delivery::Retry(_)
+        ));
+        assert!(matches!(
+            deliver(&mock, url, line).await,
+            Delivery::Retry(_)
+        ));
+
+        let req = &mock.requests()[0];
+        assert_eq!(req.url, url);
+        assert_eq!(req.body_text(), line);
+        assert_eq!(req.header_value("content-type"), Some("application/json"));
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let (first, max) = (Duration::from_secs(5), Duration::from_secs(320));
+        let delays: Vec<u64> = (1..=8).map(|n| backoff(first, max, n).as_secs()).collect();
+        assert_eq!(delays, [5, 10, 20, 40, 80, 160, 320, 320]);
+        assert_eq!(backoff(first, max, 1000), max);
+    }
+}
@@ -1,14 +1,46 @@
-const BASIC_LATIN_MAX: u32 = 0x10FF;
-const MAX_WEIGHTED_LEN: usize = 280;
+//! Weighted character counting and splitting long text into a thread.
+
+pub const MAX_WEIGHTED_LEN: usize = 280;
 const SEPARATOR: &str = "\n---\n";
 
+/// Code point ranges that weigh 1, sorted, from the official twitter-text
+/// configuration (v3). Everything else weighs 2.
+const LIGHT_RANGES: &[(u32, u32)] = &[
+    (0x0000, 0x10FF), // Latin, Greek, Cyrillic, Hebrew, Arabic, ...
+    (0x2000, 0x200D), // spaces and zero-width joiners
+    (0x2010, 0x201F), // dashes and curly quotes
+    (0x2032, 0x2037), // primes
+];
+
+fn char_weight(c: char) -> usize {
+    let cp = c as u32;
+    // Most text is in the first range; skip the search for it.
+    if cp <= LIGHT_RANGES[0].1 {
+        return 1;
+    }
+    let light = LIGHT_RANGES
+        .binary_search_by(|&(start, end)| {
+            if end < cp {
+                std::cmp::Ordering::Less
+            } else if start > cp {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok();
+    if light {
+        1
+    } else {
+        2
+    }
+}
+
 /// Compute X API weighted character count.
-/// - Basic Latin (U+0000-U+10FF): weight 1
+/// - U+0000-U+10FF and some punctuation (dashes, curly quotes): weight 1
 /// - Everything else (CJK, Korean, emoji, etc.): weight 2
 pub fn weighted_len(text: &str) -> usize {
-    text.chars()
-        .map(|c| if (c as u32) <= BASIC_LATIN_MAX { 1 } else { 2 })
-        .sum()
+    text.chars().map(char_weight).sum()
 }
 
 /// Split text into tweet-sized chunks.
@@ -19,78 +51,163 @@ pub fn weighted_len(text: &str) -> usize {
 ///    - then word boundaries
 /// 3. If text fits in one tweet, return it as-is.
 pub fn split_text(text: &str) -> Vec<String> {
+    split_iter(text).collect()
+}
+
+/// The chunks of [`split_text`], produced lazily in one pass over the text.
+/// Every character is weighed once, so multi-megabyte input (e.g. a whole
+/// essay from `--file`) splits in linear time.
+pub fn split_iter(text: &str) -> Box<dyn Iterator<Item = String> + '_> {
+    split_limited(text, MAX_WEIGHTED_LEN)
+}
+
+/// Split `text` like [`split_text`], then put `prefix` (e.g. hashtags) before
+/// the first chunk and `suffix` (e.g. a signature) after the last. A chunk
+/// they do not fit on is split again with room for them.
+pub fn split_with_affixes(text: &str, prefix: &str, suffix: &str) -> Vec<String> {
+    let mut chunks = split_text(text);
+    let (before, after) = (weighted_len(prefix), weighted_len(suffix));
+    if after > 0 {
+        let last = chunks.pop().unwrap_or_default();
+        // A single chunk needs room for both.
+        let reserve = after + if chunks.is_empty() { before } else { 0 };
+        chunks.extend(refit(last, reserve));
+    }
+    if before > 0 {
+        let first = if chunks.is_empty() {
+            String::new()
+        } else {
+            chunks.remove(0)
+        };
+        let fitted = refit(first, before);
+        chunks.splice(0..0, fitted);
+    }
+    if let Some(first) = chunks.first_mut() {
+        first.insert_str(0, prefix);
+    }
+    if let Some(last) = chunks.last_mut() {
+        last.push_str(suffix);
+    }
+    chunks
+}
+
+/// `chunk`, split again if need be so that `reserve` more fits on each piece.
+fn refit(chunk: String, reserve: usize) -> Vec<String> {
+    if weighted_len(&chunk) + reserve <= MAX_WEIGHTED_LEN || reserve >= MAX_WEIGHTED_LEN {
+        return vec![chunk];
+    }
+    split_limited(&chunk, MAX_WEIGHTED_LEN - reserve).collect()
+}
+
+/// The `tags` not already in `text` (case-insensitively), each with a `#`.
+pub fn missing_hashtags(text: &str, tags: &[String]) -> Vec<String> {
+    let present: Vec<String> = text
+        .split_whitespace()
+        .map(|w| {
+            w.trim_end_matches(|c: char| c.is_ascii_punctuation())
+                .to_lowercase()
+        })
+        .collect();
+    let mut missing: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = format!("#{}", tag.trim().trim_start_matches('#'));
+        let known = |t: &String| t.to_lowercase() == tag.to_lowercase();
+        if tag.len() > 1 && !present.iter().any(known) && !missing.iter().any(known) {
+            missing.push(tag);
+        }
+    }
+    missing
+}
+
+/// [`split_iter`] with chunks of at most `limit`.
+pub fn split_limited(text: &str, limit: usize) -> Box<dyn Iterator<Item = String> + '_> {
     // 1. Check for separator
     if text.contains(SEPARATOR) {
-        let parts: Vec<String> = text
+        let mut parts = text
             .split(SEPARATOR)
-            .map(|s| s.trim().to_string())
+            .map(str::trim)
             .filter(|s| !s.is_empty())
-            .collect();
-        if !parts.is_empty() {
-            return parts;
+            .peekable();
+        if parts.peek().is_some() {
+            return Box::new(parts.map(str::to_string));
         }
     }
 
     // 2. If fits in one tweet, return as-is
-    if weighted_len(text) <= MAX_WEIGHTED_LEN {
-        return vec![text.to_string()];
+    if weighted_len(text) <= limit {
+        return Box::new(std::iter::once(text.to_string()));
     }
 
-    // 3. Auto-split
-    auto_split(text)
+    // 3. Auto-split, paragraph by paragraph if there are any
+    if !text.contains("\n\n") {
+        return Box::new(Pack::new(units(text, limit), limit));
+    }
+    Box::new(
+        text.split("\n\n")
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .flat_map(move |p| -> Box<dyn Iterator<Item = String>> {
+                if weighted_len(p) <= limit {
+                    Box::new(std::iter::once(p.to_string()))
+                } else {
+                    Box::new(Pack::new(units(p, limit), limit))
+                }
+            }),
+    )
 }
 
-fn auto_split(text: &str) -> Vec<String> {
-    // Try paragraph split first
-    let paragraphs: Vec<&str> = text.split("\n\n").collect();
-    if paragraphs.len() > 1 {
-        let mut result = Vec::new();
-        for p in paragraphs {
-            let trimmed = p.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-            if weighted_len(trimmed) <= MAX_WEIGHTED_LEN {
-                result.push(trimmed.to_string());
-            } else {
-                result.extend(split_by_sentences(trimmed));
-            }
+/// The pieces a long paragraph is packed from: its sentences, or the words
+/// of sentences too long for one tweet.
+fn units(text: &str, limit: usize) -> impl Iterator<Item = &str> {
+    SentenceIter::new(text).flat_map(move |sentence| -> Box<dyn Iterator<Item = &str>> {
+        if weighted_len(sentence) <= limit {
+            Box::new(std::iter::once(sentence))
+        } else {
+            Box::new(sentence.split_whitespace())
         }
-        return result;
-    }
-
-    // No paragraph breaks — split by sentences
-    let sentence_chunks = split_by_sentences(text);
-    if sentence_chunks.len() > 1 {
-        return sentence_chunks;
-    }
-
-    // No sentence breaks — split by words
-    split_by_words(text)
+    })
 }
 
-fn split_by_sentences(text: &str) -> Vec<String> {
-    let mut chunks: Vec<String> = Vec::new();
-    let mut current = String::new();
+/// Greedily joins units with spaces into chunks of at most `limit`, keeping
+/// a running weight instead of re-counting each candidate.
+struct Pack<'a, I: Iterator<Item = &'a str>> {
+    units: I,
+    limit: usize,
+    current: String,
+    weight: usize,
+}
 
-    for part in SentenceIter::new(text) {
-        if current.is_empty() {
-            current = part;
-        } else if weighted_len(&format!("{current} {part}")) <= MAX_WEIGHTED_LEN {
-            current = format!("{current} {part}");
-        } else {
-            chunks.push(current);
-            current = part;
+impl<'a, I: Iterator<Item = &'a str>> Pack<'a, I> {
+    fn new(units: I, limit: usize) -> Self {
+        Pack {
+            units,
+            limit,
+            current: String::new(),
+            weight: 0,
         }
     }
-    if !current.is_empty() {
-        if weighted_len(&current) <= MAX_WEIGHTED_LEN {
-            chunks.push(current);
-        } else {
-            chunks.extend(split_by_words(&current));
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Iterator for Pack<'a, I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        for unit in self.units.by_ref() {
+            let weight = weighted_len(unit);
+            if self.current.is_empty() {
+                self.current.push_str(unit);
+                self.weight = weight;
+            } else if self.weight + 1 + weight <= self.limit {
+                self.current.push(' ');
+                self.current.push_str(unit);
+                self.weight += 1 + weight;
+            } else {
+                self.weight = weight;
+                return Some(std::mem::replace(&mut self.current, unit.to_string()));
+            }
         }
+        (!self.current.is_empty()).then(|| std::mem::take(&mut self.current))
     }
-    chunks
 }
 
 /// Iterator that splits text on sentence-ending punctuation followed by a space.
@@ -108,35 +225,30 @@ impl<'a> SentenceIter<'a> {
 }
 
 impl<'a> Iterator for SentenceIter<'a> {
-    type Item = String;
+    type Item = &'a str;
 
-    fn next(&mut self) -> Option<String> {
+    fn next(&mut self) -> Option<&'a str> {
         if self.remaining.is_empty() {
             return None;
         }
 
-        // Find the next sentence boundary: ". " or "! " or "? "
-        let terminators = [". ", "! ", "? "];
-        let mut earliest: Option<(usize, usize)> = None;
-
-        for t in &terminators {
-            if let Some(pos) = self.remaining.find(t) {
-                match earliest {
-                    None => earliest = Some((pos, t.len())),
-                    Some((ep, _)) if pos < ep => earliest = Some((pos, t.len())),
-                    _ => {}
-                }
-            }
-        }
-
-        match earliest {
-            Some((pos, tlen)) => {
-                let sentence = self.remaining[..pos + tlen - 1].trim().to_string();
-                self.remaining = self.remaining[pos + tlen..].trim();
+        // Find the next sentence boundary: ". " or "! " or "? ", in a single
+        // scan (searching for each terminator separately rescans the rest of
+        // the text per sentence).
+        let boundary = self
+            .remaining
+            .as_bytes()
+            .windows(2)
+            .position(|w| matches!(w[0], b'.' | b'!' | b'?') && w[1] == b' ');
+
+        match boundary {
+            Some(pos) => {
+                let sentence = self.remaining[..=pos].trim();
+                self.remaining = self.remaining[pos + 2..].trim();
                 Some(sentence)
             }
             None => {
-                let rest = self.remaining.trim().to_string();
+                let rest = self.remaining.trim();
                 self.remaining = "";
                 Some(rest)
             }
@@ -144,29 +256,6 @@ impl<'a> Iterator for SentenceIter<'a> {
     }
 }
 
-fn split_by_words(text: &str) -> Vec<String> {
-    let mut chunks: Vec<String> = Vec::new();
-    let mut current = String::new();
-
-    for word in text.split_whitespace() {
-        if current.is_empty() {
-            current = word.to_string();
-        } else {
-            let candidate = format!("{current} {word}");
-            if weighted_len(&candidate) <= MAX_WEIGHTED_LEN {
-                current = candidate;
-            } else {
-                chunks.push(current);
-                current = word.to_string();
-            }
-        }
-    }
-    if !current.is_empty() {
-        chunks.push(current);
-    }
-    chunks
-}
-
 /// Validate that all chunks fit within the tweet limit.
 /// Returns Err with the index and length of the first oversized chunk.
 pub fn validate_chunks(chunks: &[String]) -> Result<(), (usize, usize)> {
@@ -209,6 +298,15 @@ mod tests {
         assert_eq!(weighted_len("😀"), 2);
     }
 
+    #[test]
+    fn typographic_punctuation_is_light() {
+        // en dash, curly quotes, ellipsis (U+2026 is outside the light ranges)
+        assert_eq!(weighted_len("a–b"), 3);
+        assert_eq!(weighted_len("“hi”"), 4);
+        assert_eq!(weighted_len("…"), 2);
+        assert_eq!(weighted_len("\u{2037}\u{2038}"), 3);
+    }
+
     // split_text tests
     #[test]
     fn short_text_no_split() {
@@ -253,7 +351,7 @@ mod tests {
     #[test]
     fn auto_split_on_words() {
         let word = "abcdefghij"; // 10 chars
-        let words: Vec<&str> = std::iter::repeat(word).take(30).collect();
+        let words: Vec<&str> = std::iter::repeat_n(word, 30).collect();
         let text = words.join(" ");
         let result = split_text(&text);
         assert!(result.len() >= 2);
@@ -262,6 +360,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn long_sentence_is_split_by_words() {
+        let long = ["word"; 80].join(" ");
+        let text = format!("Short one. {long}. Tail.");
+        let result = split_text(&text);
+        assert_eq!(result.len(), 2);
+        assert!(result[0].starts_with("Short one. word"));
+        assert!(result[1].ends_with("word. Tail."));
+        assert!(validate_chunks(&result).is_ok());
+    }
+
+    #[test]
+    fn large_input_splits_lazily() {
+        let paragraph = "Sentence one is here. Sentence two follows! Does it end? ".repeat(40);
+        let text = [paragraph.trim(); 500].join("\n\n");
+        let mut chunks = split_iter(&text);
+        let first = chunks.next().unwrap();
+        assert!(first.ends_with("Sentence two follows!"));
+        assert_eq!(weighted_len(&first), 271);
+        let rest: Vec<String> = chunks.collect();
+        assert!(rest.len() > 500);
+        assert!(validate_chunks(&rest).is_ok());
+    }
+
     #[test]
     fn separator_empty_parts_filtered() {
         let result = split_text("only part\n---\n\n---\n");
@@ -280,4 +402,55 @@ mod tests {
         let chunks = vec!["a".repeat(281)];
         assert_eq!(validate_chunks(&chunks), Err((0, 281)));
     }
+
+    #[test]
+    fn affixes_go_on_the_first_and_last_chunks() {
+        assert_eq!(
+            split_with_affixes("Hello", "", " — via xcli"),
+            vec!["Hello — via xcli"]
+        );
+        assert_eq!(
+            split_with_affixes("One\n---\nTwo", "#rust ", " — me"),
+            vec!["#rust One", "Two — me"]
+        );
+        assert_eq!(split_with_affixes("Hello", "", ""), vec!["Hello"]);
+    }
+
+    #[test]
+    fn affix_width_is_reserved() {
+        // Fits alone, but not with the suffix: the words move on.
+        let text = ["word"; 55].join(" ");
+        assert_eq!(weighted_len(&text), 274);
+        let chunks = split_with_affixes(&text, "", " — via xcli");
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].ends_with("word — via xcli"));
+        assert!(validate_chunks(&chunks).is_ok());
+        assert_eq!(chunks.join(" ").replace(" — via xcli", ""), text);
+
+        let chunks = split_with_affixes(&text, "#a ", " #b");
+        assert!(chunks[0].starts_with("#a word"));
+        assert!(chunks.last().unwrap().ends_with("word #b"));
+        assert!(validate_chunks(&chunks).is_ok());
+
+        let chunks = split_with_affixes(&format!("{text}\n---\nend"), "#rustlang ", "");
+        assert_eq!(chunks.len(), 3);
+        assert!(validate_chunks(&chunks).is_ok());
+    }
+
+    #[test]
+    fn hashtags_already_in_the_text_are_skipped() {
+        let tags = [
+            "#RustLang".to_string(),
+            "xcli".to_string(),
+            "#rust".to_string(),
+        ];
+        assert_eq!(
+            missing_hashtags("Loving #rustlang, and #rust.", &tags),
+            vec!["#xcli"]
+        );
+        assert_eq!(
+            missing_hashtags("", &tags),
+            vec!["#RustLang", "#xcli", "#rust"]
+        );
+    }
 }
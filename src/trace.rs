@@ -0,0 +1,450 @@
+//! `--trace-file`: a sanitized log of every HTTP exchange of one run, to
+//! attach to bug reports.
+//!
+//! The file is JSON Lines: a `run` record with the command line, then one
+//! `exchange` record per request in the order they completed. Secrets are
+//! scrubbed the way cassettes scrub them, and credential headers keep only
+//! their scheme.
+//!
+//! `xcli debug replay` runs the traced command line again, answering its
+//! requests from the trace, to reproduce what the user saw.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::cassette::redact;
+use crate::transport::{Body, OnLine, Request, Response, StatusCode, Transport};
+
+const REDACTED: &str = "REDACTED";
+
+/// Headers whose values are credentials.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Global flags of the traced run that must not take effect again on replay.
+const RUN_FLAGS: &[&str] = &["--trace-file", "--record", "--replay"];
+
+/// Flags whose values are credentials (`xcli auth setup`).
+const SECRET_FLAGS: &[&str] = &[
+    "--api-key",
+    "--api-secret",
+    "--access-token",
+    "--access-token-secret",
+];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Run {
+        version: String,
+        started_at: String,
+        args: Vec<String>,
+    },
+    Exchange(Exchange),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Exchange {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub request_headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub request_body: String,
+    /// Absent when the request failed before a response arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub response_headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub response_body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Milliseconds from the start of the run to the request being sent.
+    pub started_ms: u64,
+    pub duration_ms: u64,
+}
+
+/// Replace credential arguments with a placeholder.
+pub fn sanitize_args(args: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(args.len());
+    let mut secret_next = false;
+    for arg in args {
+        if secret_next {
+            out.push(REDACTED.to_string());
+            secret_next = false;
+        } else if let Some((flag, _)) = arg
+            .split_once('=')
+            .filter(|(f, _)| SECRET_FLAGS.contains(f))
+        {
+            out.push(format!("{flag}={REDACTED}"));
+        } else {
+            secret_next = SECRET_FLAGS.contains(&arg.as_str());
+            out.push(arg.clone());
+        }
+    }
+    out
+}
+
+fn sanitize_headers(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            if !SECRET_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h)) {
+                return (name.clone(), redact(value));
+            }
+            // Keep the scheme (`OAuth`, `Bearer`, `Basic`) for debugging.
+            let value = match value.split_once(' ') {
+                Some((scheme, _)) if name.to_ascii_lowercase().ends_with("authorization") => {
+                    format!("{scheme} {REDACTED}")
+                }
+                _ => REDACTED.to_string(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+fn describe_body(req: &Request) -> String {
+    match &req.body {
+        Body::Multipart(parts) => parts
+            .iter()
+            .map(|p| match &p.file_name {
+                Some(file) => format!("[{}: {file}, {} bytes]", p.name, p.data.len()),
+                None => format!("[{}: {}]", p.name, String::from_utf8_lossy(&p.data)),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => redact(&req.body_text()),
+    }
+}
+
+/// An open trace file, shared by every transport of the run.
+pub struct Trace {
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Trace {
+    /// Create (or truncate) `path` and write the `run` record.
+    pub fn create(path: &Path, args: &[String]) -> Result<Self, String> {
+        let file =
+            File::create(path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        let trace = Trace {
+            file: Mutex::new(file),
+            started: Instant::now(),
+        };
+        trace.write(&Record::Run {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            args: sanitize_args(args),
+        });
+        Ok(trace)
+    }
+
+    /// Tracing must never break the command, so write errors are dropped.
+    fn write(&self, record: &Record) {
+        if let Ok(mut line) = serde_json::to_string(record) {
+            line.push('\n');
+            let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+        }
+    }
+}
+
+/// Wraps a transport and logs every exchange to a [`Trace`].
+pub struct TracingTransport {
+    inner: Arc<dyn Transport>,
+    trace: Arc<Trace>,
+}
+
+impl TracingTransport {
+    pub fn new(inner: Arc<dyn Transport>, trace: Arc<Trace>) -> Self {
+        TracingTransport { inner, trace }
+    }
+}
+
+impl TracingTransport {
+    fn start(&self, req: &Request) -> Exchange {
+        Exchange {
+            method: req.method.to_string(),
+            url: redact(&req.url),
+            request_headers: sanitize_headers(&req.headers),
+            request_body: describe_body(req),
+            status: None,
+            response_headers: Vec::new(),
+            response_body: String::new(),
+            error: None,
+            started_ms: self.trace.started.elapsed().as_millis() as u64,
+            duration_ms: 0,
+        }
+    }
+
+    fn finish(&self, mut exchange: Exchange, sent: Instant, result: &Result<Response, String>) {
+        exchange.duration_ms = sent.elapsed().as_millis() as u64;
+        match result {
+            Ok(resp) => {
+                exchange.status = Some(resp.status.as_u16());
+                exchange.response_headers = sanitize_headers(&resp.headers);
+                exchange.response_body = redact(&resp.text());
+            }
+            Err(e) => exchange.error = Some(e.clone()),
+        }
+        self.trace.write(&Record::Exchange(exchange));
+    }
+}
+
+#[async_trait]
+impl Transport for TracingTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let exchange = self.start(&req);
+        let sent = Instant::now();
+        let result = self.inner.send(req).await;
+        self.finish(exchange, sent, &result);
+        result
+    }
+
+    /// The lines of a stream are not traced, only how it ended.
+    async fn send_lines(&self, req: Request, line: &mut OnLine<'_>) -> Result<Response, String> {
+        let exchange = self.start(&req);
+        let sent = Instant::now();
+        let result = self.inner.send_lines(req, line).await;
+        self.finish(exchange, sent, &result);
+        result
+    }
+}
+
+/// A trace read back from disk.
+pub struct TraceFile {
+    pub version: String,
+    pub started_at: String,
+    /// The traced command line, including the program name.
+    pub args: Vec<String>,
+    pub exchanges: Vec<Exchange>,
+}
+
+impl TraceFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let mut records = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let parse = |(n, line): (usize, &str)| {
+            serde_json::from_str::<Record>(line)
+                .map_err(|e| format!("Invalid trace {} line {}: {e}", path.display(), n + 1))
+        };
+        let Some(Record::Run {
+            version,
+            started_at,
+            args,
+        }) = records.next().map(parse).transpose()?
+        else {
+            return Err(format!(
+                "{} is not an xcli trace (no run record)",
+                path.display()
+            ));
+        };
+        let mut exchanges = Vec::new();
+        for record in records {
+            match parse(record)? {
+                Record::Exchange(exchange) => exchanges.push(exchange),
+                Record::Run { .. } => {
+                    return Err(format!("{} holds more than one run", path.display()))
+                }
+            }
+        }
+        Ok(TraceFile {
+            version,
+            started_at,
+            args,
+            exchanges,
+        })
+    }
+
+    /// The traced arguments without the program name and without the flags
+    /// that set up tracing, recording or replay.
+    pub fn replay_args(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut args = self.args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            if RUN_FLAGS.contains(&arg.as_str()) {
+                args.next();
+            } else if !RUN_FLAGS.iter().any(|f| arg.starts_with(&format!("{f}="))) {
+                out.push(arg.clone());
+            }
+        }
+        out
+    }
+}
+
+/// Answers requests from the exchanges of a trace, in order.
+pub struct TraceReplayTransport {
+    exchanges: Mutex<std::vec::IntoIter<Exchange>>,
+}
+
+impl TraceReplayTransport {
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        TraceReplayTransport {
+            exchanges: Mutex::new(exchanges.into_iter()),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for TraceReplayTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let path = |url: &str| url.split('?').next().unwrap_or_default().to_string();
+        let Some(next) = self.exchanges.lock().unwrap().next() else {
+            return Err(format!(
+                "The trace has no more responses (next request: {} {})",
+                req.method, req.url
+            ));
+        };
+        if next.method != req.method.as_str() || path(&next.url) != path(&req.url) {
+            return Err(format!(
+                "Request does not match the trace: expected {} {}, got {} {}",
+                next.method, next.url, req.method, req.url
+            ));
+        }
+        let Some(status) = next.status else {
+            return Err(next.error.unwrap_or_else(|| "Request failed".to_string()));
+        };
+        Ok(Response {
+            status: StatusCode::from_u16(status)
+                .map_err(|e| format!("Invalid status in trace: {e}"))?,
+            headers: next.response_headers,
+            body: next.response_body.into_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{Method, MockTransport};
+    use std::env::temp_dir;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn redacts_credential_arguments() {
+        assert_eq!(
+            sanitize_args(&strings(&[
+                "xcli",
+                "auth",
+                "setup",
+                "--api-key",
+                "k",
+                "--api-secret=s"
+            ])),
+            strings(&[
+                "xcli",
+                "auth",
+                "setup",
+                "--api-key",
+                "REDACTED",
+                "--api-secret=REDACTED"
+            ])
+        );
+        assert_eq!(
+            sanitize_args(&strings(&["xcli", "tweet", "hi"])),
+            strings(&["xcli", "tweet", "hi"])
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_auth_scheme() {
+        let headers = sanitize_headers(&[
+            (
+                "Authorization".to_string(),
+                "OAuth oauth_token=\"t\"".to_string(),
+            ),
+            ("set-cookie".to_string(), "guest_id=1".to_string()),
+            ("x-transaction-id".to_string(), "abc".to_string()),
+        ]);
+        assert_eq!(headers[0].1, "OAuth REDACTED");
+        assert_eq!(headers[1].1, "REDACTED");
+        assert_eq!(headers[2].1, "abc");
+    }
+
+    #[tokio::test]
+    async fn logs_exchanges_and_failures() {
+        let path = temp_dir().join(format!("xcli_test_{}_trace.jsonl", std::process::id()));
+        let trace = Arc::new(Trace::create(&path, &strings(&["xcli", "delete", "1"])).unwrap());
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(200, r#"{"data":{"deleted":true}}"#)
+            .push(Err("Request failed: timeout".to_string()));
+        let transport = TracingTransport::new(mock, trace);
+
+        let req = Request::new(Method::DELETE, "https://api.x.com/2/tweets/1")
+            .header("Authorization", "OAuth oauth_signature=\"x\"");
+        transport.send(req.clone()).await.unwrap();
+        transport.send(req).await.unwrap_err();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<Record> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert!(matches!(&records[0], Record::Run { args, .. } if args[1] == "delete"));
+        let Record::Exchange(ok) = &records[1] else {
+            panic!("expected an exchange");
+        };
+        assert_eq!(ok.status, Some(200));
+        assert_eq!(ok.request_headers[0].1, "OAuth REDACTED");
+        let Record::Exchange(failed) = &records[2] else {
+            panic!("expected an exchange");
+        };
+        assert_eq!(failed.error.as_deref(), Some("Request failed: timeout"));
+        assert!(!text.contains("oauth_signature"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn replays_a_trace() {
+        let path = temp_dir().join(format!("xcli_test_{}_replay.jsonl", std::process::id()));
+        let args = strings(&[
+            "xcli",
+            "--trace-file",
+            "t.jsonl",
+            "--verbose",
+            "delete",
+            "1",
+        ]);
+        let trace = Arc::new(Trace::create(&path, &args).unwrap());
+        let mock = Arc::new(MockTransport::new());
+        mock.respond(200, r#"{"data":{"deleted":true}}"#)
+            .push(Err("Request failed: timeout".to_string()));
+        let transport = TracingTransport::new(mock, trace);
+        let req = Request::new(Method::DELETE, "https://api.x.com/2/tweets/1");
+        transport.send(req.clone()).await.unwrap();
+        transport.send(req.clone()).await.unwrap_err();
+
+        let loaded = TraceFile::load(&path).unwrap();
+        assert_eq!(loaded.replay_args(), strings(&["--verbose", "delete", "1"]));
+        let replay = TraceReplayTransport::new(loaded.exchanges);
+        let resp = replay.send(req.clone()).await.unwrap();
+        assert_eq!(resp.text(), r#"{"data":{"deleted":true}}"#);
+        assert_eq!(
+            replay.send(req.clone()).await.unwrap_err(),
+            "Request failed: timeout"
+        );
+        assert!(replay.send(req).await.unwrap_err().contains("no more"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,396 @@
+//! The HTTP layer under [`crate::XClient`], behind a trait so API logic can be
+//! exercised without a network (see [`MockTransport`]).
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use reqwest::{Method, StatusCode};
+
+const X_API_ORIGIN: &str = "https://api.x.com";
+
+/// X sends a keep-alive line every 20 seconds on a stream; after this long
+/// without one the connection is taken for dead.
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A fully built request. Headers are kept in order as `(name, value)`.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Body {
+    Empty,
+    Bytes(Vec<u8>),
+    Multipart(Vec<Part>),
+}
+
+/// One field of a `multipart/form-data` body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part {
+    pub name: String,
+    pub data: Vec<u8>,
+    pub file_name: Option<String>,
+    pub mime: Option<String>,
+}
+
+impl Request {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Request {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Body::Empty,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn json<T: Serialize>(self, body: &T) -> Self {
+        let bytes = serde_json::to_vec(body).expect("request body serializes to JSON");
+        let mut req = self.header("Content-Type", "application/json");
+        req.body = Body::Bytes(bytes);
+        req
+    }
+
+    pub fn form(self, body: &str) -> Self {
+        let mut req = self.header(
+            "Content-Type",
+            "application/x-www-form-urlencoded;charset=UTF-8",
+        );
+        req.body = Body::Bytes(body.as_bytes().to_vec());
+        req
+    }
+
+    pub fn multipart(mut self, parts: Vec<Part>) -> Self {
+        self.body = Body::Multipart(parts);
+        self
+    }
+
+    /// First header with this name, ignoring case.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// The body as text, for assertions and logs. Empty for multipart bodies.
+    pub fn body_text(&self) -> String {
+        match &self.body {
+            Body::Bytes(b) => String::from_utf8_lossy(b).into_owned(),
+            Body::Empty | Body::Multipart(_) => String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status: StatusCode::from_u16(status).expect("valid HTTP status"),
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_slice(&self.body).map_err(|e| format!("Failed to parse response: {e}"))
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Called with each line of a streamed response body.
+pub type OnLine<'a> = dyn FnMut(&str) + Send + 'a;
+
+/// Sends requests. Errors are transport failures (DNS, TLS, timeouts); HTTP
+/// error statuses are returned as responses.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, req: Request) -> Result<Response, String>;
+
+    /// Send a request whose response body is a stream of lines that may
+    /// never end, like X's filtered stream, passing each line (without its
+    /// line break) to `line` as it arrives. A successful response comes back
+    /// once the stream ends, with an empty body. The default reads the whole
+    /// body first, which does for finite bodies.
+    async fn send_lines(&self, req: Request, line: &mut OnLine<'_>) -> Result<Response, String> {
+        let mut resp = self.send(req).await?;
+        if resp.status.is_success() {
+            for l in resp.text().lines() {
+                line(l.trim_end_matches('\r'));
+            }
+            resp.body.clear();
+        }
+        Ok(resp)
+    }
+}
+
+/// The real transport, over `reqwest`.
+#[derive(Default)]
+pub struct HttpTransport {
+    http: reqwest::Client,
+    origin: Option<String>,
+}
+
+impl HttpTransport {
+    /// Send requests meant for `https://api.x.com` to `origin` instead
+    /// (e.g. `http://127.0.0.1:8080`), for running against a fake X API.
+    pub fn with_origin(origin: impl Into<String>) -> Self {
+        HttpTransport {
+            http: reqwest::Client::default(),
+            origin: Some(origin.into().trim_end_matches('/').to_string()),
+        }
+    }
+
+    fn rewrite(&self, url: &str) -> String {
+        match (&self.origin, url.strip_prefix(X_API_ORIGIN)) {
+            (Some(origin), Some(rest)) => format!("{origin}{rest}"),
+            _ => url.to_string(),
+        }
+    }
+
+    fn build(&self, req: Request) -> Result<reqwest::RequestBuilder, String> {
+        let mut builder = self.http.request(req.method, self.rewrite(&req.url));
+        for (name, value) in &req.headers {
+            builder = builder.header(name, value);
+        }
+        builder = match req.body {
+            Body::Empty => builder,
+            Body::Bytes(bytes) => builder.body(bytes),
+            Body::Multipart(parts) => {
+                let mut form = reqwest::multipart::Form::new();
+                for part in parts {
+                    let mut p = reqwest::multipart::Part::bytes(part.data);
+                    if let Some(file_name) = part.file_name {
+                        p = p.file_name(file_name);
+                    }
+                    if let Some(mime) = part.mime {
+                        p = p
+                            .mime_str(&mime)
+                            .map_err(|e| format!("Invalid media type: {e}"))?;
+                    }
+                    form = form.part(part.name, p);
+                }
+                builder.multipart(form)
+            }
+        };
+        Ok(builder)
+    }
+}
+
+fn response_headers(resp: &reqwest::Response) -> Vec<(String, String)> {
+    resp.headers()
+        .iter()
+        .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+        .collect()
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let resp = self
+            .build(req)?
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))?;
+        let status = resp.status();
+        let headers = response_headers(&resp);
+        let body = resp
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read response: {e}"))?
+            .to_vec();
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    async fn send_lines(&self, req: Request, line: &mut OnLine<'_>) -> Result<Response, String> {
+        let mut resp = self
+            .build(req)?
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))?;
+        let status = resp.status();
+        let headers = response_headers(&resp);
+        if !status.is_success() {
+            let body = resp
+                .bytes()
+                .await
+                .map_err(|e| format!("Failed to read response: {e}"))?
+                .to_vec();
+            return Ok(Response {
+                status,
+                headers,
+                body,
+            });
+        }
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let chunk = tokio::time::timeout(STREAM_IDLE_TIMEOUT, resp.chunk())
+                .await
+                .map_err(|_| {
+                    format!(
+                        "Nothing arrived on the stream for {}s",
+                        STREAM_IDLE_TIMEOUT.as_secs()
+                    )
+                })?
+                .map_err(|e| format!("Failed to read response: {e}"))?;
+            let Some(chunk) = chunk else {
+                break;
+            };
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let l: Vec<u8> = pending.drain(..=end).collect();
+                line(String::from_utf8_lossy(&l).trim_end_matches(['\r', '\n']));
+            }
+        }
+        Ok(Response {
+            status,
+            headers,
+            body: Vec::new(),
+        })
+    }
+}
+
+/// Replies with queued responses in order and records every request, for
+/// tests.
+#[derive(Default)]
+pub struct MockTransport {
+    responses: Mutex<VecDeque<Result<Response, String>>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response with this status and body.
+    pub fn respond(&self, status: u16, body: &str) -> &Self {
+        self.push(Ok(Response::new(status, body)))
+    }
+
+    /// Queue a response, or a transport error.
+    pub fn push(&self, response: Result<Response, String>) -> &Self {
+        self.responses.lock().unwrap().push_back(response);
+        self
+    }
+
+    /// Requests sent so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn send(&self, req: Request) -> Result<Response, String> {
+        let summary = format!("{} {}", req.method, req.url);
+        self.requests.lock().unwrap().push(req);
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| Err(format!("MockTransport: no response queued for {summary}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_are_case_insensitive() {
+        let req = Request::new(Method::GET, "https://x.test").header("Authorization", "OAuth a");
+        assert_eq!(req.header_value("authorization"), Some("OAuth a"));
+        let resp = Response::new(200, "{}").with_header("X-Rate-Limit-Reset", "10");
+        assert_eq!(resp.header("x-rate-limit-reset"), Some("10"));
+    }
+
+    #[test]
+    fn json_body_sets_content_type() {
+        let req = Request::new(Method::POST, "https://x.test").json(&serde_json::json!({"a": 1}));
+        assert_eq!(req.header_value("content-type"), Some("application/json"));
+        assert_eq!(req.body_text(), r#"{"a":1}"#);
+    }
+
+    #[test]
+    fn origin_override_only_rewrites_x_api_urls() {
+        let http = HttpTransport::with_origin("http://127.0.0.1:9000/");
+        assert_eq!(
+            http.rewrite("https://api.x.com/2/tweets?a=1"),
+            "http://127.0.0.1:9000/2/tweets?a=1"
+        );
+        assert_eq!(http.rewrite("https://x.test/a"), "https://x.test/a");
+        assert_eq!(
+            HttpTransport::default().rewrite("https://api.x.com/2/tweets"),
+            "https://api.x.com/2/tweets"
+        );
+    }
+
+    #[tokio::test]
+    async fn mock_replays_in_order_and_records() {
+        let mock = MockTransport::new();
+        mock.respond(200, "first").respond(404, "second");
+
+        let a = mock
+            .send(Request::new(Method::GET, "https://x.test/a"))
+            .await
+            .unwrap();
+        let b = mock
+            .send(Request::new(Method::DELETE, "https://x.test/b"))
+            .await
+            .unwrap();
+        assert_eq!(a.text(), "first");
+        assert_eq!(b.status, StatusCode::NOT_FOUND);
+
+        let err = mock
+            .send(Request::new(Method::GET, "https://x.test/c"))
+            .await
+            .unwrap_err();
+        assert!(err.contains("GET https://x.test/c"));
+
+        let urls: Vec<_> = mock.requests().into_iter().map(|r| r.url).collect();
+        assert_eq!(
+            urls,
+            vec!["https://x.test/a", "https://x.test/b", "https://x.test/c"]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Smart typography (`typography = true` in a profile): straight quotes become
+//! curly, `--` an em dash and `...` an ellipsis, before the text is split.
+//!
+//! URLs are left alone, as are `--flags` and runs of other lengths, so the
+//! `---` thread separator survives.
+
+/// Characters after which a quote opens rather than closes.
+fn opens_after(prev: Option<char>) -> bool {
+    prev.is_none_or(|c| c.is_whitespace() || "([{<—–-/".contains(c))
+}
+
+fn run_len(chars: &[char], i: usize) -> usize {
+    chars[i..].iter().take_while(|&&c| c == chars[i]).count()
+}
+
+/// Apply smart typography to `text`.
+pub fn smarten(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let c = chars[i];
+        if prev.is_none_or(char::is_whitespace) {
+            let word: String = chars[i..]
+                .iter()
+                .take_while(|c| !c.is_whitespace())
+                .collect();
+            if word.contains("://") {
+                out.push_str(&word);
+                i += word.chars().count();
+                continue;
+            }
+        }
+        match c {
+            '"' => out.push(if opens_after(prev) { '“' } else { '”' }),
+            // An apostrophe, unless it opens a quote. `'90s` abbreviates.
+            '\'' => {
+                let next_digit = chars.get(i + 1).is_some_and(char::is_ascii_digit);
+                out.push(if opens_after(prev) && !next_digit {
+                    '‘'
+                } else {
+                    '’'
+                });
+            }
+            '-' | '.' => {
+                let run = run_len(&chars, i);
+                let next = chars.get(i + run);
+                // `a--b` and `a -- b`, but not `--flag`.
+                let dash = c == '-'
+                    && run == 2
+                    && (prev.is_some_and(|p| !p.is_whitespace())
+                        || next.is_none_or(|n| n.is_whitespace()));
+                if dash {
+                    out.push('—');
+                } else if c == '.' && run == 3 {
+                    out.push('…');
+                } else {
+                    out.extend(&chars[i..i + run]);
+                }
+                i += run;
+                continue;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curls_quotes() {
+        assert_eq!(
+            smarten(r#"She said "it's done" ('mostly')."#),
+            "She said “it’s done” (‘mostly’)."
+        );
+        assert_eq!(smarten("back in the '90s"), "back in the ’90s");
+        assert_eq!(smarten("\"quoted\"\n\"again\""), "“quoted”\n“again”");
+    }
+
+    #[test]
+    fn dashes_and_ellipses() {
+        assert_eq!(
+            smarten("wait... what -- really--yes"),
+            "wait… what — really—yes"
+        );
+        assert_eq!(smarten("run it with --dry-run"), "run it with --dry-run");
+        assert_eq!(smarten("more...."), "more....");
+    }
+
+    #[test]
+    fn keeps_separators_and_urls() {
+        let text = "first\n---\nsee https://example.com/a--b?q=\"x\"...";
+        assert_eq!(smarten(text), text);
+    }
+}
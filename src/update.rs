@@ -0,0 +1,144 @@
+//! Opt-in check for a newer xcli release (`check_updates = true` in
+//! `config.toml`). GitHub is asked at most once a day; the answer is cached
+//! and any failure is silent.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transport::{Method, Request, Transport};
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/hulryung/xcli/releases/latest";
+pub const RELEASES_PAGE: &str = "https://github.com/hulryung/xcli/releases/latest";
+
+const CHECK_INTERVAL: i64 = 24 * 60 * 60;
+/// Never hold up the command noticeably for a courtesy check.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Default)]
+struct Cache {
+    checked_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+/// `1.2.3` or `v1.2.3` as comparable numbers. Pre-release suffixes are
+/// ignored.
+fn parse_version(v: &str) -> Option<Vec<u64>> {
+    let v = v.trim().trim_start_matches('v');
+    let core = v.split(['-', '+']).next()?;
+    core.split('.').map(|n| n.parse().ok()).collect()
+}
+
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+async fn fetch_latest(transport: &dyn Transport) -> Option<String> {
+    let req = Request::new(Method::GET, LATEST_RELEASE_URL)
+        .header("User-Agent", format!("xcli/{}", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json");
+    let resp = tokio::time::timeout(TIMEOUT, transport.send(req))
+        .await
+        .ok()?
+        .ok()?;
+    if !resp.status.is_success() {
+        return None;
+    }
+    resp.json::<Release>().ok().map(|r| r.tag_name)
+}
+
+/// The latest release if it is newer than `current`, asking GitHub only when
+/// the cache at `cache_path` is older than a day.
+pub async fn newer_release(
+    transport: &dyn Transport,
+    cache_path: &Path,
+    current: &str,
+    now: i64,
+) -> Option<String> {
+    let cached: Option<Cache> = fs::read_to_string(cache_path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok());
+    let latest = match cached {
+        Some(cache) if now - cache.checked_at < CHECK_INTERVAL => cache.latest,
+        _ => {
+            // Failures are cached too, so an offline machine tries once a day.
+            let latest = fetch_latest(transport).await;
+            let cache = Cache {
+                checked_at: now,
+                latest: latest.clone(),
+            };
+            if let Ok(json) = serde_json::to_string(&cache) {
+                let _ = fs::write(cache_path, json);
+            }
+            latest
+        }
+    };
+    latest.filter(|l| is_newer(l, current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MockTransport;
+    use std::env::temp_dir;
+    use std::path::PathBuf;
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = temp_dir().join(format!("xcli_test_{}_{name}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert!(is_newer("v1.10.0", "1.9.3"));
+        assert!(is_newer("1.2.1", "1.2.0"));
+        assert!(!is_newer("v1.2.0", "1.2.0"));
+        assert!(!is_newer("1.2.0-rc.1", "1.2.0"));
+        assert!(!is_newer("nightly", "1.2.0"));
+    }
+
+    #[tokio::test]
+    async fn checks_once_a_day() {
+        let path = cache_path("update_cache.json");
+        let mock = MockTransport::new();
+        mock.respond(200, r#"{"tag_name":"v9.0.0"}"#);
+
+        let found = newer_release(&mock, &path, "1.2.0", 1_000).await;
+        assert_eq!(found.as_deref(), Some("v9.0.0"));
+        assert_eq!(mock.requests().len(), 1);
+        assert!(mock.requests()[0].header_value("user-agent").is_some());
+
+        // Within a day the cached answer is used.
+        let found = newer_release(&mock, &path, "1.2.0", 1_000 + 3_600).await;
+        assert_eq!(found.as_deref(), Some("v9.0.0"));
+        assert_eq!(mock.requests().len(), 1);
+        assert_eq!(newer_release(&mock, &path, "9.0.0", 2_000).await, None);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn failures_are_silent_and_cached() {
+        let path = cache_path("update_offline.json");
+        let mock = MockTransport::new();
+        mock.push(Err("Request failed: offline".to_string()));
+
+        assert_eq!(newer_release(&mock, &path, "1.2.0", 1_000).await, None);
+        assert_eq!(newer_release(&mock, &path, "1.2.0", 2_000).await, None);
+        assert_eq!(mock.requests().len(), 1);
+
+        let _ = fs::remove_file(&path);
+    }
+}
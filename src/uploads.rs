@@ -0,0 +1,137 @@
+//! Chunked video uploads in progress, kept in `uploads.json` so an
+//! interrupted upload resumes where it stopped (by running the same command
+//! again, or `xcli media resume`) instead of sending hundreds of megabytes
+//! anew. A finished upload stays until it is used in a tweet or X forgets
+//! its media ID.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Upload {
+    pub media_id: String,
+    /// Absolute path of the file being uploaded.
+    pub path: PathBuf,
+    pub media_type: String,
+    pub total_bytes: u64,
+    /// Modification time of the file (Unix seconds), to notice it changed.
+    pub modified: i64,
+    pub chunk_bytes: usize,
+    /// Indexes of the chunks X already has.
+    pub segments: Vec<usize>,
+    pub finished: bool,
+    /// When X forgets the media ID (Unix seconds).
+    pub expires_at: i64,
+}
+
+impl Upload {
+    pub fn chunk_count(&self) -> usize {
+        (self.total_bytes as usize)
+            .div_ceil(self.chunk_bytes)
+            .max(1)
+    }
+
+    /// Chunks still to send, in order.
+    pub fn missing_segments(&self) -> Vec<usize> {
+        (0..self.chunk_count())
+            .filter(|i| !self.segments.contains(i))
+            .collect()
+    }
+
+    /// Whether this is an upload of `path` as it is now.
+    pub fn is_of(&self, path: &Path, total_bytes: u64, modified: i64) -> bool {
+        self.path == path && self.total_bytes == total_bytes && self.modified == modified
+    }
+}
+
+/// Uploads recorded at `path` that X still knows about at `now`.
+pub fn load(path: &Path, now: i64) -> Vec<Upload> {
+    let uploads: Vec<Upload> = fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    uploads.into_iter().filter(|u| u.expires_at > now).collect()
+}
+
+fn save(path: &Path, uploads: &[Upload]) -> Result<(), String> {
+    if uploads.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove {}: {e}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let data = serde_json::to_string_pretty(uploads).expect("uploads serialize to JSON");
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Add `upload`, or replace the record of the same media ID.
+pub fn record(path: &Path, upload: &Upload, now: i64) -> Result<(), String> {
+    let mut uploads = load(path, now);
+    uploads.retain(|u| u.media_id != upload.media_id);
+    uploads.push(upload.clone());
+    save(path, &uploads)
+}
+
+/// Drop the records of `media_ids`.
+pub fn forget(path: &Path, media_ids: &[String], now: i64) -> Result<(), String> {
+    let mut uploads = load(path, now);
+    let before = uploads.len();
+    uploads.retain(|u| !media_ids.contains(&u.media_id));
+    if uploads.len() == before {
+        return Ok(());
+    }
+    save(path, &uploads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    fn upload(id: &str, expires_at: i64) -> Upload {
+        Upload {
+            media_id: id.to_string(),
+            path: PathBuf::from("/videos/talk.mp4"),
+            media_type: "video/mp4".to_string(),
+            total_bytes: 10,
+            modified: 100,
+            chunk_bytes: 4,
+            segments: vec![0, 2],
+            finished: false,
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn records_and_forgets_uploads() {
+        let path = temp_dir().join(format!("xcli_test_{}_uploads.json", std::process::id()));
+        record(&path, &upload("1", 1000), 0).unwrap();
+        record(&path, &upload("2", 50), 0).unwrap();
+        let mut updated = upload("1", 1000);
+        updated.segments.push(1);
+        record(&path, &updated, 0).unwrap();
+
+        assert_eq!(load(&path, 0), vec![upload("2", 50), updated]);
+        // Expired uploads are dropped.
+        assert_eq!(load(&path, 60).len(), 1);
+
+        forget(&path, &["1".to_string(), "2".to_string()], 0).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn tracks_missing_chunks() {
+        let upload = upload("1", 0);
+        assert_eq!(upload.chunk_count(), 3);
+        assert_eq!(upload.missing_segments(), vec![1]);
+        assert!(upload.is_of(Path::new("/videos/talk.mp4"), 10, 100));
+        assert!(!upload.is_of(Path::new("/videos/talk.mp4"), 10, 101));
+    }
+}
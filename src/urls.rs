@@ -0,0 +1,91 @@
+//! Link cleaning (`clean_urls = true` in a profile): tracking parameters
+//! such as `utm_source` or `fbclid` are removed from the query of every URL
+//! in the text before it is split.
+
+use regex::Regex;
+
+/// Parameters removed by default. A trailing `*` matches any suffix.
+const TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi", "mkt_tok",
+];
+
+fn is_tracking(name: &str, extra: &[String]) -> bool {
+    let matches = |pattern: &str| match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    };
+    TRACKING_PARAMS.iter().any(|p| matches(p)) || extra.iter().any(|p| matches(p))
+}
+
+/// `url` without tracking parameters (the defaults and `extra`).
+fn clean_url(url: &str, extra: &[String]) -> String {
+    let (rest, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (url, None),
+    };
+    let Some((base, query)) = rest.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default();
+            !param.is_empty() && !is_tracking(name, extra)
+        })
+        .collect();
+    let mut out = base.to_string();
+    if !kept.is_empty() {
+        out.push('?');
+        out.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        out.push('#');
+        out.push_str(fragment);
+    }
+    out
+}
+
+/// `text` with tracking parameters removed from its URLs; `extra` names
+/// parameters to remove besides the usual ones.
+pub fn clean(text: &str, extra: &[String]) -> String {
+    let link = Regex::new(r"https?://[^\s]+").unwrap();
+    link.replace_all(text, |c: &regex::Captures| {
+        let url = &c[0];
+        // Punctuation ending a sentence is not part of the link.
+        let end = url
+            .trim_end_matches(|c: char| ".,;:!?)\"'".contains(c))
+            .len();
+        format!("{}{}", clean_url(&url[..end], extra), &url[end..])
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_tracking_parameters() {
+        let text = "Read https://example.com/post?utm_source=x&id=7&fbclid=abc#top, then \
+                    https://example.com/?utm_medium=social&utm_campaign=launch.";
+        assert_eq!(
+            clean(text, &[]),
+            "Read https://example.com/post?id=7#top, then https://example.com/."
+        );
+        assert_eq!(
+            clean("https://shop.test/item?ref=tw&size=m", &[]),
+            "https://shop.test/item?ref=tw&size=m"
+        );
+    }
+
+    #[test]
+    fn strips_configured_parameters() {
+        let extra = vec!["ref".to_string(), "s_*".to_string()];
+        assert_eq!(
+            clean("https://shop.test/item?ref=tw&size=m&s_cid=1", &extra),
+            "https://shop.test/item?size=m"
+        );
+        assert_eq!(clean("no links here", &extra), "no links here");
+    }
+}
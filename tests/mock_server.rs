@@ -0,0 +1,151 @@
+//! End-to-end tests of `XClient` over real HTTP against a local fake X API.
+
+use std::sync::Arc;
+
+use serde_json::json;
+use wiremock::matchers::{body_json, body_string, header, header_regex, method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+use xcli::config::Config;
+use xcli::transport::HttpTransport;
+use xcli::XClient;
+
+fn test_config() -> Config {
+    Config {
+        api_key: "key".to_string(),
+        api_secret: "secret".to_string(),
+        access_token: "token".to_string(),
+        access_token_secret: "token_secret".to_string(),
+        screen_name: None,
+    }
+}
+
+fn client_for(server: &MockServer) -> XClient {
+    XClient::with_transport(
+        test_config(),
+        Arc::new(HttpTransport::with_origin(server.uri())),
+    )
+}
+
+fn signed() -> impl wiremock::Match {
+    header_regex(
+        "authorization",
+        r#"^OAuth .*oauth_consumer_key="key".*oauth_signature="[^"]+".*oauth_token="token""#,
+    )
+}
+
+#[tokio::test]
+async fn create_tweet_sends_signed_json() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .and(signed())
+        .and(header("content-type", "application/json"))
+        .and(body_json(json!({"text": "hello"})))
+        .respond_with(
+            ResponseTemplate::new(201).set_body_json(json!({"data": {"id": "1", "text": "hello"}})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    assert_eq!(client.tweet("hello").await.unwrap(), "1");
+}
+
+#[tokio::test]
+async fn delete_tweet() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/2/tweets/42"))
+        .and(signed())
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {"deleted": true}})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    client.delete("42").await.unwrap();
+}
+
+#[tokio::test]
+async fn api_errors_include_status_and_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/2/tweets/42"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("forbidden"))
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    let err = client.delete("42").await.unwrap_err();
+    assert!(err.contains("403"), "{err}");
+    assert!(err.contains("forbidden"), "{err}");
+}
+
+#[tokio::test]
+async fn thread_replies_to_the_previous_tweet() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/2/tweets"))
+        .respond_with(|req: &Request| {
+            let body: serde_json::Value = req.body_json().unwrap();
+            let parent = body["reply"]["in_reply_to_tweet_id"]
+                .as_str()
+                .map(|id| id.parse::<u64>().unwrap())
+                .unwrap_or(100);
+            ResponseTemplate::new(201)
+                .set_body_json(json!({"data": {"id": (parent + 1).to_string()}}))
+        })
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    let chunks = vec!["one".to_string(), "two".to_string(), "three".to_string()];
+    let ids = client.thread(&chunks).await.unwrap();
+    assert_eq!(ids, vec!["101", "102", "103"]);
+
+    let requests = server.received_requests().await.unwrap();
+    let first: serde_json::Value = requests[0].body_json().unwrap();
+    assert!(first.get("reply").is_none());
+}
+
+#[tokio::test]
+async fn me_reads_the_authenticated_user() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/2/users/me"))
+        .and(signed())
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(
+                json!({"data": {"id": "7", "username": "someone", "name": "Some One"}}),
+            ),
+        )
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    assert_eq!(client.me().await.unwrap().username, "someone");
+}
+
+#[tokio::test]
+async fn bearer_token_uses_basic_auth() {
+    let server = MockServer::start().await;
+    // base64("key:secret")
+    Mock::given(method("POST"))
+        .and(path("/oauth2/token"))
+        .and(header("authorization", "Basic a2V5OnNlY3JldA=="))
+        .and(body_string("grant_type=client_credentials"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"token_type": "bearer", "access_token": "app-token"})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    let token = xcli::api::get_bearer_token(&client).await.unwrap();
+    assert_eq!(token, "app-token");
+}